@@ -81,7 +81,7 @@ impl<'a> MirVisitor for Visitor<'a> {
         }
     }
 
-    fn visit_ty(&mut self, ty: &Ty, _location: Location) {
+    fn visit_ty(&mut self, ty: &Ty, _location: Option<Location>) {
         self.tys.insert(*ty);
     }
 }
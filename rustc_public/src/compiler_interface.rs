@@ -3,29 +3,48 @@
 //! rustc_public users should not use any of the items in this module directly.
 //! These APIs have no stability guarantee.
 
-use std::cell::Cell;
-
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::{fmt, iter};
+
+use rustc_data_structures::fingerprint::Fingerprint;
+use rustc_data_structures::stable_hasher::StableHasher;
+use rustc_errors::Diag;
+use rustc_hir::HirId;
 use rustc_hir::def::DefKind;
+use rustc_hir::definitions::{DefPathData, DefPathDataName};
+use rustc_metadata::creader::CStore;
+use rustc_middle::ty::fast_reject::{TreatParams, simplify_type};
+use rustc_middle::ty::print::with_forced_trimmed_paths;
+use rustc_middle::ty::{self, TyCtxt, TypeVisitableExt};
 use rustc_public_bridge::context::CompilerCtxt;
-use rustc_public_bridge::{Bridge, Container};
+use rustc_public_bridge::{Bridge, Container, Tables};
+use rustc_span::def_id::{DefIndex, LOCAL_CRATE};
 use tracing::debug;
 
 use crate::abi::{FnAbi, Layout, LayoutShape, ReprOptions};
 use crate::crate_def::Attribute;
+use crate::diagnostics::{Applicability, Diagnostic};
+use crate::lint::{Lint, LintLevel};
 use crate::mir::alloc::{AllocId, GlobalAlloc};
 use crate::mir::mono::{Instance, InstanceDef, StaticDef};
 use crate::mir::{BinOp, Body, Place, UnOp};
 use crate::target::{MachineInfo, MachineSize};
 use crate::ty::{
-    AdtDef, AdtKind, Allocation, ClosureDef, ClosureKind, CoroutineDef, Discr, FieldDef, FnDef,
-    ForeignDef, ForeignItemKind, ForeignModule, ForeignModuleDef, GenericArgs, GenericPredicates,
-    Generics, ImplDef, ImplTrait, IntrinsicDef, LineInfo, MirConst, PolyFnSig, RigidTy, Span,
-    TraitDecl, TraitDef, Ty, TyConst, TyConstId, TyKind, UintTy, VariantDef, VariantIdx,
+    AdtDef, AdtKind, AliasKind, AliasTy, Allocation, ClosureDef, ClosureKind, CoroutineDef, Discr,
+    FieldDef, FnDef, ForeignDef, ForeignItemKind, ForeignModule, ForeignModuleDef, GenericArgs,
+    GenericPredicates, Generics, ImplDef, ImplTrait, IntrinsicDef, LineInfo, MirConst, PolyFnSig,
+    RigidTy, Span, TraitDecl, TraitDef, Ty, TyConst, TyConstId, TyConstKind, TyKind, UintTy,
+    UnevaluatedConst, VariantDef, VariantIdx,
 };
-use crate::unstable::{RustcInternal, Stable, new_item_kind};
+use crate::unstable::{RustcInternal, Stable, new_def_kind, new_item_kind};
 use crate::{
-    AssocItems, Crate, CrateDef, CrateItem, CrateItems, CrateNum, DefId, Error, Filename,
-    ImplTraitDecls, ItemKind, Symbol, TraitDecls, alloc, mir,
+    AssocItems, Crate, CrateDef, CrateItem, CrateItems, CrateNum, DefId, DefPath, DefPathSegment,
+    Error, ErrorKind, Filename, ImplTraitDecls, IndexedVal, ItemKind, LayoutError, StableDefId,
+    Symbol, TraitDecls, alloc, diagnostics, error, mir,
 };
 
 pub struct BridgeTys;
@@ -72,7 +91,10 @@ impl Bridge for BridgeTys {
 /// similar APIs but based on internal rustc constructs.
 ///
 /// Do not use this directly. This is currently used in the macro expansion.
-pub(crate) trait CompilerInterface {
+///
+/// With the `testing` feature, tools can implement this trait to replace the compiler in their
+/// unit tests. See [crate::testing] for more details.
+pub trait CompilerInterface {
     fn entry_fn(&self) -> Option<CrateItem>;
     /// Retrieve all items of the local crate that have a MIR associated with them.
     fn all_local_items(&self) -> CrateItems;
@@ -97,9 +119,26 @@ pub(crate) trait CompilerInterface {
     fn trait_impls(&self, crate_num: CrateNum) -> ImplTraitDecls;
     fn trait_impl(&self, trait_impl: &ImplDef) -> ImplTrait;
     fn generics_of(&self, def_id: DefId) -> Generics;
+
+    /// The arguments of a definition that refer to its own generic parameters, and the ones of
+    /// its parents.
+    fn identity_args(&self, def_id: DefId) -> GenericArgs;
+
     fn predicates_of(&self, def_id: DefId) -> GenericPredicates;
     fn explicit_predicates_of(&self, def_id: DefId) -> GenericPredicates;
 
+    /// Complete the generic arguments of a definition with the defaults of its parameters.
+    ///
+    /// Lifetime arguments may be omitted, in which case they are erased.
+    fn generic_args_with_defaults(
+        &self,
+        def_id: DefId,
+        args: &GenericArgs,
+    ) -> Result<GenericArgs, Error>;
+
+    /// Retrieve the inherent implementations of the given type, including incoherent ones.
+    fn inherent_impls(&self, ty: Ty) -> Vec<ImplDef>;
+
     /// Get information about the local crate.
     fn local_crate(&self) -> Crate;
     /// Retrieve a list of all external crates.
@@ -111,6 +150,21 @@ pub(crate) trait CompilerInterface {
     /// Returns the name of given `DefId`
     fn def_name(&self, def_id: DefId, trimmed: bool) -> Symbol;
 
+    /// Return the identifier of a definition that is stable across compilations.
+    fn stable_def_id(&self, def_id: DefId) -> StableDefId;
+
+    /// Find the definition with the given stable identifier, if it exists.
+    fn find_stable_def_id(&self, id: StableDefId) -> Option<DefId>;
+
+    /// Return the structured path of a definition.
+    fn def_path(&self, def_id: DefId) -> DefPath;
+
+    /// Find the definitions with the given path.
+    fn resolve_path(&self, path: &str) -> Vec<DefId>;
+
+    /// Return the kind of a definition.
+    fn def_kind(&self, def_id: DefId) -> crate::DefKind;
+
     /// Return registered tool attributes with the given attribute name.
     ///
     /// FIXME(jdonszelmann): may panic on non-tool attributes. After more attribute work, non-tool
@@ -132,6 +186,12 @@ pub(crate) trait CompilerInterface {
     /// Return lines corresponding to this `Span`
     fn get_lines(&self, span: &Span) -> LineInfo;
 
+    /// Return the span of the outermost macro call that this `Span` was expanded from.
+    fn span_source_callsite(&self, span: &Span) -> Span;
+
+    /// Return the source code of the lines covered by this `Span`, if it is available.
+    fn span_source_lines(&self, span: &Span) -> Option<Vec<String>>;
+
     /// Returns the `kind` of given `DefId`
     fn item_kind(&self, item: CrateItem) -> ItemKind;
 
@@ -203,12 +263,61 @@ pub(crate) trait CompilerInterface {
     fn try_new_const_uint(&self, value: u128, uint_ty: UintTy) -> Result<MirConst, Error>;
     fn try_new_ty_const_uint(&self, value: u128, uint_ty: UintTy) -> Result<TyConst, Error>;
 
+    /// Create a new scalar constant of the given type from its raw bits.
+    fn try_new_const_from_bits(&self, bits: u128, ty: Ty) -> Result<MirConst, Error>;
+
+    /// Create a new constant of the given type from a type-level constant, such as a const
+    /// generic parameter.
+    fn new_const_from_ty_const(&self, cnst: &TyConst, ty: Ty) -> MirConst;
+
+    /// Create a new constant that refers to a constant item, or a promoted constant, that hasn't
+    /// been evaluated.
+    fn try_new_const_unevaluated(&self, cnst: &UnevaluatedConst) -> Result<MirConst, Error>;
+
+    /// Create a new pointer constant into the allocation with the given compiler identifier, as
+    /// printed in constants, e.g.: `3` for `{alloc3: &u8}`.
+    fn try_new_const_ptr(
+        &self,
+        alloc: u64,
+        offset: u64,
+        immutable: bool,
+        ty: Ty,
+    ) -> Result<MirConst, Error>;
+
+    /// Create a new constant of an array, tuple or ADT type from the values of its fields.
+    ///
+    /// For enums, `variant` selects the variant being built.
+    fn try_new_const_aggregate(
+        &self,
+        ty: Ty,
+        variant: Option<VariantIdx>,
+        fields: &[MirConst],
+    ) -> Result<MirConst, Error>;
+
     /// Create a new type from the given kind.
     fn new_rigid_ty(&self, kind: RigidTy) -> Ty;
 
     /// Create a new box type, `Box<T>`, for the given inner type `T`.
     fn new_box_ty(&self, ty: Ty) -> Ty;
 
+    /// Create a new alias type, such as a projection or an opaque type.
+    fn new_alias_ty(&self, kind: AliasKind, alias: &AliasTy) -> Ty;
+
+    /// Create a new type-level constant from the given kind.
+    ///
+    /// Values can only be rebuilt for scalar types, and bound constants are not supported.
+    fn try_new_ty_const(&self, kind: &TyConstKind) -> Result<TyConst, Error>;
+
+    /// Create a copy of the given constant with a different type, e.g. one where generic
+    /// parameters have been substituted.
+    fn mir_const_with_ty(&self, cnst: &MirConst, ty: Ty) -> MirConst;
+
+    /// Create a copy of the given type-level constant value with a different type.
+    ///
+    /// Unlike [Self::try_new_ty_const], this keeps the value itself, so it works for values of
+    /// any type. The constant must be a value.
+    fn ty_const_with_ty(&self, cnst: &TyConst, ty: Ty) -> TyConst;
+
     /// Returns the type of given crate item.
     fn def_ty(&self, item: DefId) -> Ty;
 
@@ -216,15 +325,25 @@ pub(crate) trait CompilerInterface {
     fn def_ty_with_args(&self, item: DefId, args: &GenericArgs) -> Ty;
 
     /// Returns literal value of a const as a string.
-    fn mir_const_pretty(&self, cnst: &MirConst) -> String;
+    ///
+    /// If `trimmed` is set, paths are trimmed the same way as [Self::def_name] trims them.
+    fn mir_const_pretty(&self, cnst: &MirConst, trimmed: bool) -> String;
 
     /// `Span` of an item
     fn span_of_an_item(&self, def_id: DefId) -> Span;
 
-    fn ty_const_pretty(&self, ct: TyConstId) -> String;
+    /// A span that doesn't point to any source code.
+    fn dummy_span(&self) -> Span;
+
+    /// Describe the kind of a definition, e.g.: `function`, `struct`.
+    fn def_descr(&self, def_id: DefId) -> String;
+
+    fn ty_const_pretty(&self, ct: TyConstId, trimmed: bool) -> String;
 
     /// Obtain the representation of a type.
-    fn ty_pretty(&self, ty: Ty) -> String;
+    ///
+    /// If `trimmed` is set, paths are trimmed the same way as [Self::def_name] trims them.
+    fn ty_pretty(&self, ty: Ty, trimmed: bool) -> String;
 
     /// Obtain the kind of a type.
     fn ty_kind(&self, ty: Ty) -> TyKind;
@@ -314,6 +433,20 @@ pub(crate) trait CompilerInterface {
 
     /// Get all associated items of a definition.
     fn associated_items(&self, def_id: DefId) -> AssocItems;
+
+    /// Emit a diagnostic through the compiler session.
+    fn emit_diagnostic(&self, diagnostic: &Diagnostic);
+
+    /// Get the level of a tool lint for the given item.
+    fn lint_level(&self, lint: &'static Lint, item: DefId) -> Result<LintLevel, Error>;
+
+    /// Emit a tool lint for the given item, according to the lint level of the item.
+    fn emit_lint(
+        &self,
+        lint: &'static Lint,
+        item: DefId,
+        diagnostic: &Diagnostic,
+    ) -> Result<(), Error>;
 }
 
 impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
@@ -434,6 +567,13 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         cx.generics_of(did).stable(&mut *tables, cx)
     }
 
+    fn identity_args(&self, def_id: DefId) -> GenericArgs {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let did = tables[def_id];
+        ty::GenericArgs::identity_for_item(cx.tcx, did).stable(&mut *tables, cx)
+    }
+
     fn predicates_of(&self, def_id: DefId) -> GenericPredicates {
         let mut tables = self.tables.borrow_mut();
         let cx = &*self.cx.borrow();
@@ -462,6 +602,60 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         }
     }
 
+    fn generic_args_with_defaults(
+        &self,
+        def_id: DefId,
+        args: &GenericArgs,
+    ) -> Result<GenericArgs, Error> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let did = tables[def_id];
+        let given = args.internal(&mut *tables, tcx);
+        let mut given = given.iter().peekable();
+        let mut missing = vec![];
+        let args = ty::GenericArgs::for_item(tcx, did, |param, filled| {
+            let is_lifetime = matches!(param.kind, ty::GenericParamDefKind::Lifetime);
+            if let Some(arg) = given
+                .next_if(|arg| is_lifetime == matches!(arg.kind(), ty::GenericArgKind::Lifetime(_)))
+            {
+                arg
+            } else if is_lifetime {
+                tcx.lifetimes.re_erased.into()
+            } else if let Some(default) = param.default_value(tcx) {
+                default.instantiate(tcx, filled)
+            } else {
+                missing.push(param.name);
+                tcx.mk_param_from_def(param)
+            }
+        });
+        if let Some(name) = missing.first() {
+            Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Missing generic argument `{name}` for `{}`", tcx.def_path_str(did)),
+            ))
+        } else if given.next().is_some() {
+            Err(error!("Too many generic arguments for `{}`", tcx.def_path_str(did)))
+        } else {
+            Ok(args.stable(&mut *tables, cx))
+        }
+    }
+
+    fn inherent_impls(&self, ty: Ty) -> Vec<ImplDef> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let ty = ty.internal(&mut *tables, tcx);
+        let coherent = match ty.kind() {
+            ty::Adt(adt, _) => tcx.inherent_impls(adt.did()),
+            ty::Foreign(did) => tcx.inherent_impls(*did),
+            _ => &[],
+        };
+        let incoherent = simplify_type(tcx, ty, TreatParams::AsRigid)
+            .map_or(&[][..], |simplified| tcx.incoherent_impls(simplified));
+        coherent.iter().chain(incoherent).map(|did| tables.impl_def(*did)).collect()
+    }
+
     /// Get information about the local crate.
     fn local_crate(&self) -> Crate {
         let cx = &*self.cx.borrow();
@@ -488,6 +682,87 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         cx.def_name(did, trimmed)
     }
 
+    fn stable_def_id(&self, def_id: DefId) -> StableDefId {
+        let tables = self.tables.borrow();
+        let cx = &*self.cx.borrow();
+        stable_def_id(cx.tcx, tables[def_id])
+    }
+
+    fn find_stable_def_id(&self, id: StableDefId) -> Option<DefId> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        // Different versions of a crate may have the same name, so we search all of them.
+        let did = iter::once(LOCAL_CRATE)
+            .chain(tcx.crates(()).iter().copied())
+            .filter(|krate| stable_hash(tcx.crate_name(*krate).as_str()) == id.crate_id)
+            .find_map(|krate| {
+                let index = def_index(tcx, krate, id.path_hash)?;
+                Some(rustc_span::def_id::DefId { krate, index })
+            })?;
+        Some(tables.create_def_id(did))
+    }
+
+    fn def_path(&self, def_id: DefId) -> DefPath {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let did = tables[def_id];
+        let mut ancestors = iter::successors(Some(did), |did| tcx.opt_parent(*did))
+            .filter(|did| !did.is_crate_root())
+            .collect::<Vec<_>>();
+        ancestors.reverse();
+        let segments = ancestors
+            .into_iter()
+            .map(|did| {
+                let key = tcx.def_key(did).disambiguated_data;
+                let data = match key.data {
+                    DefPathData::Impl => crate::DefPathData::Impl {
+                        self_ty: tcx.type_of(did).instantiate_identity().stable(&mut *tables, cx),
+                        trait_def: tcx
+                            .impl_trait_ref(did)
+                            .map(|trait_ref| tables.trait_def(trait_ref.skip_binder().def_id)),
+                    },
+                    DefPathData::Closure => crate::DefPathData::Closure,
+                    DefPathData::Ctor => crate::DefPathData::Ctor,
+                    DefPathData::AnonConst => crate::DefPathData::AnonConst,
+                    data => match data.name() {
+                        DefPathDataName::Named(name) => crate::DefPathData::Named(name.to_string()),
+                        DefPathDataName::Anon { namespace } => {
+                            crate::DefPathData::Other(namespace.to_string())
+                        }
+                    },
+                };
+                DefPathSegment { data, disambiguator: key.disambiguator }
+            })
+            .collect();
+        DefPath { krate: smir_crate(cx, did.krate), segments }
+    }
+
+    fn resolve_path(&self, path: &str) -> Vec<DefId> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let mut segments = path.split("::");
+        let krate = segments.next().unwrap_or_default();
+        let mut resolved =
+            cx.find_crates(krate).into_iter().map(|krate| krate.as_def_id()).collect::<Vec<_>>();
+        for segment in segments {
+            let name = rustc_span::Symbol::intern(segment);
+            resolved =
+                resolved.into_iter().flat_map(|did| children_named(tcx, did, name)).collect();
+            let mut seen = HashSet::new();
+            resolved.retain(|did| seen.insert(*did));
+        }
+        resolved.into_iter().map(|did| tables.create_def_id(did)).collect()
+    }
+
+    fn def_kind(&self, def_id: DefId) -> crate::DefKind {
+        let tables = self.tables.borrow();
+        let cx = &*self.cx.borrow();
+        new_def_kind(cx.def_kind(tables[def_id]))
+    }
+
     /// Return registered tool attributes with the given attribute name.
     ///
     /// FIXME(jdonszelmann): may panic on non-tool attributes. After more attribute work, non-tool
@@ -541,6 +816,28 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         LineInfo::from(lines)
     }
 
+    /// Return the span of the outermost macro call that this `Span` was expanded from.
+    fn span_source_callsite(&self, span: &Span) -> Span {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let sp = tables.spans[*span];
+        sp.source_callsite().stable(&mut *tables, cx)
+    }
+
+    /// Return the source code of the lines covered by this `Span`, if it is available.
+    fn span_source_lines(&self, span: &Span) -> Option<Vec<String>> {
+        let tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let source_map = cx.tcx.sess.source_map();
+        let lines = source_map.span_to_lines(tables.spans[*span]).ok()?;
+        source_map.ensure_source_file_source_present(&lines.file);
+        lines
+            .lines
+            .iter()
+            .map(|line| lines.file.get_line(line.line_index).map(|text| text.into_owned()))
+            .collect()
+    }
+
     /// Returns the `kind` of given `DefId`.
     fn item_kind(&self, item: CrateItem) -> ItemKind {
         let tables = self.tables.borrow();
@@ -713,7 +1010,24 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         let mut tables = self.tables.borrow_mut();
         let cx = &*self.cx.borrow();
         let ty_internal = ty.internal(&mut *tables, cx.tcx);
-        cx.try_new_const_zst(ty_internal).map(|cnst| cnst.stable(&mut *tables, cx))
+        if let ty::FnDef(..) = ty_internal.kind() {
+            // Function items are zero-sized, even if their signature cannot be normalized.
+            let cnst = rustc_middle::mir::Const::Ty(ty_internal, cx.const_zero_sized(ty_internal));
+            return Ok(cnst.stable(&mut *tables, cx));
+        }
+        cx.try_new_const_zst(ty_internal).map(|cnst| {
+            // Closures and coroutines cannot be represented by a valtree.
+            let cnst = match ty_internal.kind() {
+                ty::Closure(..) | ty::Coroutine(..) | ty::CoroutineClosure(..) => {
+                    rustc_middle::mir::Const::Val(
+                        rustc_middle::mir::ConstValue::ZeroSized,
+                        ty_internal,
+                    )
+                }
+                _ => cnst,
+            };
+            cnst.stable(&mut *tables, cx)
+        })
     }
 
     /// Create a new constant that represents the given string value.
@@ -745,6 +1059,137 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         cx.try_new_ty_const_uint(value, ty).map(|cnst| cnst.stable(&mut *tables, cx))
     }
 
+    fn try_new_const_from_bits(&self, bits: u128, ty: Ty) -> Result<MirConst, Error> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let ty = ty.internal(&mut *tables, cx.tcx);
+        cx.try_new_const_uint(bits, ty).map(|cnst| cnst.stable(&mut *tables, cx))
+    }
+
+    fn new_const_from_ty_const(&self, cnst: &TyConst, ty: Ty) -> MirConst {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let cnst = rustc_middle::mir::Const::Ty(
+            ty.internal(&mut *tables, tcx),
+            cnst.internal(&mut *tables, tcx),
+        );
+        cnst.stable(&mut *tables, cx)
+    }
+
+    fn try_new_const_unevaluated(&self, cnst: &UnevaluatedConst) -> Result<MirConst, Error> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let did = tables[cnst.def.0];
+        let args = cnst.args.internal(&mut *tables, tcx);
+        let promoted = cnst.promoted.map(rustc_middle::mir::Promoted::from_u32);
+        let ty = match promoted {
+            Some(promoted) => {
+                let Some(body) = tcx.promoted_mir(did).get(promoted) else {
+                    return Err(Error::new(
+                        ErrorKind::NotFound,
+                        format!("`{}` has no `{promoted:?}`", tcx.def_path_str(did)),
+                    ));
+                };
+                ty::EarlyBinder::bind(body.return_ty()).instantiate(tcx, args)
+            }
+            None => tcx.type_of(did).instantiate(tcx, args),
+        };
+        let cnst = rustc_middle::mir::Const::Unevaluated(
+            rustc_middle::mir::UnevaluatedConst { def: did, args, promoted },
+            ty,
+        );
+        Ok(cnst.stable(&mut *tables, cx))
+    }
+
+    fn try_new_const_ptr(
+        &self,
+        alloc: u64,
+        offset: u64,
+        immutable: bool,
+        ty: Ty,
+    ) -> Result<MirConst, Error> {
+        use rustc_middle::mir::interpret::{AllocId, CtfeProvenance, Pointer, Scalar};
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let ty = ty.internal(&mut *tables, tcx);
+        let thin = ty.builtin_deref(true).is_some_and(|pointee| {
+            !pointee.has_param() && pointee.is_sized(tcx, ty::TypingEnv::fully_monomorphized())
+        });
+        if !thin {
+            return Err(error!("Cannot build a pointer constant of type `{ty}`"));
+        }
+        let Some(alloc_id) = std::num::NonZero::new(alloc)
+            .map(AllocId)
+            .filter(|alloc_id| tcx.try_get_global_alloc(*alloc_id).is_some())
+        else {
+            return Err(Error::new(ErrorKind::NotFound, format!("Cannot find `alloc{alloc}`")));
+        };
+        let provenance = CtfeProvenance::from(alloc_id);
+        let provenance = if immutable { provenance.as_immutable() } else { provenance };
+        let ptr = Pointer::new(provenance, rustc_abi::Size::from_bytes(offset));
+        let value = rustc_middle::mir::ConstValue::Scalar(Scalar::from_pointer(ptr, &tcx));
+        Ok(rustc_middle::mir::Const::Val(value, ty).stable(&mut *tables, cx))
+    }
+
+    fn try_new_const_aggregate(
+        &self,
+        ty: Ty,
+        variant: Option<VariantIdx>,
+        fields: &[MirConst],
+    ) -> Result<MirConst, Error> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let ty = ty.internal(&mut *tables, tcx);
+        if ty.has_param() {
+            return Err(error!("Cannot build a constant of generic type `{ty}`"));
+        }
+        let (field_tys, variant) = match (ty.kind(), variant) {
+            (ty::Adt(def, args), variant) if def.is_enum() == variant.is_some() => {
+                let idx =
+                    variant.map_or(rustc_abi::FIRST_VARIANT, |idx| idx.internal(&mut *tables, tcx));
+                let Some(variant) = def.variants().get(idx) else {
+                    return Err(error!("Variant `{idx:?}` doesn't exist in `{ty}`"));
+                };
+                let field_tys = variant.fields.iter().map(|field| field.ty(tcx, args)).collect();
+                (field_tys, def.is_enum().then_some(idx))
+            }
+            (ty::Tuple(tys), None) => (tys.to_vec(), None),
+            (ty::Array(elem_ty, len), None) => {
+                let len = len.try_to_target_usize(tcx).unwrap_or_default();
+                (vec![*elem_ty; len as usize], None)
+            }
+            _ => return Err(error!("Cannot build an aggregate constant of type `{ty}`")),
+        };
+        if field_tys.len() != fields.len() {
+            return Err(error!(
+                "Expected {} fields to build a constant of type `{ty}`, but found {}",
+                field_tys.len(),
+                fields.len()
+            ));
+        }
+        let mut branches = Vec::with_capacity(fields.len() + 1);
+        if let Some(idx) = variant {
+            branches.push(ty::ValTree::from_scalar_int(tcx, idx.as_u32().into()));
+        }
+        for (field, field_ty) in fields.iter().zip(field_tys) {
+            let field = field.internal(&mut *tables, tcx);
+            if field.ty() != field_ty {
+                return Err(error!(
+                    "Expected a field of type `{field_ty}`, but found `{}`",
+                    field.ty()
+                ));
+            }
+            branches.push(const_to_valtree(tcx, field)?);
+        }
+        let valtree = ty::ValTree::from_branches(tcx, branches);
+        let value = tcx.valtree_to_const_val(ty::Value { ty, valtree });
+        Ok(rustc_middle::mir::Const::Val(value, ty).stable(&mut *tables, cx))
+    }
+
     /// Create a new type from the given kind.
     fn new_rigid_ty(&self, kind: RigidTy) -> Ty {
         let mut tables = self.tables.borrow_mut();
@@ -761,6 +1206,75 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         cx.new_box_ty(inner).stable(&mut *tables, cx)
     }
 
+    fn new_alias_ty(&self, kind: AliasKind, alias: &AliasTy) -> Ty {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let kind = match kind {
+            AliasKind::Projection => ty::AliasTyKind::Projection,
+            AliasKind::Inherent => ty::AliasTyKind::Inherent,
+            AliasKind::Opaque => ty::AliasTyKind::Opaque,
+            AliasKind::Free => ty::AliasTyKind::Free,
+        };
+        let def_id = tables[alias.def_id.0];
+        let args = alias.args.internal(&mut *tables, tcx);
+        let alias = ty::AliasTy::new_from_args(tcx, def_id, args);
+        ty::Ty::new_alias(tcx, kind, alias).stable(&mut *tables, cx)
+    }
+
+    fn try_new_ty_const(&self, kind: &TyConstKind) -> Result<TyConst, Error> {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let cnst = match kind {
+            TyConstKind::Param(param) => {
+                let name = rustc_span::Symbol::intern(&param.name);
+                ty::Const::new_param(tcx, ty::ParamConst::new(param.index, name))
+            }
+            TyConstKind::Unevaluated(def, args) => {
+                let args = args.internal(&mut *tables, tcx);
+                ty::Const::new_unevaluated(tcx, ty::UnevaluatedConst::new(tables[def.0], args))
+            }
+            TyConstKind::ZSTValue(ty) => ty::Const::zero_sized(tcx, ty.internal(&mut *tables, tcx)),
+            TyConstKind::Value(ty, alloc) => {
+                let internal_ty = ty.internal(&mut *tables, tcx);
+                if !internal_ty.is_scalar() {
+                    return Err(error!("Cannot create a constant value of type `{internal_ty}`"));
+                }
+                cx.try_new_ty_const_uint(alloc.read_uint()?, internal_ty)?
+            }
+            TyConstKind::Bound(..) => return Err(error!("Cannot create a bound constant")),
+        };
+        Ok(cnst.stable(&mut *tables, cx))
+    }
+
+    fn mir_const_with_ty(&self, cnst: &MirConst, ty: Ty) -> MirConst {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let ty = ty.internal(&mut *tables, tcx);
+        let cnst = match cnst.internal(&mut *tables, tcx) {
+            rustc_middle::mir::Const::Ty(_, ct) => rustc_middle::mir::Const::Ty(ty, ct),
+            rustc_middle::mir::Const::Unevaluated(uneval, _) => {
+                rustc_middle::mir::Const::Unevaluated(uneval, ty)
+            }
+            rustc_middle::mir::Const::Val(val, _) => rustc_middle::mir::Const::Val(val, ty),
+        };
+        cnst.stable(&mut *tables, cx)
+    }
+
+    fn ty_const_with_ty(&self, cnst: &TyConst, ty: Ty) -> TyConst {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let tcx = cx.tcx;
+        let ty = ty.internal(&mut *tables, tcx);
+        let cnst = match cnst.internal(&mut *tables, tcx).kind() {
+            ty::ConstKind::Value(value) => ty::Const::new_value(tcx, value.valtree, ty),
+            kind => unreachable!("Expected a constant value, found: {kind:?}"),
+        };
+        cnst.stable(&mut *tables, cx)
+    }
+
     /// Returns the type of given crate item.
     fn def_ty(&self, item: DefId) -> Ty {
         let mut tables = self.tables.borrow_mut();
@@ -779,10 +1293,11 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
     }
 
     /// Returns literal value of a const as a string.
-    fn mir_const_pretty(&self, cnst: &MirConst) -> String {
+    fn mir_const_pretty(&self, cnst: &MirConst, trimmed: bool) -> String {
         let mut tables = self.tables.borrow_mut();
         let cx = &*self.cx.borrow();
-        cnst.internal(&mut *tables, cx.tcx).to_string()
+        let cnst = cnst.internal(&mut *tables, cx.tcx);
+        if trimmed { with_forced_trimmed_paths!(cnst.to_string()) } else { cnst.to_string() }
     }
 
     /// `Span` of an item.
@@ -793,17 +1308,35 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         cx.span_of_an_item(did).stable(&mut *tables, cx)
     }
 
-    fn ty_const_pretty(&self, ct: TyConstId) -> String {
+    fn dummy_span(&self) -> Span {
+        let mut tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        rustc_span::DUMMY_SP.stable(&mut *tables, cx)
+    }
+
+    fn def_descr(&self, def_id: DefId) -> String {
+        let tables = self.tables.borrow();
+        let cx = &*self.cx.borrow();
+        cx.tcx.def_descr(tables[def_id]).to_string()
+    }
+
+    fn ty_const_pretty(&self, ct: TyConstId, trimmed: bool) -> String {
         let tables = self.tables.borrow_mut();
         let cx = &*self.cx.borrow();
-        cx.ty_const_pretty(tables.ty_consts[ct])
+        let ct = tables.ty_consts[ct];
+        if trimmed {
+            with_forced_trimmed_paths!(cx.ty_const_pretty(ct))
+        } else {
+            cx.ty_const_pretty(ct)
+        }
     }
 
     /// Obtain the representation of a type.
-    fn ty_pretty(&self, ty: Ty) -> String {
+    fn ty_pretty(&self, ty: Ty, trimmed: bool) -> String {
         let tables = self.tables.borrow_mut();
         let cx = &*self.cx.borrow();
-        cx.ty_pretty(tables.types[ty])
+        let ty = tables.types[ty];
+        if trimmed { with_forced_trimmed_paths!(cx.ty_pretty(ty)) } else { cx.ty_pretty(ty) }
     }
 
     /// Obtain the kind of a type.
@@ -1013,7 +1546,26 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         let mut tables = self.tables.borrow_mut();
         let cx = &*self.cx.borrow();
         let internal_ty = ty.internal(&mut *tables, cx.tcx);
-        cx.ty_layout(internal_ty).map(|layout| layout.stable(&mut *tables, cx))
+        let typing_env = rustc_middle::ty::TypingEnv::fully_monomorphized();
+        match cx.tcx.layout_of(typing_env.as_query_input(internal_ty)) {
+            Ok(layout) => Ok(layout.layout.stable(&mut *tables, cx)),
+            Err(error) => {
+                use rustc_middle::ty::layout::LayoutError as E;
+                let (culprit, kind) = match *error {
+                    E::Unknown(ty) => (Some(ty), LayoutError::Unknown),
+                    E::SizeOverflow(ty) => (Some(ty), LayoutError::SizeOverflow),
+                    E::TooGeneric(ty) => (Some(ty), LayoutError::TooGeneric),
+                    E::NormalizationFailure(ty, _) => (Some(ty), LayoutError::NormalizationFailure),
+                    E::ReferencesError(_) => (None, LayoutError::ReferencesError),
+                    E::Cycle(_) => (None, LayoutError::Cycle),
+                };
+                let culprit = culprit.map_or(ty, |culprit| culprit.stable(&mut *tables, cx));
+                Err(Error::new(
+                    ErrorKind::Layout { ty: culprit, error: kind },
+                    format!("Failed to get layout for `{internal_ty}`: {error}"),
+                ))
+            }
+        }
     }
 
     /// Get the layout shape.
@@ -1057,19 +1609,371 @@ impl<'tcx> CompilerInterface for Container<'tcx, BridgeTys> {
         let did = tables[def_id];
         cx.associated_items(did).iter().map(|assoc| assoc.stable(&mut *tables, cx)).collect()
     }
+
+    /// Emit a diagnostic through the compiler session.
+    #[allow(rustc::diagnostic_outside_of_impl, rustc::untranslatable_diagnostic)]
+    fn emit_diagnostic(&self, diagnostic: &Diagnostic) {
+        let tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let level = match diagnostic.level {
+            // Only the compiler may report bugs and abort the compilation.
+            diagnostics::Level::Bug | diagnostics::Level::Fatal | diagnostics::Level::Error => {
+                rustc_errors::Level::Error
+            }
+            diagnostics::Level::Warning => rustc_errors::Level::Warning,
+            diagnostics::Level::Note => rustc_errors::Level::Note,
+            diagnostics::Level::Help => rustc_errors::Level::Help,
+        };
+        let mut diag = Diag::<()>::new(cx.tcx.dcx(), level, diagnostic.message.clone());
+        if let Some(span) = diagnostic.span {
+            diag.span(tables.spans[span]);
+        }
+        decorate_diagnostic(&mut diag, diagnostic, &tables);
+        diag.emit();
+    }
+
+    /// Get the level of a tool lint for the given item.
+    fn lint_level(&self, lint: &'static Lint, item: DefId) -> Result<LintLevel, Error> {
+        let tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let hir_id = local_hir_id(cx.tcx, item, tables[item])?;
+        let level = cx.tcx.lint_level_at_node(lint.internal(), hir_id).level;
+        Ok(match level {
+            rustc_session::lint::Level::Allow | rustc_session::lint::Level::Expect => {
+                LintLevel::Allow
+            }
+            rustc_session::lint::Level::Warn | rustc_session::lint::Level::ForceWarn => {
+                LintLevel::Warn
+            }
+            rustc_session::lint::Level::Deny => LintLevel::Deny,
+            rustc_session::lint::Level::Forbid => LintLevel::Forbid,
+        })
+    }
+
+    /// Emit a tool lint for the given item, according to the lint level of the item.
+    #[allow(rustc::diagnostic_outside_of_impl, rustc::untranslatable_diagnostic)]
+    fn emit_lint(
+        &self,
+        lint: &'static Lint,
+        item: DefId,
+        diagnostic: &Diagnostic,
+    ) -> Result<(), Error> {
+        let tables = self.tables.borrow_mut();
+        let cx = &*self.cx.borrow();
+        let def_id = tables[item];
+        let hir_id = local_hir_id(cx.tcx, item, def_id)?;
+        let span =
+            diagnostic.span.map_or_else(|| cx.tcx.def_span(def_id), |span| tables.spans[span]);
+        cx.tcx.node_span_lint(lint.internal(), hir_id, span, |diag| {
+            diag.primary_message(diagnostic.message.clone());
+            decorate_diagnostic(diag, diagnostic, &tables);
+        });
+        Ok(())
+    }
+}
+
+/// Convert the value of a constant into the value tree used to build aggregate constants.
+fn const_to_valtree<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    cnst: rustc_middle::mir::Const<'tcx>,
+) -> Result<ty::ValTree<'tcx>, Error> {
+    match cnst {
+        rustc_middle::mir::Const::Ty(_, ct) => match ct.kind() {
+            ty::ConstKind::Value(value) => Ok(value.valtree),
+            _ => Err(error!("Expected a constant value, but found `{ct}`")),
+        },
+        rustc_middle::mir::Const::Val(value, ty) => value_to_valtree(tcx, value, ty),
+        rustc_middle::mir::Const::Unevaluated(..) => {
+            Err(error!("Expected a constant value, but found `{cnst}`"))
+        }
+    }
+}
+
+fn value_to_valtree<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    value: rustc_middle::mir::ConstValue,
+    ty: ty::Ty<'tcx>,
+) -> Result<ty::ValTree<'tcx>, Error> {
+    match ty.kind() {
+        ty::Bool | ty::Char | ty::Int(_) | ty::Uint(_) | ty::Float(_) | ty::RawPtr(..) => value
+            .try_to_scalar_int()
+            .map(|int| ty::ValTree::from_scalar_int(tcx, int))
+            .ok_or_else(|| error!("Expected a scalar value of type `{ty}`")),
+        ty::Ref(_, inner, _) if inner.is_str() => value
+            .try_get_slice_bytes_for_diagnostics(tcx)
+            .map(|bytes| {
+                let bytes =
+                    bytes.iter().map(|byte| ty::ValTree::from_scalar_int(tcx, (*byte).into()));
+                ty::ValTree::from_branches(tcx, bytes)
+            })
+            .ok_or_else(|| error!("Expected a string value of type `{ty}`")),
+        ty::Adt(..) | ty::Tuple(_) | ty::Array(..) => {
+            let contents = tcx
+                .try_destructure_mir_constant_for_user_output(value, ty)
+                .ok_or_else(|| error!("Cannot read the fields of a constant of type `{ty}`"))?;
+            let variant = match ty.kind() {
+                ty::Adt(def, _) if def.is_enum() => contents.variant,
+                _ => None,
+            };
+            let variant =
+                variant.map(|idx| Ok(ty::ValTree::from_scalar_int(tcx, idx.as_u32().into())));
+            let fields =
+                contents.fields.iter().map(|(value, ty)| value_to_valtree(tcx, *value, *ty));
+            Ok(ty::ValTree::from_branches(
+                tcx,
+                variant.into_iter().chain(fields).collect::<Result<Vec<_>, _>>()?,
+            ))
+        }
+        _ if matches!(value, rustc_middle::mir::ConstValue::ZeroSized) => Ok(ty::ValTree::zst(tcx)),
+        _ => Err(error!("Cannot use a constant of type `{ty}` in an aggregate constant")),
+    }
+}
+
+/// Add the secondary elements of a diagnostic, i.e., everything but its message and primary span.
+#[allow(rustc::diagnostic_outside_of_impl, rustc::untranslatable_diagnostic)]
+fn decorate_diagnostic(
+    diag: &mut Diag<'_, ()>,
+    diagnostic: &Diagnostic,
+    tables: &Tables<'_, BridgeTys>,
+) {
+    for (span, label) in &diagnostic.labels {
+        diag.span_label(tables.spans[*span], label.clone());
+    }
+    for note in &diagnostic.notes {
+        diag.note(note.clone());
+    }
+    for help in &diagnostic.helps {
+        diag.help(help.clone());
+    }
+    for suggestion in &diagnostic.suggestions {
+        let applicability = match suggestion.applicability {
+            Applicability::MachineApplicable => rustc_errors::Applicability::MachineApplicable,
+            Applicability::MaybeIncorrect => rustc_errors::Applicability::MaybeIncorrect,
+            Applicability::HasPlaceholders => rustc_errors::Applicability::HasPlaceholders,
+            Applicability::Unspecified => rustc_errors::Applicability::Unspecified,
+        };
+        diag.span_suggestion(
+            tables.spans[suggestion.span],
+            suggestion.message.clone(),
+            suggestion.replacement.clone(),
+            applicability,
+        );
+    }
+}
+
+/// Lint levels can only be computed for items of the local crate.
+fn local_hir_id(
+    tcx: TyCtxt<'_>,
+    item: DefId,
+    def_id: rustc_span::def_id::DefId,
+) -> Result<HirId, Error> {
+    let local = def_id.as_local().ok_or_else(|| {
+        Error::new(
+            ErrorKind::UnexpectedItem(item),
+            format!("Expected a local item, but found `{}`", tcx.def_path_str(def_id)),
+        )
+    })?;
+    Ok(tcx.local_def_id_to_hir_id(local))
+}
+
+/// Find the children of a definition with the given name.
+///
+/// The children of modules, enums and traits are their items, including any re-export, and
+/// the children of types are the associated items of their inherent and trait impls.
+fn children_named(
+    tcx: TyCtxt<'_>,
+    did: rustc_span::def_id::DefId,
+    name: rustc_span::Symbol,
+) -> Vec<rustc_span::def_id::DefId> {
+    let def_kind = tcx.def_kind(did);
+    let mut children = vec![];
+    if matches!(def_kind, DefKind::Mod | DefKind::Enum | DefKind::Trait) {
+        let module_children = match did.as_local() {
+            Some(local) => tcx.module_children_local(local),
+            None => tcx.module_children(did),
+        };
+        children.extend(
+            module_children
+                .iter()
+                .filter(|child| child.ident.name == name)
+                .filter_map(|child| child.res.opt_def_id()),
+        );
+    }
+    if matches!(def_kind, DefKind::Struct | DefKind::Enum | DefKind::Union | DefKind::ForeignTy) {
+        let simplified =
+            simplify_type(tcx, tcx.type_of(did).instantiate_identity(), TreatParams::AsRigid);
+        let incoherent = simplified.map_or(&[][..], |simplified| tcx.incoherent_impls(simplified));
+        // Only the impls of traits with an item of that name can have such a child, so we look up
+        // the impls for the self type of those traits only.
+        let trait_impls = iter::once(LOCAL_CRATE)
+            .chain(tcx.crates(()).iter().copied())
+            .flat_map(|krate| tcx.traits(krate))
+            .filter(|trait_did| {
+                tcx.associated_items(**trait_did).filter_by_name_unhygienic(name).next().is_some()
+            })
+            .filter_map(|trait_did| {
+                tcx.trait_impls_of(*trait_did).non_blanket_impls().get(&simplified?)
+            })
+            .flatten();
+        let impls = tcx.inherent_impls(did).iter().chain(incoherent).chain(trait_impls).copied();
+        children.extend(impls.flat_map(|impl_did| {
+            tcx.associated_items(impl_did).filter_by_name_unhygienic(name).map(|item| item.def_id)
+        }));
+    }
+    children
+}
+
+/// Identify a compilation session.
+///
+/// Every compilation has its own rustc_public context, thus, a process may run several
+/// compilations one after another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct SessionId(u32);
+
+impl SessionId {
+    /// Create the identifier of a new session.
+    pub(crate) fn fresh() -> SessionId {
+        static NEXT_SESSION: AtomicU32 = AtomicU32::new(0);
+        SessionId(NEXT_SESSION.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The identifier of the ongoing session.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if there is no ongoing session.
+    pub(crate) fn current() -> SessionId {
+        with_context(|context| context.session)
+    }
+
+    /// Check that a handle created in this session is used in the ongoing session.
+    pub(crate) fn check(self, handle: &str) {
+        let current = TLV.is_set().then(SessionId::current);
+        match current {
+            Some(current) if current == self => {}
+            Some(current) => session_error(format!(
+                "`{handle}` from session {self} cannot be used in session {current}"
+            )),
+            None => session_error(format!(
+                "`{handle}` from session {self} cannot be used once its compilation is over"
+            )),
+        }
+    }
+}
+
+fn stable_def_id(tcx: TyCtxt<'_>, did: rustc_span::def_id::DefId) -> StableDefId {
+    StableDefId {
+        crate_id: stable_hash(tcx.crate_name(did.krate).as_str()),
+        path_hash: def_path_hash(tcx, did),
+    }
+}
+
+/// Hash the path of a definition, including the disambiguators of unnamed definitions.
+fn def_path_hash(tcx: TyCtxt<'_>, did: rustc_span::def_id::DefId) -> u64 {
+    stable_hash(tcx.def_path(did).to_string_no_crate_verbose())
+}
+
+/// Hash a value the same way in every compilation.
+///
+/// We cannot use the compiler's `DefPathHash`, since both of its halves depend on the
+/// `StableCrateId`, which differs between the library and the test builds of a crate.
+fn stable_hash(value: impl std::hash::Hash) -> u64 {
+    let mut hasher = StableHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish::<Fingerprint>().to_smaller_hash().as_u64()
+}
+
+/// The definitions of each crate, indexed by the hash of their path.
+struct DefIndices {
+    /// The session that the indices were built for, since crate numbers are only valid in it.
+    session: SessionId,
+    crates: HashMap<rustc_span::def_id::CrateNum, HashMap<u64, DefIndex>>,
+}
+
+thread_local! {
+    static DEF_INDICES: RefCell<Option<DefIndices>> = const { RefCell::new(None) };
+}
+
+/// Find the definition of a crate with the given path hash.
+///
+/// The compiler can only look up definitions by their `DefPathHash`, so we build our own index
+/// of the crate definitions the first time the crate is searched in a session.
+fn def_index(
+    tcx: TyCtxt<'_>,
+    krate: rustc_span::def_id::CrateNum,
+    path_hash: u64,
+) -> Option<DefIndex> {
+    let session = SessionId::current();
+    DEF_INDICES.with_borrow_mut(|indices| {
+        if indices.as_ref().is_none_or(|indices| indices.session != session) {
+            *indices = Some(DefIndices { session, crates: HashMap::new() });
+        }
+        let crates = &mut indices.as_mut().unwrap().crates;
+        let defs = crates.entry(krate).or_insert_with(|| {
+            let defs: Vec<_> = if krate == LOCAL_CRATE {
+                tcx.iter_local_def_id().map(|did| did.local_def_index).collect()
+            } else {
+                let num_defs = CStore::from_tcx(tcx).num_def_ids_untracked(krate);
+                (0..num_defs).map(DefIndex::from_usize).collect()
+            };
+            defs.into_iter()
+                .map(|index| {
+                    (def_path_hash(tcx, rustc_span::def_id::DefId { krate, index }), index)
+                })
+                .collect()
+        });
+        defs.get(&path_hash).copied()
+    })
+}
+
+/// Convert an index of the session tables into the one stored by handles.
+///
+/// Handles store a `u32` index next to their [SessionId], so they are no larger than a `usize`.
+pub(crate) fn handle_index(index: usize) -> u32 {
+    u32::try_from(index).expect("rustc_public tables cannot have more than `u32::MAX` entries")
+}
+
+/// A handle that can only be used in the session that created it.
+pub(crate) trait SessionHandle: IndexedVal {
+    /// Create the handle with the given index for the given session.
+    ///
+    /// Unlike [IndexedVal::to_val], this can be used outside of the session, e.g.: to create the
+    /// handles of a backend before its session starts.
+    fn new_in(index: usize, session: SessionId) -> Self;
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The context of the ongoing session.
+struct Context<'a> {
+    session: SessionId,
+    interface: &'a dyn CompilerInterface,
 }
 
-// A thread local variable that stores a pointer to [`CompilerInterface`].
+// A thread local variable that stores a pointer to the [`Context`] of the ongoing session.
 scoped_tls::scoped_thread_local!(static TLV: Cell<*const ()>);
 
-pub(crate) fn run<F, T>(interface: &dyn CompilerInterface, f: F) -> Result<T, Error>
+pub(crate) fn run<F, T>(
+    interface: &dyn CompilerInterface,
+    session: SessionId,
+    f: F,
+) -> Result<T, Error>
 where
     F: FnOnce() -> T,
 {
     if TLV.is_set() {
-        Err(Error::from("rustc_public already running"))
+        let running = with_context(|context| context.session);
+        Err(Error::new(
+            ErrorKind::Session,
+            format!("rustc_public already running session {running}"),
+        ))
     } else {
-        let ptr: *const () = (&raw const interface) as _;
+        let context = Context { session, interface };
+        let ptr: *const () = (&raw const context) as _;
         TLV.set(&Cell::new(ptr), || Ok(f()))
     }
 }
@@ -1078,15 +1982,131 @@ where
 ///
 /// I.e., This function will load the current interface and calls a function with it.
 /// Do not nest these, as that will ICE.
+///
+/// # Panics
+///
+/// This function panics if there is no ongoing session.
 pub(crate) fn with<R>(f: impl FnOnce(&dyn CompilerInterface) -> R) -> R {
-    assert!(TLV.is_set());
+    with_context(|context| f(context.interface))
+}
+
+fn with_context<R>(f: impl FnOnce(&Context<'_>) -> R) -> R {
+    if !TLV.is_set() {
+        session_error(
+            "rustc_public objects can only be used while the compilation that created them is \
+            running, i.e., inside its callbacks"
+                .to_string(),
+        );
+    }
     TLV.with(|tlv| {
         let ptr = tlv.get();
         assert!(!ptr.is_null());
-        f(unsafe { *(ptr as *const &dyn CompilerInterface) })
+        f(unsafe { &*(ptr as *const Context<'_>) })
     })
 }
 
+thread_local! {
+    /// The number of nested [try_with] calls.
+    static TRY_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// The number of nested [catch_internal_error] calls.
+    static CATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// The message of the last panic caught by [catch_internal_error], recorded by the hook.
+    static CAUGHT_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The payload used to unwind up to the closest [try_with] call.
+struct UsageError(Error);
+
+/// Report a misuse of rustc_public objects.
+fn session_error(msg: String) -> ! {
+    usage_error(Error::new(ErrorKind::Session, msg))
+}
+
+/// Report a request that cannot be fulfilled and that has no way to return an error, such as a
+/// misuse of rustc_public objects.
+///
+/// This returns an error from the closest [try_with] call if there is one, or panics otherwise.
+pub(crate) fn usage_error(error: Error) -> ! {
+    if TRY_DEPTH.get() > 0 {
+        panic::resume_unwind(Box::new(UsageError(error)))
+    } else {
+        panic!("{error}")
+    }
+}
+
+/// Execute the given function, and return an error if it tries to use the rustc_public context
+/// outside of a session, if it uses objects that belong to a different session, or if it makes
+/// a request that the backend cannot fulfill.
+pub(crate) fn try_with<R>(f: impl FnOnce() -> R) -> Result<R, Error> {
+    TRY_DEPTH.set(TRY_DEPTH.get() + 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    TRY_DEPTH.set(TRY_DEPTH.get() - 1);
+    result.map_err(|payload| match payload.downcast::<UsageError>() {
+        Ok(error) => error.0,
+        Err(payload) => panic::resume_unwind(payload),
+    })
+}
+
+/// Execute the given function, and return an error if the compiler panics while running it.
+///
+/// This allows tools to recover from internal compiler errors triggered by a single request.
+/// The tables remain consistent after a panic, since entries are only ever added to them, and a
+/// panic cannot interrupt the insertion of an entry.
+///
+/// The panic is not reported by the panic hook, since the error already describes it, including
+/// the location of the panic. See [install_panic_hook] for the side effects of this.
+/// Misuses of rustc_public objects are not caught, so they are still reported by [try_with].
+pub(crate) fn catch_internal_error<R>(f: impl FnOnce() -> R) -> Result<R, Error> {
+    install_panic_hook();
+    CAUGHT_PANIC.take();
+    CATCH_DEPTH.set(CATCH_DEPTH.get() + 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCH_DEPTH.set(CATCH_DEPTH.get() - 1);
+    result.map_err(|payload| {
+        if payload.is::<UsageError>() {
+            panic::resume_unwind(payload)
+        }
+        // Panics raised with `resume_unwind` bypass the hook, so fall back to the payload.
+        let message = CAUGHT_PANIC.take().unwrap_or_else(|| panic_message(&*payload).to_string());
+        Error::new(ErrorKind::Internal, format!("The compiler panicked: {message}"))
+    })
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown cause"
+    }
+}
+
+/// Wrap the panic hook, so the panics that are caught by [catch_internal_error] are recorded
+/// instead of reported.
+///
+/// The panic hook is global, and this wrapper stays installed for the rest of the process once
+/// the first request is caught. It forwards every panic that is raised outside of
+/// [catch_internal_error], or on another thread, to the previous hook. A hook installed
+/// afterwards replaces the wrapper, thus, it reports every panic, including the caught ones.
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if CATCH_DEPTH.try_with(Cell::get).unwrap_or(0) == 0 {
+                return hook(info);
+            }
+            let message = panic_message(info.payload());
+            let message = match info.location() {
+                Some(location) => format!("{message} at {location}"),
+                None => message.to_string(),
+            };
+            let _ = CAUGHT_PANIC.try_with(|caught| caught.replace(Some(message)));
+        }))
+    });
+}
+
 fn smir_crate<'tcx>(
     cx: &CompilerCtxt<'tcx, BridgeTys>,
     crate_num: rustc_span::def_id::CrateNum,
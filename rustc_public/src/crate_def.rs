@@ -1,14 +1,20 @@
 //! Module that define a common trait for things that represent a crate definition,
 //! such as, a function, a trait, an enum, and any other definitions.
 
-use serde::Serialize;
+use std::fmt;
 
-use crate::ty::{GenericArgs, Span, Ty};
-use crate::{AssocItems, Crate, Symbol, with};
+use serde::{Deserialize, Serialize};
+
+use crate::compiler_interface::SessionId;
+use crate::export::handle_serde;
+use crate::ty::{GenericArgs, Span, TraitDef, Ty};
+use crate::{AssocItems, Crate, CtorKind, Error, ErrorKind, Symbol, with};
 
 /// A unique identification number for each item accessible for the current compilation unit.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize)]
-pub struct DefId(pub(crate) usize);
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DefId(pub(crate) u32, pub(crate) SessionId);
+
+handle_serde!(DefId, Def);
 
 impl DefId {
     /// Return fully qualified name of this definition
@@ -29,6 +35,128 @@ impl DefId {
     pub fn trimmed_name(&self) -> Symbol {
         with(|cx| cx.def_name(*self, true))
     }
+
+    /// Return the identifier of this definition that is stable across compilations.
+    ///
+    /// See [`StableDefId`] for more details.
+    pub fn stable_id(&self) -> StableDefId {
+        with(|cx| cx.stable_def_id(*self))
+    }
+
+    /// Return the structured path of this definition, from its crate root.
+    pub fn def_path(&self) -> DefPath {
+        with(|cx| cx.def_path(*self))
+    }
+
+    /// Return the kind of this definition.
+    pub fn kind(&self) -> DefKind {
+        with(|cx| cx.def_kind(*self))
+    }
+}
+
+/// The kind of a definition.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum DefKind {
+    Mod,
+    Struct,
+    Union,
+    Enum,
+    Variant,
+    Trait,
+    TyAlias,
+    ForeignTy,
+    AssocTy,
+    Fn,
+    AssocFn,
+    Const,
+    AssocConst,
+    Static,
+    /// The constructor of a tuple or unit struct or variant.
+    Ctor(CtorKind),
+    /// A closure, a coroutine or an `async` block.
+    Closure,
+    Impl,
+    /// Any other definition, such as a generic parameter or a macro.
+    Other,
+}
+
+/// The path of a definition, which is composed of the crate that declares the definition, and
+/// of one segment for the definition and each of its parents, except the crate root.
+///
+/// For example, the path of `Vec::push` has the segments: `vec`, `impl<T, A> Vec<T, A>` and
+/// `push`, and it is declared in the `alloc` crate.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefPath {
+    pub krate: Crate,
+    pub segments: Vec<DefPathSegment>,
+}
+
+/// A segment of a definition path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefPathSegment {
+    pub data: DefPathData,
+    /// Distinguish the definitions of the same parent that would have the same segment
+    /// otherwise, e.g.: the closures in a function.
+    pub disambiguator: u32,
+}
+
+/// The definition that a path segment represents.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DefPathData {
+    /// A named definition, such as a module, a type, a function or an associated item.
+    Named(Symbol),
+    /// An `impl` block, with its self type, and its trait for trait impls.
+    Impl { self_ty: Ty, trait_def: Option<TraitDef> },
+    /// A closure, a coroutine or an `async` block.
+    Closure,
+    /// The constructor of a tuple or unit struct or variant.
+    Ctor,
+    /// An anonymous constant, such as an array length.
+    AnonConst,
+    /// Any other unnamed definition, such as an `extern` block or an opaque type, described by
+    /// its namespace, e.g.: `{foreign}`.
+    Other(Symbol),
+}
+
+/// An identifier of a definition that is stable across compilations.
+///
+/// Unlike [DefId], which is only valid in the compilation that created it, this identifier can
+/// be stored, and used to correlate definitions of different compilations, e.g.: between the
+/// library and the test builds of a crate, or between runs of a tool.
+///
+/// It is composed of the hash of the name of the crate that declares the definition, and of the
+/// hash of the definition path in that crate. Thus, it stays the same as long as the crate name
+/// and the definition path do, regardless of the flags that the crate is built with, e.g.: `--test`
+/// or `-C metadata`. Note that unnamed definitions, such as impls, are identified by their
+/// position among their siblings, so adding items under `#[cfg(test)]` may change their path.
+///
+/// If several crates with the same name are linked, such as different versions of a dependency,
+/// the definition is searched in all of them.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct StableDefId {
+    /// The hash of the name of the crate that declares the definition.
+    pub crate_id: u64,
+    /// The hash of the definition path in its crate.
+    pub path_hash: u64,
+}
+
+impl StableDefId {
+    /// Find the definition with this identifier in the ongoing compilation.
+    ///
+    /// An error with kind [ErrorKind::NotFound] is returned if no definition has this
+    /// identifier, e.g.: if the definition was removed or renamed.
+    pub fn def_id(&self) -> Result<DefId, Error> {
+        with(|cx| cx.find_stable_def_id(*self)).ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, format!("Cannot find definition with id `{self}`"))
+        })
+    }
+}
+
+impl fmt::Display for StableDefId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}{:016x}", self.crate_id, self.path_hash)
+    }
 }
 
 /// A trait for retrieving information about a particular definition.
@@ -53,6 +181,20 @@ pub trait CrateDef {
         self.def_id().trimmed_name()
     }
 
+    /// Return the identifier of this definition that is stable across compilations.
+    ///
+    /// See [`StableDefId`] for more details.
+    fn stable_id(&self) -> StableDefId {
+        self.def_id().stable_id()
+    }
+
+    /// Return the structured path of this definition.
+    ///
+    /// See [`DefId::def_path`] for more details
+    fn def_path(&self) -> DefPath {
+        self.def_id().def_path()
+    }
+
     /// Return information about the crate where this definition is declared.
     ///
     /// This will return the crate number and its name.
@@ -0,0 +1,186 @@
+//! Module that allows tools to report their findings using the compiler's diagnostic machinery.
+//!
+//! Diagnostics are emitted through the compiler session, thus, they are rendered according to
+//! the options provided to the compiler, such as `--error-format=json`.
+//! Emitting a diagnostic with level [Level::Error] will cause the compilation to fail.
+//!
+//! ```ignore(needs-extern-crate)
+//! use rustc_public::diagnostics::Diagnostic;
+//!
+//! Diagnostic::error("this function should not be called")
+//!     .span(call_span)
+//!     .label(fn_span, "function defined here")
+//!     .note("calls to this function are forbidden by the analysis")
+//!     .emit();
+//! ```
+
+use serde::Serialize;
+
+use crate::Filename;
+use crate::compiler_interface::with;
+use crate::ty::{LineInfo, Span};
+
+/// The severity of a diagnostic.
+///
+/// Tools cannot report internal compiler errors nor abort the compilation, thus, diagnostics
+/// emitted with [Level::Bug] or [Level::Fatal] are reported as errors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum Level {
+    /// An internal compiler error (ICE).
+    Bug,
+    /// An error that aborts the compilation immediately.
+    Fatal,
+    /// An error that will cause the compilation to fail.
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// Indicates how confident we are that a suggestion is correct.
+///
+/// This mirrors the information used by tools, such as `rustfix`, to decide whether a suggestion
+/// can be automatically applied.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended, and it can be applied automatically.
+    MachineApplicable,
+    /// The suggestion may be what the user intended, but it is uncertain.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders that the user must fill in.
+    HasPlaceholders,
+    /// The applicability of the suggestion is unknown.
+    Unspecified,
+}
+
+/// A suggestion to replace the source code in a given span.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Suggestion {
+    pub span: Span,
+    pub message: String,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+/// A diagnostic that can be emitted by the compiler on behalf of a tool.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    /// The primary span of this diagnostic, if any.
+    pub span: Option<Span>,
+    /// Secondary spans with their respective labels.
+    pub labels: Vec<(Span, String)>,
+    pub notes: Vec<String>,
+    pub helps: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    /// Create a new diagnostic with the given level and message.
+    pub fn new(level: Level, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            level,
+            message: message.into(),
+            span: None,
+            labels: vec![],
+            notes: vec![],
+            helps: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    /// Create a new error diagnostic.
+    pub fn error(message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(Level::Error, message)
+    }
+
+    /// Create a new warning diagnostic.
+    pub fn warning(message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(Level::Warning, message)
+    }
+
+    /// Set the primary span of this diagnostic.
+    pub fn span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Add a secondary span with a label.
+    pub fn label(mut self, span: Span, label: impl Into<String>) -> Self {
+        self.labels.push((span, label.into()));
+        self
+    }
+
+    /// Add a note to this diagnostic.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Add a help message to this diagnostic.
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.helps.push(help.into());
+        self
+    }
+
+    /// Suggest replacing the source code in the given span.
+    pub fn suggestion(
+        mut self,
+        span: Span,
+        message: impl Into<String>,
+        replacement: impl Into<String>,
+        applicability: Applicability,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            message: message.into(),
+            replacement: replacement.into(),
+            applicability,
+        });
+        self
+    }
+
+    /// Emit this diagnostic through the compiler session.
+    pub fn emit(self) {
+        with(|cx| cx.emit_diagnostic(&self))
+    }
+}
+
+/// A diagnostic that was emitted by the compiler.
+///
+/// See [Driver::run_with_diagnostics](crate::driver::Driver::run_with_diagnostics) for how to
+/// collect them.
+///
+/// Unlike [Diagnostic], it doesn't store any [Span], since spans cannot be used once the
+/// compilation is over. Its location is resolved when the diagnostic is emitted instead.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct EmittedDiagnostic {
+    pub level: Level,
+    /// The error code, e.g.: `E0308`, if any.
+    pub code: Option<String>,
+    pub message: String,
+    /// The location of the primary span, if any.
+    pub location: Option<DiagnosticLocation>,
+    /// Sub-diagnostics, such as notes and help messages.
+    pub children: Vec<EmittedDiagnostic>,
+}
+
+impl EmittedDiagnostic {
+    /// Whether this diagnostic reports an internal compiler error.
+    pub fn is_ice(&self) -> bool {
+        self.level == Level::Bug
+    }
+
+    /// Whether this diagnostic causes the compilation to fail.
+    pub fn is_error(&self) -> bool {
+        matches!(self.level, Level::Bug | Level::Fatal | Level::Error)
+    }
+}
+
+/// The source location of an emitted diagnostic.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct DiagnosticLocation {
+    pub filename: Filename,
+    pub lines: LineInfo,
+}
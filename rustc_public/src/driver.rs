@@ -0,0 +1,558 @@
+//! Instantiate and run the Rust compiler with rustc_public callbacks.
+//!
+//! The [Driver] is a builder that collects the compiler arguments and the callbacks that should
+//! be invoked during the compilation. E.g.:
+//!
+//! ```ignore(needs-extern-crate)
+//! # use std::ops::ControlFlow;
+//! # use rustc_public::driver::{CompilerPhase, Driver};
+//! let result = Driver::new(std::env::args())
+//!     .unstable_flag("always-encode-mir")
+//!     .callback(CompilerPhase::Analysis, || {
+//!         for item in rustc_public::all_local_items() {
+//!             println!("{}", item.name());
+//!         }
+//!         ControlFlow::<()>::Continue(())
+//!     })
+//!     .run();
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use rustc_builtin_macros::cmdline_attrs;
+use rustc_driver::{Callbacks, Compilation, run_compiler};
+use rustc_interface::interface;
+use rustc_middle::ty::TyCtxt;
+use rustc_public_bridge::context::CompilerCtxt;
+use rustc_public_bridge::{Container, Tables};
+use rustc_session::config::Input;
+use rustc_span::source_map::RealFileLoader;
+use rustc_span::{FileName, RealFileName, Symbol, sym};
+
+use crate::compiler_interface::{BridgeTys, SessionId};
+use crate::diagnostics::{EmittedDiagnostic, Level};
+use crate::lint::Lint;
+use crate::{CompilerError, Error};
+
+mod capture;
+mod codegen;
+mod source;
+
+/// The phases of a compilation after which a rustc_public callback can be invoked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CompilerPhase {
+    /// After the crate root has been parsed. Submodules have not been parsed yet.
+    ///
+    /// The type context hasn't been created at this point, thus, rustc_public APIs cannot be
+    /// used by callbacks invoked in this phase.
+    CrateRootParsing,
+    /// After macro expansion and name resolution.
+    Expansion,
+    /// After the compiler ran all its analyses, but before code generation.
+    Analysis,
+    /// After code generation, but before linking.
+    ///
+    /// Interrupting the compilation in this phase has no effect on linking.
+    Codegen,
+}
+
+/// A callback registered for one phase of the compilation.
+///
+/// The type context is `None` for [CompilerPhase::CrateRootParsing].
+type PhaseCallback<'a, B, C> =
+    Box<dyn for<'tcx> FnOnce(Option<TyCtxt<'tcx>>) -> ControlFlow<B, C> + Send + 'a>;
+
+/// Instantiate and run the compiler with the provided arguments and callbacks.
+///
+/// The callbacks are invoked in the order of the compilation phases, and in the order they were
+/// registered for the same phase. They all share the same rustc_public context, thus, items
+/// retrieved in one phase can be used in the following ones.
+///
+/// A process may run several compilations one after another, each of them with its own
+/// rustc_public context. Items retrieved during one compilation cannot be used once it is over,
+/// so callbacks should return owned data, e.g.: item names, instead of rustc_public objects.
+pub struct Driver<'a, B = (), C = ()> {
+    args: Vec<String>,
+    unstable_flags: Vec<String>,
+    sysroot: Option<PathBuf>,
+    source: Option<(PathBuf, String)>,
+    virtual_files: HashMap<PathBuf, String>,
+    continue_compilation: bool,
+    lints: Vec<&'static Lint>,
+    diagnostics: Option<capture::Diagnostics>,
+    callbacks: Vec<(CompilerPhase, PhaseCallback<'a, B, C>)>,
+    state: Arc<Mutex<PhaseState<B, C>>>,
+}
+
+impl<'a, B, C> Driver<'a, B, C>
+where
+    B: Send,
+    C: Send,
+{
+    /// Create a driver with the given compiler arguments.
+    ///
+    /// Similar to `std::env::args()`, the first argument is the name of the binary, and it
+    /// is ignored by the compiler.
+    pub fn new<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Driver {
+            args: args.into_iter().map(Into::into).collect(),
+            unstable_flags: vec![],
+            sysroot: None,
+            source: None,
+            virtual_files: HashMap::new(),
+            continue_compilation: true,
+            lints: vec![],
+            diagnostics: None,
+            callbacks: vec![],
+            state: Arc::new(Mutex::new(PhaseState::new())),
+        }
+    }
+
+    /// Add an unstable compiler flag, which is passed as `-Z<flag>`. E.g.: `mir-opt-level=0`.
+    pub fn unstable_flag(mut self, flag: impl Into<String>) -> Self {
+        self.unstable_flags.push(flag.into());
+        self
+    }
+
+    /// Override the system root, including any `--sysroot` given in the arguments.
+    pub fn sysroot(mut self, sysroot: impl Into<PathBuf>) -> Self {
+        self.sysroot = Some(sysroot.into());
+        self
+    }
+
+    /// Compile the given source code instead of a file on disk.
+    ///
+    /// The path is used as the name of the crate root file, which is also used to look up the
+    /// crate modules. It is added to the compiler arguments, which should not contain any
+    /// other input file.
+    pub fn source(mut self, path: impl Into<PathBuf>, source: impl Into<String>) -> Self {
+        self.source = Some((path.into(), source.into()));
+        self
+    }
+
+    /// Provide the contents of a file, which the compiler reads instead of the file on disk.
+    ///
+    /// E.g.: This can be used to provide the modules of a crate given with [Driver::source].
+    pub fn virtual_file(mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        self.virtual_files.insert(path.into(), contents.into());
+        self
+    }
+
+    /// Whether the compilation should continue once every callback returned
+    /// `ControlFlow::Continue`. This is `true` by default.
+    pub fn continue_compilation(mut self, continue_compilation: bool) -> Self {
+        self.continue_compilation = continue_compilation;
+        self
+    }
+
+    /// Register a tool lint, so its level can be controlled by command line flags and
+    /// lint attributes.
+    ///
+    /// The lint tool is automatically registered in the crate being compiled.
+    pub fn lint(mut self, lint: &'static Lint) -> Self {
+        self.lints.push(lint);
+        self
+    }
+
+    /// Register a callback to be invoked after the given compilation phase.
+    pub fn callback<F>(mut self, phase: CompilerPhase, callback: F) -> Self
+    where
+        F: FnOnce() -> ControlFlow<B, C> + Send + 'a,
+    {
+        self.callbacks.push((phase, Box::new(move |_| callback())));
+        self
+    }
+
+    /// Register a callback that takes the compiler's `TyCtxt`, which can be used to invoke
+    /// internal APIs.
+    ///
+    /// # Panics
+    ///
+    /// The callback panics if registered for [CompilerPhase::CrateRootParsing].
+    #[cfg(feature = "rustc_internal")]
+    pub fn callback_with_tcx<F>(mut self, phase: CompilerPhase, callback: F) -> Self
+    where
+        F: for<'tcx> FnOnce(TyCtxt<'tcx>) -> ControlFlow<B, C> + Send + 'a,
+    {
+        self.callbacks.push((
+            phase,
+            Box::new(move |tcx| {
+                callback(tcx.expect("The type context is not available in this phase"))
+            }),
+        ));
+        self
+    }
+
+    /// Run the compiler and invoke the registered callbacks.
+    ///
+    /// The compilation stops as soon as one callback returns `ControlFlow::Break`, and this
+    /// returns `Err(CompilerError::Interrupted)` with its value.
+    /// Otherwise, it returns the value of the last callback.
+    /// If the compilation finishes before every callback was invoked, e.g.: because
+    /// code generation was skipped, this returns `Err(CompilerError::Skipped)`.
+    ///
+    /// Use [Driver::run_with_diagnostics] to find out why a compilation failed.
+    pub fn run(mut self) -> Result<C, CompilerError<B>> {
+        let args = self.compiler_args();
+        self.state.lock().unwrap().pending = self.callbacks.len();
+        let compiler_result = rustc_driver::catch_fatal_errors(|| -> interface::Result<()> {
+            run_compiler(&args, &mut self);
+            Ok(())
+        });
+        match (compiler_result, self.state.lock().unwrap().result()) {
+            (Ok(Ok(())), Some(ControlFlow::Continue(value))) => Ok(value),
+            (Ok(Ok(())), Some(ControlFlow::Break(value))) => Err(CompilerError::Interrupted(value)),
+            (Ok(Ok(_)), None) => Err(CompilerError::Skipped),
+            // Two cases here:
+            // - `run` finished normally and returned `Err`
+            // - `run` panicked with `FatalErr`
+            // You might think that normal compile errors cause the former, and
+            // ICEs cause the latter. But some normal compiler errors also cause
+            // the latter. So we can't meaningfully distinguish them, and group
+            // them together.
+            (Ok(Err(_)), _) | (Err(_), _) => Err(CompilerError::Failed),
+        }
+    }
+
+    /// Run the compiler like [Driver::run], and collect every diagnostic emitted by the compiler.
+    ///
+    /// The diagnostics are still rendered as usual.
+    /// Internal compiler errors are reported as diagnostics with level [Level::Bug],
+    /// including any panic that happens during the compilation.
+    pub fn run_with_diagnostics(mut self) -> CompilationResult<B, C> {
+        let diagnostics = capture::Diagnostics::default();
+        self.diagnostics = Some(diagnostics.clone());
+        let result = panic::catch_unwind(AssertUnwindSafe(move || self.run()));
+        let mut diagnostics = mem::take(&mut *diagnostics.lock().unwrap());
+        let result = result.unwrap_or_else(|payload| {
+            // Bugs reported by the compiler have already been emitted before unwinding.
+            if !diagnostics.iter().any(EmittedDiagnostic::is_ice) {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|msg| msg.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "the compiler panicked".to_string());
+                diagnostics.push(EmittedDiagnostic {
+                    level: Level::Bug,
+                    code: None,
+                    message,
+                    location: None,
+                    children: vec![],
+                });
+            }
+            Err(CompilerError::Failed)
+        });
+        CompilationResult { result, diagnostics }
+    }
+
+    /// The arguments given to the compiler, including the driver options.
+    fn compiler_args(&self) -> Vec<String> {
+        let mut args = Vec::with_capacity(self.args.len() + self.unstable_flags.len() + 1);
+        let mut iter = self.args.iter();
+        while let Some(arg) = iter.next() {
+            if self.sysroot.is_some() && arg.starts_with("--sysroot") {
+                if arg == "--sysroot" {
+                    // Skip the value as well.
+                    iter.next();
+                }
+                continue;
+            }
+            args.push(arg.clone());
+        }
+        args.extend(self.unstable_flags.iter().map(|flag| format!("-Z{flag}")));
+        if let Some(sysroot) = &self.sysroot {
+            args.push(format!("--sysroot={}", sysroot.display()));
+        }
+        if let Some((path, _)) = &self.source {
+            args.push(path.display().to_string());
+        }
+        args
+    }
+
+    /// Invoke all the callbacks registered for the given phase.
+    fn invoke(&mut self, phase: CompilerPhase, tcx: Option<TyCtxt<'_>>) -> Compilation {
+        let mut compilation = Compilation::Continue;
+        while compilation == Compilation::Continue
+            && let Some(callback) = self.take_callback(phase)
+        {
+            compilation = self.state.lock().unwrap().invoke(tcx, callback);
+        }
+        // The callbacks of later phases may have been moved to hooks already, so we check the
+        // callbacks that haven't been invoked instead of the registered ones.
+        if compilation == Compilation::Continue
+            && self.state.lock().unwrap().pending == 0
+            && !self.continue_compilation
+        {
+            Compilation::Stop
+        } else {
+            compilation
+        }
+    }
+
+    /// Tool lints can only be used in lint attributes and flags if their tool is registered.
+    /// Inject `#![register_tool(..)]` for every lint tool that the crate doesn't register already.
+    fn register_lint_tools(&self, compiler: &interface::Compiler, krate: &mut rustc_ast::Crate) {
+        let attr_list = |name: Symbol| {
+            krate
+                .attrs
+                .iter()
+                .filter(move |attr| attr.has_name(name))
+                .flat_map(|attr| attr.meta_item_list().unwrap_or_default())
+                .filter_map(|item| item.ident())
+                .map(|ident| ident.name)
+        };
+        let registered: Vec<_> = attr_list(sym::register_tool).collect();
+        let mut attrs = vec![];
+        for lint in &self.lints {
+            let tool = lint.tool();
+            let attr = format!("register_tool({tool})");
+            if !registered.contains(&Symbol::intern(tool)) && !attrs.contains(&attr) {
+                attrs.push(attr);
+            }
+        }
+        if !attrs.is_empty() && !attr_list(sym::feature).any(|name| name == sym::register_tool) {
+            attrs.insert(0, "feature(register_tool)".to_string());
+        }
+        cmdline_attrs::inject(krate, &compiler.sess.psess, &attrs);
+    }
+
+    fn take_callback(&mut self, phase: CompilerPhase) -> Option<PhaseCallback<'a, B, C>> {
+        let idx = self.callbacks.iter().position(|(registered, _)| *registered == phase)?;
+        Some(self.callbacks.remove(idx).1)
+    }
+}
+
+impl<'a, B, C> Callbacks for Driver<'a, B, C>
+where
+    B: Send,
+    C: Send,
+{
+    /// Set up the in-memory sources, the diagnostics capture and the tool lints if requested.
+    /// Also install the callbacks that are invoked after code generation if there are any.
+    fn config(&mut self, config: &mut interface::Config) {
+        if let Some((path, input)) = self.source.take() {
+            let name = FileName::Real(RealFileName::LocalPath(path));
+            config.input = Input::Str { name, input };
+        }
+        if !self.virtual_files.is_empty() {
+            let fallback = config.file_loader.take().unwrap_or_else(|| Box::new(RealFileLoader));
+            let files = mem::take(&mut self.virtual_files);
+            config.file_loader = Some(Box::new(source::VirtualFileLoader { files, fallback }));
+        }
+        if let Some(diagnostics) = &self.diagnostics {
+            capture::install_capture(config, diagnostics.clone());
+        }
+        if !self.lints.is_empty() {
+            let lints: Vec<_> = self.lints.iter().map(|lint| lint.internal()).collect();
+            let previous = config.register_lints.take();
+            config.register_lints = Some(Box::new(move |sess, store| {
+                if let Some(previous) = &previous {
+                    previous(sess, store);
+                }
+                store.register_lints(&lints);
+            }));
+        }
+        let mut callbacks = vec![];
+        while let Some(callback) = self.take_callback(CompilerPhase::Codegen) {
+            callbacks.push(callback);
+        }
+        if !callbacks.is_empty() {
+            let state = self.state.clone();
+            let hook = Box::new(move |tcx: TyCtxt<'_>| {
+                for callback in callbacks.drain(..) {
+                    if state.lock().unwrap().invoke(Some(tcx), callback) == Compilation::Stop {
+                        break;
+                    }
+                }
+            });
+            // SAFETY: The compilation finishes before `run` returns.
+            unsafe { codegen::install_after_codegen(config, hook) }
+        }
+    }
+
+    fn after_crate_root_parsing(
+        &mut self,
+        compiler: &interface::Compiler,
+        krate: &mut rustc_ast::Crate,
+    ) -> Compilation {
+        self.register_lint_tools(compiler, krate);
+        self.invoke(CompilerPhase::CrateRootParsing, None)
+    }
+
+    fn after_expansion<'tcx>(
+        &mut self,
+        _compiler: &interface::Compiler,
+        tcx: TyCtxt<'tcx>,
+    ) -> Compilation {
+        self.invoke(CompilerPhase::Expansion, Some(tcx))
+    }
+
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &interface::Compiler,
+        tcx: TyCtxt<'tcx>,
+    ) -> Compilation {
+        self.invoke(CompilerPhase::Analysis, Some(tcx))
+    }
+}
+
+/// Compile the given source code and invoke the callback after the compiler analyses.
+///
+/// The crate root is named after the crate, e.g.: `my_crate.rs`, which is the file name returned
+/// by [Span::get_filename](crate::ty::Span::get_filename).
+/// The arguments are passed to the compiler, and they should not include the name of the binary
+/// nor any input file. The compilation stops once the callback returns, so no artifact is
+/// generated.
+///
+/// ```ignore(needs-extern-crate)
+/// # use std::ops::ControlFlow;
+/// let result = rustc_public::run_on_source("pub fn foo() {}", "foo", ["--crate-type=lib"], || {
+///     let items = rustc_public::all_local_items();
+///     ControlFlow::<(), _>::Continue(items.len())
+/// });
+/// assert_eq!(result, Ok(1));
+/// ```
+pub fn run_on_source<B, C, F, I, S>(
+    source: impl Into<String>,
+    crate_name: &str,
+    args: I,
+    callback: F,
+) -> Result<C, CompilerError<B>>
+where
+    B: Send,
+    C: Send,
+    F: FnOnce() -> ControlFlow<B, C> + Send,
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut compiler_args = vec!["rustc".to_string(), format!("--crate-name={crate_name}")];
+    compiler_args.extend(args.into_iter().map(Into::into));
+    Driver::new(compiler_args)
+        .source(format!("{crate_name}.rs"), source)
+        .continue_compilation(false)
+        .callback(CompilerPhase::Analysis, callback)
+        .run()
+}
+
+/// The result of a compilation, together with the diagnostics emitted by the compiler.
+#[derive(Debug)]
+pub struct CompilationResult<B, C> {
+    pub result: Result<C, CompilerError<B>>,
+    pub diagnostics: Vec<EmittedDiagnostic>,
+}
+
+impl<B, C> CompilationResult<B, C> {
+    /// Whether the compiler hit an internal compiler error.
+    pub fn is_ice(&self) -> bool {
+        self.diagnostics.iter().any(EmittedDiagnostic::is_ice)
+    }
+
+    /// The diagnostics that caused the compilation to fail.
+    pub fn errors(&self) -> impl Iterator<Item = &EmittedDiagnostic> {
+        self.diagnostics.iter().filter(|diag| diag.is_error())
+    }
+}
+
+/// Keep track of the rustc_public state across the phases of a compilation.
+///
+/// The tables are shared by every phase that has a type context, so items retrieved by a
+/// callback can still be used by the callbacks of the following phases.
+struct PhaseState<B, C> {
+    /// The tables of the ongoing compilation.
+    ///
+    /// The type context lifetime is erased in between phases. They are only used while the
+    /// type context they were created with is still alive.
+    tables: Option<Tables<'static, BridgeTys>>,
+    /// The rustc_public session of this compilation.
+    session: SessionId,
+    /// The result of the last callback invoked.
+    result: Option<ControlFlow<B, C>>,
+    /// The number of registered callbacks that haven't been invoked yet.
+    pending: usize,
+}
+
+impl<B, C> PhaseState<B, C> {
+    fn new() -> Self {
+        PhaseState { tables: None, session: SessionId::fresh(), result: None, pending: 0 }
+    }
+
+    /// Invoke a callback registered for the current phase.
+    ///
+    /// Return whether the compilation should continue.
+    fn invoke(
+        &mut self,
+        tcx: Option<TyCtxt<'_>>,
+        callback: PhaseCallback<'_, B, C>,
+    ) -> Compilation {
+        self.pending -= 1;
+        let result = match tcx {
+            Some(tcx) => self.run(tcx, || callback(Some(tcx))).unwrap(),
+            None => callback(None),
+        };
+        let compilation =
+            if result.is_continue() { Compilation::Continue } else { Compilation::Stop };
+        self.result = Some(result);
+        compilation
+    }
+
+    /// The aggregated result of all the callbacks.
+    ///
+    /// This is the result of the first callback that interrupted the compilation, or the result
+    /// of the last callback if every one of them was invoked. Otherwise, return `None`.
+    fn result(&mut self) -> Option<ControlFlow<B, C>> {
+        self.tables = None;
+        let interrupted = matches!(self.result, Some(ControlFlow::Break(_)));
+        if self.pending > 0 && !interrupted { None } else { self.result.take() }
+    }
+
+    fn run<'tcx, F, T>(&mut self, tcx: TyCtxt<'tcx>, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> T,
+    {
+        // SAFETY: All phases of a compilation share the same type context, which outlives
+        // the compilation callbacks.
+        let tables = self
+            .tables
+            .take()
+            .map(|tables| unsafe {
+                mem::transmute::<Tables<'static, BridgeTys>, Tables<'tcx, BridgeTys>>(tables)
+            })
+            .unwrap_or_default();
+        let container =
+            Container { tables: RefCell::new(tables), cx: RefCell::new(CompilerCtxt::new(tcx)) };
+        let result = run_with_container(&container, self.session, f);
+        let tables = container.tables.into_inner();
+        // SAFETY: See above. The tables are dropped once the compilation is over.
+        self.tables = Some(unsafe {
+            mem::transmute::<Tables<'tcx, BridgeTys>, Tables<'static, BridgeTys>>(tables)
+        });
+        result
+    }
+}
+
+/// Run the given function with the rustc_public context set up.
+fn run_with_container<F, T>(
+    container: &Container<'_, BridgeTys>,
+    session: SessionId,
+    f: F,
+) -> Result<T, Error>
+where
+    F: FnOnce() -> T,
+{
+    #[cfg(feature = "rustc_internal")]
+    return crate::compiler_interface::run(container, session, || {
+        crate::rustc_internal::init(container, f)
+    });
+    #[cfg(not(feature = "rustc_internal"))]
+    crate::compiler_interface::run(container, session, f)
+}
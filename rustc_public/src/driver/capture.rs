@@ -0,0 +1,194 @@
+//! Emitter used to collect the diagnostics emitted during a compilation.
+//!
+//! The compiler doesn't allow us to retrieve the emitter it created for the session. Instead, we
+//! replace it with an emitter that records every diagnostic before forwarding it to an emitter
+//! created the same way the compiler would create it.
+
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rustc_errors::emitter::{
+    DynEmitter, Emitter, HumanEmitter, HumanReadableErrorType, OutputTheme, TimingEvent,
+    stderr_destination,
+};
+use rustc_errors::json::JsonEmitter;
+use rustc_errors::registry::Registry;
+use rustc_errors::timings::TimingRecord;
+use rustc_errors::translation::{Translator, to_fluent_args};
+use rustc_errors::{DiagInner, DiagMessage, MultiSpan, Style};
+use rustc_interface::interface;
+use rustc_session::config::{self, ErrorOutputType};
+use rustc_session::lint;
+use rustc_span::source_map::SourceMap;
+use rustc_span::{FileNameDisplayPreference, Span};
+
+use crate::diagnostics::{DiagnosticLocation, EmittedDiagnostic, Level};
+use crate::ty::LineInfo;
+
+/// The diagnostics collected during a compilation.
+pub(crate) type Diagnostics = Arc<Mutex<Vec<EmittedDiagnostic>>>;
+
+/// Record every diagnostic emitted during the compilation started with this configuration.
+pub(crate) fn install_capture(config: &mut interface::Config, diagnostics: Diagnostics) {
+    let opts = config.opts.clone();
+    let resources = config.locale_resources.clone();
+    let psess_created = config.psess_created.take();
+    config.psess_created = Some(Box::new(move |psess| {
+        if let Some(psess_created) = psess_created {
+            psess_created(psess);
+        }
+        let translator = Translator::with_fallback_bundle(
+            resources,
+            opts.unstable_opts.translate_directionality_markers,
+        );
+        let source_map = psess.clone_source_map();
+        let inner = default_emitter(&opts, source_map.clone(), translator);
+        psess.dcx().set_emitter(Box::new(CaptureEmitter { inner, source_map, diagnostics }));
+    }));
+}
+
+/// Create an emitter according to the error format options given to the compiler.
+fn default_emitter(
+    opts: &config::Options,
+    source_map: Arc<SourceMap>,
+    translator: Translator,
+) -> Box<DynEmitter> {
+    let source_map = if opts.unstable_opts.link_only { None } else { Some(source_map) };
+    let macro_backtrace = opts.unstable_opts.macro_backtrace;
+    let track_diagnostics = opts.unstable_opts.track_diagnostics;
+    let ignored_directories =
+        opts.unstable_opts.ignore_directory_in_diagnostics_source_blocks.clone();
+    match opts.error_format {
+        ErrorOutputType::HumanReadable { kind, color_config } => {
+            let theme = if let HumanReadableErrorType::Unicode = kind {
+                OutputTheme::Unicode
+            } else {
+                OutputTheme::Ascii
+            };
+            let emitter = HumanEmitter::new(stderr_destination(color_config), translator)
+                .sm(source_map)
+                .short_message(kind.short())
+                .diagnostic_width(opts.diagnostic_width)
+                .macro_backtrace(macro_backtrace)
+                .track_diagnostics(track_diagnostics)
+                .theme(theme)
+                .ignored_directories_in_source_blocks(ignored_directories);
+            Box::new(emitter.ui_testing(opts.unstable_opts.ui_testing))
+        }
+        ErrorOutputType::Json { pretty, json_rendered, color_config } => Box::new(
+            JsonEmitter::new(
+                Box::new(io::BufWriter::new(io::stderr())),
+                source_map,
+                translator,
+                pretty,
+                json_rendered,
+                color_config,
+            )
+            .ui_testing(opts.unstable_opts.ui_testing)
+            .ignored_directories_in_source_blocks(ignored_directories)
+            .diagnostic_width(opts.diagnostic_width)
+            .macro_backtrace(macro_backtrace)
+            .track_diagnostics(track_diagnostics),
+        ),
+    }
+}
+
+struct CaptureEmitter {
+    inner: Box<DynEmitter>,
+    source_map: Arc<SourceMap>,
+    diagnostics: Diagnostics,
+}
+
+impl CaptureEmitter {
+    fn convert(&self, diag: &DiagInner) -> EmittedDiagnostic {
+        let args = to_fluent_args(diag.args.iter());
+        let translate = |messages: &[(DiagMessage, Style)]| {
+            self.translator().translate_messages(messages, &args).into_owned()
+        };
+        let children = diag
+            .children
+            .iter()
+            .map(|child| EmittedDiagnostic {
+                level: convert_level(child.level),
+                code: None,
+                message: translate(&child.messages),
+                location: self.location(&child.span),
+                children: vec![],
+            })
+            .collect();
+        EmittedDiagnostic {
+            level: convert_level(diag.level()),
+            code: diag.code.map(|code| code.to_string()),
+            message: translate(&diag.messages),
+            location: self.location(&diag.span),
+            children,
+        }
+    }
+
+    fn location(&self, span: &MultiSpan) -> Option<DiagnosticLocation> {
+        let span: Span = span.primary_span().filter(|span| !span.is_dummy())?;
+        let (_, start_line, start_col, end_line, end_col) =
+            self.source_map.span_to_location_info(span);
+        Some(DiagnosticLocation {
+            filename: self
+                .source_map
+                .span_to_filename(span)
+                .display(FileNameDisplayPreference::Local)
+                .to_string(),
+            lines: LineInfo::from((start_line, start_col, end_line, end_col)),
+        })
+    }
+}
+
+fn convert_level(level: rustc_errors::Level) -> Level {
+    use rustc_errors::Level::*;
+    match level {
+        Bug | DelayedBug => Level::Bug,
+        Fatal => Level::Fatal,
+        Error => Level::Error,
+        ForceWarning | Warning => Level::Warning,
+        Note | OnceNote | FailureNote | Allow | Expect => Level::Note,
+        Help | OnceHelp => Level::Help,
+    }
+}
+
+impl Emitter for CaptureEmitter {
+    fn emit_diagnostic(&mut self, diag: DiagInner, registry: &Registry) {
+        let emitted = self.convert(&diag);
+        self.diagnostics.lock().unwrap().push(emitted);
+        self.inner.emit_diagnostic(diag, registry);
+    }
+
+    fn emit_artifact_notification(&mut self, path: &Path, artifact_type: &str) {
+        self.inner.emit_artifact_notification(path, artifact_type);
+    }
+
+    fn emit_timing_section(&mut self, record: TimingRecord, event: TimingEvent) {
+        self.inner.emit_timing_section(record, event);
+    }
+
+    fn emit_future_breakage_report(&mut self, diags: Vec<DiagInner>, registry: &Registry) {
+        self.inner.emit_future_breakage_report(diags, registry);
+    }
+
+    fn emit_unused_externs(&mut self, lint_level: lint::Level, unused_externs: &[&str]) {
+        self.inner.emit_unused_externs(lint_level, unused_externs);
+    }
+
+    fn should_show_explain(&self) -> bool {
+        self.inner.should_show_explain()
+    }
+
+    fn supports_color(&self) -> bool {
+        self.inner.supports_color()
+    }
+
+    fn source_map(&self) -> Option<&SourceMap> {
+        self.inner.source_map()
+    }
+
+    fn translator(&self) -> &Translator {
+        self.inner.translator()
+    }
+}
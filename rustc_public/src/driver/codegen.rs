@@ -0,0 +1,122 @@
+//! Code generation backend used to invoke rustc_public callbacks after code generation.
+//!
+//! The rustc driver doesn't provide a callback for this phase. Instead, we wrap the codegen
+//! backend selected by the user and invoke a hook once the wrapped backend has generated the
+//! code for the crate, while the type context is still alive.
+
+use std::any::Any;
+use std::mem;
+use std::sync::Mutex;
+
+use rustc_codegen_ssa::traits::CodegenBackend;
+use rustc_codegen_ssa::{CodegenResults, TargetConfig};
+use rustc_data_structures::fx::FxIndexMap;
+use rustc_interface::interface;
+use rustc_metadata::EncodedMetadata;
+use rustc_metadata::creader::MetadataLoaderDyn;
+use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
+use rustc_middle::ty::TyCtxt;
+use rustc_middle::util::Providers;
+use rustc_session::config::{self, OutputFilenames, PrintRequest};
+use rustc_session::{EarlyDiagCtxt, Session};
+
+/// A hook invoked right after code generation.
+type Hook<'a> = Box<dyn for<'tcx> FnMut(TyCtxt<'tcx>) + Send + 'a>;
+
+/// Install a codegen backend that invokes `hook` after the crate has been code generated.
+///
+/// The backend that would have been used otherwise is still in charge of generating code.
+///
+/// # Safety
+///
+/// The compiler keeps the backend alive until the end of the compilation. The caller must
+/// ensure that `hook` outlives the compilation started with this configuration.
+pub(crate) unsafe fn install_after_codegen<'a>(config: &mut interface::Config, hook: Hook<'a>) {
+    // SAFETY: The hook is dropped together with the backend, which the caller guarantees
+    // happens before anything borrowed by the hook goes out of scope.
+    let hook: Hook<'static> = unsafe { mem::transmute(hook) };
+    let make_backend = config.make_codegen_backend.take();
+    config.make_codegen_backend = Some(Box::new(move |opts: &config::Options| {
+        let backend = match make_backend {
+            Some(make_backend) => make_backend(opts),
+            None => default_backend(opts),
+        };
+        Box::new(AfterCodegen { backend, hook: Mutex::new(hook) })
+    }));
+}
+
+/// Load the backend the compiler would have picked without our wrapper.
+fn default_backend(opts: &config::Options) -> Box<dyn CodegenBackend> {
+    let early_dcx = EarlyDiagCtxt::new(opts.error_format);
+    let target = config::build_target_config(&early_dcx, &opts.target_triple, opts.sysroot.path());
+    rustc_interface::util::get_codegen_backend(
+        &early_dcx,
+        &opts.sysroot,
+        opts.unstable_opts.codegen_backend.as_deref(),
+        &target,
+    )
+}
+
+struct AfterCodegen {
+    backend: Box<dyn CodegenBackend>,
+    hook: Mutex<Hook<'static>>,
+}
+
+impl CodegenBackend for AfterCodegen {
+    fn locale_resource(&self) -> &'static str {
+        self.backend.locale_resource()
+    }
+
+    fn init(&self, sess: &Session) {
+        self.backend.init(sess)
+    }
+
+    fn print(&self, req: &PrintRequest, out: &mut String, sess: &Session) {
+        self.backend.print(req, out, sess)
+    }
+
+    fn target_config(&self, sess: &Session) -> TargetConfig {
+        self.backend.target_config(sess)
+    }
+
+    fn print_passes(&self) {
+        self.backend.print_passes()
+    }
+
+    fn print_version(&self) {
+        self.backend.print_version()
+    }
+
+    fn metadata_loader(&self) -> Box<MetadataLoaderDyn> {
+        self.backend.metadata_loader()
+    }
+
+    fn provide(&self, providers: &mut Providers) {
+        self.backend.provide(providers)
+    }
+
+    fn codegen_crate<'tcx>(&self, tcx: TyCtxt<'tcx>) -> Box<dyn Any> {
+        let ongoing_codegen = self.backend.codegen_crate(tcx);
+        (self.hook.lock().unwrap())(tcx);
+        ongoing_codegen
+    }
+
+    fn join_codegen(
+        &self,
+        ongoing_codegen: Box<dyn Any>,
+        sess: &Session,
+        outputs: &OutputFilenames,
+    ) -> (CodegenResults, FxIndexMap<WorkProductId, WorkProduct>) {
+        self.backend.join_codegen(ongoing_codegen, sess, outputs)
+    }
+
+    fn link(
+        &self,
+        sess: &Session,
+        codegen_results: CodegenResults,
+        metadata: EncodedMetadata,
+        outputs: &OutputFilenames,
+    ) {
+        self.backend.link(sess, codegen_results, metadata, outputs)
+    }
+}
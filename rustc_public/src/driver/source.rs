@@ -0,0 +1,34 @@
+//! File loader used to compile crates whose files are kept in memory.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rustc_span::source_map::FileLoader;
+
+/// Load files from memory, and fall back to another loader for any file that isn't in memory.
+pub(crate) struct VirtualFileLoader {
+    pub(crate) files: HashMap<PathBuf, String>,
+    pub(crate) fallback: Box<dyn FileLoader + Send + Sync>,
+}
+
+impl FileLoader for VirtualFileLoader {
+    fn file_exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path) || self.fallback.file_exists(path)
+    }
+
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        match self.files.get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => self.fallback.read_file(path),
+        }
+    }
+
+    fn read_binary_file(&self, path: &Path) -> io::Result<Arc<[u8]>> {
+        match self.files.get(path) {
+            Some(contents) => Ok(Arc::from(contents.as_bytes())),
+            None => self.fallback.read_binary_file(path),
+        }
+    }
+}
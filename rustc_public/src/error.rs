@@ -3,15 +3,19 @@
 //!
 //! - [CompilerError]: This represents errors that can be raised when invoking the compiler.
 //! - [Error]: Generic error that represents the reason why a request that could not be fulfilled.
+//!   Its [ErrorKind] allows tools to handle different failures differently.
 
 use std::fmt::{Debug, Display, Formatter};
 use std::{fmt, io};
 
 use rustc_public_bridge::bridge;
 
+use crate::DefId;
+use crate::ty::{Span, Ty};
+
 macro_rules! error {
-     ($fmt: literal $(,)?) => { Error(format!($fmt)) };
-     ($fmt: literal, $($arg:tt)*) => { Error(format!($fmt, $($arg)*)) };
+     ($fmt: literal $(,)?) => { $crate::Error::new($crate::ErrorKind::Other, format!($fmt)) };
+     ($fmt: literal, $($arg:tt)*) => { $crate::Error::new($crate::ErrorKind::Other, format!($fmt, $($arg)*)) };
 }
 
 pub(crate) use error;
@@ -29,28 +33,141 @@ pub enum CompilerError<T> {
 }
 
 /// A generic error to represent an API request that cannot be fulfilled.
+///
+/// Use [Error::kind] to find out why the request failed, and its [Display] implementation to
+/// report the error to users.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+}
+
+/// The reason why a request could not be fulfilled.
+///
+/// Note that the objects carried by some of these variants, such as [Ty] and [Span], can only be
+/// inspected while the compilation that created them is running.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Error(pub(crate) String);
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The item is generic, and it must be instantiated before this request can be fulfilled.
+    RequiresMonomorphization(DefId),
+    /// The item isn't of the expected kind, e.g.: a function was expected but a static was found.
+    UnexpectedItem(DefId),
+    /// The type isn't of the expected kind, e.g.: an array was expected but a reference was found.
+    UnexpectedTy(Ty),
+    /// The item could not be resolved for the given generic arguments.
+    Resolution(DefId),
+    /// The layout of the given type could not be computed.
+    Layout { ty: Ty, error: LayoutError },
+    /// Constant evaluation failed.
+    ConstEval {
+        /// The location of the constant whose evaluation failed, if any.
+        span: Option<Span>,
+    },
+    /// The requested item or value does not exist.
+    NotFound,
+    /// The request involves a construct that is not supported.
+    Unsupported,
+    /// The input could not be parsed, e.g.: the textual MIR given to [Body::parse].
+    ///
+    /// The line and the column of the error start at 1.
+    ///
+    /// [Body::parse]: crate::mir::Body::parse
+    Parse { line: usize, column: usize },
+    /// A rustc_public object was used outside of the compilation that created it.
+    Session,
+    /// The compiler panicked while fulfilling the request, i.e., an internal compiler error.
+    ///
+    /// This is a bug in the compiler or in rustc_public.
+    Internal,
+    /// Any other failure.
+    Other,
+}
+
+/// The reason why the layout of a type could not be computed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum LayoutError {
+    /// The type doesn't have a sensible layout.
+    Unknown,
+    /// The size of the type exceeds the maximum object size of the target.
+    SizeOverflow,
+    /// The layout depends on generic parameters that haven't been instantiated.
+    TooGeneric,
+    /// An associated type or opaque type could not be normalized.
+    NormalizationFailure,
+    /// The type references an item that failed to compile.
+    ReferencesError,
+    /// The type contains itself without indirection.
+    Cycle,
+}
+
+impl Error {
+    /// Create a new error of the given kind, with a message that describes it.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Error {
+        Error { kind, message: message.into() }
+    }
+
+    /// The reason why the request failed.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// A human readable description of this error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The source location associated with this error, if any.
+    pub fn span(&self) -> Option<Span> {
+        match self.kind {
+            ErrorKind::ConstEval { span } => span,
+            _ => None,
+        }
+    }
+}
 
 impl bridge::Error for Error {
     fn new(msg: String) -> Self {
-        Self(msg)
+        Error::new(ErrorKind::Other, msg)
     }
 
     fn from_internal<T: Debug>(err: T) -> Self {
-        Self(format!("{err:?}"))
+        Error::new(ErrorKind::Other, format!("{err:?}"))
     }
 }
 
 impl From<&str> for Error {
     fn from(value: &str) -> Self {
-        Self(value.into())
+        Error::new(ErrorKind::Other, value)
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        Display::fmt(&self.0, f)
+        Display::fmt(&self.message, f)
+    }
+}
+
+/// Errors may outlive the compilation that created them, so we avoid printing the objects
+/// carried by their kind, since that requires the compiler.
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ErrorKind::RequiresMonomorphization(_) => "RequiresMonomorphization",
+            ErrorKind::UnexpectedItem(_) => "UnexpectedItem",
+            ErrorKind::UnexpectedTy(_) => "UnexpectedTy",
+            ErrorKind::Resolution(_) => "Resolution",
+            ErrorKind::Layout { .. } => "Layout",
+            ErrorKind::ConstEval { .. } => "ConstEval",
+            ErrorKind::NotFound => "NotFound",
+            ErrorKind::Unsupported => "Unsupported",
+            ErrorKind::Parse { .. } => "Parse",
+            ErrorKind::Session => "Session",
+            ErrorKind::Internal => "Internal",
+            ErrorKind::Other => "Other",
+        };
+        f.debug_struct("Error").field("kind", &kind).field("message", &self.message).finish()
     }
 }
 
@@ -86,6 +203,6 @@ impl<T> std::error::Error for CompilerError<T> where T: Display + Debug {}
 
 impl From<io::Error> for Error {
     fn from(value: io::Error) -> Self {
-        Error(value.to_string())
+        Error::new(ErrorKind::Other, value.to_string())
     }
 }
@@ -0,0 +1,333 @@
+//! Export the MIR of a crate in a format that can be consumed without linking to the compiler.
+//!
+//! The objects used by rustc_public, such as [Ty] and [DefId], are handles that can only be
+//! resolved while the compilation is running. When serialized, they are represented by their
+//! index. Thus, the export also includes tables that map every index found in the export to the
+//! information it represents:
+//!
+//! - `types`: type id to its [TyKind].
+//! - `defs`: definition id to its path, kind, crate and [StableDefId].
+//! - `spans`: span id to its file and lines.
+//! - `allocs`: allocation id to its [GlobalAlloc].
+//! - `layouts`: layout id to its [LayoutShape].
+//! - `type_layouts`: type id to its [Layout], for the types of the exported items and of the
+//!   locals of the exported bodies whose layout can be computed.
+//! - `instances`: instance id to its definition, arguments and symbol name.
+//!
+//! The format of the export is identified by [SCHEMA_VERSION], which is bumped every time the
+//! format changes.
+//!
+//! ```ignore(needs-extern-crate)
+//! let result = run_on_source(source, "my_crate", &[], || {
+//!     let mut file = File::create("my_crate.json").unwrap();
+//!     ControlFlow::<(), _>::Continue(rustc_public::export::write_crate_json(&mut file))
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::abi::{Layout, LayoutShape};
+use crate::mir::alloc::{AllocId, GlobalAlloc};
+use crate::mir::mono::{Instance, InstanceDef};
+use crate::mir::visit::Location;
+use crate::mir::{Body, MirVisitor, Terminator, TerminatorKind};
+use crate::target::MachineInfo;
+use crate::ty::{GenericArgs, LineInfo, MirConst, RigidTy, Span, Ty, TyKind};
+use crate::{
+    CrateDef, DefId, Error, ErrorKind, Filename, IndexedVal, ItemKind, StableDefId, Symbol, with,
+};
+
+/// The version of the export format.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// The MIR of a crate, together with the tables that resolve the handles it contains.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrateExport {
+    pub schema_version: u32,
+    pub crate_name: Symbol,
+    pub machine: MachineInfo,
+    /// The items of the local crate.
+    pub items: Vec<ItemExport>,
+    /// The monomorphic instances reachable from the non-generic items of the local crate.
+    pub instances: Vec<InstanceExport>,
+    pub tables: ExportTables,
+}
+
+/// An item of the local crate.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemExport {
+    pub def_id: DefId,
+    pub name: Symbol,
+    pub kind: ItemKind,
+    pub ty: Ty,
+    pub span: Span,
+    pub requires_monomorphization: bool,
+    /// The body of the item, or `None` if the body isn't available or cannot be built.
+    pub body: Option<Body>,
+}
+
+/// A monomorphic instance, together with its body.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InstanceExport {
+    pub instance: Instance,
+    /// The monomorphized body of the instance, or `None` if it doesn't have one, e.g.: an
+    /// intrinsic, or if it cannot be built.
+    pub body: Option<Body>,
+}
+
+/// The tables that map the indices found in an export to the information they represent.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ExportTables {
+    pub types: BTreeMap<usize, TyKind>,
+    pub defs: BTreeMap<usize, DefInfo>,
+    pub spans: BTreeMap<usize, SpanInfo>,
+    pub allocs: BTreeMap<usize, GlobalAlloc>,
+    pub layouts: BTreeMap<usize, LayoutShape>,
+    pub instances: BTreeMap<usize, InstanceInfo>,
+    pub type_layouts: BTreeMap<usize, Layout>,
+}
+
+/// Information about a definition.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefInfo {
+    /// The fully qualified path of the definition.
+    pub path: Symbol,
+    /// A description of the kind of definition, e.g.: `function`, `struct`.
+    pub kind: String,
+    /// The name of the crate where the definition is declared.
+    pub krate: Symbol,
+    /// The identifier of the definition that is stable across compilations.
+    pub stable_id: StableDefId,
+}
+
+/// Information about a span.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanInfo {
+    pub file: Filename,
+    pub lines: LineInfo,
+}
+
+/// Information about an instance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub def_id: DefId,
+    pub args: GenericArgs,
+    pub mangled_name: Symbol,
+}
+
+/// Export the local crate.
+pub fn export_crate() -> Result<CrateExport, Error> {
+    let items = crate::all_local_items()
+        .into_iter()
+        .map(|item| ItemExport {
+            def_id: item.def_id(),
+            name: item.name(),
+            kind: item.kind(),
+            ty: item.ty(),
+            span: item.span(),
+            requires_monomorphization: item.requires_monomorphization(),
+            body: item.try_body().ok().flatten(),
+        })
+        .collect::<Vec<_>>();
+    let instances = reachable_instances()
+        .into_iter()
+        .map(|instance| InstanceExport { instance, body: instance.try_body().ok().flatten() })
+        .collect::<Vec<_>>();
+    let bodies = items.iter().filter_map(|item| item.body.as_ref());
+    let bodies = bodies.chain(instances.iter().filter_map(|instance| instance.body.as_ref()));
+    let types = items.iter().map(|item| item.ty);
+    let type_layouts = types
+        .chain(bodies.flat_map(|body| body.locals()).map(|local| local.ty))
+        .filter_map(|ty| Some((ty.to_index(), ty.try_layout().ok()?)))
+        .collect();
+    let tables = collect_tables(&(&items, &instances), type_layouts)?;
+    Ok(CrateExport {
+        schema_version: SCHEMA_VERSION,
+        crate_name: crate::local_crate().name,
+        machine: MachineInfo::target(),
+        items,
+        instances,
+        tables,
+    })
+}
+
+/// Export the local crate as JSON to the given writer.
+pub fn write_crate_json<W: io::Write>(writer: W) -> Result<(), Error> {
+    let export = export_crate()?;
+    serde_json::to_writer(writer, &export).map_err(json_error)
+}
+
+/// The handles that have an entry in the export tables.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Handle {
+    Ty(Ty),
+    Def(DefId),
+    Span(Span),
+    Alloc(AllocId),
+    Layout(Layout),
+    Instance(InstanceDef),
+}
+
+thread_local! {
+    /// The handles serialized since we started recording them, if we are recording them.
+    static RECORDED: RefCell<Option<Vec<Handle>>> = const { RefCell::new(None) };
+}
+
+/// Record that a handle was serialized, so we can add its entry to the export tables.
+pub(crate) fn record(handle: Handle) {
+    RECORDED.with_borrow_mut(|recorded| {
+        if let Some(recorded) = recorded {
+            recorded.push(handle);
+        }
+    })
+}
+
+/// Serialize the given value, and return the handles that it contains.
+fn handles_in(value: &impl Serialize) -> Result<Vec<Handle>, Error> {
+    RECORDED.set(Some(vec![]));
+    let result = serde_json::to_value(value);
+    let recorded = RECORDED.take().unwrap_or_default();
+    result.map(|_| recorded).map_err(json_error)
+}
+
+/// Build the tables for every handle in the given value, including the handles found in the
+/// table entries themselves.
+fn collect_tables(
+    value: &impl Serialize,
+    type_layouts: BTreeMap<usize, Layout>,
+) -> Result<ExportTables, Error> {
+    let mut pending = handles_in(value)?;
+    pending.extend(handles_in(&type_layouts)?);
+    let mut tables = ExportTables { type_layouts, ..ExportTables::default() };
+    while let Some(handle) = pending.pop() {
+        let new_handles = match handle {
+            Handle::Ty(ty) => add_entry(&mut tables.types, ty.to_index(), || ty.kind()),
+            Handle::Def(def_id) => add_entry(&mut tables.defs, def_id.to_index(), || DefInfo {
+                path: def_id.name(),
+                kind: with(|cx| cx.def_descr(def_id)),
+                krate: with(|cx| cx.krate(def_id)).name,
+                stable_id: def_id.stable_id(),
+            }),
+            Handle::Span(span) => add_entry(&mut tables.spans, span.to_index(), || SpanInfo {
+                file: span.get_filename(),
+                lines: span.get_lines(),
+            }),
+            Handle::Alloc(alloc_id) => {
+                add_entry(&mut tables.allocs, alloc_id.to_index(), || GlobalAlloc::from(alloc_id))
+            }
+            Handle::Layout(layout) => {
+                add_entry(&mut tables.layouts, layout.to_index(), || layout.shape())
+            }
+            Handle::Instance(def) => add_entry(&mut tables.instances, def.to_index(), || {
+                with(|cx| InstanceInfo {
+                    def_id: cx.instance_def_id(def),
+                    args: cx.instance_args(def),
+                    mangled_name: cx.instance_mangled_name(def),
+                })
+            }),
+        }?;
+        pending.extend(new_handles);
+    }
+    Ok(tables)
+}
+
+/// Add the entry of a handle to a table, unless it's already there, and return the handles
+/// found in the new entry.
+fn add_entry<T: Serialize>(
+    table: &mut BTreeMap<usize, T>,
+    index: usize,
+    entry: impl FnOnce() -> T,
+) -> Result<Vec<Handle>, Error> {
+    if table.contains_key(&index) {
+        return Ok(vec![]);
+    }
+    let entry = entry();
+    let handles = handles_in(&entry)?;
+    table.insert(index, entry);
+    Ok(handles)
+}
+
+/// Collect the monomorphic instances that are reachable from the non-generic functions of the
+/// local crate.
+fn reachable_instances() -> Vec<Instance> {
+    let mut pending = crate::all_local_items()
+        .into_iter()
+        .filter(|item| item.kind() == ItemKind::Fn && !item.requires_monomorphization())
+        .filter_map(|item| Instance::try_from(item).ok())
+        .collect::<Vec<_>>();
+    let mut visited = HashSet::new();
+    let mut instances = vec![];
+    while let Some(instance) = pending.pop() {
+        if !visited.insert(instance) {
+            continue;
+        }
+        if let Ok(Some(body)) = instance.try_body() {
+            let mut collector = InstanceCollector { body: &body, instances: vec![] };
+            collector.visit_body(&body);
+            pending.extend(collector.instances);
+        }
+        instances.push(instance);
+    }
+    instances
+}
+
+/// Collect the instances used by a monomorphic body.
+struct InstanceCollector<'a> {
+    body: &'a Body,
+    instances: Vec<Instance>,
+}
+
+impl MirVisitor for InstanceCollector<'_> {
+    fn visit_mir_const(&mut self, constant: &MirConst, location: Option<Location>) {
+        if let TyKind::RigidTy(RigidTy::FnDef(def, args)) = constant.ty().kind()
+            && let Ok(instance) = Instance::resolve(def, &args)
+        {
+            self.instances.push(instance);
+        }
+        self.super_mir_const(constant, location);
+    }
+
+    fn visit_terminator(&mut self, term: &Terminator, location: Location) {
+        if let TerminatorKind::Drop { place, .. } = &term.kind
+            && let Ok(ty) = place.ty(self.body.locals())
+        {
+            let instance = Instance::resolve_drop_in_place(ty);
+            if !instance.is_empty_shim() {
+                self.instances.push(instance);
+            }
+        }
+        self.super_terminator(term, location);
+    }
+}
+
+fn json_error(error: serde_json::Error) -> Error {
+    Error::new(ErrorKind::Other, format!("Failed to serialize crate: {error}"))
+}
+
+/// Implement [Serialize] and [Deserialize] for a handle, which is serialized as its index.
+///
+/// Handles that have an entry in the export tables are recorded as they are serialized, so
+/// their entries can be added to the tables. Deserialized handles belong to the ongoing session.
+macro_rules! handle_serde {
+    ($name:ident $(, $handle:ident)?) => {
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $($crate::export::record($crate::export::Handle::$handle(*self));)?
+                serializer.serialize_newtype_struct(stringify!($name), &self.0)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let index = <usize as serde::Deserialize>::deserialize(deserializer)?;
+                Ok(<$name as $crate::IndexedVal>::to_val(index))
+            }
+        }
+    };
+}
+
+pub(crate) use handle_serde;
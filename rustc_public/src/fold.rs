@@ -0,0 +1,386 @@
+//! Rewrite the types, constants and regions inside a value.
+//!
+//! A [TypeFolder] builds a new value bottom-up, where the folder decides what to replace every
+//! type, type-level constant and region with. Types and constants that contain a folded
+//! component are created again through the compiler, so the result can be used like any other
+//! value retrieved from it. Note that the compiler cannot recover regions from a stable
+//! region, so regions inside re-created types are erased.
+
+use super::ty::{
+    AliasTerm, AliasTy, Allocation, Binder, ClauseKind, CoercePredicate, ConstantKind,
+    ExistentialPredicate, ExistentialProjection, ExistentialTraitRef, FnSig, GenericArgKind,
+    GenericArgs, MirConst, OutlivesPredicate, Pattern, PredicateKind, ProjectionPredicate, Region,
+    RigidTy, SubtypePredicate, TermKind, TraitPredicate, TraitRef, Ty, TyConst, TyConstKind,
+    TyKind, UnevaluatedConst,
+};
+use crate::compiler_interface::with;
+use crate::mir::visit::Location;
+use crate::mir::{AggregateKind, Body, MutMirVisitor, Rvalue};
+
+pub trait TypeFolder: Sized {
+    fn fold_ty(&mut self, ty: &Ty) -> Ty {
+        ty.super_fold(self)
+    }
+    fn fold_const(&mut self, c: &TyConst) -> TyConst {
+        c.super_fold(self)
+    }
+    fn fold_reg(&mut self, reg: &Region) -> Region {
+        reg.super_fold(self)
+    }
+}
+
+pub trait Foldable: Sized {
+    fn fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        self.super_fold(folder)
+    }
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self;
+}
+
+impl Foldable for Ty {
+    fn fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        folder.fold_ty(self)
+    }
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self.kind() {
+            TyKind::RigidTy(ty) => {
+                let folded = ty.fold(folder);
+                if folded == ty { *self } else { Ty::from_rigid_kind(folded) }
+            }
+            TyKind::Alias(kind, alias) => {
+                let folded = alias.fold(folder);
+                if folded == alias { *self } else { with(|cx| cx.new_alias_ty(kind, &folded)) }
+            }
+            TyKind::Param(_) | TyKind::Bound(_, _) => *self,
+        }
+    }
+}
+
+/// Parameters and bound constants are returned unchanged, and values keep their value with
+/// their type replaced by the folded one.
+impl Foldable for TyConst {
+    fn fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        folder.fold_const(self)
+    }
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        let kind = match &self.kind {
+            TyConstKind::Param(_) | TyConstKind::Bound(_, _) => return self.clone(),
+            TyConstKind::Unevaluated(def, args) => {
+                TyConstKind::Unevaluated(*def, args.fold(folder))
+            }
+            TyConstKind::Value(ty, _) => {
+                let folded = ty.fold(folder);
+                return if folded == *ty {
+                    self.clone()
+                } else {
+                    with(|cx| cx.ty_const_with_ty(self, folded))
+                };
+            }
+            TyConstKind::ZSTValue(ty) => TyConstKind::ZSTValue(ty.fold(folder)),
+        };
+        if kind == self.kind {
+            self.clone()
+        } else {
+            with(|cx| cx.try_new_ty_const(&kind))
+                .unwrap_or_else(|error| panic!("Failed to fold `{self:?}`: {error}"))
+        }
+    }
+}
+
+/// Constants that refer to a constant item are created again from their folded arguments.
+/// Other constants keep their value, with their type replaced by the folded one.
+impl Foldable for MirConst {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        let ty = self.ty().fold(folder);
+        match self.kind() {
+            ConstantKind::Ty(ct) => {
+                let folded = ct.fold(folder);
+                if folded == *ct && ty == self.ty() {
+                    self.clone()
+                } else {
+                    with(|cx| cx.new_const_from_ty_const(&folded, ty))
+                }
+            }
+            ConstantKind::Unevaluated(uv) => {
+                let folded = uv.fold(folder);
+                if folded == *uv {
+                    return self.clone();
+                }
+                with(|cx| cx.try_new_const_unevaluated(&folded))
+                    .unwrap_or_else(|_| with(|cx| cx.mir_const_with_ty(self, ty)))
+            }
+            ConstantKind::Allocated(_) | ConstantKind::Param(_) | ConstantKind::ZeroSized => {
+                if ty == self.ty() {
+                    self.clone()
+                } else {
+                    with(|cx| cx.mir_const_with_ty(self, ty))
+                }
+            }
+        }
+    }
+}
+
+impl Foldable for Allocation {
+    fn super_fold<F: TypeFolder>(&self, _folder: &mut F) -> Self {
+        self.clone()
+    }
+}
+
+impl Foldable for UnevaluatedConst {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        let UnevaluatedConst { def, args, promoted } = self;
+        UnevaluatedConst { def: *def, args: args.fold(folder), promoted: *promoted }
+    }
+}
+
+impl<T: Foldable> Foldable for Option<T> {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        self.as_ref().map(|val| val.fold(folder))
+    }
+}
+
+impl Foldable for GenericArgs {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        GenericArgs(self.0.fold(folder))
+    }
+}
+
+impl Foldable for Region {
+    fn fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        folder.fold_reg(self)
+    }
+    fn super_fold<F: TypeFolder>(&self, _: &mut F) -> Self {
+        self.clone()
+    }
+}
+
+impl Foldable for GenericArgKind {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            GenericArgKind::Lifetime(lt) => GenericArgKind::Lifetime(lt.fold(folder)),
+            GenericArgKind::Type(t) => GenericArgKind::Type(t.fold(folder)),
+            GenericArgKind::Const(c) => GenericArgKind::Const(c.fold(folder)),
+        }
+    }
+}
+
+impl Foldable for RigidTy {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            RigidTy::Bool
+            | RigidTy::Char
+            | RigidTy::Int(_)
+            | RigidTy::Uint(_)
+            | RigidTy::Float(_)
+            | RigidTy::Never
+            | RigidTy::Foreign(_)
+            | RigidTy::Str => self.clone(),
+            RigidTy::Array(t, c) => RigidTy::Array(t.fold(folder), c.fold(folder)),
+            RigidTy::Pat(t, p) => RigidTy::Pat(t.fold(folder), p.fold(folder)),
+            RigidTy::Slice(inner) => RigidTy::Slice(inner.fold(folder)),
+            RigidTy::RawPtr(ty, mutability) => RigidTy::RawPtr(ty.fold(folder), *mutability),
+            RigidTy::Ref(reg, ty, mutability) => {
+                RigidTy::Ref(reg.fold(folder), ty.fold(folder), *mutability)
+            }
+            RigidTy::Adt(def, args) => RigidTy::Adt(*def, args.fold(folder)),
+            RigidTy::Closure(def, args) => RigidTy::Closure(*def, args.fold(folder)),
+            RigidTy::Coroutine(def, args) => RigidTy::Coroutine(*def, args.fold(folder)),
+            RigidTy::CoroutineWitness(def, args) => {
+                RigidTy::CoroutineWitness(*def, args.fold(folder))
+            }
+            RigidTy::CoroutineClosure(def, args) => {
+                RigidTy::CoroutineClosure(*def, args.fold(folder))
+            }
+            RigidTy::FnDef(def, args) => RigidTy::FnDef(*def, args.fold(folder)),
+            RigidTy::FnPtr(sig) => RigidTy::FnPtr(sig.fold(folder)),
+            RigidTy::Dynamic(preds, r, kind) => {
+                RigidTy::Dynamic(preds.fold(folder), r.fold(folder), kind.clone())
+            }
+            RigidTy::Tuple(fields) => RigidTy::Tuple(fields.fold(folder)),
+        }
+    }
+}
+
+impl Foldable for Pattern {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            Pattern::Range { start, end, include_end } => Pattern::Range {
+                start: start.fold(folder),
+                end: end.fold(folder),
+                include_end: *include_end,
+            },
+        }
+    }
+}
+
+impl<T: Foldable> Foldable for Vec<T> {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        self.iter().map(|arg| arg.fold(folder)).collect()
+    }
+}
+
+impl<T: Foldable> Foldable for Binder<T> {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        Binder { value: self.value.fold(folder), bound_vars: self.bound_vars.clone() }
+    }
+}
+
+impl Foldable for ExistentialPredicate {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            ExistentialPredicate::Trait(tr) => ExistentialPredicate::Trait(tr.fold(folder)),
+            ExistentialPredicate::Projection(p) => ExistentialPredicate::Projection(p.fold(folder)),
+            ExistentialPredicate::AutoTrait(def) => ExistentialPredicate::AutoTrait(*def),
+        }
+    }
+}
+
+impl Foldable for ExistentialTraitRef {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        ExistentialTraitRef { def_id: self.def_id, generic_args: self.generic_args.fold(folder) }
+    }
+}
+
+impl Foldable for ExistentialProjection {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        ExistentialProjection {
+            def_id: self.def_id,
+            generic_args: self.generic_args.fold(folder),
+            term: self.term.fold(folder),
+        }
+    }
+}
+
+impl Foldable for TermKind {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            TermKind::Type(t) => TermKind::Type(t.fold(folder)),
+            TermKind::Const(c) => TermKind::Const(c.fold(folder)),
+        }
+    }
+}
+
+impl Foldable for FnSig {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        FnSig { inputs_and_output: self.inputs_and_output.fold(folder), ..self.clone() }
+    }
+}
+
+impl Foldable for AliasTy {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        AliasTy { def_id: self.def_id, args: self.args.fold(folder) }
+    }
+}
+
+impl Foldable for AliasTerm {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        AliasTerm { def_id: self.def_id, args: self.args.fold(folder) }
+    }
+}
+
+impl Foldable for TraitRef {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        // Folding keeps the kind of every argument, so `Self` is still a type.
+        TraitRef::try_new(self.def_id, self.args().fold(folder)).unwrap()
+    }
+}
+
+impl Foldable for PredicateKind {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            PredicateKind::Clause(clause) => PredicateKind::Clause(clause.fold(folder)),
+            PredicateKind::DynCompatible(def) => PredicateKind::DynCompatible(*def),
+            PredicateKind::SubType(SubtypePredicate { a, b }) => {
+                PredicateKind::SubType(SubtypePredicate { a: a.fold(folder), b: b.fold(folder) })
+            }
+            PredicateKind::Coerce(CoercePredicate { a, b }) => {
+                PredicateKind::Coerce(CoercePredicate { a: a.fold(folder), b: b.fold(folder) })
+            }
+            PredicateKind::ConstEquate(a, b) => {
+                PredicateKind::ConstEquate(a.fold(folder), b.fold(folder))
+            }
+            PredicateKind::Ambiguous => PredicateKind::Ambiguous,
+            PredicateKind::AliasRelate(a, b, direction) => {
+                PredicateKind::AliasRelate(a.fold(folder), b.fold(folder), direction.clone())
+            }
+        }
+    }
+}
+
+impl Foldable for ClauseKind {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        match self {
+            ClauseKind::Trait(TraitPredicate { trait_ref, polarity }) => {
+                ClauseKind::Trait(TraitPredicate {
+                    trait_ref: trait_ref.fold(folder),
+                    polarity: polarity.clone(),
+                })
+            }
+            ClauseKind::RegionOutlives(OutlivesPredicate(a, b)) => {
+                ClauseKind::RegionOutlives(OutlivesPredicate(a.fold(folder), b.fold(folder)))
+            }
+            ClauseKind::TypeOutlives(OutlivesPredicate(a, b)) => {
+                ClauseKind::TypeOutlives(OutlivesPredicate(a.fold(folder), b.fold(folder)))
+            }
+            ClauseKind::Projection(ProjectionPredicate { projection_term, term }) => {
+                ClauseKind::Projection(ProjectionPredicate {
+                    projection_term: projection_term.fold(folder),
+                    term: term.fold(folder),
+                })
+            }
+            ClauseKind::ConstArgHasType(c, ty) => {
+                ClauseKind::ConstArgHasType(c.fold(folder), ty.fold(folder))
+            }
+            ClauseKind::WellFormed(term) => ClauseKind::WellFormed(term.fold(folder)),
+            ClauseKind::ConstEvaluatable(c) => ClauseKind::ConstEvaluatable(c.fold(folder)),
+        }
+    }
+}
+
+/// Every type, constant and region that appears in the body is folded, including the ones
+/// in local declarations, projections and aggregates.
+impl Foldable for Body {
+    fn super_fold<F: TypeFolder>(&self, folder: &mut F) -> Self {
+        let mut body = self.clone();
+        BodyFolder { folder }.visit_body(&mut body);
+        body
+    }
+}
+
+/// Replace the types in a body in place.
+struct BodyFolder<'a, F> {
+    folder: &'a mut F,
+}
+
+impl<F: TypeFolder> MutMirVisitor for BodyFolder<'_, F> {
+    fn visit_ty(&mut self, ty: &mut Ty, _location: Option<Location>) {
+        *ty = ty.fold(self.folder);
+    }
+
+    fn visit_ty_const(&mut self, constant: &mut TyConst, _location: Location) {
+        *constant = constant.fold(self.folder);
+    }
+
+    fn visit_mir_const(&mut self, constant: &mut MirConst, _location: Option<Location>) {
+        *constant = constant.fold(self.folder);
+    }
+
+    fn visit_region(&mut self, region: &mut Region, _location: Location) {
+        *region = region.fold(self.folder);
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &mut Rvalue, location: Location) {
+        if let Rvalue::Aggregate(kind, _) = rvalue {
+            match kind {
+                AggregateKind::Array(ty) | AggregateKind::RawPtr(ty, _) => {
+                    *ty = ty.fold(self.folder)
+                }
+                AggregateKind::Adt(_, _, args, _, _)
+                | AggregateKind::Closure(_, args)
+                | AggregateKind::Coroutine(_, args)
+                | AggregateKind::CoroutineClosure(_, args) => *args = args.fold(self.folder),
+                AggregateKind::Tuple => {}
+            }
+        }
+        self.super_rvalue(rvalue, location)
+    }
+}
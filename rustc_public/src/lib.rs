@@ -24,8 +24,15 @@
 macro_rules! rustc_crates {
     () => {
         extern crate rustc_abi;
+        extern crate rustc_ast;
+        extern crate rustc_builtin_macros;
+        extern crate rustc_codegen_ssa;
+        extern crate rustc_data_structures;
         extern crate rustc_driver;
+        extern crate rustc_errors;
         extern crate rustc_hir;
+        extern crate rustc_interface;
+        extern crate rustc_metadata;
         extern crate rustc_middle;
         extern crate rustc_public_bridge;
         extern crate rustc_session;
@@ -47,10 +54,17 @@ use rustc_public_bridge::context::CompilerCtxt;
 /// guarantees and it is not taken into account for semver.
 #[cfg(feature = "rustc_internal")]
 pub mod rustc_internal;
-use serde::Serialize;
-
-use crate::compiler_interface::with;
-pub use crate::crate_def::{CrateDef, CrateDefItems, CrateDefType, DefId};
+use serde::{Deserialize, Serialize};
+
+use crate::compiler_interface::{
+    SessionHandle, SessionId, catch_internal_error, handle_index, with,
+};
+pub use crate::crate_def::{
+    CrateDef, CrateDefItems, CrateDefType, DefId, DefKind, DefPath, DefPathData, DefPathSegment,
+    StableDefId,
+};
+#[cfg(not(feature = "rustc-build"))]
+pub use crate::driver::run_on_source;
 pub use crate::error::*;
 use crate::mir::mono::StaticDef;
 use crate::mir::{Body, Mutability};
@@ -63,10 +77,19 @@ pub(crate) mod unstable;
 #[macro_use]
 pub mod crate_def;
 pub mod compiler_interface;
+pub mod diagnostics;
+#[cfg(not(feature = "rustc-build"))]
+pub mod driver;
 #[macro_use]
 pub mod error;
+pub mod export;
+pub mod fold;
+pub mod lint;
 pub mod mir;
+pub mod offline;
 pub mod target;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod ty;
 pub mod visitor;
 
@@ -82,13 +105,20 @@ impl Debug for DefId {
     }
 }
 
+impl SessionHandle for DefId {
+    fn new_in(index: usize, session: SessionId) -> Self {
+        DefId(handle_index(index), session)
+    }
+}
+
 impl IndexedVal for DefId {
     fn to_val(index: usize) -> Self {
-        DefId(index)
+        DefId::new_in(index, SessionId::current())
     }
 
     fn to_index(&self) -> usize {
-        self.0
+        self.1.check("DefId");
+        self.0 as usize
     }
 }
 
@@ -105,7 +135,7 @@ pub type ImplTraitDecls = Vec<ImplDef>;
 pub type AssocItems = Vec<AssocItem>;
 
 /// Holds information about a crate.
-#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Crate {
     pub id: CrateNum,
     pub name: Symbol,
@@ -139,7 +169,7 @@ impl Crate {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Serialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum ItemKind {
     Fn,
     Static,
@@ -147,7 +177,7 @@ pub enum ItemKind {
     Ctor(CtorKind),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Serialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum CtorKind {
     Const,
     Fn,
@@ -157,7 +187,7 @@ pub type Filename = String;
 
 crate_def_with_ty! {
     /// Holds information about an item in a crate.
-    #[derive(Serialize)]
+    #[derive(Serialize, Deserialize)]
     pub CrateItem;
 }
 
@@ -172,6 +202,14 @@ impl CrateItem {
         with(|cx| cx.has_body(self.0).then(|| cx.mir_body(self.0)))
     }
 
+    /// Return the body of an item if available, or an error if the compiler panics while
+    /// building it.
+    ///
+    /// Unlike [CrateItem::body], this allows tools to skip items that trigger a compiler bug.
+    pub fn try_body(&self) -> Result<Option<mir::Body>, Error> {
+        with(|cx| catch_internal_error(|| cx.has_body(self.0).then(|| cx.mir_body(self.0))))
+    }
+
     /// Check if a body is available for this item.
     pub fn has_body(&self) -> bool {
         with(|cx| cx.has_body(self.0))
@@ -203,6 +241,17 @@ impl CrateItem {
             .ok_or_else(|| io::Error::other(format!("No body found for `{}`", self.name())))?
             .dump(w, &self.name())
     }
+
+    /// Emit MIR for this item body next to the source code it was built from.
+    pub fn emit_mir_with_source<W: io::Write>(
+        &self,
+        w: &mut W,
+        options: &mir::ListingOptions,
+    ) -> io::Result<()> {
+        self.body()
+            .ok_or_else(|| io::Error::other(format!("No body found for `{}`", self.name())))?
+            .dump_with_source(options, w, &self.name())
+    }
 }
 
 /// Return the function where execution starts if the current
@@ -222,6 +271,16 @@ pub fn find_crates(name: &str) -> Vec<Crate> {
     with(|cx| cx.find_crates(name))
 }
 
+/// Find the definitions with the given path, e.g.: `alloc::vec::Vec::push`.
+///
+/// The path must start with the name of a crate, and its remaining segments are resolved in the
+/// modules, enums and traits, and in the inherent and trait impls of types, including any
+/// re-export. Multiple definitions are returned if the path is ambiguous, e.g.: when a type and
+/// a function share the same name, or when several crates have the same name.
+pub fn resolve_path(path: &str) -> Vec<DefId> {
+    with(|cx| cx.resolve_path(path))
+}
+
 /// Try to find a crate with the given name.
 pub fn external_crates() -> Vec<Crate> {
     with(|cx| cx.external_crates())
@@ -240,8 +299,25 @@ pub fn all_trait_impls() -> ImplTraitDecls {
     with(|cx| cx.all_trait_impls())
 }
 
+/// Execute the given function, and return an error if it misuses rustc_public objects.
+///
+/// Objects, such as [Ty] and [DefId], can only be used during the compilation that created them.
+/// Using them after their compilation is over, or during another compilation, panics with a
+/// message describing the misuse. This function returns that message as an error instead.
+///
+/// Requests that a backend cannot fulfill, such as queries that weren't recorded in an
+/// [offline](crate::offline) snapshot, are reported the same way.
+///
+/// ```ignore(needs-extern-crate)
+/// let ty = run_on_source(source, "krate", &[], || ty_of_interest())?;
+/// assert!(rustc_public::try_with(|| ty.kind()).is_err());
+/// ```
+pub fn try_with<R>(f: impl FnOnce() -> R) -> Result<R, Error> {
+    compiler_interface::try_with(f)
+}
+
 /// A type that provides internal information but that can still be used for debug purpose.
-#[derive(Clone, PartialEq, Eq, Hash, Serialize)]
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Opaque(String);
 
 impl std::fmt::Display for Opaque {
@@ -0,0 +1,141 @@
+//! Module that allows tools to declare their own lints, similar to how clippy lints work.
+//!
+//! Tool lints are named after the tool that declared them, e.g.: `mytool::unchecked_index`.
+//! Their level is resolved by the compiler, which takes into account the lint default level,
+//! command line flags, such as `-A mytool::unchecked_index`, and lint attributes, such as
+//! `#[deny(mytool::unchecked_index)]`.
+//!
+//! Lints must be registered with the [Driver](crate::driver::Driver) before the compilation
+//! starts. E.g.:
+//!
+//! ```ignore(needs-extern-crate)
+//! use rustc_public::diagnostics::Diagnostic;
+//! use rustc_public::lint::{Lint, LintLevel};
+//!
+//! static UNCHECKED_INDEX: Lint =
+//!     Lint::new("mytool", "unchecked_index", LintLevel::Warn, "indexing without bound checks");
+//!
+//! let result = Driver::new(args)
+//!     .lint(&UNCHECKED_INDEX)
+//!     .callback(CompilerPhase::Analysis, || {
+//!         for (item, span) in find_unchecked_index() {
+//!             UNCHECKED_INDEX.report(item.def_id(), Diagnostic::warning("unchecked index").span(span));
+//!         }
+//!         ControlFlow::<()>::Continue(())
+//!     })
+//!     .run();
+//! ```
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::compiler_interface::with;
+use crate::diagnostics::Diagnostic;
+use crate::{DefId, Error};
+
+/// The level of a lint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+    /// Same as `Deny`, but it cannot be overridden by lint attributes.
+    Forbid,
+}
+
+/// A lint declared by a tool.
+///
+/// Lints should be declared as `static` items, since the compiler keeps track of them by their
+/// address.
+pub struct Lint {
+    tool: &'static str,
+    name: &'static str,
+    default_level: LintLevel,
+    description: &'static str,
+    /// The compiler representation of this lint, which is created on demand.
+    internal: OnceLock<&'static rustc_session::lint::Lint>,
+}
+
+impl Lint {
+    /// Declare a new lint for the given tool.
+    ///
+    /// The lint name should be in snake case, e.g.: `unchecked_index`.
+    pub const fn new(
+        tool: &'static str,
+        name: &'static str,
+        default_level: LintLevel,
+        description: &'static str,
+    ) -> Lint {
+        Lint { tool, name, default_level, description, internal: OnceLock::new() }
+    }
+
+    /// The name of the tool that declared this lint.
+    pub fn tool(&self) -> &'static str {
+        self.tool
+    }
+
+    /// The name of this lint without the tool prefix.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The full name of this lint, e.g.: `mytool::unchecked_index`.
+    pub fn full_name(&self) -> String {
+        format!("{}::{}", self.tool, self.name)
+    }
+
+    pub fn default_level(&self) -> LintLevel {
+        self.default_level
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Return the level of this lint for the given item.
+    ///
+    /// This can be used to skip expensive checks for lints that are allowed.
+    /// An error is returned if the item is not defined in the local crate.
+    pub fn level(&'static self, item: DefId) -> Result<LintLevel, Error> {
+        with(|cx| cx.lint_level(self, item))
+    }
+
+    /// Report a finding of this lint for the given item.
+    ///
+    /// The diagnostic level is ignored, and the lint level for the item is used instead.
+    /// If the diagnostic has no span, the span of the item is used.
+    /// Nothing is emitted if the lint is allowed for the given item.
+    /// An error is returned if the item is not defined in the local crate.
+    pub fn report(&'static self, item: DefId, diagnostic: Diagnostic) -> Result<(), Error> {
+        with(|cx| cx.emit_lint(self, item, &diagnostic))
+    }
+
+    /// Return the compiler representation of this lint.
+    pub(crate) fn internal(&'static self) -> &'static rustc_session::lint::Lint {
+        self.internal.get_or_init(|| {
+            let default_level = match self.default_level {
+                LintLevel::Allow => rustc_session::lint::Level::Allow,
+                LintLevel::Warn => rustc_session::lint::Level::Warn,
+                LintLevel::Deny => rustc_session::lint::Level::Deny,
+                LintLevel::Forbid => rustc_session::lint::Level::Forbid,
+            };
+            Box::leak(Box::new(rustc_session::lint::Lint {
+                name: Box::leak(self.full_name().into_boxed_str()),
+                default_level,
+                desc: self.description,
+                is_externally_loaded: true,
+                ..rustc_session::lint::Lint::default_fields_for_macro()
+            }))
+        })
+    }
+}
+
+impl std::fmt::Debug for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lint")
+            .field("name", &self.full_name())
+            .field("default_level", &self.default_level)
+            .finish()
+    }
+}
@@ -1,8 +1,15 @@
 pub mod alloc;
 mod body;
+pub mod dataflow;
+pub mod graphviz;
+pub mod listing;
 pub mod mono;
+pub mod parse;
 pub mod pretty;
 pub mod visit;
 
 pub use body::*;
-pub use visit::{MirVisitor, MutMirVisitor};
+pub use graphviz::DotOptions;
+pub use listing::{ListingFormat, ListingOptions};
+pub use pretty::{PathStyle, PrettyConfig};
+pub use visit::{MirVisitor, MutMirVisitor, TryMirVisitor};
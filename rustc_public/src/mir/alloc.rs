@@ -1,17 +1,26 @@
 //! This module provides methods to retrieve allocation information, such as static variables.
 
+use std::collections::{HashSet, VecDeque};
+use std::io;
 use std::io::Read;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::abi::{FieldsShape, LayoutShape, Scalar, TagEncoding, VariantsShape};
+use crate::compiler_interface::{SessionHandle, SessionId, handle_index};
+use crate::crate_def::CrateDef;
+use crate::export::handle_serde;
 use crate::mir::mono::{Instance, StaticDef};
 use crate::target::{Endian, MachineInfo};
-use crate::ty::{Allocation, Binder, ExistentialTraitRef, Ty};
-use crate::{Error, IndexedVal, with};
+use crate::ty::{
+    AdtDef, AdtKind, Allocation, Binder, ConstantKind, ExistentialTraitRef, FloatTy, GenericArgs,
+    MirConst, Prov, RigidTy, Ty, VariantIdx,
+};
+use crate::{Error, IndexedVal, Mutability, error, with};
 
 /// An allocation in the rustc_public's IR global memory can be either a function pointer,
 /// a static, or a "real" allocation with some data in it.
-#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum GlobalAlloc {
     /// The alloc ID is used as a function pointer.
     Function(Instance),
@@ -47,15 +56,24 @@ impl GlobalAlloc {
 }
 
 /// A unique identification number for each provenance
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize)]
-pub struct AllocId(usize);
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct AllocId(u32, SessionId);
+
+handle_serde!(AllocId, Alloc);
+
+impl SessionHandle for AllocId {
+    fn new_in(index: usize, session: SessionId) -> Self {
+        AllocId(handle_index(index), session)
+    }
+}
 
 impl IndexedVal for AllocId {
     fn to_val(index: usize) -> Self {
-        AllocId(index)
+        AllocId::new_in(index, SessionId::current())
     }
     fn to_index(&self) -> usize {
-        self.0
+        self.1.check("AllocId");
+        self.0 as usize
     }
 }
 
@@ -88,3 +106,429 @@ pub(crate) fn read_target_int(mut bytes: &[u8]) -> Result<i128, Error> {
         }
     }
 }
+
+impl Allocation {
+    /// Write a hex dump of this allocation, followed by every allocation that is reachable from
+    /// its pointers.
+    ///
+    /// Uninitialized bytes are printed as `__`, and pointers as `╾allocN╼`. Each reachable
+    /// allocation is printed once, either as a hex dump if it is memory, or as a description of
+    /// the function, static, vtable or type id that it stands for.
+    pub fn dump<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut printer = AllocPrinter::default();
+        printer.write_memory(w, "alloc", self)?;
+        printer.write_reachable(w)
+    }
+
+    /// Write the value stored in this allocation, decoded as a value of type `ty`.
+    ///
+    /// The value is decoded according to the layout of `ty`. References are followed into the
+    /// memory they point to, while raw pointers are printed as the allocation they point to.
+    /// Parts of the value that cannot be decoded, such as uninitialized bytes or trait
+    /// objects, are printed as bytes.
+    pub fn dump_typed<W: io::Write>(&self, ty: Ty, w: &mut W) -> io::Result<()> {
+        let value = render_value(self, 0, ty, 0).map_err(io::Error::other)?;
+        writeln!(w, "{value}")
+    }
+}
+
+impl MirConst {
+    /// Write this constant decoded as a value of its type, followed by the allocations that are
+    /// reachable from it.
+    ///
+    /// See [Allocation::dump] and [Allocation::dump_typed] for the format.
+    pub fn dump<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let ConstantKind::Allocated(alloc) = self.kind() else {
+            return writeln!(w, "{}", with(|cx| cx.mir_const_pretty(self, false)));
+        };
+        alloc.dump_typed(self.ty(), w)?;
+        let mut printer = AllocPrinter::default();
+        printer.visit_pointers(alloc);
+        printer.write_reachable(w)
+    }
+}
+
+const BYTES_PER_ROW: usize = 16;
+
+/// Print allocations, and keep track of the allocations they point to.
+#[derive(Default)]
+struct AllocPrinter {
+    seen: HashSet<AllocId>,
+    pending: VecDeque<AllocId>,
+}
+
+impl AllocPrinter {
+    fn visit_pointers(&mut self, alloc: &Allocation) {
+        for (_, prov) in &alloc.provenance.ptrs {
+            self.visit(prov.0);
+        }
+    }
+
+    fn visit(&mut self, id: AllocId) {
+        if self.seen.insert(id) {
+            self.pending.push_back(id);
+        }
+    }
+
+    fn write_reachable<W: io::Write>(&mut self, w: &mut W) -> io::Result<()> {
+        while let Some(id) = self.pending.pop_front() {
+            let name = alloc_name(id);
+            match GlobalAlloc::from(id) {
+                GlobalAlloc::Memory(alloc) => self.write_memory(w, &name, &alloc)?,
+                GlobalAlloc::Function(instance) => writeln!(w, "{name} (fn: {})", instance.name())?,
+                GlobalAlloc::Static(def) => writeln!(w, "{name} (static: {})", def.name())?,
+                GlobalAlloc::VTable(ty, Some(trait_ref)) => {
+                    writeln!(w, "{name} (vtable: impl {} for {ty})", trait_ref.value.def_id.name())?
+                }
+                GlobalAlloc::VTable(ty, None) => writeln!(w, "{name} (vtable: {ty})")?,
+                GlobalAlloc::TypeId { ty } => writeln!(w, "{name} (type id: {ty})")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_memory<W: io::Write>(
+        &mut self,
+        w: &mut W,
+        name: &str,
+        alloc: &Allocation,
+    ) -> io::Result<()> {
+        write!(w, "{name} (size: {}, align: {}", alloc.bytes.len(), alloc.align)?;
+        if alloc.mutability == Mutability::Mut {
+            write!(w, ", mutable")?;
+        }
+        writeln!(w, ") {{")?;
+        let ptr_size = MachineInfo::target_pointer_width().bytes();
+        let offset_width = format!("{:x}", alloc.bytes.len()).len().max(2);
+        for row in (0..alloc.bytes.len()).step_by(BYTES_PER_ROW) {
+            let end = alloc.bytes.len().min(row + BYTES_PER_ROW);
+            let mut hex = String::new();
+            let mut ascii = String::new();
+            let mut offset = row;
+            while offset < end {
+                if let Some(prov) = provenance_at(alloc, offset)
+                    && offset + ptr_size <= end
+                {
+                    self.visit(prov.0);
+                    let label = pointer_label(alloc, offset, prov);
+                    hex.push_str(&pointer_slot(&label, 3 * ptr_size - 1));
+                    hex.push(' ');
+                    ascii.push_str(&pointer_slot("", ptr_size));
+                    offset += ptr_size;
+                    continue;
+                }
+                match alloc.bytes[offset] {
+                    Some(byte) => {
+                        hex.push_str(&format!("{byte:02x} "));
+                        let printable = byte.is_ascii_graphic() || byte == b' ';
+                        ascii.push(if printable { byte as char } else { '.' });
+                    }
+                    None => {
+                        hex.push_str("__ ");
+                        ascii.push('░');
+                    }
+                }
+                offset += 1;
+            }
+            let hex_width = 3 * BYTES_PER_ROW;
+            writeln!(w, "    0x{row:0offset_width$x} │ {hex:<hex_width$}│ {ascii}")?;
+        }
+        writeln!(w, "}}")
+    }
+}
+
+fn alloc_name(id: AllocId) -> String {
+    format!("alloc{}", id.to_index())
+}
+
+fn provenance_at(alloc: &Allocation, offset: usize) -> Option<Prov> {
+    alloc.provenance.ptrs.iter().find(|(start, _)| *start == offset).map(|(_, prov)| *prov)
+}
+
+/// The allocation a pointer points to, and the offset into it if that isn't zero.
+fn pointer_label(alloc: &Allocation, offset: usize, prov: Prov) -> String {
+    let ptr_size = MachineInfo::target_pointer_width().bytes();
+    match alloc.read_partial_uint(offset..offset + ptr_size) {
+        Ok(0) => alloc_name(prov.0),
+        Ok(target) => format!("{}+0x{target:x}", alloc_name(prov.0)),
+        Err(_) => format!("{}+?", alloc_name(prov.0)),
+    }
+}
+
+/// Center `label` in a slot of `width` characters that starts with `╾` and ends with `╼`.
+fn pointer_slot(label: &str, width: usize) -> String {
+    let dashes = width.saturating_sub(label.chars().count() + 2);
+    let left = dashes / 2;
+    format!("╾{}{label}{}╼", "─".repeat(left), "─".repeat(dashes - left))
+}
+
+/// References are followed at most this many times when decoding a value.
+const MAX_DEPTH: usize = 8;
+
+/// Decode the value of type `ty` stored at `offset` in `alloc`.
+fn render_value(alloc: &Allocation, offset: usize, ty: Ty, depth: usize) -> Result<String, Error> {
+    let layout = ty.layout()?.shape();
+    let size = layout.size.bytes();
+    let Some(kind) = ty.kind().rigid().cloned() else {
+        return Ok(render_bytes(alloc, offset, size));
+    };
+    if !matches!(kind, RigidTy::Str | RigidTy::Slice(_)) && alloc.bytes.len() < offset + size {
+        return Err(error!(
+            "Allocation of size `{}` is too small for a value of type `{ty}` at offset `{offset}`",
+            alloc.bytes.len()
+        ));
+    }
+    let value = match kind {
+        RigidTy::Bool => match alloc.read_partial_uint(offset..offset + size) {
+            Ok(0) => "false".to_string(),
+            Ok(1) => "true".to_string(),
+            _ => render_bytes(alloc, offset, size),
+        },
+        RigidTy::Char => match alloc.read_partial_uint(offset..offset + size) {
+            Ok(value) => match u32::try_from(value).ok().and_then(char::from_u32) {
+                Some(c) => format!("{c:?}"),
+                None => render_bytes(alloc, offset, size),
+            },
+            Err(_) => render_bytes(alloc, offset, size),
+        },
+        RigidTy::Int(_) => match alloc.read_partial_uint(offset..offset + size) {
+            Ok(value) => {
+                let shift = 128 - 8 * size;
+                format!("{}_{ty}", ((value << shift) as i128) >> shift)
+            }
+            Err(_) => render_bytes(alloc, offset, size),
+        },
+        RigidTy::Uint(_) => match alloc.read_partial_uint(offset..offset + size) {
+            Ok(value) => format!("{value}_{ty}"),
+            Err(_) => render_bytes(alloc, offset, size),
+        },
+        RigidTy::Float(float) => match alloc.read_partial_uint(offset..offset + size) {
+            Ok(bits) => match float {
+                FloatTy::F32 => format!("{:?}_f32", f32::from_bits(bits as u32)),
+                FloatTy::F64 => format!("{:?}_f64", f64::from_bits(bits as u64)),
+                FloatTy::F16 | FloatTy::F128 => format!("0x{bits:x}_{ty}"),
+            },
+            Err(_) => render_bytes(alloc, offset, size),
+        },
+        RigidTy::Ref(_, pointee, _) => render_reference(alloc, offset, pointee, depth)?,
+        RigidTy::RawPtr(..) | RigidTy::FnPtr(_) => render_pointer(alloc, offset),
+        RigidTy::Tuple(fields) => {
+            let values = render_fields(alloc, offset, &layout.fields, &fields, depth)?;
+            if values.len() == 1 {
+                format!("({},)", values[0])
+            } else {
+                format!("({})", values.join(", "))
+            }
+        }
+        RigidTy::Array(elem, _) => {
+            let FieldsShape::Array { stride, count } = layout.fields else {
+                return Ok(render_bytes(alloc, offset, size));
+            };
+            render_elements(alloc, offset, elem, stride.bytes(), count as usize, depth)?
+        }
+        RigidTy::Str => render_str(alloc, offset, alloc.bytes.len().saturating_sub(offset)),
+        RigidTy::Slice(elem) => {
+            let stride = elem.layout()?.shape().size.bytes();
+            let count = alloc.bytes.len().saturating_sub(offset).checked_div(stride).unwrap_or(0);
+            render_elements(alloc, offset, elem, stride, count, depth)?
+        }
+        RigidTy::Adt(def, args) => render_adt(alloc, offset, def, &args, &layout, depth)?,
+        _ => render_bytes(alloc, offset, size),
+    };
+    Ok(value)
+}
+
+fn render_bytes(alloc: &Allocation, offset: usize, size: usize) -> String {
+    let end = alloc.bytes.len().min(offset + size);
+    let bytes: Vec<String> = alloc.bytes[offset.min(end)..end]
+        .iter()
+        .map(|byte| byte.map_or("__".to_string(), |byte| format!("{byte:02x}")))
+        .collect();
+    format!("<bytes: {}>", bytes.join(" "))
+}
+
+fn render_str(alloc: &Allocation, offset: usize, len: usize) -> String {
+    match alloc.bytes.get(offset..offset + len).and_then(|bytes| {
+        let bytes = bytes.iter().copied().collect::<Option<Vec<u8>>>()?;
+        String::from_utf8(bytes).ok()
+    }) {
+        Some(text) => format!("{text:?}"),
+        None => render_bytes(alloc, offset, len),
+    }
+}
+
+fn render_elements(
+    alloc: &Allocation,
+    offset: usize,
+    elem: Ty,
+    stride: usize,
+    count: usize,
+    depth: usize,
+) -> Result<String, Error> {
+    let values = (0..count)
+        .map(|index| render_value(alloc, offset + index * stride, elem, depth))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("[{}]", values.join(", ")))
+}
+
+fn render_fields(
+    alloc: &Allocation,
+    offset: usize,
+    shape: &FieldsShape,
+    tys: &[Ty],
+    depth: usize,
+) -> Result<Vec<String>, Error> {
+    let FieldsShape::Arbitrary { offsets } = shape else {
+        return Err(error!("Expected fields with arbitrary offsets, but found `{shape:?}`"));
+    };
+    tys.iter()
+        .zip(offsets)
+        .map(|(ty, field_offset)| render_value(alloc, offset + field_offset.bytes(), *ty, depth))
+        .collect()
+}
+
+fn render_adt(
+    alloc: &Allocation,
+    offset: usize,
+    def: AdtDef,
+    args: &GenericArgs,
+    layout: &LayoutShape,
+    depth: usize,
+) -> Result<String, Error> {
+    if def.kind() == AdtKind::Union {
+        return Ok(format!("{} {}", def.name(), render_bytes(alloc, offset, layout.size.bytes())));
+    }
+    let (variant, fields_shape) = match &layout.variants {
+        VariantsShape::Empty => return Ok(render_bytes(alloc, offset, layout.size.bytes())),
+        VariantsShape::Single { index } => (*index, &layout.fields),
+        VariantsShape::Multiple { tag, tag_encoding, tag_field, variants } => {
+            let Some(index) =
+                read_variant(alloc, offset, def, layout, tag, tag_encoding, *tag_field)
+            else {
+                return Ok(render_bytes(alloc, offset, layout.size.bytes()));
+            };
+            (index, &variants[index.to_index()].fields)
+        }
+    };
+    let variant = def.variant(variant).unwrap();
+    let fields = variant.fields();
+    let tys: Vec<Ty> = fields.iter().map(|field| field.ty_with_args(args)).collect();
+    let values = render_fields(alloc, offset, fields_shape, &tys, depth)?;
+    let name = if def.kind() == AdtKind::Enum {
+        format!("{}::{}", def.name(), variant.name())
+    } else {
+        def.name()
+    };
+    Ok(if values.is_empty() {
+        name
+    } else if fields.iter().all(|field| field.name.parse::<usize>().is_ok()) {
+        format!("{name}({})", values.join(", "))
+    } else {
+        let values: Vec<String> = fields
+            .iter()
+            .zip(values)
+            .map(|(field, value)| format!("{}: {value}", field.name))
+            .collect();
+        format!("{name} {{ {} }}", values.join(", "))
+    })
+}
+
+/// Find the variant of an enum with multiple variants from its tag, if the tag is initialized.
+fn read_variant(
+    alloc: &Allocation,
+    offset: usize,
+    def: AdtDef,
+    layout: &LayoutShape,
+    tag: &Scalar,
+    tag_encoding: &TagEncoding,
+    tag_field: usize,
+) -> Option<VariantIdx> {
+    let FieldsShape::Arbitrary { offsets } = &layout.fields else { return None };
+    let tag_offset = offset + offsets[tag_field].bytes();
+    let tag_size = match tag {
+        Scalar::Initialized { value, .. } | Scalar::Union { value } => {
+            value.size(&MachineInfo::target()).bytes()
+        }
+    };
+    let mask = u128::MAX >> (128 - 8 * tag_size);
+    match tag_encoding {
+        TagEncoding::Direct => {
+            let value = alloc.read_partial_uint(tag_offset..tag_offset + tag_size).ok()?;
+            def.variants_iter()
+                .map(|variant| variant.idx)
+                .find(|idx| def.discriminant_for_variant(*idx).val & mask == value)
+        }
+        TagEncoding::Niche { untagged_variant, niche_variants, niche_start } => {
+            // A pointer is never a niche value.
+            if provenance_at(alloc, tag_offset).is_some() {
+                return Some(*untagged_variant);
+            }
+            let value = alloc.read_partial_uint(tag_offset..tag_offset + tag_size).ok()?;
+            let relative = value.wrapping_sub(*niche_start) & mask;
+            let start = niche_variants.start().to_index();
+            let end = niche_variants.end().to_index();
+            if relative <= (end - start) as u128 {
+                Some(VariantIdx::to_val(start + relative as usize))
+            } else {
+                Some(*untagged_variant)
+            }
+        }
+    }
+}
+
+fn render_pointer(alloc: &Allocation, offset: usize) -> String {
+    let ptr_size = MachineInfo::target_pointer_width().bytes();
+    match provenance_at(alloc, offset) {
+        Some(prov) => match GlobalAlloc::from(prov.0) {
+            GlobalAlloc::Function(instance) => format!("fn {}", instance.name()),
+            _ => pointer_label(alloc, offset, prov),
+        },
+        None => match alloc.read_partial_uint(offset..offset + ptr_size) {
+            Ok(address) => format!("0x{address:x}"),
+            Err(_) => render_bytes(alloc, offset, ptr_size),
+        },
+    }
+}
+
+/// Decode a reference by decoding the value it points to.
+///
+/// Only references into memory are followed, and slices and string slices use the length
+/// stored next to the pointer.
+fn render_reference(
+    alloc: &Allocation,
+    offset: usize,
+    pointee: Ty,
+    depth: usize,
+) -> Result<String, Error> {
+    let ptr_size = MachineInfo::target_pointer_width().bytes();
+    let Some(prov) = provenance_at(alloc, offset) else {
+        return Ok(render_pointer(alloc, offset));
+    };
+    let target = match GlobalAlloc::from(prov.0) {
+        GlobalAlloc::Memory(target) if depth < MAX_DEPTH => target,
+        GlobalAlloc::Static(def) => return Ok(format!("&{}", def.name())),
+        _ => return Ok(format!("&{}", render_pointer(alloc, offset))),
+    };
+    let Ok(target_offset) = alloc.read_partial_uint(offset..offset + ptr_size) else {
+        return Ok(render_bytes(alloc, offset, ptr_size));
+    };
+    let target_offset = target_offset as usize;
+    let value = match pointee.kind().rigid() {
+        Some(RigidTy::Str) | Some(RigidTy::Slice(_)) => {
+            let metadata = offset + ptr_size;
+            let Ok(len) = alloc.read_partial_uint(metadata..metadata + ptr_size) else {
+                return Ok(render_bytes(alloc, offset, 2 * ptr_size));
+            };
+            match pointee.kind().rigid() {
+                Some(RigidTy::Slice(elem)) => {
+                    let stride = elem.layout()?.shape().size.bytes();
+                    render_elements(&target, target_offset, *elem, stride, len as usize, depth + 1)?
+                }
+                _ => render_str(&target, target_offset, len as usize),
+            }
+        }
+        Some(RigidTy::Dynamic(..)) => pointer_label(alloc, offset, prov),
+        _ => render_value(&target, target_offset, pointee, depth + 1)?,
+    };
+    Ok(format!("&{value}"))
+}
@@ -1,17 +1,20 @@
 use std::io;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::compiler_interface::with;
-use crate::mir::pretty::function_body;
+use crate::mir::graphviz::{DotOptions, write_dot};
+use crate::mir::listing::{ListingOptions, write_listing};
+use crate::mir::pretty::{PrettyConfig, function_body};
+use crate::mir::visit::Location;
 use crate::ty::{
     AdtDef, ClosureDef, CoroutineClosureDef, CoroutineDef, GenericArgs, MirConst, Movability,
     Region, RigidTy, Ty, TyConst, TyKind, VariantIdx,
 };
-use crate::{Error, Opaque, Span, Symbol};
+use crate::{Error, ErrorKind, Opaque, Span, Symbol};
 
 /// The rustc_public's IR representation of a single function.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Body {
     pub blocks: Vec<BasicBlock>,
 
@@ -35,6 +38,13 @@ pub struct Body {
 
     /// The span that covers the entire function body.
     pub span: Span,
+
+    /// The source scope of each statement and terminator, by block and then by statement index,
+    /// where the terminator follows the statements of its block.
+    ///
+    /// This is empty for bodies that were not built by the compiler, whose statements and
+    /// terminators are all in the outermost scope.
+    pub(super) source_scopes: Vec<Vec<SourceScope>>,
 }
 
 pub type BasicBlockIdx = usize;
@@ -58,7 +68,12 @@ impl Body {
             locals.len() > arg_count,
             "A Body must contain at least a local for the return value and each of the function's arguments"
         );
-        Self { blocks, locals, arg_count, var_debug_info, spread_arg, span }
+        Self { blocks, locals, arg_count, var_debug_info, spread_arg, span, source_scopes: vec![] }
+    }
+
+    /// Set the source scope of each statement and terminator of the body.
+    pub(crate) fn with_source_scopes(self, source_scopes: Vec<Vec<SourceScope>>) -> Self {
+        Self { source_scopes, ..self }
     }
 
     /// Return local that holds this function's return value.
@@ -111,9 +126,79 @@ impl Body {
         self.locals.iter().enumerate()
     }
 
+    /// Get the statement at the given location, if it is a valid statement location.
+    pub fn stmt_at(&self, location: Location) -> Option<&Statement> {
+        self.blocks.get(location.block)?.statements.get(location.statement_index)
+    }
+
+    /// Get the terminator at the given location, if it is the location of a terminator, i.e.,
+    /// the index one past the last statement of its block.
+    pub fn terminator_at(&self, location: Location) -> Option<&Terminator> {
+        let bb = self.blocks.get(location.block)?;
+        (location.statement_index == bb.statements.len()).then_some(&bb.terminator)
+    }
+
+    /// Get the source scope of the statement or terminator at the given location, if it is a
+    /// valid location.
+    ///
+    /// Bodies created with [Body::new] only have the outermost scope.
+    pub fn source_scope(&self, location: Location) -> Option<SourceScope> {
+        self.scope_at(location.block, location.statement_index)
+    }
+
+    /// Get the source scope of the statement or terminator at the given index of `block`.
+    pub(crate) fn scope_at(
+        &self,
+        block: BasicBlockIdx,
+        statement_index: usize,
+    ) -> Option<SourceScope> {
+        if statement_index > self.blocks.get(block)?.statements.len() {
+            return None;
+        }
+        let scope = self.source_scopes.get(block).and_then(|scopes| scopes.get(statement_index));
+        Some(scope.copied().unwrap_or(OUTERMOST_SOURCE_SCOPE))
+    }
+
     /// Emit the body using the provided name for the signature.
     pub fn dump<W: io::Write>(&self, w: &mut W, fn_name: &str) -> io::Result<()> {
-        function_body(w, self, fn_name)
+        function_body(w, self, fn_name, &PrettyConfig::default())
+    }
+
+    /// Emit the body using the provided name for the signature, formatted according to `config`.
+    pub fn dump_with<W: io::Write>(
+        &self,
+        config: &PrettyConfig,
+        w: &mut W,
+        fn_name: &str,
+    ) -> io::Result<()> {
+        function_body(w, self, fn_name, config)
+    }
+
+    /// Emit the body with its statements and terminators listed below the source code they
+    /// come from.
+    pub fn dump_with_source<W: io::Write>(
+        &self,
+        options: &ListingOptions,
+        w: &mut W,
+        fn_name: &str,
+    ) -> io::Result<()> {
+        write_listing(w, self, fn_name, options)
+    }
+
+    /// Emit the control-flow graph of the body in the Graphviz DOT format.
+    pub fn write_dot<W: io::Write>(&self, w: &mut W, options: &DotOptions) -> io::Result<()> {
+        write_dot(w, self, options)
+    }
+
+    /// Parse the textual representation of a single function, as printed by [Body::dump].
+    ///
+    /// See [parse](crate::mir::parse) for how the text is interpreted.
+    pub fn parse(input: &str) -> Result<Body, Error> {
+        let mut bodies = crate::mir::parse::parse_bodies(input)?;
+        if bodies.len() != 1 {
+            return Err(error!("Expected a single function, but found {}", bodies.len()));
+        }
+        Ok(bodies.pop().unwrap().1)
     }
 
     pub fn spread_arg(&self) -> Option<Local> {
@@ -123,20 +208,20 @@ impl Body {
 
 type LocalDecls = Vec<LocalDecl>;
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct LocalDecl {
     pub ty: Ty,
     pub span: Span,
     pub mutability: Mutability,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct BasicBlock {
     pub statements: Vec<Statement>,
     pub terminator: Terminator,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Terminator {
     pub kind: TerminatorKind,
     pub span: Span,
@@ -150,7 +235,7 @@ impl Terminator {
 
 pub type Successors = Vec<BasicBlockIdx>;
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum TerminatorKind {
     Goto {
         target: BasicBlockIdx,
@@ -240,7 +325,7 @@ impl TerminatorKind {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct InlineAsmOperand {
     pub in_value: Option<Operand>,
     pub out_place: Option<Place>,
@@ -249,7 +334,7 @@ pub struct InlineAsmOperand {
     pub raw_rpr: String,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum UnwindAction {
     Continue,
     Unreachable,
@@ -257,7 +342,7 @@ pub enum UnwindAction {
     Cleanup(BasicBlockIdx),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum AssertMessage {
     BoundsCheck { len: Operand, index: Operand },
     Overflow(BinOp, Operand, Operand),
@@ -349,7 +434,7 @@ impl AssertMessage {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum BinOp {
     Add,
     AddUnchecked,
@@ -384,7 +469,7 @@ impl BinOp {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum UnOp {
     Not,
     Neg,
@@ -399,20 +484,20 @@ impl UnOp {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CoroutineKind {
     Desugared(CoroutineDesugaring, CoroutineSource),
     Coroutine(Movability),
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CoroutineSource {
     Block,
     Closure,
     Fn,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CoroutineDesugaring {
     Async,
 
@@ -428,7 +513,7 @@ pub(crate) type LocalDefId = Opaque;
 pub(crate) type Coverage = Opaque;
 
 /// The FakeReadCause describes the type of pattern why a FakeRead statement exists.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum FakeReadCause {
     ForMatchGuard,
     ForMatchedPlace(LocalDefId),
@@ -438,7 +523,7 @@ pub enum FakeReadCause {
 }
 
 /// Describes what kind of retag is to be performed
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum RetagKind {
     FnEntry,
     TwoPhase,
@@ -446,7 +531,7 @@ pub enum RetagKind {
     Default,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Variance {
     Covariant,
     Invariant,
@@ -454,26 +539,26 @@ pub enum Variance {
     Bivariant,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct CopyNonOverlapping {
     pub src: Operand,
     pub dst: Operand,
     pub count: Operand,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum NonDivergingIntrinsic {
     Assume(Operand),
     CopyNonOverlapping(CopyNonOverlapping),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Statement {
     pub kind: StatementKind,
     pub span: Span,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum StatementKind {
     Assign(Place, Rvalue),
     FakeRead(FakeReadCause, Place),
@@ -490,7 +575,7 @@ pub enum StatementKind {
     Nop,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Rvalue {
     /// Creates a pointer with the indicated mutability to the place.
     ///
@@ -637,10 +722,12 @@ impl Rvalue {
             }
             Rvalue::Discriminant(place) => {
                 let place_ty = place.ty(locals)?;
-                place_ty
-                    .kind()
-                    .discriminant_ty()
-                    .ok_or_else(|| error!("Expected a `RigidTy` but found: {place_ty:?}"))
+                place_ty.kind().discriminant_ty().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::UnexpectedTy(place_ty),
+                        format!("Expected a `RigidTy` but found: {place_ty:?}"),
+                    )
+                })
             }
             Rvalue::NullaryOp(NullOp::SizeOf | NullOp::AlignOf | NullOp::OffsetOf(..), _) => {
                 Ok(Ty::usize_ty())
@@ -666,7 +753,7 @@ impl Rvalue {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum AggregateKind {
     Array(Ty),
     Tuple,
@@ -677,14 +764,14 @@ pub enum AggregateKind {
     RawPtr(Ty, Mutability),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Operand {
     Copy(Place),
     Move(Place),
     Constant(ConstOperand),
 }
 
-#[derive(Clone, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Place {
     pub local: Local,
     /// projection out of a place (access a field, deref a pointer, etc)
@@ -697,7 +784,7 @@ impl From<Local> for Place {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct ConstOperand {
     pub span: Span,
     pub user_ty: Option<UserTypeAnnotationIndex>,
@@ -705,7 +792,7 @@ pub struct ConstOperand {
 }
 
 /// Debug information pertaining to a user variable.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct VarDebugInfo {
     /// The variable name.
     pub name: Symbol,
@@ -747,19 +834,22 @@ impl VarDebugInfo {
 
 pub type SourceScope = u32;
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+/// The scope of the function body, which contains every other scope.
+pub const OUTERMOST_SOURCE_SCOPE: SourceScope = 0;
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SourceInfo {
     pub span: Span,
     pub scope: SourceScope,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct VarDebugInfoFragment {
     pub ty: Ty,
     pub projection: Vec<ProjectionElem>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum VarDebugInfoContents {
     Place(Place),
     Const(ConstOperand),
@@ -770,7 +860,7 @@ pub enum VarDebugInfoContents {
 // ProjectionElem<Local, Ty>) and user-provided type annotations (for which the projection elements
 // are of type ProjectionElem<(), ()>).
 // In rustc_public's IR we don't need this generality, so we just use ProjectionElem for Places.
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum ProjectionElem {
     /// Dereference projections (e.g. `*_1`) project to the address referenced by the base place.
     Deref,
@@ -846,7 +936,7 @@ pub enum ProjectionElem {
     Subtype(Ty),
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct UserTypeProjection {
     pub base: UserTypeAnnotationIndex,
 
@@ -876,7 +966,7 @@ pub type FieldIdx = usize;
 type UserTypeAnnotationIndex = usize;
 
 /// The possible branch sites of a [TerminatorKind::SwitchInt].
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SwitchTargets {
     /// The conditional branches where the first element represents the value that guards this
     /// branch, and the second element is the branch target.
@@ -913,7 +1003,7 @@ impl SwitchTargets {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum BorrowKind {
     /// Data must be immutable and is aliasable.
     Shared,
@@ -940,7 +1030,7 @@ impl BorrowKind {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum RawPtrKind {
     Mut,
     Const,
@@ -958,14 +1048,14 @@ impl RawPtrKind {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum MutBorrowKind {
     Default,
     TwoPhaseBorrow,
     ClosureCapture,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum FakeBorrowKind {
     /// A shared (deep) borrow. Data must be immutable and is aliasable.
     Deep,
@@ -976,19 +1066,19 @@ pub enum FakeBorrowKind {
     Shallow,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Mutability {
     Not,
     Mut,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Safety {
     Safe,
     Unsafe,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum PointerCoercion {
     /// Go from a fn-item type to a fn-pointer type.
     ReifyFnPointer,
@@ -1015,7 +1105,7 @@ pub enum PointerCoercion {
     Unsize,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum CastKind {
     // FIXME(smir-rename): rename this to PointerExposeProvenance
     PointerExposeAddress,
@@ -1030,7 +1120,7 @@ pub enum CastKind {
     Transmute,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum NullOp {
     /// Returns the size of a value of that type.
     SizeOf,
@@ -1094,7 +1184,9 @@ impl ProjectionElem {
     }
 
     fn index_ty(ty: Ty) -> Result<Ty, Error> {
-        ty.kind().builtin_index().ok_or_else(|| error!("Cannot index non-array type: {ty:?}"))
+        ty.kind().builtin_index().ok_or_else(|| {
+            Error::new(ErrorKind::UnexpectedTy(ty), format!("Cannot index non-array type: {ty:?}"))
+        })
     }
 
     fn subslice_ty(ty: Ty, from: u64, to: u64, from_end: bool) -> Result<Ty, Error> {
@@ -1110,15 +1202,17 @@ impl ProjectionElem {
                 let len = size - from - to;
                 Ty::try_new_array(inner, len)
             }
-            _ => Err(Error(format!("Cannot subslice non-array type: `{ty_kind:?}`"))),
+            _ => Err(Error::new(
+                ErrorKind::UnexpectedTy(ty),
+                format!("Cannot subslice non-array type: `{ty_kind:?}`"),
+            )),
         }
     }
 
     fn deref_ty(ty: Ty) -> Result<Ty, Error> {
-        let deref_ty = ty
-            .kind()
-            .builtin_deref(true)
-            .ok_or_else(|| error!("Cannot dereference type: {ty:?}"))?;
+        let deref_ty = ty.kind().builtin_deref(true).ok_or_else(|| {
+            Error::new(ErrorKind::UnexpectedTy(ty), format!("Cannot dereference type: {ty:?}"))
+        })?;
         Ok(deref_ty.ty)
     }
 }
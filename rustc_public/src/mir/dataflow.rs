@@ -0,0 +1,406 @@
+//! A generic framework for dataflow analyses over a [Body].
+//!
+//! An analysis describes a lattice of states through [Analysis::Domain], and how each statement
+//! and terminator transforms a state. Calling [Analysis::iterate_to_fixpoint] runs a worklist
+//! over the control-flow graph until the state at the entry of every block stops changing. The
+//! resulting [Results] can then be inspected at any [Location] with a [ResultsCursor].
+//!
+//! Analyses run either in the [Forward] or [Backward] direction. The graph is derived from
+//! [TerminatorKind::successors], and two kinds of edges may carry their own effect:
+//! - the edges of a [TerminatorKind::SwitchInt], which know the value of the discriminant;
+//! - the edge from a [TerminatorKind::Call] to its return target, which is only taken once the
+//!   call returned and its destination was written.
+//!
+//! Blocks that are unreachable from the boundary of the analysis keep the bottom state.
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::mir::visit::{Location, statement_location, terminator_location};
+use crate::mir::{BasicBlockIdx, Body, Operand, Place, Statement, Terminator, TerminatorKind};
+
+/// A join-semilattice, i.e., a partial order in which any two elements have a least upper bound.
+///
+/// The bottom element usually depends on the body being analyzed, e.g., on its number of locals,
+/// and is provided by [Analysis::bottom_value].
+pub trait Lattice: Clone + Eq {
+    /// Update `self` to the least upper bound of `self` and `other`.
+    ///
+    /// Returns `true` if `self` changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+impl Lattice for bool {
+    fn join(&mut self, other: &Self) -> bool {
+        let changed = !*self && *other;
+        *self |= *other;
+        changed
+    }
+}
+
+impl<T: Clone + Ord> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        let len = self.len();
+        self.extend(other.iter().cloned());
+        self.len() != len
+    }
+}
+
+/// Join each element with the element of `other` at the same index.
+///
+/// This is handy for per-local states. Both vectors must have the same length.
+impl<L: Lattice> Lattice for Vec<L> {
+    fn join(&mut self, other: &Self) -> bool {
+        assert_eq!(self.len(), other.len(), "Cannot join vectors of different lengths");
+        let mut changed = false;
+        for (this, other) in self.iter_mut().zip(other) {
+            changed |= this.join(other);
+        }
+        changed
+    }
+}
+
+/// A lattice that extends a set of values with a bottom and a top element.
+///
+/// Distinct values are incomparable, so joining two of them results in [FlatSet::Top]. This is
+/// the lattice used by constant propagation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FlatSet<T> {
+    Bottom,
+    Elem(T),
+    Top,
+}
+
+impl<T: Clone + Eq> Lattice for FlatSet<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        let result = match (&*self, other) {
+            (_, FlatSet::Bottom) | (FlatSet::Top, _) => return false,
+            (FlatSet::Elem(this), FlatSet::Elem(other)) if this == other => return false,
+            (FlatSet::Bottom, FlatSet::Elem(other)) => FlatSet::Elem(other.clone()),
+            _ => FlatSet::Top,
+        };
+        *self = result;
+        true
+    }
+}
+
+/// The direction in which an [Analysis] propagates its state.
+pub trait Direction {
+    /// Whether states flow from a block to its successors.
+    const IS_FORWARD: bool;
+}
+
+/// States flow from the start of the body, through each block in program order, to the
+/// successors of the block.
+pub struct Forward;
+
+/// States flow from the end of the body, through each block in reverse program order, to the
+/// predecessors of the block.
+pub struct Backward;
+
+impl Direction for Forward {
+    const IS_FORWARD: bool = true;
+}
+
+impl Direction for Backward {
+    const IS_FORWARD: bool = false;
+}
+
+/// The value of the discriminant of a [TerminatorKind::SwitchInt] on one of its outgoing edges.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SwitchIntEdge {
+    /// The edge is taken when the discriminant has this value.
+    Value(u128),
+    /// The edge is taken when the discriminant matches none of the values.
+    Otherwise,
+}
+
+/// A dataflow analysis.
+///
+/// Only the transfer functions of statements and terminators are required. Edge effects default
+/// to doing nothing.
+pub trait Analysis {
+    /// The lattice of states tracked by this analysis.
+    type Domain: Lattice;
+
+    /// The direction of this analysis, either [Forward] or [Backward].
+    type Direction: Direction;
+
+    /// The bottom element of the lattice for the given body.
+    ///
+    /// Every block starts with this state, and unreachable blocks keep it.
+    fn bottom_value(&self, body: &Body) -> Self::Domain;
+
+    /// Set up the state at the boundary of the analysis.
+    ///
+    /// For a forward analysis, this is the state on entry to the start block. For a backward
+    /// analysis, it is the state on exit from every block without successors, such as the ones
+    /// that return.
+    fn initialize_start_state(&self, body: &Body, state: &mut Self::Domain);
+
+    /// Apply the effect of the statement at `location` to `state`.
+    fn apply_statement_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        statement: &Statement,
+        location: Location,
+    );
+
+    /// Apply the effect of the terminator at `location` to `state`.
+    ///
+    /// For a call, the effect of writing the destination should be applied in
+    /// [Analysis::apply_call_return_effect] instead, since it does not happen when unwinding.
+    fn apply_terminator_effect(
+        &mut self,
+        state: &mut Self::Domain,
+        terminator: &Terminator,
+        location: Location,
+    );
+
+    /// Apply the effect of taking an edge of a [TerminatorKind::SwitchInt] that switches on
+    /// `discr`.
+    ///
+    /// This is applied after the terminator effect in a forward analysis, and before it in a
+    /// backward one.
+    fn apply_switch_int_edge_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _discr: &Operand,
+        _edge: SwitchIntEdge,
+    ) {
+    }
+
+    /// Apply the effect of a call at `location` successfully returning and writing its result
+    /// to `destination`.
+    ///
+    /// This is only applied to the edge to the return target of the call, not to the unwind edge.
+    fn apply_call_return_effect(
+        &mut self,
+        _state: &mut Self::Domain,
+        _destination: &Place,
+        _location: Location,
+    ) {
+    }
+
+    /// Run this analysis on `body` until it reaches a fixpoint.
+    ///
+    /// This will not terminate if the lattice has infinite ascending chains or if the transfer
+    /// functions are not monotone.
+    fn iterate_to_fixpoint(mut self, body: &Body) -> Results<Self>
+    where
+        Self: Sized,
+    {
+        let bottom = self.bottom_value(body);
+        let mut entry_states = vec![bottom; body.blocks.len()];
+        let mut dirty = vec![true; body.blocks.len()];
+        let mut worklist: VecDeque<BasicBlockIdx> = (0..body.blocks.len()).collect();
+        if Self::Direction::IS_FORWARD {
+            if let Some(state) = entry_states.first_mut() {
+                self.initialize_start_state(body, state);
+            }
+        } else {
+            for (bb, block) in body.blocks.iter().enumerate() {
+                if block.terminator.successors().is_empty() {
+                    self.initialize_start_state(body, &mut entry_states[bb]);
+                }
+            }
+            worklist.make_contiguous().reverse();
+        }
+
+        let predecessors = if Self::Direction::IS_FORWARD { vec![] } else { predecessors(body) };
+        while let Some(bb) = worklist.pop_front() {
+            dirty[bb] = false;
+            let mut state = entry_states[bb].clone();
+            let effects = body.blocks[bb].statements.len() + 1;
+            for effect in 0..effects {
+                apply_effect(&mut self, body, bb, effect, &mut state);
+            }
+
+            let mut propagate = |analysis: &mut Self, from, edge, target: BasicBlockIdx| {
+                let mut state = state.clone();
+                apply_edge_effect(analysis, body, from, edge, &mut state);
+                if entry_states[target].join(&state) && !dirty[target] {
+                    dirty[target] = true;
+                    worklist.push_back(target);
+                }
+            };
+            if Self::Direction::IS_FORWARD {
+                for (edge, target) in
+                    body.blocks[bb].terminator.successors().into_iter().enumerate()
+                {
+                    propagate(&mut self, bb, edge, target);
+                }
+            } else {
+                for &(pred, edge) in &predecessors[bb] {
+                    propagate(&mut self, pred, edge, pred);
+                }
+            }
+        }
+
+        Results { analysis: self, entry_states }
+    }
+}
+
+/// The fixpoint of an [Analysis] over a body.
+pub struct Results<A: Analysis> {
+    /// The analysis that produced these results.
+    pub analysis: A,
+    entry_states: Vec<A::Domain>,
+}
+
+impl<A: Analysis> Results<A> {
+    /// The state on entry to `block` in the direction of the analysis.
+    ///
+    /// This is the state before the first statement of a forward analysis, and the state after
+    /// the terminator of a backward one.
+    pub fn entry_state(&self, block: BasicBlockIdx) -> &A::Domain {
+        &self.entry_states[block]
+    }
+
+    /// Create a cursor to inspect the state at any location of `body`.
+    ///
+    /// The body must be the one these results were computed for.
+    pub fn cursor<'a>(&'a mut self, body: &'a Body) -> ResultsCursor<'a, A> {
+        let state = self.analysis.bottom_value(body);
+        ResultsCursor { body, results: self, state, position: None }
+    }
+}
+
+/// Inspect the state of an analysis at arbitrary locations of a body.
+///
+/// The state is recomputed from the entry state of the block, so seeking to locations of the same
+/// block in the direction of the analysis is cheaper than seeking in arbitrary order.
+pub struct ResultsCursor<'a, A: Analysis> {
+    body: &'a Body,
+    results: &'a mut Results<A>,
+    state: A::Domain,
+    /// The block of the current state, and how many effects of the block have been applied to it.
+    position: Option<(BasicBlockIdx, usize)>,
+}
+
+impl<'a, A: Analysis> ResultsCursor<'a, A> {
+    /// The body this cursor inspects.
+    pub fn body(&self) -> &'a Body {
+        self.body
+    }
+
+    /// The results this cursor inspects.
+    pub fn results(&self) -> &Results<A> {
+        self.results
+    }
+
+    /// The state at the current position of the cursor.
+    pub fn get(&self) -> &A::Domain {
+        &self.state
+    }
+
+    /// Move to the state right before the statement or terminator at `location` is executed.
+    pub fn seek_before(&mut self, location: Location) {
+        let (block, index, effects) = self.checked(location);
+        self.seek(block, if A::Direction::IS_FORWARD { index } else { effects - index });
+    }
+
+    /// Move to the state right after the statement or terminator at `location` is executed.
+    ///
+    /// For a terminator, this does not include the effects of any of its outgoing edges.
+    pub fn seek_after(&mut self, location: Location) {
+        let (block, index, effects) = self.checked(location);
+        self.seek(block, if A::Direction::IS_FORWARD { index + 1 } else { effects - index - 1 });
+    }
+
+    /// Move to the state before the first statement of `block` is executed.
+    pub fn seek_to_block_start(&mut self, block: BasicBlockIdx) {
+        let effects = self.body.blocks[block].statements.len() + 1;
+        self.seek(block, if A::Direction::IS_FORWARD { 0 } else { effects });
+    }
+
+    /// Move to the state after the terminator of `block` is executed.
+    ///
+    /// This does not include the effects of any of its outgoing edges.
+    pub fn seek_to_block_end(&mut self, block: BasicBlockIdx) {
+        let effects = self.body.blocks[block].statements.len() + 1;
+        self.seek(block, if A::Direction::IS_FORWARD { effects } else { 0 });
+    }
+
+    /// Returns the block and statement index of `location`, with the number of effects in its
+    /// block.
+    fn checked(&self, location: Location) -> (BasicBlockIdx, usize, usize) {
+        let effects = self.body.blocks[location.block].statements.len() + 1;
+        assert!(location.statement_index < effects, "Invalid location: {location:?}");
+        (location.block, location.statement_index, effects)
+    }
+
+    fn seek(&mut self, block: BasicBlockIdx, target: usize) {
+        let applied = match self.position {
+            Some((current, applied)) if current == block && applied <= target => applied,
+            _ => {
+                self.state.clone_from(&self.results.entry_states[block]);
+                0
+            }
+        };
+        for effect in applied..target {
+            apply_effect(&mut self.results.analysis, self.body, block, effect, &mut self.state);
+        }
+        self.position = Some((block, target));
+    }
+}
+
+/// Apply the `effect`-th effect of `block` in the direction of the analysis.
+///
+/// The effects of a block are its statements followed by its terminator in a forward analysis,
+/// and the reverse in a backward one.
+fn apply_effect<A: Analysis>(
+    analysis: &mut A,
+    body: &Body,
+    block: BasicBlockIdx,
+    effect: usize,
+    state: &mut A::Domain,
+) {
+    let bb = &body.blocks[block];
+    let index = if A::Direction::IS_FORWARD { effect } else { bb.statements.len() - effect };
+    match bb.statements.get(index) {
+        Some(statement) => {
+            let location = statement_location(body, &block, index);
+            analysis.apply_statement_effect(state, statement, location)
+        }
+        None => {
+            let location = terminator_location(body, &block);
+            analysis.apply_terminator_effect(state, &bb.terminator, location)
+        }
+    }
+}
+
+/// Apply the effect of the `edge`-th successor of the terminator of `block`, if it has any.
+fn apply_edge_effect<A: Analysis>(
+    analysis: &mut A,
+    body: &Body,
+    block: BasicBlockIdx,
+    edge: usize,
+    state: &mut A::Domain,
+) {
+    match &body.blocks[block].terminator.kind {
+        TerminatorKind::SwitchInt { discr, targets } => {
+            let edge = match targets.branches().nth(edge) {
+                Some((value, _)) => SwitchIntEdge::Value(value),
+                None => SwitchIntEdge::Otherwise,
+            };
+            analysis.apply_switch_int_edge_effect(state, discr, edge);
+        }
+        // The return target always comes first in the successors of a call.
+        TerminatorKind::Call { destination, target: Some(_), .. } if edge == 0 => {
+            let location = terminator_location(body, &block);
+            analysis.apply_call_return_effect(state, destination, location);
+        }
+        _ => {}
+    }
+}
+
+/// For each block, the blocks that branch to it along with the index of the edge within the
+/// successors of their terminator.
+fn predecessors(body: &Body) -> Vec<Vec<(BasicBlockIdx, usize)>> {
+    let mut predecessors = vec![vec![]; body.blocks.len()];
+    for (bb, block) in body.blocks.iter().enumerate() {
+        for (edge, target) in block.terminator.successors().into_iter().enumerate() {
+            predecessors[target].push((bb, edge));
+        }
+    }
+    predecessors
+}
@@ -0,0 +1,135 @@
+//! Render the control-flow graph of a body in the Graphviz DOT format.
+//!
+//! Each basic block becomes a node that lists its statements and its terminator, and each edge
+//! is labelled by the kind of control flow it represents. Blocks that are only reachable by
+//! unwinding are drawn with a different background.
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::iter;
+
+use crate::mir::pretty::{PrettyConfig, basic_block_lines};
+use crate::mir::{BasicBlockIdx, Body, SourceScope, TerminatorKind, UnwindAction};
+
+/// Options that control how [Body::write_dot] renders a body.
+#[derive(Clone, Debug, Default)]
+pub struct DotOptions {
+    /// A label for the whole graph, e.g. the name of the function.
+    pub title: Option<String>,
+    /// Put the blocks of each source scope in their own cluster.
+    ///
+    /// A block belongs to the scope of its terminator.
+    pub group_by_scope: bool,
+    /// How statements and terminators are printed inside the nodes.
+    pub pretty: PrettyConfig,
+}
+
+const FONT: &str = "Courier, monospace";
+
+pub(crate) fn write_dot<W: Write>(
+    writer: &mut W,
+    body: &Body,
+    options: &DotOptions,
+) -> io::Result<()> {
+    writeln!(writer, "digraph mir {{")?;
+    write!(writer, "    graph [fontname=\"{FONT}\"")?;
+    if let Some(title) = &options.title {
+        write!(writer, ", label=\"{}\"", escape(title))?;
+    }
+    writeln!(writer, "];")?;
+    writeln!(writer, "    node [fontname=\"{FONT}\", shape=\"box\"];")?;
+    writeln!(writer, "    edge [fontname=\"{FONT}\"];")?;
+
+    let cleanup = cleanup_blocks(body);
+    for (index, is_cleanup) in cleanup.into_iter().enumerate() {
+        let label: String = iter::once(format!("bb{index}:"))
+            .chain(
+                basic_block_lines(body, index, &options.pretty, false)?
+                    .into_iter()
+                    .map(|(_, line)| line),
+            )
+            .map(|line| format!("{}\\l", escape(&line)))
+            .collect();
+        write!(writer, "    bb{index} [label=\"{label}\"")?;
+        if is_cleanup {
+            write!(writer, ", style=\"filled\", fillcolor=\"lightblue\"")?;
+        }
+        writeln!(writer, "];")?;
+    }
+
+    if options.group_by_scope {
+        let mut scopes = BTreeMap::<SourceScope, Vec<BasicBlockIdx>>::new();
+        for (index, block) in body.blocks.iter().enumerate() {
+            let scope = body.scope_at(index, block.statements.len()).unwrap();
+            scopes.entry(scope).or_default().push(index);
+        }
+        for (scope, blocks) in scopes {
+            writeln!(writer, "    subgraph cluster_scope{scope} {{")?;
+            writeln!(writer, "        label=\"scope {scope}\";")?;
+            for index in blocks {
+                writeln!(writer, "        bb{index};")?;
+            }
+            writeln!(writer, "    }}")?;
+        }
+    }
+
+    for (index, block) in body.blocks.iter().enumerate() {
+        let kind = &block.terminator.kind;
+        for (target, label) in edges(kind) {
+            writeln!(writer, "    bb{index} -> bb{target} [label=\"{}\"];", escape(&label))?;
+        }
+        if let Some(UnwindAction::Cleanup(target)) = kind.unwind() {
+            writeln!(writer, "    bb{index} -> bb{target} [label=\"unwind\", style=\"dashed\"];")?;
+        }
+    }
+    writeln!(writer, "}}")
+}
+
+/// The edges leaving a block when it doesn't unwind, with their labels.
+fn edges(kind: &TerminatorKind) -> Vec<(BasicBlockIdx, String)> {
+    match kind {
+        TerminatorKind::Goto { target } => vec![(*target, "goto".into())],
+        TerminatorKind::SwitchInt { targets, .. } => targets
+            .branches()
+            .map(|(value, target)| (target, value.to_string()))
+            .chain(iter::once((targets.otherwise(), "otherwise".into())))
+            .collect(),
+        TerminatorKind::Drop { target, .. } => vec![(*target, "return".into())],
+        TerminatorKind::Call { target, .. } => {
+            target.iter().map(|target| (*target, "return".into())).collect()
+        }
+        TerminatorKind::Assert { target, .. } => vec![(*target, "success".into())],
+        TerminatorKind::InlineAsm { destination, .. } => {
+            destination.iter().map(|target| (*target, "goto".into())).collect()
+        }
+        TerminatorKind::Resume
+        | TerminatorKind::Abort
+        | TerminatorKind::Return
+        | TerminatorKind::Unreachable => vec![],
+    }
+}
+
+/// Find the blocks that are reachable from an unwind edge.
+///
+/// Cleanup code never jumps back into regular code, so these are the cleanup blocks.
+fn cleanup_blocks(body: &Body) -> Vec<bool> {
+    let mut cleanup = vec![false; body.blocks.len()];
+    let mut worklist: Vec<BasicBlockIdx> = body
+        .blocks
+        .iter()
+        .filter_map(|block| match block.terminator.kind.unwind() {
+            Some(UnwindAction::Cleanup(target)) => Some(*target),
+            _ => None,
+        })
+        .collect();
+    while let Some(index) = worklist.pop() {
+        if !cleanup[index] {
+            cleanup[index] = true;
+            worklist.extend(body.blocks[index].terminator.successors());
+        }
+    }
+    cleanup
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,143 @@
+//! Print a body next to the source code that it was built from.
+//!
+//! Statements and terminators are grouped by the source lines that their span covers, and each
+//! group is printed below those lines. Code that comes from a macro expansion is attributed to
+//! the macro call. Groups are sorted by file and line, so a single line may be followed by code
+//! from several basic blocks.
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::mir::pretty::{PrettyConfig, basic_block_lines, function_preamble};
+use crate::mir::{BasicBlockIdx, Body};
+use crate::ty::Span;
+
+/// The output format of [Body::dump_with_source].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ListingFormat {
+    /// Plain text, where the source code is printed as comments.
+    #[default]
+    Text,
+    /// An HTML fragment, where every source range and every statement has an anchor.
+    Html,
+}
+
+/// Options that control how [Body::dump_with_source] prints a body.
+#[derive(Clone, Debug, Default)]
+pub struct ListingOptions {
+    /// Whether to print plain text or HTML.
+    pub format: ListingFormat,
+    /// How statements and terminators are printed.
+    pub pretty: PrettyConfig,
+}
+
+/// A range of source lines, which is used to sort the groups.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SourceRange {
+    file: String,
+    start: usize,
+    end: usize,
+}
+
+struct Group {
+    /// The span the source lines are read from.
+    span: Span,
+    /// The statements and terminators, with their location.
+    lines: Vec<(BasicBlockIdx, usize, String)>,
+}
+
+pub(crate) fn write_listing<W: Write>(
+    writer: &mut W,
+    body: &Body,
+    name: &str,
+    options: &ListingOptions,
+) -> io::Result<()> {
+    let mut groups = BTreeMap::<SourceRange, Group>::new();
+    for (block, data) in body.blocks.iter().enumerate() {
+        for (index, line) in basic_block_lines(body, block, &options.pretty, true)? {
+            let span = match data.statements.get(index) {
+                Some(statement) => statement.span,
+                None => data.terminator.span,
+            }
+            .source_callsite();
+            let lines = span.get_lines();
+            let range = SourceRange {
+                file: span.get_filename(),
+                start: lines.start_line,
+                end: lines.end_line,
+            };
+            let group = groups.entry(range).or_insert_with(|| Group { span, lines: vec![] });
+            group.lines.push((block, index, line));
+        }
+    }
+    let width = groups.keys().map(|range| range.end.to_string().len()).max().unwrap_or(1);
+
+    let mut preamble = vec![];
+    function_preamble(&mut preamble, body, name, &options.pretty)?;
+    let preamble = String::from_utf8(preamble).unwrap();
+    match options.format {
+        ListingFormat::Text => {
+            write!(writer, "{preamble}")?;
+            let mut file = None;
+            for (range, group) in &groups {
+                if file != Some(&range.file) {
+                    writeln!(writer, "    // {}", range.file)?;
+                    file = Some(&range.file);
+                }
+                for (number, source) in source_lines(range, group) {
+                    writeln!(writer, "    // {number:>width$} | {source}")?;
+                }
+                for (block, index, line) in &group.lines {
+                    writeln!(writer, "        bb{block}[{index}]: {line}")?;
+                }
+            }
+            writeln!(writer, "}}")
+        }
+        ListingFormat::Html => {
+            writeln!(writer, "<div class=\"mir-listing\">")?;
+            writeln!(writer, "<pre class=\"mir-preamble\">{}</pre>", escape(&preamble))?;
+            let mut files = vec![];
+            for (range, group) in &groups {
+                if files.last() != Some(&&range.file) {
+                    writeln!(writer, "<div class=\"mir-file\">{}</div>", escape(&range.file))?;
+                    files.push(&range.file);
+                }
+                let mut anchor = format!("f{}-L{}", files.len() - 1, range.start);
+                if range.end != range.start {
+                    anchor.push_str(&format!("-L{}", range.end));
+                }
+                write!(writer, "<pre class=\"mir-source\" id=\"{anchor}\">")?;
+                for (number, source) in source_lines(range, group) {
+                    writeln!(
+                        writer,
+                        "<span class=\"mir-line-number\">{number:>width$}</span> {}",
+                        escape(&source)
+                    )?;
+                }
+                writeln!(writer, "</pre>")?;
+                write!(writer, "<pre class=\"mir-statements\">")?;
+                for (block, index, line) in &group.lines {
+                    writeln!(
+                        writer,
+                        "<span id=\"bb{block}-{index}\"><a href=\"#bb{block}-{index}\">bb{block}[{index}]</a>: {}</span>",
+                        escape(line)
+                    )?;
+                }
+                writeln!(writer, "</pre>")?;
+            }
+            writeln!(writer, "<pre class=\"mir-preamble\">}}</pre>")?;
+            writeln!(writer, "</div>")
+        }
+    }
+}
+
+/// The numbered source lines of a group, or a placeholder if the source isn't available.
+fn source_lines(range: &SourceRange, group: &Group) -> Vec<(usize, String)> {
+    match group.span.source_lines() {
+        Some(lines) => (range.start..).zip(lines).collect(),
+        None => vec![(range.start, "<source not available>".to_string())],
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -1,23 +1,24 @@
 use std::fmt::{Debug, Formatter};
 use std::io;
 
-use rustc_public_bridge::bridge;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::abi::FnAbi;
+use crate::compiler_interface::{SessionHandle, SessionId, catch_internal_error, handle_index};
 use crate::crate_def::CrateDef;
-use crate::mir::Body;
+use crate::export::handle_serde;
+use crate::mir::{Body, ListingOptions};
 use crate::ty::{Allocation, ClosureDef, ClosureKind, FnDef, GenericArgs, Ty};
-use crate::{CrateItem, DefId, Error, IndexedVal, ItemKind, Opaque, Symbol, with};
+use crate::{CrateItem, DefId, Error, ErrorKind, IndexedVal, ItemKind, Opaque, Symbol, with};
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MonoItem {
     Fn(Instance),
     Static(StaticDef),
     GlobalAsm(Opaque),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Instance {
     /// The type of instance.
     pub kind: InstanceKind,
@@ -26,7 +27,7 @@ pub struct Instance {
     pub def: InstanceDef,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum InstanceKind {
     /// A user defined item.
     Item,
@@ -54,6 +55,13 @@ impl Instance {
         with(|context| context.instance_body(self.def))
     }
 
+    /// Get the body of an Instance, or an error if the compiler panics while building it.
+    ///
+    /// See [Instance::body] for more details.
+    pub fn try_body(&self) -> Result<Option<Body>, Error> {
+        with(|context| catch_internal_error(|| context.instance_body(self.def)))
+    }
+
     /// Check whether this instance has a body available.
     ///
     /// For intrinsics with fallback body, this will return `true`. It is up to the user to decide
@@ -121,7 +129,10 @@ impl Instance {
     pub fn resolve(def: FnDef, args: &GenericArgs) -> Result<Instance, Error> {
         with(|context| {
             context.resolve_instance(def, args).ok_or_else(|| {
-                bridge::Error::new(format!("Failed to resolve `{def:?}` with `{args:?}`"))
+                Error::new(
+                    ErrorKind::Resolution(def.def_id()),
+                    format!("Failed to resolve `{def:?}` with `{args:?}`"),
+                )
             })
         })
     }
@@ -135,7 +146,10 @@ impl Instance {
     pub fn resolve_for_fn_ptr(def: FnDef, args: &GenericArgs) -> Result<Instance, Error> {
         with(|context| {
             context.resolve_for_fn_ptr(def, args).ok_or_else(|| {
-                bridge::Error::new(format!("Failed to resolve `{def:?}` with `{args:?}`"))
+                Error::new(
+                    ErrorKind::Resolution(def.def_id()),
+                    format!("Failed to resolve `{def:?}` with `{args:?}`"),
+                )
             })
         })
     }
@@ -148,7 +162,10 @@ impl Instance {
     ) -> Result<Instance, Error> {
         with(|context| {
             context.resolve_closure(def, args, kind).ok_or_else(|| {
-                bridge::Error::new(format!("Failed to resolve `{def:?}` with `{args:?}`"))
+                Error::new(
+                    ErrorKind::Resolution(def.def_id()),
+                    format!("Failed to resolve `{def:?}` with `{args:?}`"),
+                )
             })
         })
     }
@@ -178,6 +195,19 @@ impl Instance {
     pub fn emit_mir<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
         if let Some(body) = self.body() { body.dump(w, &self.name()) } else { Ok(()) }
     }
+
+    /// Emit the body of this instance next to the source code it was built from, if it has one.
+    pub fn emit_mir_with_source<W: io::Write>(
+        &self,
+        w: &mut W,
+        options: &ListingOptions,
+    ) -> io::Result<()> {
+        if let Some(body) = self.body() {
+            body.dump_with_source(options, w, &self.name())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl Debug for Instance {
@@ -201,7 +231,10 @@ impl TryFrom<CrateItem> for Instance {
             if !context.requires_monomorphization(def_id) {
                 Ok(context.mono_instance(def_id))
             } else {
-                Err(bridge::Error::new("Item requires monomorphization".to_string()))
+                Err(Error::new(
+                    ErrorKind::RequiresMonomorphization(def_id),
+                    "Item requires monomorphization",
+                ))
             }
         })
     }
@@ -217,7 +250,10 @@ impl TryFrom<Instance> for CrateItem {
             if value.kind == InstanceKind::Item && context.has_body(value.def.def_id()) {
                 Ok(CrateItem(context.instance_def_id(value.def)))
             } else {
-                Err(bridge::Error::new(format!("Item kind `{:?}` cannot be converted", value.kind)))
+                Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("Item kind `{:?}` cannot be converted", value.kind),
+                ))
             }
         })
     }
@@ -241,8 +277,10 @@ impl From<StaticDef> for CrateItem {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
-pub struct InstanceDef(usize);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceDef(u32, SessionId);
+
+handle_serde!(InstanceDef, Instance);
 
 impl CrateDef for InstanceDef {
     fn def_id(&self) -> DefId {
@@ -252,7 +290,7 @@ impl CrateDef for InstanceDef {
 
 crate_def! {
     /// Holds information about a static variable definition.
-    #[derive(Serialize)]
+    #[derive(Serialize, Deserialize)]
     pub StaticDef;
 }
 
@@ -263,7 +301,10 @@ impl TryFrom<CrateItem> for StaticDef {
         if matches!(value.kind(), ItemKind::Static) {
             Ok(StaticDef(value.0))
         } else {
-            Err(bridge::Error::new(format!("Expected a static item, but found: {value:?}")))
+            Err(Error::new(
+                ErrorKind::UnexpectedItem(value.0),
+                format!("Expected a static item, but found: {value:?}"),
+            ))
         }
     }
 }
@@ -295,11 +336,18 @@ impl StaticDef {
     }
 }
 
+impl SessionHandle for InstanceDef {
+    fn new_in(index: usize, session: SessionId) -> Self {
+        InstanceDef(handle_index(index), session)
+    }
+}
+
 impl IndexedVal for InstanceDef {
     fn to_val(index: usize) -> Self {
-        InstanceDef(index)
+        InstanceDef::new_in(index, SessionId::current())
     }
     fn to_index(&self) -> usize {
-        self.0
+        self.1.check("InstanceDef");
+        self.0 as usize
     }
 }
@@ -0,0 +1,2593 @@
+//! Parse the textual MIR emitted by [Body::dump] back into a [Body].
+//!
+//! This allows analysis inputs and expected results to be written as short snippets, e.g.:
+//!
+//! ```text
+//! fn add(_1: u8, _2: u8) -> u8 {
+//!     let mut _0: u8;
+//!     debug a => _1;
+//!     debug b => _2;
+//!     bb0: {
+//!         _0 = Add(_1, _2);
+//!         return;
+//!     }
+//! }
+//! ```
+//!
+//! Types and items are resolved in the current compilation. Paths are looked up with
+//! [resolve_path](crate::resolve_path), and items of the local crate may be named without the
+//! crate prefix, as the printer does. Closures and coroutines are found by matching their printed
+//! type against the ones defined in the local crate, and the generic parameters in scope are the
+//! ones of the local item whose name is the name of the parsed function. Pointer constants, e.g.:
+//! `{alloc3: &u8}`, refer to the allocations of the current compilation by their number.
+//!
+//! The text doesn't contain everything that is stored in a body:
+//! - Spans are replaced by a dummy span, and every variable, statement and terminator belongs
+//!   to the outermost scope.
+//! - Cast kinds are inferred from the operand and target types, mutable borrows use the default
+//!   borrow kind, and unwinding coroutines are assumed to be movable and desugared from functions.
+//! - The left operand of a shift overflow assertion isn't printed, so the right operand is used
+//!   in its place.
+//! - Const generic parameters used as values are assumed to be `usize`, unless their type can be
+//!   inferred from where they are used.
+//!
+//! Parsing the output of [Body::dump] and dumping the result produces the same text.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::mir::{
+    AggregateKind, AssertMessage, BasicBlock, BasicBlockIdx, BinOp, Body, BorrowKind, CastKind,
+    ConstOperand, CoroutineDesugaring, CoroutineKind, CoroutineSource, FakeBorrowKind,
+    FakeReadCause, Local, LocalDecl, MutBorrowKind, Mutability, NullOp, Operand, Place,
+    PointerCoercion, ProjectionElem, RawPtrKind, RetagKind, Rvalue, Safety, SourceInfo, Statement,
+    StatementKind, SwitchTargets, Terminator, TerminatorKind, UnOp, UnwindAction, VarDebugInfo,
+    VarDebugInfoContents,
+};
+use crate::ty::{
+    Abi, AdtDef, AdtKind, AliasDef, AliasKind, AliasTy, AssocKind, Binder, ConstDef, DynKind,
+    ExistentialPredicate, ExistentialProjection, ExistentialTraitRef, FloatTy, FnDef, FnSig,
+    ForeignDef, GenericArgKind, GenericArgs, ImplDef, IntTy, MirConst, Movability, Region,
+    RegionKind, RigidTy, Span, TermKind, TraitDef, Ty, TyConst, TyConstKind, TyKind, UintTy,
+    UnevaluatedConst, VariantIdx,
+};
+use crate::{
+    CrateDef, CrateDefItems, CtorKind, DefId, DefKind, Error, ErrorKind, IndexedVal, Opaque,
+    Symbol, all_local_items, local_crate, resolve_path, with,
+};
+
+/// Parse all the functions in the given textual MIR, and return their names and bodies.
+pub fn parse_bodies(input: &str) -> Result<Vec<(Symbol, Body)>, Error> {
+    let mut parser = Parser::new(input);
+    let mut bodies = vec![];
+    while !parser.at_end() {
+        bodies.push(parser.function()?);
+    }
+    Ok(bodies)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    /// The span given to everything that is parsed.
+    span: Span,
+    /// The generic parameters of the function being parsed, by name.
+    params: HashMap<String, GenericArgKind>,
+    /// The locals declared so far in the function being parsed.
+    locals: Vec<LocalDecl>,
+    /// The types of the closures and coroutines in the local crate, by their printed form.
+    closures: Option<HashMap<String, Ty>>,
+}
+
+/// A segment of the path of a value, e.g.: `Vec::<u8>` or `<impl [u8]>`.
+enum Segment {
+    Name(String, Option<Vec<GenericArgKind>>),
+    Impl(Ty),
+}
+
+/// The self type of a qualified path, with the path and the arguments of its trait, if any.
+type QSelf = (Ty, Option<(String, Vec<GenericArgKind>)>);
+
+/// The path of a value, e.g.: `std::vec::Vec::<u8>::new` or `<u8 as std::clone::Clone>::clone`.
+struct ValuePath {
+    qself: Option<QSelf>,
+    segments: Vec<Segment>,
+    /// The index of the promoted constant of the item, e.g.: `main::promoted[0]`.
+    promoted: Option<u32>,
+}
+
+/// The successors of a terminator, e.g.: `-> [return: bb1, unwind continue]`.
+#[derive(Default)]
+struct Targets {
+    labeled: Vec<(String, BasicBlockIdx)>,
+    unwind: Option<UnwindAction>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            input,
+            pos: 0,
+            span: with(|cx| cx.dummy_span()),
+            params: HashMap::new(),
+            locals: vec![],
+            closures: None,
+        }
+    }
+
+    fn function(&mut self) -> Result<(Symbol, Body), Error> {
+        self.expect("fn")?;
+        self.skip_ws();
+        let name_start = self.pos;
+        let name = self.fn_name()?;
+        self.params = generic_params(&name);
+        self.locals.clear();
+
+        self.expect("(")?;
+        let mut arg_tys = vec![];
+        if !self.eat(")") {
+            loop {
+                self.skip_ws();
+                let start = self.pos;
+                if self.local()? != arg_tys.len() + 1 {
+                    return Err(self.error_at(start, format!("expected `_{}`", arg_tys.len() + 1)));
+                }
+                self.expect(":")?;
+                arg_tys.push(self.ty()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        self.expect("->")?;
+        let ret_ty = self.ty()?;
+        self.expect("{")?;
+
+        let arg_count = arg_tys.len();
+        self.locals.push(self.local_decl(ret_ty, Mutability::Mut));
+        for ty in arg_tys {
+            self.locals.push(self.local_decl(ty, Mutability::Not));
+        }
+        while self.eat("let") {
+            let mutability = if self.eat("mut") { Mutability::Mut } else { Mutability::Not };
+            self.skip_ws();
+            let start = self.pos;
+            let local = self.local()?;
+            self.expect(":")?;
+            let ty = self.ty()?;
+            let decl = self.local_decl(ty, mutability);
+            self.expect(";")?;
+            if local == 0 {
+                self.locals[0] = decl;
+            } else if local == self.locals.len() {
+                self.locals.push(decl);
+            } else {
+                return Err(self.error_at(start, format!("expected `_{}`", self.locals.len())));
+            }
+        }
+
+        let mut var_debug_info: Vec<VarDebugInfo> = vec![];
+        while self.eat("debug") {
+            let name = self.ident()?;
+            self.expect("=>")?;
+            let value = if self.at_place() {
+                VarDebugInfoContents::Place(self.place()?)
+            } else {
+                VarDebugInfoContents::Const(self.const_operand(None)?)
+            };
+            self.expect(";")?;
+            // Arguments are described before any other variable.
+            let argument_index = match &value {
+                VarDebugInfoContents::Place(place)
+                    if place.projection.is_empty()
+                        && (1..=arg_count).contains(&place.local)
+                        && !var_debug_info.iter().any(|info| info.local() == Some(place.local)) =>
+                {
+                    Some(place.local as u16)
+                }
+                _ => None,
+            };
+            var_debug_info.push(VarDebugInfo {
+                name,
+                source_info: SourceInfo { span: self.span, scope: 0 },
+                composite: None,
+                value,
+                argument_index,
+            });
+        }
+
+        let mut blocks = vec![];
+        while !self.eat("}") {
+            self.skip_ws();
+            let start = self.pos;
+            if self.block_idx()? != blocks.len() {
+                return Err(self.error_at(start, format!("expected `bb{}`", blocks.len())));
+            }
+            self.expect(":")?;
+            self.expect("{")?;
+            blocks.push(self.block()?);
+        }
+        for (idx, block) in blocks.iter().enumerate() {
+            if let Some(target) =
+                block.terminator.successors().into_iter().find(|t| *t >= blocks.len())
+            {
+                return Err(self.error_at(
+                    name_start,
+                    format!("`bb{idx}` jumps to `bb{target}`, which doesn't exist"),
+                ));
+            }
+        }
+        self.skip_ws();
+
+        let locals = std::mem::take(&mut self.locals);
+        let body = Body::new(blocks, locals, arg_count, var_debug_info, None, self.span);
+        Ok((name, body))
+    }
+
+    /// Parse the name of a function, which may contain spaces, e.g.: `<impl Foo for u8>::foo`.
+    fn fn_name(&mut self) -> Result<Symbol, Error> {
+        let start = self.pos;
+        let mut depth = 0usize;
+        for (offset, c) in self.rest().char_indices() {
+            match c {
+                '(' if depth == 0 => {
+                    let name = self.input[start..start + offset].trim_end();
+                    if name.is_empty() {
+                        break;
+                    }
+                    self.pos = start + offset;
+                    return Ok(name.to_string());
+                }
+                '(' | '[' | '{' | '<' => depth += 1,
+                ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+                '\n' => break,
+                _ => {}
+            }
+        }
+        Err(self.error_at(start, "expected the name of a function"))
+    }
+
+    fn local_decl(&self, ty: Ty, mutability: Mutability) -> LocalDecl {
+        LocalDecl { ty, span: self.span, mutability }
+    }
+
+    fn block(&mut self) -> Result<BasicBlock, Error> {
+        let mut statements = vec![];
+        loop {
+            // The last entry of a block is its terminator.
+            let end = self.entry_end()?;
+            if self.lookahead(end + 1).starts_with('}') {
+                let kind = self.terminator()?;
+                self.expect(";")?;
+                self.expect("}")?;
+                let terminator = Terminator { kind, span: self.span };
+                return Ok(BasicBlock { statements, terminator });
+            }
+            let kind = self.statement()?;
+            self.expect(";")?;
+            statements.push(Statement { kind, span: self.span });
+        }
+    }
+
+    /// Find the `;` that ends the statement or terminator at the current position.
+    fn entry_end(&mut self) -> Result<usize, Error> {
+        self.skip_ws();
+        let bytes = self.input.as_bytes();
+        let mut depth = 0usize;
+        let mut pos = self.pos;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' => depth = depth.saturating_sub(1),
+                b'}' if depth == 0 => break,
+                b'}' => depth -= 1,
+                b';' if depth == 0 => return Ok(pos),
+                b'"' => pos = self.skip_quoted(pos, b'"'),
+                // Character literals, as opposed to lifetimes, e.g.: `'a'` and `'\n'`.
+                b'\'' if bytes.get(pos + 1) == Some(&b'\\') => pos = self.skip_quoted(pos, b'\''),
+                b'\'' => {
+                    let len = self.input[pos + 1..].chars().next().map_or(0, char::len_utf8);
+                    if bytes.get(pos + 1 + len) == Some(&b'\'') {
+                        pos += len + 1;
+                    }
+                }
+                _ => {}
+            }
+            pos += 1;
+        }
+        Err(self.error_at(pos, "expected `;`"))
+    }
+
+    /// Return the position of the quote that closes the literal starting at the given position.
+    fn skip_quoted(&self, start: usize, quote: u8) -> usize {
+        let bytes = self.input.as_bytes();
+        let mut pos = start + 1;
+        while pos < bytes.len() && bytes[pos] != quote {
+            pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+        }
+        pos
+    }
+
+    fn statement(&mut self) -> Result<StatementKind, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.eat("StorageLive") {
+            self.expect("(")?;
+            let local = self.checked_local()?;
+            self.expect(")")?;
+            return Ok(StatementKind::StorageLive(local));
+        }
+        if self.eat("StorageDead") {
+            self.expect("(")?;
+            let local = self.checked_local()?;
+            self.expect(")")?;
+            return Ok(StatementKind::StorageDead(local));
+        }
+        if self.eat("discriminant") {
+            self.expect("(")?;
+            let place = self.place()?;
+            self.expect(")")?;
+            self.expect("=")?;
+            let variant_index = VariantIdx::to_val(self.number()?);
+            return Ok(StatementKind::SetDiscriminant { place, variant_index });
+        }
+        if self.eat("FakeRead") {
+            self.expect("(")?;
+            let cause = self.fake_read_cause()?;
+            self.expect(",")?;
+            let place = self.place()?;
+            self.expect(")")?;
+            return Ok(StatementKind::FakeRead(cause, place));
+        }
+        if self.eat("Deinit") {
+            self.expect("(")?;
+            let place = self.place()?;
+            self.expect(")")?;
+            return Ok(StatementKind::Deinit(place));
+        }
+        if self.eat("Retag") {
+            self.expect("(")?;
+            let kind = match self.ident()?.as_str() {
+                "FnEntry" => RetagKind::FnEntry,
+                "TwoPhase" => RetagKind::TwoPhase,
+                "Raw" => RetagKind::Raw,
+                "Default" => RetagKind::Default,
+                kind => return Err(self.error_at(start, format!("unknown retag kind `{kind}`"))),
+            };
+            self.expect(",")?;
+            let place = self.place()?;
+            self.expect(")")?;
+            return Ok(StatementKind::Retag(kind, place));
+        }
+        if self.eat("PlaceMention") {
+            self.expect("(")?;
+            let place = self.place()?;
+            self.expect(")")?;
+            return Ok(StatementKind::PlaceMention(place));
+        }
+        if self.eat("ConstEvalCounter") {
+            return Ok(StatementKind::ConstEvalCounter);
+        }
+        if self.eat("nop") {
+            return Ok(StatementKind::Nop);
+        }
+        for name in ["AscribeUserType", "Coverage", "Intrinsic"] {
+            if self.eat(name) {
+                return Err(self.unsupported_at(start, format!("`{name}` statements")));
+            }
+        }
+        let place = self.place()?;
+        self.expect("=")?;
+        let ty = self.place_ty(&place, start)?;
+        let rvalue = self.rvalue(ty)?;
+        Ok(StatementKind::Assign(place, rvalue))
+    }
+
+    fn fake_read_cause(&mut self) -> Result<FakeReadCause, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let name = self.ident()?;
+        let cause = match name.as_str() {
+            "ForMatchGuard" => FakeReadCause::ForMatchGuard,
+            "ForGuardBinding" => FakeReadCause::ForGuardBinding,
+            "ForIndex" => FakeReadCause::ForIndex,
+            "ForMatchedPlace" | "ForLet" => {
+                let def_id = Opaque(self.delimited('(', ')')?.to_string());
+                if name == "ForLet" {
+                    FakeReadCause::ForLet(def_id)
+                } else {
+                    FakeReadCause::ForMatchedPlace(def_id)
+                }
+            }
+            _ => return Err(self.error_at(start, format!("unknown fake read cause `{name}`"))),
+        };
+        Ok(cause)
+    }
+
+    fn terminator(&mut self) -> Result<TerminatorKind, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.eat("goto") {
+            let targets = self.targets()?;
+            return match targets.labeled.as_slice() {
+                [(label, target)] if label.is_empty() && targets.unwind.is_none() => {
+                    Ok(TerminatorKind::Goto { target: *target })
+                }
+                _ => Err(self.error_at(start, "expected a single target")),
+            };
+        }
+        if self.eat("switchInt") {
+            self.expect("(")?;
+            let discr = self.operand(None)?;
+            self.expect(")")?;
+            let targets_start = self.pos;
+            let targets = self.targets()?;
+            let Some(((_, otherwise), branches)) = targets.labeled.split_last() else {
+                return Err(self.error_at(targets_start, "expected the targets of `switchInt`"));
+            };
+            let branches = branches
+                .iter()
+                .map(|(value, target)| value.parse().map(|value| (value, *target)))
+                .collect::<Result<_, _>>()
+                .map_err(|_| self.error_at(targets_start, "expected a value"))?;
+            let targets = SwitchTargets::new(branches, *otherwise);
+            return Ok(TerminatorKind::SwitchInt { discr, targets });
+        }
+        if self.eat("resume") {
+            return Ok(TerminatorKind::Resume);
+        }
+        if self.eat("abort") {
+            return Ok(TerminatorKind::Abort);
+        }
+        if self.eat("return") {
+            return Ok(TerminatorKind::Return);
+        }
+        if self.eat("unreachable") {
+            return Ok(TerminatorKind::Unreachable);
+        }
+        if self.eat("InlineAsm") {
+            return Err(self.unsupported_at(start, "inline assembly"));
+        }
+        if self.eat("drop") {
+            self.expect("(")?;
+            let place = self.place()?;
+            self.expect(")")?;
+            let (target, unwind) = self.target_and_unwind("return")?;
+            let Some(target) = target else {
+                return Err(self.error_at(start, "expected the target of `drop`"));
+            };
+            return Ok(TerminatorKind::Drop { place, target, unwind });
+        }
+        if self.eat("assert") {
+            self.expect("(")?;
+            let expected = !self.eat("!");
+            let cond = self.operand(None)?;
+            self.expect(",")?;
+            let msg = self.assert_message()?;
+            self.expect(")")?;
+            let (target, unwind) = self.target_and_unwind("success")?;
+            let Some(target) = target else {
+                return Err(self.error_at(start, "expected the target of `assert`"));
+            };
+            return Ok(TerminatorKind::Assert { cond, expected, msg, target, unwind });
+        }
+
+        let destination = self.place()?;
+        self.expect("=")?;
+        let func = if self.at_place() || self.peek_word("move") {
+            self.operand(None)?
+        } else {
+            Operand::Constant(ConstOperand {
+                span: self.span,
+                user_ty: None,
+                const_: self.path_const(None, false)?,
+            })
+        };
+        let inputs = func
+            .ty(&self.locals)
+            .ok()
+            .and_then(|ty| ty.kind().fn_sig())
+            .map(|sig| sig.value.inputs().to_vec())
+            .unwrap_or_default();
+        self.expect("(")?;
+        let mut args = vec![];
+        if !self.eat(")") {
+            loop {
+                args.push(self.operand(inputs.get(args.len()).copied())?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        let (target, unwind) = self.target_and_unwind("return")?;
+        Ok(TerminatorKind::Call { func, args, destination, target, unwind })
+    }
+
+    fn targets(&mut self) -> Result<Targets, Error> {
+        let mut targets = Targets::default();
+        if !self.eat("->") {
+            return Ok(targets);
+        }
+        if self.eat("[") {
+            loop {
+                if self.eat("unwind") {
+                    if self.eat(":") {
+                        targets.labeled.push(("unwind".to_string(), self.block_idx()?));
+                    } else {
+                        targets.unwind = Some(self.unwind_action()?);
+                    }
+                } else {
+                    self.skip_ws();
+                    let label = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                    self.expect(":")?;
+                    targets.labeled.push((label.to_string(), self.block_idx()?));
+                }
+                if self.eat("]") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        } else if self.eat("unwind") {
+            targets.unwind = Some(self.unwind_action()?);
+        } else {
+            targets.labeled.push((String::new(), self.block_idx()?));
+        }
+        Ok(targets)
+    }
+
+    /// Parse the targets of a terminator that may unwind, where `label` is the name of its
+    /// regular target.
+    fn target_and_unwind(
+        &mut self,
+        label: &str,
+    ) -> Result<(Option<BasicBlockIdx>, UnwindAction), Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let targets = self.targets()?;
+        match (targets.labeled.as_slice(), targets.unwind) {
+            ([(name, target)], Some(unwind)) if name == label => Ok((Some(*target), unwind)),
+            ([(name, target), (cleanup_label, cleanup)], None)
+                if name == label && cleanup_label == "unwind" =>
+            {
+                Ok((Some(*target), UnwindAction::Cleanup(*cleanup)))
+            }
+            ([(name, cleanup)], None) if name.is_empty() => {
+                Ok((None, UnwindAction::Cleanup(*cleanup)))
+            }
+            ([], Some(unwind)) => Ok((None, unwind)),
+            _ => Err(self.error_at(start, "expected an unwind action")),
+        }
+    }
+
+    fn unwind_action(&mut self) -> Result<UnwindAction, Error> {
+        if self.eat("continue") {
+            Ok(UnwindAction::Continue)
+        } else if self.eat("unreachable") {
+            Ok(UnwindAction::Unreachable)
+        } else if self.eat("terminate") {
+            Ok(UnwindAction::Terminate)
+        } else {
+            Err(self.error("expected `continue`, `unreachable` or `terminate`"))
+        }
+    }
+
+    fn block_idx(&mut self) -> Result<BasicBlockIdx, Error> {
+        self.skip_ws();
+        if !self.rest().starts_with("bb") {
+            return Err(self.error("expected a basic block"));
+        }
+        self.pos += 2;
+        self.number()
+    }
+
+    fn assert_message(&mut self) -> Result<AssertMessage, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if !self.rest().starts_with('"') {
+            // Coroutine messages are printed without quotes, and end with the assert operands.
+            let line = self.rest().split('\n').next().unwrap_or_default();
+            let end = line.rfind(") ->").map_or(self.pos + line.len(), |end| self.pos + end);
+            let description = self.input[self.pos..end].trim();
+            let kinds = [
+                CoroutineKind::Coroutine(Movability::Movable),
+                CoroutineKind::Desugared(CoroutineDesugaring::Async, CoroutineSource::Fn),
+                CoroutineKind::Desugared(CoroutineDesugaring::Gen, CoroutineSource::Fn),
+                CoroutineKind::Desugared(CoroutineDesugaring::AsyncGen, CoroutineSource::Fn),
+            ];
+            let msg = kinds
+                .into_iter()
+                .flat_map(|kind| {
+                    [
+                        AssertMessage::ResumedAfterReturn(kind.clone()),
+                        AssertMessage::ResumedAfterPanic(kind.clone()),
+                        AssertMessage::ResumedAfterDrop(kind),
+                    ]
+                })
+                .find(|msg| msg.description().is_ok_and(|text| text == description));
+            return match msg {
+                Some(msg) => {
+                    self.pos = end;
+                    Ok(msg)
+                }
+                None => Err(self.error_at(start, "expected an assertion message")),
+            };
+        }
+        let template = self.string()?;
+        let bin_op = |op| match op {
+            "+" => Some(BinOp::Add),
+            "-" => Some(BinOp::Sub),
+            "*" => Some(BinOp::Mul),
+            "/" => Some(BinOp::Div),
+            "%" => Some(BinOp::Rem),
+            _ => None,
+        };
+        let msg = match template.as_str() {
+            "index out of bounds: the length is {} but the index is {}" => {
+                let len = self.next_operand(Some(Ty::usize_ty()))?;
+                let index = self.next_operand(Some(Ty::usize_ty()))?;
+                AssertMessage::BoundsCheck { len, index }
+            }
+            "attempt to shift right by `{}`, which would overflow"
+            | "attempt to shift left by `{}`, which would overflow" => {
+                let op = if template.contains("right") { BinOp::Shr } else { BinOp::Shl };
+                let rhs = self.next_operand(None)?;
+                AssertMessage::Overflow(op, rhs.clone(), rhs)
+            }
+            "attempt to negate `{}`, which would overflow" => {
+                AssertMessage::OverflowNeg(self.next_operand(None)?)
+            }
+            "attempt to divide `{}` by zero" => {
+                AssertMessage::DivisionByZero(self.next_operand(None)?)
+            }
+            "attempt to calculate the remainder of `{}` with a divisor of zero" => {
+                AssertMessage::RemainderByZero(self.next_operand(None)?)
+            }
+            "misaligned pointer dereference: address must be a multiple of {} but is {}" => {
+                let required = self.next_operand(Some(Ty::usize_ty()))?;
+                let found = self.next_operand(Some(Ty::usize_ty()))?;
+                AssertMessage::MisalignedPointerDereference { required, found }
+            }
+            "null pointer dereference occurred" => AssertMessage::NullPointerDereference,
+            "trying to construct an enum from an invalid value {}" => {
+                AssertMessage::InvalidEnumConstruction(self.next_operand(None)?)
+            }
+            template => {
+                let op = template
+                    .strip_prefix("attempt to compute `{} ")
+                    .and_then(|rest| rest.strip_suffix(" {}`, which would overflow"))
+                    .and_then(bin_op);
+                let Some(op) = op else {
+                    return Err(self.error_at(start, "unknown assertion message"));
+                };
+                let lhs = self.next_operand(None)?;
+                let rhs = self.next_operand(None)?;
+                AssertMessage::Overflow(op, lhs, rhs)
+            }
+        };
+        Ok(msg)
+    }
+
+    /// Parse an operand that follows a comma.
+    fn next_operand(&mut self, expected: Option<Ty>) -> Result<Operand, Error> {
+        self.expect(",")?;
+        self.operand(expected)
+    }
+
+    fn rvalue(&mut self, dest: Ty) -> Result<Rvalue, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.eat_raw("&") {
+            if self.eat("raw") {
+                let kind = if self.eat("mut") {
+                    RawPtrKind::Mut
+                } else {
+                    self.expect("const")?;
+                    if self.eat("(fake)") {
+                        RawPtrKind::FakeForPtrMetadata
+                    } else {
+                        RawPtrKind::Const
+                    }
+                };
+                return Ok(Rvalue::AddressOf(kind, self.place()?));
+            }
+            let kind = if self.eat("fake") {
+                if self.eat("shallow") {
+                    BorrowKind::Fake(FakeBorrowKind::Shallow)
+                } else {
+                    BorrowKind::Fake(FakeBorrowKind::Deep)
+                }
+            } else if self.eat("mut") {
+                BorrowKind::Mut { kind: MutBorrowKind::Default }
+            } else {
+                BorrowKind::Shared
+            };
+            let region = Region { kind: RegionKind::ReErased };
+            return Ok(Rvalue::Ref(region, kind, self.place()?));
+        }
+        if self.eat_raw("[") {
+            let elem_ty = match dest.kind().rigid() {
+                Some(RigidTy::Array(elem_ty, _)) => Some(*elem_ty),
+                _ => None,
+            };
+            let mut operands = vec![];
+            if !self.eat("]") {
+                operands.push(self.operand(elem_ty)?);
+                if self.eat(";") {
+                    let count = self.ty_const()?;
+                    self.expect("]")?;
+                    return Ok(Rvalue::Repeat(operands.pop().unwrap(), count));
+                }
+                while !self.eat("]") {
+                    self.expect(",")?;
+                    operands.push(self.operand(elem_ty)?);
+                }
+            }
+            let elem_ty = match (elem_ty, operands.first()) {
+                (Some(elem_ty), _) => elem_ty,
+                (None, Some(operand)) => self.operand_ty(operand, start)?,
+                (None, None) => return Err(self.error_at(start, "expected an array type")),
+            };
+            return Ok(Rvalue::Aggregate(AggregateKind::Array(elem_ty), operands));
+        }
+        if self.rest().starts_with("*const ") || self.rest().starts_with("*mut ") {
+            self.eat_raw("*");
+            let mutability = if self.eat("mut") { Mutability::Mut } else { Mutability::Not };
+            self.eat("const");
+            let ty = self.ty()?;
+            self.expect("from")?;
+            self.expect("(")?;
+            let operands = self.operands(")", &[])?;
+            return Ok(Rvalue::Aggregate(AggregateKind::RawPtr(ty, mutability), operands));
+        }
+        // Pointer constants, such as `{alloc1: &u8}`, are operands rather than aggregates.
+        let rest = self.rest();
+        if rest.starts_with('{') && !rest.starts_with("{alloc") && !rest.starts_with("{0x") {
+            return self.closure_aggregate(dest);
+        }
+        if let Some(rvalue) = self.place_rvalue()? {
+            return Ok(rvalue);
+        }
+        if let Some(rvalue) = self.adt_aggregate(dest)? {
+            return Ok(rvalue);
+        }
+        if let Some(rvalue) = self.operator(start)? {
+            return Ok(rvalue);
+        }
+        // Places may also start with a parenthesis, e.g.: `(_1.0: u8)`.
+        let save = self.pos;
+        let at_place = self.at_place() && self.place().is_ok();
+        self.pos = save;
+        if self.rest().starts_with('(') && !self.rest().starts_with("()") && !at_place {
+            let tys = match dest.kind().rigid() {
+                Some(RigidTy::Tuple(tys)) => tys.clone(),
+                _ => vec![],
+            };
+            self.eat_raw("(");
+            let operands = self.operands(")", &tys)?;
+            // Tuples with a single element that end with a comma are constants.
+            if !self.input[..self.pos - 1].trim_end().ends_with(',') {
+                return Ok(Rvalue::Aggregate(AggregateKind::Tuple, operands));
+            }
+            self.pos = save;
+        }
+
+        let operand = self.operand(Some(dest))?;
+        if self.eat("as") {
+            let ty = self.ty()?;
+            let kind = cast_kind(self.operand_ty(&operand, start)?, ty);
+            return Ok(Rvalue::Cast(kind, operand, ty));
+        }
+        Ok(Rvalue::Use(operand))
+    }
+
+    /// Parse the aggregate of a closure, a coroutine or a coroutine-closure.
+    fn closure_aggregate(&mut self, dest: Ty) -> Result<Rvalue, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let text = self.delimited('{', '}')?;
+        let (kind, prefix, span) = match dest.kind().rigid() {
+            Some(RigidTy::Closure(def, args)) => {
+                (AggregateKind::Closure(*def, args.clone()), "closure", def.span())
+            }
+            Some(RigidTy::Coroutine(def, args)) => {
+                (AggregateKind::Coroutine(*def, args.clone()), "coroutine", def.span())
+            }
+            Some(RigidTy::CoroutineClosure(def, args)) => {
+                let kind = AggregateKind::CoroutineClosure(*def, args.clone());
+                (kind, "coroutine-closure", def.span())
+            }
+            _ => return Err(self.error_at(start, format!("expected a value of type `{dest}`"))),
+        };
+        let expected = format!("{prefix}@{}", span.diagnostic());
+        if !same_text(text, &expected) {
+            return Err(self.error_at(start, format!("expected `{{{expected}}}`")));
+        }
+        self.expect("(")?;
+        let operands = self.operands(")", &[])?;
+        Ok(Rvalue::Aggregate(kind, operands))
+    }
+
+    /// Parse the rvalues that apply to a place, e.g.: `discriminant(_1)`.
+    fn place_rvalue(&mut self) -> Result<Option<Rvalue>, Error> {
+        let constructors = [
+            ("CopyForDeref", Rvalue::CopyForDeref as fn(Place) -> Rvalue),
+            ("discriminant", Rvalue::Discriminant),
+            ("len", Rvalue::Len),
+        ];
+        for (name, constructor) in constructors {
+            if self.rest().strip_prefix(name).is_some_and(|rest| rest.starts_with('(')) {
+                self.eat_raw(name);
+                self.expect("(")?;
+                let place = self.place()?;
+                self.expect(")")?;
+                return Ok(Some(constructor(place)));
+            }
+        }
+        if self.rest().starts_with("ShallowInitBox(") {
+            self.eat_raw("ShallowInitBox(");
+            let operand = self.operand(None)?;
+            self.expect(",")?;
+            let ty = self.ty()?;
+            self.expect(")")?;
+            return Ok(Some(Rvalue::ShallowInitBox(operand, ty)));
+        }
+        if self.rest().starts_with("thread_local_ref") {
+            return Err(self.unsupported("thread local references"));
+        }
+        Ok(None)
+    }
+
+    /// Parse the aggregate of an ADT whose type is the type of the destination, e.g.:
+    /// `std::option::Option::Some(move _1)`.
+    fn adt_aggregate(&mut self, dest: Ty) -> Result<Option<Rvalue>, Error> {
+        let Some(RigidTy::Adt(def, args)) = dest.kind().rigid().cloned() else {
+            return Ok(None);
+        };
+        for variant in def.variants_iter() {
+            let name = match def.kind() {
+                AdtKind::Enum => format!("{}::{}", def.name(), variant.name()),
+                AdtKind::Struct | AdtKind::Union => variant.name(),
+            };
+            let Some(rest) = self.rest().strip_prefix(name.as_str()) else {
+                continue;
+            };
+            if rest.starts_with(|c: char| c == ':' || c == '<' || is_ident_char(c)) {
+                continue;
+            }
+            self.pos += name.len();
+            let field_tys: Vec<_> =
+                variant.fields().iter().map(|field| field.ty_with_args(&args)).collect();
+            let operands = if self.eat_raw("(") { self.operands(")", &field_tys)? } else { vec![] };
+            let active_field = match def.kind() {
+                AdtKind::Union => Some(
+                    operands
+                        .first()
+                        .and_then(|operand| operand.ty(&self.locals).ok())
+                        .and_then(|ty| field_tys.iter().position(|field_ty| *field_ty == ty))
+                        .unwrap_or_default(),
+                ),
+                AdtKind::Enum | AdtKind::Struct => None,
+            };
+            let kind = AggregateKind::Adt(def, variant.idx, args, None, active_field);
+            return Ok(Some(Rvalue::Aggregate(kind, operands)));
+        }
+        Ok(None)
+    }
+
+    /// Parse unary, binary and nullary operations, e.g.: `Add(_1, 1_u8)`.
+    fn operator(&mut self, start: usize) -> Result<Option<Rvalue>, Error> {
+        let name: String = self.rest().chars().take_while(|c| is_ident_char(*c)).collect();
+        let rest = &self.rest()[name.len()..];
+        if rest.starts_with("::<") {
+            let null_op = match name.as_str() {
+                "SizeOf" => NullOp::SizeOf,
+                "AlignOf" => NullOp::AlignOf,
+                "UbChecks" => NullOp::UbChecks,
+                "ContractChecks" => NullOp::ContractChecks,
+                _ => return Ok(None),
+            };
+            self.pos += name.len() + 3;
+            let ty = self.ty()?;
+            self.expect(">")?;
+            self.expect("(")?;
+            self.expect(")")?;
+            return Ok(Some(Rvalue::NullaryOp(null_op, ty)));
+        }
+        if name == "OffsetOf" && rest.starts_with("([") {
+            return Err(self.unsupported_at(start, "`OffsetOf` operations"));
+        }
+        if !rest.starts_with('(') {
+            return Ok(None);
+        }
+        let (checked, bin_op) = match name.strip_prefix("Checked") {
+            Some(op) => (true, bin_op(op)),
+            None => (false, bin_op(&name)),
+        };
+        if let Some(bin_op) = bin_op {
+            self.pos += name.len() + 1;
+            let lhs = self.operand(None)?;
+            self.expect(",")?;
+            let rhs = self.operand(None)?;
+            self.expect(")")?;
+            return Ok(Some(if checked {
+                Rvalue::CheckedBinaryOp(bin_op, lhs, rhs)
+            } else {
+                Rvalue::BinaryOp(bin_op, lhs, rhs)
+            }));
+        }
+        let un_op = match name.as_str() {
+            "Not" => UnOp::Not,
+            "Neg" => UnOp::Neg,
+            "PtrMetadata" => UnOp::PtrMetadata,
+            _ => return Ok(None),
+        };
+        self.pos += name.len() + 1;
+        let operand = self.operand(None)?;
+        self.expect(")")?;
+        Ok(Some(Rvalue::UnaryOp(un_op, operand)))
+    }
+
+    /// Parse a comma separated list of operands up to the given delimiter, using the given types
+    /// as the expected types of the operands.
+    fn operands(&mut self, close: &str, tys: &[Ty]) -> Result<Vec<crate::mir::Operand>, Error> {
+        let mut operands = vec![];
+        while !self.eat(close) {
+            if !operands.is_empty() {
+                self.expect(",")?;
+                if self.eat(close) {
+                    break;
+                }
+            }
+            operands.push(self.operand(tys.get(operands.len()).copied())?);
+        }
+        Ok(operands)
+    }
+
+    fn operand(&mut self, expected: Option<Ty>) -> Result<Operand, Error> {
+        if self.eat("move") {
+            Ok(Operand::Move(self.place()?))
+        } else if self.at_place() {
+            Ok(Operand::Copy(self.place()?))
+        } else {
+            Ok(Operand::Constant(self.const_operand(expected)?))
+        }
+    }
+
+    fn operand_ty(&self, operand: &Operand, start: usize) -> Result<Ty, Error> {
+        operand.ty(&self.locals).map_err(|error| self.error_at(start, error.message()))
+    }
+
+    fn const_operand(&mut self, expected: Option<Ty>) -> Result<ConstOperand, Error> {
+        let const_ = self.constant(expected)?;
+        Ok(ConstOperand { span: self.span, user_ty: None, const_ })
+    }
+
+    /// Whether a place starts at the current position, as opposed to a constant.
+    fn at_place(&mut self) -> bool {
+        self.skip_ws();
+        let rest = self.rest();
+        let unwrapped = rest.trim_start_matches('(');
+        (unwrapped.len() < rest.len() && unwrapped.starts_with('*'))
+            || unwrapped
+                .strip_prefix('_')
+                .is_some_and(|local| local.starts_with(|c: char| c.is_ascii_digit()))
+    }
+
+    fn place(&mut self) -> Result<Place, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        // Whether each parenthesis that has been opened is a dereference.
+        let mut opened = vec![];
+        loop {
+            if self.eat_raw("(*") {
+                opened.push(true);
+            } else if self.eat_raw("(") {
+                opened.push(false);
+            } else {
+                break;
+            }
+        }
+        let local = self.checked_local()?;
+        let mut projection = vec![];
+        loop {
+            if self.eat_raw("[") {
+                projection.push(self.index_projection()?);
+                self.expect("]")?;
+                continue;
+            }
+            match opened.last() {
+                Some(true) if self.eat_raw(")") => projection.push(ProjectionElem::Deref),
+                Some(false) if self.eat_raw(".") => {
+                    let field = self.number()?;
+                    self.expect(":")?;
+                    projection.push(ProjectionElem::Field(field, self.ty()?));
+                    self.expect(")")?;
+                }
+                Some(false) if self.eat("as") => {
+                    self.skip_ws();
+                    if self.eat_raw("variant#") {
+                        projection
+                            .push(ProjectionElem::Downcast(VariantIdx::to_val(self.number()?)));
+                    } else if self.eat("subtype") {
+                        projection.push(ProjectionElem::Subtype(self.ty()?));
+                    } else {
+                        projection.push(ProjectionElem::OpaqueCast(self.ty()?));
+                    }
+                    self.expect(")")?;
+                }
+                Some(_) => return Err(self.error("expected `)`")),
+                None => break,
+            }
+            opened.pop();
+        }
+        let place = Place { local, projection };
+        self.place_ty(&place, start)?;
+        Ok(place)
+    }
+
+    /// Parse the projections that are printed between brackets, e.g.: `[_2]` and `[1 of 3]`.
+    fn index_projection(&mut self) -> Result<ProjectionElem, Error> {
+        if self.rest().starts_with('_') {
+            return Ok(ProjectionElem::Index(self.checked_local()?));
+        }
+        if self.eat(":") {
+            self.expect("-")?;
+            return Ok(ProjectionElem::Subslice { from: 0, to: self.number()?, from_end: true });
+        }
+        if self.eat("-") {
+            let offset = self.number()?;
+            self.expect("of")?;
+            let min_length = self.number()?;
+            return Ok(ProjectionElem::ConstantIndex { offset, min_length, from_end: true });
+        }
+        let from = self.number()?;
+        if self.eat("of") {
+            let min_length = self.number()?;
+            Ok(ProjectionElem::ConstantIndex { offset: from, min_length, from_end: false })
+        } else if self.eat("..") {
+            Ok(ProjectionElem::Subslice { from, to: self.number()?, from_end: false })
+        } else {
+            self.expect(":")?;
+            let to = if self.eat("-") { self.number()? } else { 0 };
+            Ok(ProjectionElem::Subslice { from, to, from_end: true })
+        }
+    }
+
+    fn place_ty(&self, place: &Place, start: usize) -> Result<Ty, Error> {
+        place.ty(&self.locals).map_err(|error| self.error_at(start, error.message()))
+    }
+
+    fn local(&mut self) -> Result<Local, Error> {
+        self.skip_ws();
+        if !self.eat_raw("_") {
+            return Err(self.error("expected a local"));
+        }
+        self.number()
+    }
+
+    /// Parse a local that has been declared.
+    fn checked_local(&mut self) -> Result<Local, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let local = self.local()?;
+        if local >= self.locals.len() {
+            return Err(self.error_at(start, format!("`_{local}` isn't declared")));
+        }
+        Ok(local)
+    }
+
+    fn ty(&mut self) -> Result<Ty, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.eat_raw("!") {
+            return Ok(Ty::from_rigid_kind(RigidTy::Never));
+        }
+        if self.eat_raw("(") {
+            let mut tys = vec![];
+            while !self.eat(")") {
+                tys.push(self.ty()?);
+                if !self.eat(",") {
+                    self.expect(")")?;
+                    break;
+                }
+            }
+            return Ok(Ty::new_tuple(&tys));
+        }
+        if self.eat_raw("[") {
+            let elem_ty = self.ty()?;
+            if self.eat(";") {
+                let len = self.ty_const()?;
+                self.expect("]")?;
+                return Ok(Ty::new_array_with_const_len(elem_ty, len));
+            }
+            self.expect("]")?;
+            return Ok(Ty::from_rigid_kind(RigidTy::Slice(elem_ty)));
+        }
+        if self.eat_raw("&") {
+            let region = if self.rest().starts_with('\'') {
+                self.region()?
+            } else {
+                Region { kind: RegionKind::ReErased }
+            };
+            let mutability = if self.eat("mut") { Mutability::Mut } else { Mutability::Not };
+            return Ok(Ty::new_ref(region, self.ty()?, mutability));
+        }
+        if self.eat_raw("*") {
+            let mutability = if self.eat("mut") {
+                Mutability::Mut
+            } else {
+                self.expect("const")?;
+                Mutability::Not
+            };
+            return Ok(Ty::new_ptr(self.ty()?, mutability));
+        }
+        if ["fn", "unsafe", "extern", "for"].into_iter().any(|word| self.peek_word(word)) {
+            return self.fn_ty();
+        }
+        if self.rest().starts_with('{') {
+            let text = self.delimited('{', '}')?;
+            return self
+                .closure_ty(text)
+                .ok_or_else(|| self.error_at(start, format!("cannot find type `{{{text}}}`")));
+        }
+        if self.peek_word("impl") {
+            let Some((len, ty)) = self.impl_param(self.pos) else {
+                return Err(self.unsupported_at(start, "opaque types"));
+            };
+            self.pos += len;
+            return Ok(ty);
+        }
+        if self.eat("dyn") {
+            return self.dyn_ty();
+        }
+        if self.eat_raw("<") {
+            return self.projection_ty(start);
+        }
+
+        let path = self.path()?;
+        let args = if self.eat_raw("<") { self.generic_args()? } else { vec![] };
+        if !path.contains("::") && args.is_empty() {
+            if let Some(kind) = primitive(&path) {
+                return Ok(Ty::from_rigid_kind(kind));
+            }
+            if let Some(GenericArgKind::Type(ty)) = self.params.get(&path) {
+                return Ok(*ty);
+            }
+        }
+        for def in resolve(&path) {
+            match def.kind() {
+                DefKind::Struct | DefKind::Enum | DefKind::Union => {
+                    let args = self.complete_args(def, args, start)?;
+                    return Ok(Ty::from_rigid_kind(RigidTy::Adt(AdtDef(def), args)));
+                }
+                DefKind::ForeignTy if args.is_empty() => {
+                    return Ok(Ty::from_rigid_kind(RigidTy::Foreign(ForeignDef(def))));
+                }
+                _ => {}
+            }
+        }
+        Err(self.error_at(start, format!("cannot find type `{path}`")))
+    }
+
+    /// Find the anonymous type parameter whose name is at the given position, and return the
+    /// length of its name and its type.
+    ///
+    /// These parameters are named after their bounds, e.g.: `impl Fn(T) -> U`.
+    fn impl_param(&self, pos: usize) -> Option<(usize, Ty)> {
+        let rest = &self.input[pos..];
+        self.params
+            .iter()
+            .filter(|(name, _)| name.starts_with("impl ") && rest.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .and_then(|(name, arg)| match arg {
+                GenericArgKind::Type(ty) => Some((name.len(), *ty)),
+                _ => None,
+            })
+    }
+
+    /// Parse a trait object type after its `dyn` keyword, e.g.: `dyn Iterator<Item = u8> + Send`.
+    fn dyn_ty(&mut self) -> Result<Ty, Error> {
+        let mut predicates = vec![];
+        let mut region = Region { kind: RegionKind::ReErased };
+        loop {
+            self.skip_ws();
+            if self.rest().starts_with('\'') {
+                region = self.region()?;
+            } else {
+                let start = self.pos;
+                let path = self.path()?;
+                let Some(trait_def) =
+                    resolve(&path).into_iter().find(|def| def.kind() == DefKind::Trait)
+                else {
+                    return Err(self.error_at(start, format!("cannot find trait `{path}`")));
+                };
+                let trait_def = TraitDef(trait_def);
+                if TraitDef::declaration(&trait_def).has_auto_impl {
+                    predicates.push(ExistentialPredicate::AutoTrait(trait_def));
+                } else {
+                    predicates.extend(self.dyn_principal(trait_def, &path, start)?);
+                }
+            }
+            if !self.eat("+") {
+                break;
+            }
+        }
+        let predicates = predicates.into_iter().map(Binder::dummy).collect();
+        Ok(Ty::from_rigid_kind(RigidTy::Dynamic(predicates, region, DynKind::Dyn)))
+    }
+
+    /// Parse the arguments of the principal trait of a trait object, e.g.: `<Item = u8>` or
+    /// `(u8) -> u8`, and return the trait and the projections that they stand for.
+    ///
+    /// Only the associated types of the principal trait can be bound, or the output of closure
+    /// traits, which is printed like the output of a function.
+    fn dyn_principal(
+        &mut self,
+        trait_def: TraitDef,
+        path: &str,
+        start: usize,
+    ) -> Result<Vec<ExistentialPredicate>, Error> {
+        let mut args = vec![];
+        let mut bindings = vec![];
+        if self.eat_raw("(") {
+            let mut inputs = vec![];
+            while !self.eat(")") {
+                inputs.push(self.ty()?);
+                if !self.eat(",") {
+                    self.expect(")")?;
+                    break;
+                }
+            }
+            args.push(GenericArgKind::Type(Ty::new_tuple(&inputs)));
+            let output = if self.eat("->") { self.ty()? } else { Ty::new_tuple(&[]) };
+            bindings.push(("core::ops::FnOnce::Output".to_string(), output));
+        } else if self.eat_raw("<") {
+            while !self.eat(">") {
+                self.skip_ws();
+                let name: String = self.rest().chars().take_while(|c| is_ident_char(*c)).collect();
+                let after = self.rest()[name.len()..].trim_start();
+                if !name.is_empty() && after.starts_with('=') && !after.starts_with("==") {
+                    self.pos += name.len();
+                    self.expect("=")?;
+                    bindings.push((format!("{path}::{name}"), self.ty()?));
+                } else {
+                    args.push(self.generic_arg()?);
+                }
+                if !self.eat(",") {
+                    self.expect(">")?;
+                    break;
+                }
+            }
+        }
+        // The arguments don't include the `Self` type.
+        if with(|cx| cx.generics_of(trait_def.0)).params.len() != args.len() + 1 {
+            return Err(self.unsupported_at(start, "default arguments of trait objects"));
+        }
+        let generic_args = GenericArgs(args);
+        let mut predicates = vec![ExistentialPredicate::Trait(ExistentialTraitRef {
+            def_id: trait_def,
+            generic_args: generic_args.clone(),
+        })];
+        for (binding, ty) in bindings {
+            let Some(item) =
+                resolve(&binding).into_iter().find(|def| def.kind() == DefKind::AssocTy)
+            else {
+                return Err(self.unsupported_at(start, "associated types of supertraits"));
+            };
+            predicates.push(ExistentialPredicate::Projection(ExistentialProjection {
+                def_id: TraitDef(item),
+                generic_args: generic_args.clone(),
+                term: TermKind::Type(ty),
+            }));
+        }
+        Ok(predicates)
+    }
+
+    /// Parse a projection type after its opening `<`, e.g.: `<T as Iterator>::Item`.
+    fn projection_ty(&mut self, start: usize) -> Result<Ty, Error> {
+        let self_ty = self.ty()?;
+        if !self.eat("as") {
+            return Err(self.unsupported_at(start, "inherent associated types"));
+        }
+        let trait_path = self.path()?;
+        let trait_args = if self.eat_raw("<") { self.generic_args()? } else { vec![] };
+        self.expect(">")?;
+        if !self.eat_raw("::") {
+            return Err(self.error("expected `::`"));
+        }
+        let name = self.ident()?;
+        let own_args = if self.eat_raw("<") { self.generic_args()? } else { vec![] };
+        for trait_def in resolve(&trait_path) {
+            if trait_def.kind() != DefKind::Trait {
+                continue;
+            }
+            let mut args = vec![GenericArgKind::Type(self_ty)];
+            args.extend(trait_args.iter().cloned());
+            let Ok(args) = self.complete_args(trait_def, args, start) else {
+                continue;
+            };
+            for item in resolve(&format!("{trait_path}::{name}")) {
+                if item.kind() != DefKind::AssocTy {
+                    continue;
+                }
+                let mut args = args.0.clone();
+                args.extend(own_args.iter().cloned());
+                let alias = AliasTy { def_id: AliasDef(item), args: GenericArgs(args) };
+                return Ok(with(|cx| cx.new_alias_ty(AliasKind::Projection, &alias)));
+            }
+        }
+        let text = &self.input[start..self.pos];
+        Err(self.error_at(start, format!("cannot find type `{text}`")))
+    }
+
+    /// Parse the type of a function pointer or of a function definition, e.g.:
+    /// `fn(u8) -> u8 {foo}`.
+    fn fn_ty(&mut self) -> Result<Ty, Error> {
+        let start = self.pos;
+        if let Some(path_start) = self.fn_def_path() {
+            self.pos = path_start;
+            let cnst = self.path_const(None, false)?;
+            self.expect("}")?;
+            let ty = cnst.ty();
+            return match ty.kind().rigid() {
+                Some(RigidTy::FnDef(..)) => Ok(ty),
+                _ => Err(self.error_at(path_start, "expected a function")),
+            };
+        }
+        if self.peek_word("for") {
+            return Err(self.unsupported_at(start, "higher-ranked function pointers"));
+        }
+        let safety = if self.eat("unsafe") { Safety::Unsafe } else { Safety::Safe };
+        let abi = if self.eat("extern") {
+            self.skip_ws();
+            let abi_start = self.pos;
+            let name = self.string()?;
+            abi(&name).ok_or_else(|| self.unsupported_at(abi_start, format!("the `{name}` ABI")))?
+        } else {
+            Abi::Rust
+        };
+        self.expect("fn")?;
+        self.expect("(")?;
+        let mut inputs_and_output = vec![];
+        let mut c_variadic = false;
+        while !self.eat(")") {
+            if self.eat("...") {
+                c_variadic = true;
+            } else {
+                inputs_and_output.push(self.ty()?);
+            }
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        inputs_and_output.push(if self.eat("->") { self.ty()? } else { Ty::new_tuple(&[]) });
+        let sig = FnSig { inputs_and_output, c_variadic, safety, abi };
+        Ok(Ty::from_rigid_kind(RigidTy::FnPtr(Binder::dummy(sig))))
+    }
+
+    /// If the function signature at the current position is followed by the path of a function
+    /// definition, return the position of the path.
+    fn fn_def_path(&self) -> Option<usize> {
+        let bytes = self.input.as_bytes();
+        let mut pos = self.pos + self.rest().find('(')?;
+        let mut depth = 0usize;
+        // Skip the arguments.
+        loop {
+            match bytes.get(pos)? {
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth -= 1,
+                _ => {}
+            }
+            pos += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+        // Skip the return type, if any.
+        let rest = &self.input[pos..];
+        if let Some(ret) = rest.strip_prefix(" -> ") {
+            pos = self.input.len() - ret.len();
+            let ret_start = pos;
+            loop {
+                match bytes.get(pos)? {
+                    b'-' if bytes.get(pos + 1) == Some(&b'>') => pos += 1,
+                    b'{' if depth == 0 && pos > ret_start && bytes[pos - 1] == b' ' => break,
+                    b'(' | b'[' | b'{' | b'<' => depth += 1,
+                    b')' | b']' | b'}' | b'>' if depth > 0 => depth -= 1,
+                    b')' | b']' | b'}' | b'>' | b',' | b';' | b'\n' => return None,
+                    _ => {}
+                }
+                pos += 1;
+            }
+        } else if rest.starts_with(" {") {
+            pos += 1;
+        } else {
+            return None;
+        }
+        // Exclude the body of a function that returns a function pointer.
+        let path = pos + 1;
+        bytes.get(path).filter(|c| !c.is_ascii_whitespace()).map(|_| path)
+    }
+
+    fn region(&mut self) -> Result<Region, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.eat_raw("'_") {
+            return Ok(Region { kind: RegionKind::ReErased });
+        }
+        if self.eat_raw("'static") {
+            return Ok(Region { kind: RegionKind::ReStatic });
+        }
+        Err(self.unsupported_at(start, "named lifetimes"))
+    }
+
+    /// Parse the length of an array type, which may be a generic parameter.
+    fn ty_const(&mut self) -> Result<TyConst, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            let len = self.number()?;
+            return TyConst::try_from_target_usize(len)
+                .map_err(|error| self.error_at(start, error.message()));
+        }
+        let name = self.ident()?;
+        match self.params.get(&name) {
+            Some(GenericArgKind::Const(cnst)) => Ok(cnst.clone()),
+            _ => Err(self.error_at(start, format!("cannot find constant `{name}`"))),
+        }
+    }
+
+    /// Parse generic arguments after their opening `<`.
+    fn generic_args(&mut self) -> Result<Vec<GenericArgKind>, Error> {
+        let mut args = vec![];
+        while !self.eat(">") {
+            args.push(self.generic_arg()?);
+            if !self.eat(",") {
+                self.expect(">")?;
+                break;
+            }
+        }
+        Ok(args)
+    }
+
+    fn generic_arg(&mut self) -> Result<GenericArgKind, Error> {
+        self.skip_ws();
+        if self.rest().starts_with('\'') {
+            return Ok(GenericArgKind::Lifetime(self.region()?));
+        }
+        if self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(GenericArgKind::Const(self.ty_const()?));
+        }
+        let name: String = self.rest().chars().take_while(|c| is_ident_char(*c)).collect();
+        match self.params.get(&name) {
+            Some(arg @ GenericArgKind::Const(_)) => {
+                let arg = arg.clone();
+                self.pos += name.len();
+                Ok(arg)
+            }
+            _ => Ok(GenericArgKind::Type(self.ty()?)),
+        }
+    }
+
+    /// Complete the given arguments of a definition with its default arguments.
+    fn complete_args(
+        &self,
+        def: DefId,
+        args: Vec<GenericArgKind>,
+        start: usize,
+    ) -> Result<GenericArgs, Error> {
+        with(|cx| cx.generic_args_with_defaults(def, &GenericArgs(args)))
+            .map_err(|error| self.error_at(start, error.message()))
+    }
+
+    fn closure_ty(&mut self, text: &str) -> Option<Ty> {
+        let closures = self.closures.get_or_insert_with(|| {
+            let mut closures = HashMap::new();
+            for item in all_local_items() {
+                if item.def_id().kind() == DefKind::Closure {
+                    let ty = item.ty();
+                    closures.entry(ty.to_string()).or_insert(ty);
+                }
+            }
+            closures
+        });
+        let text = format!("{{{text}}}");
+        closures.iter().find(|(printed, _)| same_text(printed, &text)).map(|(_, ty)| *ty)
+    }
+
+    fn constant(&mut self, expected: Option<Ty>) -> Result<MirConst, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let rest = self.rest();
+        if rest.starts_with('"') {
+            return Ok(MirConst::from_str(&self.string()?));
+        }
+        if rest.starts_with('\'') {
+            let value = self.char()?;
+            return self.scalar(value as u128, Ty::from_rigid_kind(RigidTy::Char), start);
+        }
+        if rest.starts_with("b\"") || rest.starts_with("*b\"") {
+            return Err(self.unsupported("byte string constants"));
+        }
+        if self.eat("ZeroSized") {
+            // Zero-sized constants whose value has no path, such as closures.
+            self.expect(":")?;
+            let ty = self.ty()?;
+            return MirConst::try_new_zero_sized(ty)
+                .map_err(|error| self.error_at(start, error.message()));
+        }
+        if rest.starts_with("{alloc") {
+            return self.pointer_const();
+        }
+        if rest.starts_with("{0x") {
+            return Err(self.unsupported("pointer constants without provenance"));
+        }
+        if self.eat("true") {
+            return Ok(MirConst::from_bool(true));
+        }
+        if self.eat("false") {
+            return Ok(MirConst::from_bool(false));
+        }
+        if self.eat_raw("()") {
+            return MirConst::try_new_zero_sized(Ty::new_tuple(&[]))
+                .map_err(|error| self.error_at(start, error.message()));
+        }
+        if self.eat_raw("(") {
+            let tys = match expected.as_ref().and_then(|ty| ty.kind().rigid().cloned()) {
+                Some(RigidTy::Tuple(tys)) => tys,
+                _ => vec![],
+            };
+            let fields = self.constants(")", &tys)?;
+            let ty = Ty::new_tuple(&fields.iter().map(MirConst::ty).collect::<Vec<_>>());
+            return self.aggregate(ty, None, fields, start);
+        }
+        if self.eat_raw("[") {
+            let elem_ty = match expected.as_ref().and_then(|ty| ty.kind().rigid().cloned()) {
+                Some(RigidTy::Array(elem_ty, _)) => Some(elem_ty),
+                _ => None,
+            };
+            let fields = self.constants("]", &elem_ty.into_iter().collect::<Vec<_>>())?;
+            let Some(elem_ty) = elem_ty.or_else(|| fields.first().map(MirConst::ty)) else {
+                return Err(self.error_at(start, "cannot infer the type of the array"));
+            };
+            let ty = Ty::try_new_array(elem_ty, fields.len() as u64)
+                .map_err(|error| self.error_at(start, error.message()))?;
+            return self.aggregate(ty, None, fields, start);
+        }
+        if let Some(cnst) = self.number_const(expected)? {
+            return Ok(cnst);
+        }
+        let name: String = rest.chars().take_while(|c| is_ident_char(*c)).collect();
+        if let Some(GenericArgKind::Const(cnst)) = self.params.get(&name)
+            && !rest[name.len()..].starts_with("::")
+        {
+            let cnst = cnst.clone();
+            self.pos += name.len();
+            let ty = expected.unwrap_or(Ty::usize_ty());
+            return Ok(with(|cx| cx.new_const_from_ty_const(&cnst, ty)));
+        }
+        self.path_const(expected, true)
+    }
+
+    /// Parse a comma separated list of constants up to the given delimiter. Every constant uses
+    /// the type at the same position, or the last type, as its expected type.
+    fn constants(&mut self, close: &str, tys: &[Ty]) -> Result<Vec<MirConst>, Error> {
+        let mut fields = vec![];
+        while !self.eat(close) {
+            let expected = tys.get(fields.len()).or(tys.last()).copied();
+            fields.push(self.constant(expected)?);
+            if !self.eat(",") {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(fields)
+    }
+
+    fn aggregate(
+        &self,
+        ty: Ty,
+        variant: Option<VariantIdx>,
+        fields: Vec<MirConst>,
+        start: usize,
+    ) -> Result<MirConst, Error> {
+        with(|cx| cx.try_new_const_aggregate(ty, variant, &fields))
+            .map_err(|error| self.error_at(start, error.message()))
+    }
+
+    fn scalar(&self, bits: u128, ty: Ty, start: usize) -> Result<MirConst, Error> {
+        with(|cx| cx.try_new_const_from_bits(bits, ty))
+            .map_err(|error| self.error_at(start, error.message()))
+    }
+
+    /// Parse a numeric constant, e.g.: `1_u8`, `i32::MIN` and `1.5f32`.
+    fn number_const(&mut self, expected: Option<Ty>) -> Result<Option<MirConst>, Error> {
+        let start = self.pos;
+        let rest = self.rest();
+        // Integer bounds, e.g.: `u8::MAX`.
+        let bound = ["MIN", "MAX"].into_iter().find_map(|bound| {
+            let (ty, rest) = rest.split_once(&format!("::{bound}"))?;
+            let kind = primitive(ty)?;
+            (!rest.starts_with(is_ident_char)).then_some((kind, ty.len() + bound.len() + 2, bound))
+        });
+        if let Some((kind, len, bound)) = bound {
+            let (bits, signed) = match kind {
+                RigidTy::Int(int_ty) => (int_ty.num_bytes() * 8, true),
+                RigidTy::Uint(uint_ty) => (uint_ty.num_bytes() * 8, false),
+                _ => return Ok(None),
+            };
+            let max = u128::MAX >> (128 - bits);
+            let value = match (bound, signed) {
+                ("MIN", true) => 1 << (bits - 1),
+                ("MAX", true) => max >> 1,
+                ("MAX", false) => max,
+                _ => return Ok(None),
+            };
+            self.pos += len;
+            return self.scalar(value, Ty::from_rigid_kind(kind), start).map(Some);
+        }
+        // Floats that aren't finite, e.g.: `NaN_f32` and `-inf_f64`.
+        for (name, value) in [("NaN", f64::NAN), ("inf", f64::INFINITY), ("-inf", -f64::INFINITY)] {
+            for (suffix, kind) in [("f32", FloatTy::F32), ("f64", FloatTy::F64)] {
+                let text = format!("{name}_{suffix}");
+                if rest.starts_with(&text) && !rest[text.len()..].starts_with(is_ident_char) {
+                    self.pos += text.len();
+                    let bits = match kind {
+                        FloatTy::F32 => (value as f32).to_bits() as u128,
+                        _ => value.to_bits() as u128,
+                    };
+                    let ty = Ty::from_rigid_kind(RigidTy::Float(kind));
+                    return self.scalar(bits, ty, start).map(Some);
+                }
+            }
+        }
+        let negative = rest.starts_with('-');
+        if !rest[negative as usize..].starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        let number = self.take_while(|c| c.is_ascii_alphanumeric() || "-+._".contains(c));
+        let (value, suffix) = match number.find(['_', 'f', 'i', 'u']) {
+            Some(idx) if !number[idx..].contains(['e', 'E']) || number.contains('_') => {
+                (&number[..idx], number[idx..].trim_start_matches('_'))
+            }
+            _ => (number, ""),
+        };
+        let kind = match primitive(suffix) {
+            Some(kind) => kind,
+            None if suffix.is_empty() => {
+                match expected.as_ref().and_then(|ty| ty.kind().rigid().cloned()) {
+                    Some(kind @ (RigidTy::Int(_) | RigidTy::Uint(_) | RigidTy::Float(_))) => kind,
+                    _ => {
+                        return Err(
+                            self.error_at(start, format!("cannot infer the type of `{number}`"))
+                        );
+                    }
+                }
+            }
+            None => return Err(self.error_at(start, format!("unknown suffix `{suffix}`"))),
+        };
+        let invalid = || self.error_at(start, format!("invalid literal `{number}`"));
+        let bits = match kind {
+            RigidTy::Int(int_ty) => {
+                let value: i128 = value.parse().map_err(|_| invalid())?;
+                let bits = int_ty.num_bytes() * 8;
+                let (min, max) = (-1i128 << (bits - 1), (1i128 << (bits - 1)).wrapping_sub(1));
+                if bits < 128 && !(min..=max).contains(&value) {
+                    return Err(invalid());
+                }
+                value as u128 & (u128::MAX >> (128 - bits))
+            }
+            RigidTy::Uint(uint_ty) => {
+                let value: u128 = value.parse().map_err(|_| invalid())?;
+                if value > u128::MAX >> (128 - uint_ty.num_bytes() * 8) {
+                    return Err(invalid());
+                }
+                value
+            }
+            RigidTy::Float(FloatTy::F32) => {
+                value.parse::<f32>().map_err(|_| invalid())?.to_bits() as u128
+            }
+            RigidTy::Float(FloatTy::F64) => {
+                value.parse::<f64>().map_err(|_| invalid())?.to_bits() as u128
+            }
+            RigidTy::Float(_) => {
+                return Err(self.unsupported_at(start, "`f16` and `f128` constants"));
+            }
+            _ => return Err(self.error_at(start, format!("unknown suffix `{suffix}`"))),
+        };
+        self.scalar(bits, Ty::from_rigid_kind(kind), start).map(Some)
+    }
+
+    /// Parse a constant that is named by a path, such as a function or a unit variant.
+    ///
+    /// If `fields` is true, the path may be followed by the fields of an ADT, e.g.:
+    /// `std::option::Option::Some(1_u8)`.
+    fn path_const(&mut self, expected: Option<Ty>, fields: bool) -> Result<MirConst, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let path = self.value_path()?;
+        if fields && (self.rest().starts_with('(') || self.rest().starts_with(" {")) {
+            return self.adt_const(&path, expected, start);
+        }
+        let text = &self.input[start..self.pos];
+        if let Some(promoted) = path.promoted {
+            return self.promoted_const(&path, promoted, start);
+        }
+        if path
+            .segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Name(name, _) if name.starts_with('{')))
+        {
+            return Err(self.unsupported_at(start, "anonymous constants"));
+        }
+        for (def, args) in self.value_candidates(&path, start)? {
+            let Ok(cnst) = self.item_const(def, args, expected) else {
+                continue;
+            };
+            if same_text(&with(|cx| cx.mir_const_pretty(&cnst, false)), text) {
+                return Ok(cnst);
+            }
+        }
+        Err(self.error_at(start, format!("cannot find value `{text}`")))
+    }
+
+    /// Build the constant that refers to the given item.
+    fn item_const(
+        &self,
+        def: DefId,
+        args: Vec<GenericArgKind>,
+        expected: Option<Ty>,
+    ) -> Result<MirConst, Error> {
+        match def.kind() {
+            DefKind::Fn | DefKind::AssocFn | DefKind::Ctor(CtorKind::Fn) => {
+                let args = with(|cx| cx.generic_args_with_defaults(def, &GenericArgs(args)))?;
+                MirConst::try_new_zero_sized(Ty::from_rigid_kind(RigidTy::FnDef(FnDef(def), args)))
+            }
+            DefKind::Const | DefKind::AssocConst => {
+                let args = with(|cx| cx.generic_args_with_defaults(def, &GenericArgs(args)))?;
+                let cnst = UnevaluatedConst { def: ConstDef(def), args, promoted: None };
+                with(|cx| cx.try_new_const_unevaluated(&cnst))
+            }
+            DefKind::Ctor(CtorKind::Const) => {
+                // Unit structs and variants, whose arguments are usually not printed.
+                let adt_of = |ty: Ty| match ty.kind().rigid() {
+                    Some(RigidTy::Adt(adt, _)) => Some(*adt),
+                    _ => None,
+                };
+                let ty = match expected {
+                    Some(ty)
+                        if adt_of(ty).is_some()
+                            && adt_of(ty) == adt_of(with(|cx| cx.def_ty(def))) =>
+                    {
+                        ty
+                    }
+                    _ => {
+                        let args =
+                            with(|cx| cx.generic_args_with_defaults(def, &GenericArgs(args)))?;
+                        with(|cx| cx.def_ty_with_args(def, &args))
+                    }
+                };
+                let Some(RigidTy::Adt(adt, _)) = ty.kind().rigid().cloned() else {
+                    return Err(Error::new(ErrorKind::UnexpectedItem(def), "expected an ADT"));
+                };
+                let variant = (adt.kind() == AdtKind::Enum)
+                    .then(|| variant_named(adt, &def.name()))
+                    .flatten();
+                with(|cx| cx.try_new_const_aggregate(ty, variant, &[]))
+            }
+            _ => Err(Error::new(ErrorKind::UnexpectedItem(def), "expected a value")),
+        }
+    }
+
+    /// Parse a pointer into an allocation, e.g.: `{alloc3+0x4<imm>: &u8}`.
+    fn pointer_const(&mut self) -> Result<MirConst, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        self.expect("{")?;
+        if !self.eat_raw("alloc") {
+            return Err(self.error("expected an allocation"));
+        }
+        let alloc = self.number()?;
+        let offset = if self.eat_raw("+0x") {
+            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+            u64::from_str_radix(digits, 16).map_err(|_| self.error("expected an offset"))?
+        } else {
+            0
+        };
+        let immutable = self.eat_raw("<imm>");
+        self.expect(":")?;
+        let ty = self.ty()?;
+        self.expect("}")?;
+        with(|cx| cx.try_new_const_ptr(alloc, offset, immutable, ty))
+            .map_err(|error| self.error_at(start, error.message()))
+    }
+
+    /// Parse the fields of an ADT constant named by the given path.
+    fn adt_const(
+        &mut self,
+        path: &ValuePath,
+        expected: Option<Ty>,
+        start: usize,
+    ) -> Result<MirConst, Error> {
+        let Some(Segment::Name(name, _)) = path.segments.last() else {
+            return Err(self.error_at(start, "expected the name of a constructor"));
+        };
+        // The ADT is the expected one, or the one of the constructor named by the path.
+        let mut adts = vec![];
+        if let Some(RigidTy::Adt(adt, args)) = expected.and_then(|ty| ty.kind().rigid().cloned()) {
+            adts.push((adt, args));
+        }
+        for (def, args) in self.value_candidates(path, start).unwrap_or_default() {
+            let ty = match def.kind() {
+                DefKind::Ctor(CtorKind::Fn) => self
+                    .item_const(def, args, None)
+                    .ok()
+                    .and_then(|cnst| cnst.ty().kind().fn_sig())
+                    .map(|sig| sig.value.output()),
+                DefKind::Struct | DefKind::Union => self
+                    .complete_args(def, args, start)
+                    .ok()
+                    .map(|args| AdtDef(def).ty_with_args(&args)),
+                _ => None,
+            };
+            if let Some(RigidTy::Adt(adt, args)) = ty.and_then(|ty| ty.kind().rigid().cloned()) {
+                adts.push((adt, args));
+            }
+        }
+        let fields_start = self.pos;
+        for (adt, args) in adts {
+            let variant = match adt.kind() {
+                AdtKind::Enum => variant_named(adt, name),
+                AdtKind::Struct | AdtKind::Union => Some(VariantIdx::to_val(0)),
+            };
+            let Some(variant) = variant.and_then(|idx| adt.variant(idx)) else {
+                continue;
+            };
+            let field_tys: Vec<_> =
+                variant.fields().iter().map(|field| field.ty_with_args(&args)).collect();
+            self.pos = fields_start;
+            let fields = if self.eat_raw("(") {
+                self.constants(")", &field_tys)
+            } else {
+                self.braced_fields(&field_tys)
+            };
+            let fields = match fields {
+                Ok(fields) => fields,
+                Err(error) if *error.kind() == ErrorKind::Unsupported => return Err(error),
+                Err(_) => continue,
+            };
+            let variant = (adt.kind() == AdtKind::Enum).then_some(variant.idx);
+            let ty = adt.ty_with_args(&args);
+            let Ok(cnst) = with(|cx| cx.try_new_const_aggregate(ty, variant, &fields)) else {
+                continue;
+            };
+            if same_text(
+                &with(|cx| cx.mir_const_pretty(&cnst, false)),
+                &self.input[start..self.pos],
+            ) {
+                return Ok(cnst);
+            }
+        }
+        Err(self.error_at(start, "cannot find the type of this constant"))
+    }
+
+    /// Parse the named fields of a constant, e.g.: ` {{ a: 1_u8, b: 2_u8 }}`.
+    ///
+    /// The compiler prints these braces doubled, but single braces are accepted as well.
+    fn braced_fields(&mut self, tys: &[Ty]) -> Result<Vec<MirConst>, Error> {
+        let close = if self.eat("{{") { "}}" } else { "}" };
+        if close == "}" {
+            self.expect("{")?;
+        }
+        let mut fields = vec![];
+        while !self.eat(close) {
+            self.ident()?;
+            self.expect(":")?;
+            fields.push(self.constant(tys.get(fields.len()).copied())?);
+            if !self.eat(",") {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Build the promoted constant named by the given path, e.g.: `main::promoted[0]`.
+    fn promoted_const(
+        &mut self,
+        path: &ValuePath,
+        promoted: u32,
+        start: usize,
+    ) -> Result<MirConst, Error> {
+        let text = &self.input[start..self.pos];
+        let mut candidates = self.value_candidates(path, start).unwrap_or_default();
+        // Closures can't be named by a path, so look for local items with the same name.
+        let mut name = vec![];
+        let mut explicit_args = vec![];
+        for segment in &path.segments {
+            if let Segment::Name(segment, args) = segment {
+                name.push(segment.as_str());
+                explicit_args.extend(args.iter().flatten().cloned());
+            }
+        }
+        let name = name.join("::");
+        for item in all_local_items() {
+            if item.name() == name {
+                candidates.push((item.def_id(), explicit_args.clone()));
+            }
+        }
+        for (def, args) in candidates {
+            // Items that are generic are printed with their arguments, the others use the
+            // identity arguments, which is what closures are given.
+            let args = match self.complete_args(def, args, start) {
+                Ok(args) => args,
+                Err(_) => with(|cx| cx.identity_args(def)),
+            };
+            let cnst = UnevaluatedConst { def: ConstDef(def), args, promoted: Some(promoted) };
+            let Ok(cnst) = with(|cx| cx.try_new_const_unevaluated(&cnst)) else {
+                continue;
+            };
+            if same_text(&with(|cx| cx.mir_const_pretty(&cnst, false)), text) {
+                return Ok(cnst);
+            }
+        }
+        Err(self.error_at(start, format!("cannot find value `{text}`")))
+    }
+
+    fn value_path(&mut self) -> Result<ValuePath, Error> {
+        self.skip_ws();
+        let mut path = ValuePath { qself: None, segments: vec![], promoted: None };
+        let impl_segment =
+            self.rest().starts_with("<impl ") && self.impl_param(self.pos + 1).is_none();
+        if self.rest().starts_with('<') && !impl_segment {
+            self.eat_raw("<");
+            let self_ty = self.ty()?;
+            let trait_ref = if self.eat("as") {
+                let trait_path = self.path()?;
+                if self.rest().starts_with("::<") {
+                    return Err(self.unsupported("traits that are defined inside of impls"));
+                }
+                let args = if self.eat_raw("<") { self.generic_args()? } else { vec![] };
+                Some((trait_path, args))
+            } else {
+                None
+            };
+            self.expect(">")?;
+            if !self.eat_raw("::") {
+                return Err(self.error("expected `::`"));
+            }
+            path.qself = Some((self_ty, trait_ref));
+        }
+        loop {
+            if self.eat_raw("<") {
+                self.expect("impl")?;
+                path.segments.push(Segment::Impl(self.ty()?));
+                self.expect(">")?;
+            } else {
+                let name = if self.rest().starts_with('{') {
+                    format!("{{{}}}", self.delimited('{', '}')?)
+                } else {
+                    self.ident()?
+                };
+                let args = if self.rest().starts_with("::<") && !self.rest().starts_with("::<impl ")
+                {
+                    self.pos += 3;
+                    Some(self.generic_args()?)
+                } else {
+                    None
+                };
+                path.segments.push(Segment::Name(name, args));
+            }
+            if self.eat_raw("::promoted[") {
+                path.promoted = Some(self.number()?);
+                self.expect("]")?;
+                break;
+            }
+            if !self.rest().starts_with("::") {
+                break;
+            }
+            self.pos += 2;
+        }
+        Ok(path)
+    }
+
+    /// Find the items that the given path may refer to, and the arguments given to them.
+    ///
+    /// The arguments are the ones of the item and its parents, but they may be incomplete.
+    fn value_candidates(
+        &mut self,
+        path: &ValuePath,
+        start: usize,
+    ) -> Result<Vec<(DefId, Vec<GenericArgKind>)>, Error> {
+        let mut candidates = vec![];
+        if let Some((self_ty, trait_ref)) = &path.qself {
+            let [Segment::Name(name, own_args)] = path.segments.as_slice() else {
+                return Err(self.unsupported_at(start, "nested associated items"));
+            };
+            let own_args = own_args.clone().unwrap_or_default();
+            let Some((trait_path, trait_args)) = trait_ref else {
+                return Ok(self.inherent_items(*self_ty, name, own_args));
+            };
+            for trait_def in resolve(trait_path) {
+                if trait_def.kind() != DefKind::Trait {
+                    continue;
+                }
+                let mut args = vec![GenericArgKind::Type(*self_ty)];
+                args.extend(trait_args.iter().cloned());
+                let Ok(args) = self.complete_args(trait_def, args, start) else {
+                    continue;
+                };
+                for item in resolve(&format!("{trait_path}::{name}")) {
+                    let mut args = args.0.clone();
+                    args.extend(own_args.iter().cloned());
+                    candidates.push((item, args));
+                }
+            }
+            return Ok(candidates);
+        }
+
+        let mut prefix = String::new();
+        // The type that the next segment is an associated item of, and its path and arguments if
+        // it is an ADT.
+        let mut self_ty: Option<(Ty, Option<(String, GenericArgs)>)> = None;
+        for (idx, segment) in path.segments.iter().enumerate() {
+            let last = idx + 1 == path.segments.len();
+            let (name, args) = match segment {
+                Segment::Impl(ty) if !last => {
+                    self_ty = Some((*ty, None));
+                    continue;
+                }
+                Segment::Impl(_) => return Err(self.error_at(start, "expected an item")),
+                Segment::Name(name, args) => (name, args.clone()),
+            };
+            if let Some((ty, adt)) = self_ty.take() {
+                if !last {
+                    return Err(self.unsupported_at(start, "nested associated items"));
+                }
+                if let Some((adt_path, adt_args)) = adt {
+                    for def in resolve(&format!("{adt_path}::{name}")) {
+                        if matches!(def.kind(), DefKind::Ctor(_) | DefKind::Variant) {
+                            candidates.push((def, adt_args.0.clone()));
+                        }
+                    }
+                }
+                candidates.extend(self.inherent_items(ty, name, args.unwrap_or_default()));
+                return Ok(candidates);
+            }
+            if !prefix.is_empty() {
+                prefix.push_str("::");
+            }
+            prefix.push_str(name);
+            if last {
+                let args = args.unwrap_or_default();
+                candidates.extend(resolve(&prefix).into_iter().map(|def| (def, args.clone())));
+            } else if let Some(args) = args {
+                let Some(adt) = resolve(&prefix).into_iter().find(|def| {
+                    matches!(def.kind(), DefKind::Struct | DefKind::Enum | DefKind::Union)
+                }) else {
+                    return Err(self.error_at(start, format!("cannot find type `{prefix}`")));
+                };
+                let args = self.complete_args(adt, args, start)?;
+                let ty = Ty::from_rigid_kind(RigidTy::Adt(AdtDef(adt), args.clone()));
+                self_ty = Some((ty, Some((prefix.clone(), args))));
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Find the items with the given name in the inherent impls of the given type.
+    fn inherent_items(
+        &self,
+        ty: Ty,
+        name: &str,
+        own_args: Vec<GenericArgKind>,
+    ) -> Vec<(DefId, Vec<GenericArgKind>)> {
+        let mut items = vec![];
+        for impl_def in with(|cx| cx.inherent_impls(ty)) {
+            let Some(impl_args) = impl_args(impl_def, ty) else {
+                continue;
+            };
+            for item in impl_def.associated_items() {
+                let item_name = match &item.kind {
+                    AssocKind::Fn { name, .. } | AssocKind::Const { name } => name,
+                    AssocKind::Type { .. } => continue,
+                };
+                if item_name == name {
+                    let mut args = impl_args.clone();
+                    args.extend(own_args.iter().cloned());
+                    items.push((item.def_id.def_id(), args));
+                }
+            }
+        }
+        items
+    }
+
+    /// Parse a path made of identifiers, e.g.: `std::vec::Vec`.
+    fn path(&mut self) -> Result<String, Error> {
+        let mut path = self.ident()?;
+        while self.rest().starts_with("::") && self.rest()[2..].starts_with(is_ident_char) {
+            self.pos += 2;
+            path.push_str("::");
+            path.push_str(&self.ident()?);
+        }
+        Ok(path)
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        self.skip_ws();
+        let ident = self.take_while(is_ident_char);
+        if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+            self.pos -= ident.len();
+            return Err(self.error("expected an identifier"));
+        }
+        Ok(ident.to_string())
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        digits.parse().map_err(|_| self.error_at(start, "expected a number"))
+    }
+
+    /// Parse a string literal, as printed by `Debug`.
+    fn string(&mut self) -> Result<String, Error> {
+        self.skip_ws();
+        if !self.eat_raw("\"") {
+            return Err(self.error("expected a string"));
+        }
+        let mut value = String::new();
+        loop {
+            match self.rest().chars().next() {
+                Some('"') => break,
+                Some(_) => value.push(self.escaped_char()?),
+                None => return Err(self.error("expected `\"`")),
+            }
+        }
+        self.pos += 1;
+        Ok(value)
+    }
+
+    /// Parse a character literal, as printed by `Debug`.
+    fn char(&mut self) -> Result<char, Error> {
+        self.skip_ws();
+        if !self.eat_raw("'") {
+            return Err(self.error("expected a character"));
+        }
+        let value = self.escaped_char()?;
+        if !self.eat_raw("'") {
+            return Err(self.error("expected `'`"));
+        }
+        Ok(value)
+    }
+
+    fn escaped_char(&mut self) -> Result<char, Error> {
+        let start = self.pos;
+        let mut chars = self.rest().chars();
+        let Some(c) = chars.next() else {
+            return Err(self.error("unexpected end of input"));
+        };
+        if c != '\\' {
+            self.pos += c.len_utf8();
+            return Ok(c);
+        }
+        let value = match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '\'' | '"')) => c,
+            Some('u') => {
+                let rest = chars.as_str();
+                let code = rest
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.split_once('}'))
+                    .and_then(|(code, _)| u32::from_str_radix(code, 16).ok())
+                    .and_then(char::from_u32);
+                let Some(value) = code else {
+                    return Err(self.error_at(start, "invalid escape sequence"));
+                };
+                self.pos += rest.find('}').unwrap() + 1;
+                value
+            }
+            _ => return Err(self.error_at(start, "invalid escape sequence")),
+        };
+        self.pos += 2;
+        Ok(value)
+    }
+
+    /// Return the text between the given delimiters, which must be at the current position.
+    fn delimited(&mut self, open: char, close: char) -> Result<&'a str, Error> {
+        self.skip_ws();
+        let start = self.pos;
+        if !self.eat_raw(&open.to_string()) {
+            return Err(self.error(format!("expected `{open}`")));
+        }
+        let mut depth = 1;
+        for (offset, c) in self.rest().char_indices() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    let text = &self.input[start + 1..self.pos + offset];
+                    self.pos += offset + 1;
+                    return Ok(text);
+                }
+            }
+        }
+        Err(self.error_at(start, format!("expected `{close}`")))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_ws();
+        self.pos == self.input.len()
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_ws(&mut self) {
+        self.pos = self.input.len() - self.lookahead(self.pos).len();
+    }
+
+    /// Return the input from the given position, without the whitespace and comments that it
+    /// starts with.
+    fn lookahead(&self, pos: usize) -> &'a str {
+        let mut rest = &self.input[pos..];
+        loop {
+            rest = rest.trim_start();
+            match rest.strip_prefix("//") {
+                Some(comment) => rest = comment.find('\n').map_or("", |end| &comment[end..]),
+                None => return rest,
+            }
+        }
+    }
+
+    /// Whether the given word is at the current position.
+    fn peek_word(&mut self, word: &str) -> bool {
+        self.skip_ws();
+        self.rest().strip_prefix(word).is_some_and(|rest| !rest.starts_with(is_ident_char))
+    }
+
+    /// Consume the given token, if it follows the current position. Words must not be followed
+    /// by other identifier characters.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        let Some(rest) = self.rest().strip_prefix(token) else {
+            return false;
+        };
+        if token.ends_with(is_ident_char) && rest.starts_with(is_ident_char) {
+            return false;
+        }
+        self.pos += token.len();
+        true
+    }
+
+    /// Consume the given token, if it is exactly at the current position.
+    fn eat_raw(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), Error> {
+        if self.eat(token) { Ok(()) } else { Err(self.error(format!("expected `{token}`"))) }
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> Error {
+        let (line, column) = self.line_column(pos);
+        Error::new(
+            ErrorKind::Parse { line, column },
+            format!("{line}:{column}: {}", message.into()),
+        )
+    }
+
+    fn unsupported(&self, what: impl Into<String>) -> Error {
+        self.unsupported_at(self.pos, what)
+    }
+
+    fn unsupported_at(&self, pos: usize, what: impl Into<String>) -> Error {
+        let (line, column) = self.line_column(pos);
+        let message = format!("{line}:{column}: parsing {} is not supported", what.into());
+        Error::new(ErrorKind::Unsupported, message)
+    }
+
+    fn line_column(&self, pos: usize) -> (usize, usize) {
+        let before = &self.input[..pos];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether two printed items are the same, regardless of whitespace.
+fn same_text(printed: &str, text: &str) -> bool {
+    printed.chars().filter(|c| !c.is_whitespace()).eq(text.chars().filter(|c| !c.is_whitespace()))
+}
+
+/// Find the definitions with the given path, which may be relative to the local crate.
+fn resolve(path: &str) -> Vec<DefId> {
+    let local_path = path.strip_prefix("crate::").unwrap_or(path);
+    let mut defs = resolve_path(&format!("{}::{local_path}", local_crate().name));
+    defs.extend(resolve_path(path));
+    defs
+}
+
+fn primitive(name: &str) -> Option<RigidTy> {
+    let kind = match name {
+        "bool" => RigidTy::Bool,
+        "char" => RigidTy::Char,
+        "str" => RigidTy::Str,
+        "isize" => RigidTy::Int(IntTy::Isize),
+        "i8" => RigidTy::Int(IntTy::I8),
+        "i16" => RigidTy::Int(IntTy::I16),
+        "i32" => RigidTy::Int(IntTy::I32),
+        "i64" => RigidTy::Int(IntTy::I64),
+        "i128" => RigidTy::Int(IntTy::I128),
+        "usize" => RigidTy::Uint(UintTy::Usize),
+        "u8" => RigidTy::Uint(UintTy::U8),
+        "u16" => RigidTy::Uint(UintTy::U16),
+        "u32" => RigidTy::Uint(UintTy::U32),
+        "u64" => RigidTy::Uint(UintTy::U64),
+        "u128" => RigidTy::Uint(UintTy::U128),
+        "f16" => RigidTy::Float(FloatTy::F16),
+        "f32" => RigidTy::Float(FloatTy::F32),
+        "f64" => RigidTy::Float(FloatTy::F64),
+        "f128" => RigidTy::Float(FloatTy::F128),
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn bin_op(name: &str) -> Option<BinOp> {
+    let op = match name {
+        "Add" => BinOp::Add,
+        "AddUnchecked" => BinOp::AddUnchecked,
+        "Sub" => BinOp::Sub,
+        "SubUnchecked" => BinOp::SubUnchecked,
+        "Mul" => BinOp::Mul,
+        "MulUnchecked" => BinOp::MulUnchecked,
+        "Div" => BinOp::Div,
+        "Rem" => BinOp::Rem,
+        "BitXor" => BinOp::BitXor,
+        "BitAnd" => BinOp::BitAnd,
+        "BitOr" => BinOp::BitOr,
+        "Shl" => BinOp::Shl,
+        "ShlUnchecked" => BinOp::ShlUnchecked,
+        "Shr" => BinOp::Shr,
+        "ShrUnchecked" => BinOp::ShrUnchecked,
+        "Eq" => BinOp::Eq,
+        "Lt" => BinOp::Lt,
+        "Le" => BinOp::Le,
+        "Ne" => BinOp::Ne,
+        "Ge" => BinOp::Ge,
+        "Gt" => BinOp::Gt,
+        "Cmp" => BinOp::Cmp,
+        "Offset" => BinOp::Offset,
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn abi(name: &str) -> Option<Abi> {
+    let (name, unwind) = match name.strip_suffix("-unwind") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let abi = match name {
+        "Rust" if !unwind => Abi::Rust,
+        "rust-call" if !unwind => Abi::RustCall,
+        "rust-cold" if !unwind => Abi::RustCold,
+        "C" => Abi::C { unwind },
+        "system" => Abi::System { unwind },
+        "cdecl" => Abi::Cdecl { unwind },
+        "stdcall" => Abi::Stdcall { unwind },
+        "fastcall" => Abi::Fastcall { unwind },
+        "vectorcall" => Abi::Vectorcall { unwind },
+        "thiscall" => Abi::Thiscall { unwind },
+        "aapcs" => Abi::Aapcs { unwind },
+        "win64" => Abi::Win64 { unwind },
+        "sysv64" => Abi::SysV64 { unwind },
+        _ => return None,
+    };
+    Some(abi)
+}
+
+/// The generic parameters of the local item with the given name, by their name.
+fn generic_params(name: &str) -> HashMap<String, GenericArgKind> {
+    let Some(item) = all_local_items().into_iter().find(|item| item.name() == name) else {
+        return HashMap::new();
+    };
+    let args = with(|cx| cx.identity_args(item.def_id()));
+    args.0
+        .into_iter()
+        .filter_map(|arg| {
+            let name = match &arg {
+                GenericArgKind::Type(ty) => match ty.kind() {
+                    TyKind::Param(param) => param.name,
+                    _ => return None,
+                },
+                GenericArgKind::Const(cnst) => match cnst.kind() {
+                    TyConstKind::Param(param) => param.name.clone(),
+                    _ => return None,
+                },
+                GenericArgKind::Lifetime(_) => return None,
+            };
+            Some((name, arg))
+        })
+        .collect()
+}
+
+/// Find the variant of an enum whose name is the last segment of the given path.
+fn variant_named(adt: AdtDef, path: &str) -> Option<VariantIdx> {
+    let name = path.rsplit("::").next()?;
+    adt.variants_iter().find(|variant| variant.name() == name).map(|variant| variant.idx)
+}
+
+/// Find the arguments of an impl whose self type is the given type.
+fn impl_args(impl_def: ImplDef, ty: Ty) -> Option<Vec<GenericArgKind>> {
+    let generics = with(|cx| cx.generics_of(impl_def.def_id()));
+    let mut args = vec![None; generics.params.len()];
+    if !unify(with(|cx| cx.def_ty(impl_def.def_id())), ty, &mut args) {
+        return None;
+    }
+    args.into_iter()
+        .zip(&generics.params)
+        .map(|(arg, param)| match (arg, &param.kind) {
+            (Some(arg), _) => Some(arg),
+            (None, crate::ty::GenericParamDefKind::Lifetime) => {
+                Some(GenericArgKind::Lifetime(Region { kind: RegionKind::ReErased }))
+            }
+            (None, _) => None,
+        })
+        .collect()
+}
+
+/// Check whether a type matches a generic type, and bind the generic parameters to the types
+/// that they stand for.
+fn unify(generic: Ty, ty: Ty, args: &mut [Option<GenericArgKind>]) -> bool {
+    let (generic_kind, kind) = (generic.kind(), ty.kind());
+    if let TyKind::Param(param) = &generic_kind {
+        return bind(args, param.index, GenericArgKind::Type(ty));
+    }
+    let (TyKind::RigidTy(generic_kind), TyKind::RigidTy(kind)) = (generic_kind, kind) else {
+        return generic == ty;
+    };
+    match (generic_kind, kind) {
+        (RigidTy::Adt(generic_def, generic_args), RigidTy::Adt(def, args_)) => {
+            generic_def == def
+                && generic_args.0.len() == args_.0.len()
+                && generic_args.0.iter().zip(&args_.0).all(|(generic_arg, arg)| {
+                    match (generic_arg, arg) {
+                        (GenericArgKind::Type(generic), GenericArgKind::Type(ty)) => {
+                            unify(*generic, *ty, args)
+                        }
+                        (GenericArgKind::Const(generic), GenericArgKind::Const(cnst)) => {
+                            unify_const(generic, cnst, args)
+                        }
+                        (GenericArgKind::Lifetime(_), GenericArgKind::Lifetime(_)) => true,
+                        _ => false,
+                    }
+                })
+        }
+        (RigidTy::Ref(_, generic, generic_mut), RigidTy::Ref(_, ty, mutability))
+        | (RigidTy::RawPtr(generic, generic_mut), RigidTy::RawPtr(ty, mutability)) => {
+            generic_mut == mutability && unify(generic, ty, args)
+        }
+        (RigidTy::Slice(generic), RigidTy::Slice(ty)) => unify(generic, ty, args),
+        (RigidTy::Array(generic, generic_len), RigidTy::Array(ty, len)) => {
+            unify(generic, ty, args) && unify_const(&generic_len, &len, args)
+        }
+        (RigidTy::Tuple(generics), RigidTy::Tuple(tys)) => {
+            generics.len() == tys.len()
+                && generics.iter().zip(tys).all(|(generic, ty)| unify(*generic, ty, args))
+        }
+        (generic_kind, kind) => generic_kind == kind,
+    }
+}
+
+fn unify_const(generic: &TyConst, cnst: &TyConst, args: &mut [Option<GenericArgKind>]) -> bool {
+    match generic.kind() {
+        TyConstKind::Param(param) => bind(args, param.index, GenericArgKind::Const(cnst.clone())),
+        kind => kind == cnst.kind(),
+    }
+}
+
+fn bind(args: &mut [Option<GenericArgKind>], index: u32, arg: GenericArgKind) -> bool {
+    match args.get_mut(index as usize) {
+        Some(Some(bound)) => *bound == arg,
+        Some(slot) => {
+            *slot = Some(arg);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Infer the kind of a cast from the type of its operand and its target type.
+fn cast_kind(from: Ty, to: Ty) -> CastKind {
+    let (Some(from), Some(to)) = (from.kind().rigid().cloned(), to.kind().rigid().cloned()) else {
+        return CastKind::Transmute;
+    };
+    match (from, to) {
+        (RigidTy::FnDef(..), RigidTy::FnPtr(_)) => {
+            CastKind::PointerCoercion(PointerCoercion::ReifyFnPointer)
+        }
+        (RigidTy::Closure(..), RigidTy::FnPtr(sig)) => {
+            CastKind::PointerCoercion(PointerCoercion::ClosureFnPointer(sig.value.safety))
+        }
+        (RigidTy::FnPtr(from), RigidTy::FnPtr(to))
+            if from.value.safety == Safety::Safe && to.value.safety == Safety::Unsafe =>
+        {
+            CastKind::PointerCoercion(PointerCoercion::UnsafeFnPointer)
+        }
+        (RigidTy::FnPtr(_), RigidTy::RawPtr(..)) => CastKind::FnPtrToPtr,
+        (
+            from @ (RigidTy::Ref(..) | RigidTy::RawPtr(..)),
+            to @ (RigidTy::Ref(..) | RigidTy::RawPtr(..)),
+        ) => {
+            let pointee = |kind: &RigidTy| match kind {
+                RigidTy::Ref(_, ty, mutability) | RigidTy::RawPtr(ty, mutability) => {
+                    (*ty, *mutability)
+                }
+                _ => unreachable!(),
+            };
+            let ((from_ty, from_mut), (to_ty, to_mut)) = (pointee(&from), pointee(&to));
+            let to_raw = matches!(to, RigidTy::RawPtr(..));
+            let unsized_target = matches!(
+                to_ty.kind().rigid(),
+                Some(RigidTy::Slice(_) | RigidTy::Str | RigidTy::Dynamic(..))
+            );
+            match from_ty.kind().rigid() {
+                _ if from_ty == to_ty
+                    && from_mut == Mutability::Mut
+                    && to_mut == Mutability::Not =>
+                {
+                    CastKind::PointerCoercion(PointerCoercion::MutToConstPointer)
+                }
+                Some(RigidTy::Array(elem_ty, _)) if *elem_ty == to_ty && to_raw => {
+                    CastKind::PointerCoercion(PointerCoercion::ArrayToPointer)
+                }
+                _ if from_ty != to_ty && unsized_target => {
+                    CastKind::PointerCoercion(PointerCoercion::Unsize)
+                }
+                _ => CastKind::PtrToPtr,
+            }
+        }
+        (RigidTy::Adt(..), RigidTy::Adt(..)) => CastKind::PointerCoercion(PointerCoercion::Unsize),
+        (
+            RigidTy::Int(_) | RigidTy::Uint(_) | RigidTy::Bool | RigidTy::Char | RigidTy::Adt(..),
+            RigidTy::Int(_) | RigidTy::Uint(_) | RigidTy::Char,
+        ) => CastKind::IntToInt,
+        (RigidTy::Float(_), RigidTy::Int(_) | RigidTy::Uint(_)) => CastKind::FloatToInt,
+        (RigidTy::Int(_) | RigidTy::Uint(_), RigidTy::Float(_)) => CastKind::IntToFloat,
+        (RigidTy::Float(_), RigidTy::Float(_)) => CastKind::FloatToFloat,
+        (RigidTy::RawPtr(..), RigidTy::Int(_) | RigidTy::Uint(_)) => CastKind::PointerExposeAddress,
+        (RigidTy::Int(_) | RigidTy::Uint(_), RigidTy::RawPtr(..)) => {
+            CastKind::PointerWithExposedProvenance
+        }
+        _ => CastKind::Transmute,
+    }
+}
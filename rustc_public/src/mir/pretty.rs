@@ -1,4 +1,5 @@
 //! Implement methods to pretty print rustc_public's IR body.
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::Write;
 use std::{fmt, io, iter};
@@ -7,14 +8,15 @@ use fmt::{Display, Formatter};
 
 use super::{AggregateKind, AssertMessage, BinOp, BorrowKind, FakeBorrowKind, TerminatorKind};
 use crate::mir::{
-    Operand, Place, RawPtrKind, Rvalue, StatementKind, UnwindAction, VarDebugInfoContents,
+    BasicBlockIdx, Local, Operand, Place, ProjectionElem, RawPtrKind, Rvalue, Statement,
+    StatementKind, UnwindAction, VarDebugInfoContents,
 };
-use crate::ty::{AdtKind, AssocKind, MirConst, Ty, TyConst};
+use crate::ty::{AdtKind, AssocKind, MirConst, Span, Ty, TyConst};
 use crate::{Body, CrateDef, IndexedVal, Mutability, with};
 
 impl Display for Ty {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        with(|ctx| write!(f, "{}", ctx.ty_pretty(*self)))
+        with(|ctx| write!(f, "{}", ctx.ty_pretty(*self, false)))
     }
 }
 
@@ -35,170 +37,636 @@ impl Debug for Place {
     }
 }
 
-pub(crate) fn function_body<W: Write>(writer: &mut W, body: &Body, name: &str) -> io::Result<()> {
-    write!(writer, "fn {name}(")?;
-    let mut sep = "";
-    for (index, local) in body.arg_locals().iter().enumerate() {
-        write!(writer, "{}_{}: {}", sep, index + 1, local.ty)?;
-        sep = ", ";
+/// Options that control how [Body::dump_with] prints a body.
+///
+/// The default configuration produces the same output as [Body::dump].
+#[derive(Clone, Debug, Default)]
+pub struct PrettyConfig {
+    /// Annotate each statement and terminator with its `bbN[i]` location and source span.
+    pub locations: bool,
+    /// Print the type of a local wherever it is used, e.g. `(_1: u32)`.
+    pub local_types: bool,
+    /// Print the names of user variables instead of `_N`.
+    ///
+    /// Locals that share their name with another local, e.g. because of shadowing, are still
+    /// printed as `_N`.
+    pub var_names: bool,
+    /// Omit `StorageLive`, `StorageDead` and `nop` statements.
+    pub elide_storage_markers: bool,
+    /// How the paths of types, functions and constants are printed.
+    pub paths: PathStyle,
+}
+
+/// How item paths are printed by [Body::dump_with].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PathStyle {
+    /// Print paths as they are, e.g. `std::option::Option::<u8>::None`.
+    #[default]
+    Full,
+    /// Print the paths that the compiler trims in diagnostics, e.g. `Option::<u8>::None`.
+    ///
+    /// These are the same paths as [crate::CrateDef::trimmed_name] returns.
+    Trimmed,
+    /// Only keep the last segment of a path, e.g. `None`.
+    ///
+    /// Associated items of primitive types are printed with their type, e.g. `u8::MAX`.
+    NameOnly,
+}
+
+pub(crate) fn function_body<W: Write>(
+    writer: &mut W,
+    body: &Body,
+    name: &str,
+    config: &PrettyConfig,
+) -> io::Result<()> {
+    Printer::new(body, config).function_body(writer, name)
+}
+
+/// Print the signature of a function followed by its local and debug declarations.
+pub(crate) fn function_preamble<W: Write>(
+    writer: &mut W,
+    body: &Body,
+    name: &str,
+    config: &PrettyConfig,
+) -> io::Result<()> {
+    Printer::new(body, config).preamble(writer, name)
+}
+
+/// Print the statements and the terminator of a basic block, one per line and without
+/// indentation, along with their statement index.
+///
+/// The successors of the terminator are only printed if `successors` is true.
+pub(crate) fn basic_block_lines(
+    body: &Body,
+    block: BasicBlockIdx,
+    config: &PrettyConfig,
+    successors: bool,
+) -> io::Result<Vec<(usize, String)>> {
+    let printer = Printer::new(body, config);
+    let data = &body.blocks[block];
+    let mut lines = vec![];
+    for (index, statement) in data.statements.iter().enumerate() {
+        if printer.is_elided(statement) {
+            continue;
+        }
+        let mut line = vec![];
+        printer.statement(&mut line, &statement.kind)?;
+        printer.end_line(&mut line, block, index, statement.span)?;
+        lines.push((index, String::from_utf8(line).unwrap().trim().to_string()));
+    }
+    let mut line = vec![];
+    if successors {
+        printer.terminator(&mut line, &data.terminator.kind)?;
+    } else {
+        printer.terminator_head(&mut line, &data.terminator.kind)?;
     }
-    write!(writer, ")")?;
+    printer.end_line(&mut line, block, data.statements.len(), data.terminator.span)?;
+    lines.push((data.statements.len(), String::from_utf8(line).unwrap().trim().to_string()));
+    Ok(lines)
+}
 
-    let return_local = body.ret_local();
-    writeln!(writer, " -> {} {{", return_local.ty)?;
+struct Printer<'a> {
+    body: &'a Body,
+    config: &'a PrettyConfig,
+    /// The user variable names to print instead of `_N`.
+    names: HashMap<Local, &'a str>,
+}
 
-    body.locals().iter().enumerate().try_for_each(|(index, local)| -> io::Result<()> {
-        if index == 0 || index > body.arg_count {
-            writeln!(writer, "    let {}_{}: {};", pretty_mut(local.mutability), index, local.ty)
-        } else {
-            Ok(())
+impl<'a> Printer<'a> {
+    fn new(body: &'a Body, config: &'a PrettyConfig) -> Self {
+        let mut names = HashMap::new();
+        if config.var_names {
+            for info in &body.var_debug_info {
+                if let VarDebugInfoContents::Place(place) = &info.value
+                    && place.projection.is_empty()
+                    && info.composite.is_none()
+                {
+                    names.entry(place.local).or_insert(info.name.as_str());
+                }
+            }
+            let mut count = HashMap::<&str, usize>::new();
+            for name in names.values() {
+                *count.entry(*name).or_default() += 1;
+            }
+            names.retain(|_, name| count[name] == 1);
         }
-    })?;
+        Printer { body, config, names }
+    }
 
-    body.var_debug_info.iter().try_for_each(|info| {
-        let content = match &info.value {
-            VarDebugInfoContents::Place(place) => {
-                format!("{place:?}")
+    fn function_body<W: Write>(&self, writer: &mut W, name: &str) -> io::Result<()> {
+        self.preamble(writer, name)?;
+        for (index, block) in self.body.blocks.iter().enumerate() {
+            writeln!(writer, "    bb{index}: {{")?;
+            for (statement_index, statement) in block.statements.iter().enumerate() {
+                if self.is_elided(statement) {
+                    continue;
+                }
+                self.statement(writer, &statement.kind)?;
+                self.end_line(writer, index, statement_index, statement.span)?;
             }
-            VarDebugInfoContents::Const(constant) => pretty_mir_const(&constant.const_),
-        };
-        writeln!(writer, "    debug {} => {};", info.name, content)
-    })?;
+            self.terminator(writer, &block.terminator.kind)?;
+            self.end_line(writer, index, block.statements.len(), block.terminator.span)?;
+            writeln!(writer, "    }}")?;
+        }
+        writeln!(writer, "}}")
+    }
 
-    body.blocks
-        .iter()
-        .enumerate()
-        .map(|(index, block)| -> io::Result<()> {
-            writeln!(writer, "    bb{index}: {{")?;
-            let _ = block
-                .statements
-                .iter()
-                .map(|statement| -> io::Result<()> {
-                    pretty_statement(writer, &statement.kind)?;
-                    Ok(())
-                })
-                .collect::<Vec<_>>();
-            pretty_terminator(writer, &block.terminator.kind)?;
-            writeln!(writer, "    }}").unwrap();
-            Ok(())
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-    writeln!(writer, "}}")?;
-    Ok(())
-}
+    fn preamble<W: Write>(&self, writer: &mut W, name: &str) -> io::Result<()> {
+        let body = self.body;
+        write!(writer, "fn {name}(")?;
+        let mut sep = "";
+        for (index, local) in body.arg_locals().iter().enumerate() {
+            write!(writer, "{}_{}: {}", sep, index + 1, self.ty(local.ty))?;
+            sep = ", ";
+        }
+        write!(writer, ")")?;
 
-fn pretty_statement<W: Write>(writer: &mut W, statement: &StatementKind) -> io::Result<()> {
-    const INDENT: &str = "        ";
-    match statement {
-        StatementKind::Assign(place, rval) => {
-            write!(writer, "{INDENT}{place:?} = ")?;
-            pretty_rvalue(writer, rval)?;
-            writeln!(writer, ";")
-        }
-        // FIXME: Add rest of the statements
-        StatementKind::FakeRead(cause, place) => {
-            writeln!(writer, "{INDENT}FakeRead({cause:?}, {place:?});")
+        let return_local = body.ret_local();
+        writeln!(writer, " -> {} {{", self.ty(return_local.ty))?;
+
+        for (index, local) in body.local_decls() {
+            if index == 0 || index > body.arg_count {
+                let ty = self.ty(local.ty);
+                writeln!(writer, "    let {}_{}: {};", pretty_mut(local.mutability), index, ty)?;
+            }
         }
-        StatementKind::SetDiscriminant { place, variant_index } => {
-            writeln!(writer, "{INDENT}discriminant({place:?}) = {};", variant_index.to_index())
+
+        for info in &body.var_debug_info {
+            let content = match &info.value {
+                // The debug info is where names come from, so always refer to locals by index.
+                VarDebugInfoContents::Place(place) => {
+                    self.projected(place, format!("_{}", place.local))
+                }
+                VarDebugInfoContents::Const(constant) => self.mir_const(&constant.const_),
+            };
+            writeln!(writer, "    debug {} => {};", info.name, content)?;
         }
-        StatementKind::Deinit(place) => writeln!(writer, "Deinit({place:?};"),
-        StatementKind::StorageLive(local) => {
-            writeln!(writer, "{INDENT}StorageLive(_{local});")
+        Ok(())
+    }
+
+    fn is_elided(&self, statement: &Statement) -> bool {
+        self.config.elide_storage_markers
+            && matches!(
+                statement.kind,
+                StatementKind::StorageLive(_) | StatementKind::StorageDead(_) | StatementKind::Nop
+            )
+    }
+
+    /// Finish the line of a statement or terminator, annotating it with its location if
+    /// requested.
+    fn end_line<W: Write>(
+        &self,
+        writer: &mut W,
+        block: usize,
+        index: usize,
+        span: Span,
+    ) -> io::Result<()> {
+        if self.config.locations {
+            write!(writer, "  // bb{block}[{index}]: {}", span.diagnostic())?;
+        }
+        writeln!(writer)
+    }
+
+    fn statement<W: Write>(&self, writer: &mut W, statement: &StatementKind) -> io::Result<()> {
+        const INDENT: &str = "        ";
+        match statement {
+            StatementKind::Assign(place, rval) => {
+                write!(writer, "{INDENT}{} = ", self.place(place))?;
+                self.rvalue(writer, rval)?;
+                write!(writer, ";")
+            }
+            // FIXME: Add rest of the statements
+            StatementKind::FakeRead(cause, place) => {
+                write!(writer, "{INDENT}FakeRead({cause:?}, {});", self.place(place))
+            }
+            StatementKind::SetDiscriminant { place, variant_index } => {
+                let place = self.place(place);
+                write!(writer, "{INDENT}discriminant({place}) = {};", variant_index.to_index())
+            }
+            StatementKind::Deinit(place) => {
+                write!(writer, "{INDENT}Deinit({});", self.place(place))
+            }
+            StatementKind::StorageLive(local) => {
+                write!(writer, "{INDENT}StorageLive({});", self.local(*local))
+            }
+            StatementKind::StorageDead(local) => {
+                write!(writer, "{INDENT}StorageDead({});", self.local(*local))
+            }
+            StatementKind::Retag(kind, place) => {
+                write!(writer, "{INDENT}Retag({kind:?}, {});", self.place(place))
+            }
+            StatementKind::PlaceMention(place) => {
+                write!(writer, "{INDENT}PlaceMention({});", self.place(place))
+            }
+            StatementKind::ConstEvalCounter => {
+                write!(writer, "{INDENT}ConstEvalCounter;")
+            }
+            StatementKind::Nop => write!(writer, "{INDENT}nop;"),
+            StatementKind::AscribeUserType { .. }
+            | StatementKind::Coverage(_)
+            | StatementKind::Intrinsic(_) => {
+                // FIX-ME: Make them pretty.
+                write!(writer, "{INDENT}{statement:?};")
+            }
         }
-        StatementKind::StorageDead(local) => {
-            writeln!(writer, "{INDENT}StorageDead(_{local});")
+    }
+
+    fn terminator<W: Write>(&self, writer: &mut W, terminator: &TerminatorKind) -> io::Result<()> {
+        self.terminator_head(writer, terminator)?;
+        let successors = terminator.successors();
+        let successor_count = successors.len();
+        let labels = pretty_successor_labels(terminator);
+
+        let show_unwind = !matches!(terminator.unwind(), None | Some(UnwindAction::Cleanup(_)));
+        let fmt_unwind = |w: &mut W| -> io::Result<()> {
+            write!(w, "unwind ")?;
+            match terminator.unwind() {
+                None | Some(UnwindAction::Cleanup(_)) => unreachable!(),
+                Some(UnwindAction::Continue) => write!(w, "continue"),
+                Some(UnwindAction::Unreachable) => write!(w, "unreachable"),
+                Some(UnwindAction::Terminate) => write!(w, "terminate"),
+            }
+        };
+
+        match (successor_count, show_unwind) {
+            (0, false) => {}
+            (0, true) => {
+                write!(writer, " -> ")?;
+                fmt_unwind(writer)?;
+            }
+            (1, false) => write!(writer, " -> bb{:?}", successors[0])?,
+            _ => {
+                write!(writer, " -> [")?;
+                for (i, target) in successors.iter().enumerate() {
+                    if i > 0 {
+                        write!(writer, ", ")?;
+                    }
+                    write!(writer, "{}: bb{:?}", labels[i], target)?;
+                }
+                if show_unwind {
+                    write!(writer, ", ")?;
+                    fmt_unwind(writer)?;
+                }
+                write!(writer, "]")?;
+            }
+        };
+
+        write!(writer, ";")
+    }
+
+    fn terminator_head<W: Write>(
+        &self,
+        writer: &mut W,
+        terminator: &TerminatorKind,
+    ) -> io::Result<()> {
+        use self::TerminatorKind::*;
+        const INDENT: &str = "        ";
+        match terminator {
+            Goto { .. } => write!(writer, "{INDENT}goto"),
+            SwitchInt { discr, .. } => {
+                write!(writer, "{INDENT}switchInt({})", self.operand(discr))
+            }
+            Resume => write!(writer, "{INDENT}resume"),
+            Abort => write!(writer, "{INDENT}abort"),
+            Return => write!(writer, "{INDENT}return"),
+            Unreachable => write!(writer, "{INDENT}unreachable"),
+            Drop { place, .. } => write!(writer, "{INDENT}drop({})", self.place(place)),
+            Call { func, args, destination, .. } => {
+                write!(writer, "{INDENT}{} = {}(", self.place(destination), self.operand(func))?;
+                let mut args_iter = args.iter();
+                args_iter.next().map_or(Ok(()), |arg| write!(writer, "{}", self.operand(arg)))?;
+                args_iter.try_for_each(|arg| write!(writer, ", {}", self.operand(arg)))?;
+                write!(writer, ")")
+            }
+            Assert { cond, expected, msg, target: _, unwind: _ } => {
+                write!(writer, "{INDENT}assert(")?;
+                if !expected {
+                    write!(writer, "!")?;
+                }
+                write!(writer, "{}, ", self.operand(cond))?;
+                self.assert_message(writer, msg)?;
+                write!(writer, ")")
+            }
+            InlineAsm { .. } => write!(writer, "{INDENT}InlineAsm"),
         }
-        StatementKind::Retag(kind, place) => writeln!(writer, "Retag({kind:?}, {place:?});"),
-        StatementKind::PlaceMention(place) => {
-            writeln!(writer, "{INDENT}PlaceMention({place:?};")
+    }
+
+    fn assert_message<W: Write>(&self, writer: &mut W, msg: &AssertMessage) -> io::Result<()> {
+        match msg {
+            AssertMessage::BoundsCheck { len, index } => {
+                let pretty_len = self.operand(len);
+                let pretty_index = self.operand(index);
+                write!(
+                    writer,
+                    "\"index out of bounds: the length is {{}} but the index is {{}}\", {pretty_len}, {pretty_index}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Add, l, r) => {
+                let pretty_l = self.operand(l);
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to compute `{{}} + {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Sub, l, r) => {
+                let pretty_l = self.operand(l);
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to compute `{{}} - {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Mul, l, r) => {
+                let pretty_l = self.operand(l);
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to compute `{{}} * {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Div, l, r) => {
+                let pretty_l = self.operand(l);
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to compute `{{}} / {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Rem, l, r) => {
+                let pretty_l = self.operand(l);
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to compute `{{}} % {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Shr, _, r) => {
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to shift right by `{{}}`, which would overflow\", {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(BinOp::Shl, _, r) => {
+                let pretty_r = self.operand(r);
+                write!(
+                    writer,
+                    "\"attempt to shift left by `{{}}`, which would overflow\", {pretty_r}"
+                )
+            }
+            AssertMessage::Overflow(op, _, _) => unreachable!("`{:?}` cannot overflow", op),
+            AssertMessage::OverflowNeg(op) => {
+                let pretty_op = self.operand(op);
+                write!(writer, "\"attempt to negate `{{}}`, which would overflow\", {pretty_op}")
+            }
+            AssertMessage::DivisionByZero(op) => {
+                let pretty_op = self.operand(op);
+                write!(writer, "\"attempt to divide `{{}}` by zero\", {pretty_op}")
+            }
+            AssertMessage::RemainderByZero(op) => {
+                let pretty_op = self.operand(op);
+                write!(
+                    writer,
+                    "\"attempt to calculate the remainder of `{{}}` with a divisor of zero\", {pretty_op}"
+                )
+            }
+            AssertMessage::MisalignedPointerDereference { required, found } => {
+                let pretty_required = self.operand(required);
+                let pretty_found = self.operand(found);
+                write!(
+                    writer,
+                    "\"misaligned pointer dereference: address must be a multiple of {{}} but is {{}}\",{pretty_required}, {pretty_found}"
+                )
+            }
+            AssertMessage::NullPointerDereference => {
+                write!(writer, "\"null pointer dereference occurred\"")
+            }
+            AssertMessage::InvalidEnumConstruction(op) => {
+                let pretty_op = self.operand(op);
+                write!(
+                    writer,
+                    "\"trying to construct an enum from an invalid value {{}}\",{pretty_op}"
+                )
+            }
+            AssertMessage::ResumedAfterReturn(_)
+            | AssertMessage::ResumedAfterPanic(_)
+            | AssertMessage::ResumedAfterDrop(_) => {
+                write!(writer, "{}", msg.description().unwrap())
+            }
         }
-        StatementKind::ConstEvalCounter => {
-            writeln!(writer, "{INDENT}ConstEvalCounter;")
+    }
+
+    fn operand(&self, operand: &Operand) -> String {
+        match operand {
+            Operand::Copy(copy) => self.place(copy),
+            Operand::Move(mv) => {
+                format!("move {}", self.place(mv))
+            }
+            Operand::Constant(cnst) => self.mir_const(&cnst.const_),
         }
-        StatementKind::Nop => writeln!(writer, "{INDENT}nop;"),
-        StatementKind::AscribeUserType { .. }
-        | StatementKind::Coverage(_)
-        | StatementKind::Intrinsic(_) => {
-            // FIX-ME: Make them pretty.
-            writeln!(writer, "{INDENT}{statement:?};")
+    }
+
+    fn mir_const(&self, literal: &MirConst) -> String {
+        self.paths(with(|cx| cx.mir_const_pretty(literal, self.trimmed())))
+    }
+
+    fn ty_const(&self, ct: &TyConst) -> String {
+        self.paths(with(|cx| cx.ty_const_pretty(ct.id, self.trimmed())))
+    }
+
+    fn ty(&self, ty: Ty) -> String {
+        self.paths(with(|cx| cx.ty_pretty(ty, self.trimmed())))
+    }
+
+    /// Whether the compiler should print trimmed paths, which [PathStyle::NameOnly] shortens
+    /// further.
+    fn trimmed(&self) -> bool {
+        self.config.paths != PathStyle::Full
+    }
+
+    fn paths(&self, text: String) -> String {
+        match self.config.paths {
+            PathStyle::NameOnly => name_only(&text),
+            PathStyle::Full | PathStyle::Trimmed => text,
         }
     }
-}
 
-fn pretty_terminator<W: Write>(writer: &mut W, terminator: &TerminatorKind) -> io::Result<()> {
-    pretty_terminator_head(writer, terminator)?;
-    let successors = terminator.successors();
-    let successor_count = successors.len();
-    let labels = pretty_successor_labels(terminator);
-
-    let show_unwind = !matches!(terminator.unwind(), None | Some(UnwindAction::Cleanup(_)));
-    let fmt_unwind = |w: &mut W| -> io::Result<()> {
-        write!(w, "unwind ")?;
-        match terminator.unwind() {
-            None | Some(UnwindAction::Cleanup(_)) => unreachable!(),
-            Some(UnwindAction::Continue) => write!(w, "continue"),
-            Some(UnwindAction::Unreachable) => write!(w, "unreachable"),
-            Some(UnwindAction::Terminate) => write!(w, "terminate"),
+    fn local(&self, local: Local) -> String {
+        let name = match self.names.get(&local) {
+            Some(name) => name.to_string(),
+            None => format!("_{local}"),
+        };
+        match self.body.local_decl(local) {
+            Some(decl) if self.config.local_types => format!("({name}: {})", self.ty(decl.ty)),
+            _ => name,
         }
-    };
+    }
+
+    fn place(&self, place: &Place) -> String {
+        self.projected(place, self.local(place.local))
+    }
 
-    match (successor_count, show_unwind) {
-        (0, false) => {}
-        (0, true) => {
-            write!(writer, " -> ")?;
-            fmt_unwind(writer)?;
+    /// Print the projections of `place` around the already printed `local`, in the same
+    /// format as the compiler uses for places.
+    fn projected(&self, place: &Place, local: String) -> String {
+        let mut out = String::new();
+        for elem in place.projection.iter().rev() {
+            match elem {
+                ProjectionElem::Deref => out.push_str("(*"),
+                ProjectionElem::Field(..)
+                | ProjectionElem::Downcast(_)
+                | ProjectionElem::OpaqueCast(_)
+                | ProjectionElem::Subtype(_) => out.push('('),
+                ProjectionElem::Index(_)
+                | ProjectionElem::ConstantIndex { .. }
+                | ProjectionElem::Subslice { .. } => {}
+            }
         }
-        (1, false) => write!(writer, " -> bb{:?}", successors[0])?,
-        _ => {
-            write!(writer, " -> [")?;
-            for (i, target) in successors.iter().enumerate() {
-                if i > 0 {
-                    write!(writer, ", ")?;
+        out.push_str(&local);
+        for elem in &place.projection {
+            let suffix = match elem {
+                ProjectionElem::Deref => ")".to_string(),
+                ProjectionElem::Field(field, ty) => format!(".{field}: {})", self.ty(*ty)),
+                ProjectionElem::Index(local) => format!("[{}]", self.local(*local)),
+                ProjectionElem::ConstantIndex { offset, min_length, from_end: false } => {
+                    format!("[{offset} of {min_length}]")
+                }
+                ProjectionElem::ConstantIndex { offset, min_length, from_end: true } => {
+                    format!("[-{offset} of {min_length}]")
                 }
-                write!(writer, "{}: bb{:?}", labels[i], target)?;
+                ProjectionElem::Subslice { from, to: 0, from_end: true } => format!("[{from}:]"),
+                ProjectionElem::Subslice { from: 0, to, from_end: true } => format!("[:-{to}]"),
+                ProjectionElem::Subslice { from, to, from_end: true } => {
+                    format!("[{from}:-{to}]")
+                }
+                ProjectionElem::Subslice { from, to, from_end: false } => {
+                    format!("[{from}..{to}]")
+                }
+                ProjectionElem::Downcast(variant) => {
+                    format!(" as variant#{})", variant.to_index())
+                }
+                ProjectionElem::OpaqueCast(ty) => format!(" as {})", self.ty(*ty)),
+                ProjectionElem::Subtype(ty) => format!(" as subtype {})", self.ty(*ty)),
+            };
+            out.push_str(&suffix);
+        }
+        out
+    }
+
+    fn rvalue<W: Write>(&self, writer: &mut W, rval: &Rvalue) -> io::Result<()> {
+        match rval {
+            Rvalue::AddressOf(mutability, place) => {
+                write!(writer, "&raw {} {}", pretty_raw_ptr_kind(*mutability), self.place(place))
             }
-            if show_unwind {
-                write!(writer, ", ")?;
-                fmt_unwind(writer)?;
+            Rvalue::Aggregate(aggregate_kind, operands) => {
+                // FIXME: Add pretty_aggregate function that returns a pretty string
+                self.aggregate(writer, aggregate_kind, operands)
+            }
+            Rvalue::BinaryOp(bin, op1, op2) => {
+                write!(writer, "{:?}({}, {})", bin, self.operand(op1), self.operand(op2))
+            }
+            Rvalue::Cast(_, op, ty) => {
+                write!(writer, "{} as {}", self.operand(op), self.ty(*ty))
+            }
+            Rvalue::CheckedBinaryOp(bin, op1, op2) => {
+                write!(writer, "Checked{:?}({}, {})", bin, self.operand(op1), self.operand(op2))
+            }
+            Rvalue::CopyForDeref(deref) => {
+                write!(writer, "CopyForDeref({})", self.place(deref))
+            }
+            Rvalue::Discriminant(place) => {
+                write!(writer, "discriminant({})", self.place(place))
+            }
+            Rvalue::Len(len) => {
+                write!(writer, "len({})", self.place(len))
             }
-            write!(writer, "]")?;
+            Rvalue::Ref(_, borrowkind, place) => {
+                let kind = match borrowkind {
+                    BorrowKind::Shared => "&",
+                    BorrowKind::Fake(FakeBorrowKind::Deep) => "&fake ",
+                    BorrowKind::Fake(FakeBorrowKind::Shallow) => "&fake shallow ",
+                    BorrowKind::Mut { .. } => "&mut ",
+                };
+                write!(writer, "{kind}{}", self.place(place))
+            }
+            Rvalue::Repeat(op, cnst) => {
+                write!(writer, "[{}; {}]", self.operand(op), self.ty_const(cnst))
+            }
+            Rvalue::ShallowInitBox(op, ty) => {
+                write!(writer, "ShallowInitBox({}, {})", self.operand(op), self.ty(*ty))
+            }
+            Rvalue::ThreadLocalRef(item) => {
+                write!(writer, "thread_local_ref{item:?}")
+            }
+            Rvalue::NullaryOp(nul, ty) => {
+                write!(writer, "{nul:?}::<{}>()", self.ty(*ty))
+            }
+            Rvalue::UnaryOp(un, op) => {
+                write!(writer, "{:?}({})", un, self.operand(op))
+            }
+            Rvalue::Use(op) => write!(writer, "{}", self.operand(op)),
         }
-    };
-
-    writeln!(writer, ";")
-}
+    }
 
-fn pretty_terminator_head<W: Write>(writer: &mut W, terminator: &TerminatorKind) -> io::Result<()> {
-    use self::TerminatorKind::*;
-    const INDENT: &str = "        ";
-    match terminator {
-        Goto { .. } => write!(writer, "{INDENT}goto"),
-        SwitchInt { discr, .. } => {
-            write!(writer, "{INDENT}switchInt({})", pretty_operand(discr))
-        }
-        Resume => write!(writer, "{INDENT}resume"),
-        Abort => write!(writer, "{INDENT}abort"),
-        Return => write!(writer, "{INDENT}return"),
-        Unreachable => write!(writer, "{INDENT}unreachable"),
-        Drop { place, .. } => write!(writer, "{INDENT}drop({place:?})"),
-        Call { func, args, destination, .. } => {
-            write!(writer, "{INDENT}{:?} = {}(", destination, pretty_operand(func))?;
-            let mut args_iter = args.iter();
-            args_iter.next().map_or(Ok(()), |arg| write!(writer, "{}", pretty_operand(arg)))?;
-            args_iter.try_for_each(|arg| write!(writer, ", {}", pretty_operand(arg)))?;
-            write!(writer, ")")
-        }
-        Assert { cond, expected, msg, target: _, unwind: _ } => {
-            write!(writer, "{INDENT}assert(")?;
-            if !expected {
-                write!(writer, "!")?;
-            }
-            write!(writer, "{}, ", pretty_operand(cond))?;
-            pretty_assert_message(writer, msg)?;
-            write!(writer, ")")
+    fn aggregate<W: Write>(
+        &self,
+        writer: &mut W,
+        aggregate_kind: &AggregateKind,
+        operands: &Vec<Operand>,
+    ) -> io::Result<()> {
+        let suffix = match aggregate_kind {
+            AggregateKind::Array(_) => {
+                write!(writer, "[")?;
+                "]"
+            }
+            AggregateKind::Tuple => {
+                write!(writer, "(")?;
+                ")"
+            }
+            AggregateKind::Adt(def, var, _, _, _) => {
+                if def.kind() == AdtKind::Enum {
+                    let variant = def.variant(*var).unwrap().name();
+                    let name = if self.trimmed() { def.trimmed_name() } else { def.name() };
+                    write!(writer, "{}", self.paths(format!("{name}::{variant}")))?;
+                } else {
+                    write!(writer, "{}", def.variant(*var).unwrap().name())?;
+                }
+                if operands.is_empty() {
+                    return Ok(());
+                }
+                // FIXME: Change this once we have CtorKind in StableMIR.
+                write!(writer, "(")?;
+                ")"
+            }
+            AggregateKind::Closure(def, _) => {
+                write!(writer, "{{closure@{}}}(", def.span().diagnostic())?;
+                ")"
+            }
+            AggregateKind::Coroutine(def, _) => {
+                write!(writer, "{{coroutine@{}}}(", def.span().diagnostic())?;
+                ")"
+            }
+            AggregateKind::CoroutineClosure(def, _) => {
+                write!(writer, "{{coroutine-closure@{}}}(", def.span().diagnostic())?;
+                ")"
+            }
+            AggregateKind::RawPtr(ty, mutability) => {
+                write!(
+                    writer,
+                    "*{} {} from (",
+                    if *mutability == Mutability::Mut { "mut" } else { "const" },
+                    self.ty(*ty)
+                )?;
+                ")"
+            }
+        };
+        let mut separator = "";
+        for op in operands {
+            write!(writer, "{}{}", separator, self.operand(op))?;
+            separator = ", ";
         }
-        InlineAsm { .. } => write!(writer, "{INDENT}InlineAsm"),
+        write!(writer, "{suffix}")
     }
 }
 
@@ -227,230 +695,119 @@ fn pretty_successor_labels(terminator: &TerminatorKind) -> Vec<String> {
     }
 }
 
-fn pretty_assert_message<W: Write>(writer: &mut W, msg: &AssertMessage) -> io::Result<()> {
-    match msg {
-        AssertMessage::BoundsCheck { len, index } => {
-            let pretty_len = pretty_operand(len);
-            let pretty_index = pretty_operand(index);
-            write!(
-                writer,
-                "\"index out of bounds: the length is {{}} but the index is {{}}\", {pretty_len}, {pretty_index}"
-            )
-        }
-        AssertMessage::Overflow(BinOp::Add, l, r) => {
-            let pretty_l = pretty_operand(l);
-            let pretty_r = pretty_operand(r);
-            write!(
-                writer,
-                "\"attempt to compute `{{}} + {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
-            )
-        }
-        AssertMessage::Overflow(BinOp::Sub, l, r) => {
-            let pretty_l = pretty_operand(l);
-            let pretty_r = pretty_operand(r);
-            write!(
-                writer,
-                "\"attempt to compute `{{}} - {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
-            )
-        }
-        AssertMessage::Overflow(BinOp::Mul, l, r) => {
-            let pretty_l = pretty_operand(l);
-            let pretty_r = pretty_operand(r);
-            write!(
-                writer,
-                "\"attempt to compute `{{}} * {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
-            )
-        }
-        AssertMessage::Overflow(BinOp::Div, l, r) => {
-            let pretty_l = pretty_operand(l);
-            let pretty_r = pretty_operand(r);
-            write!(
-                writer,
-                "\"attempt to compute `{{}} / {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
-            )
-        }
-        AssertMessage::Overflow(BinOp::Rem, l, r) => {
-            let pretty_l = pretty_operand(l);
-            let pretty_r = pretty_operand(r);
-            write!(
-                writer,
-                "\"attempt to compute `{{}} % {{}}`, which would overflow\", {pretty_l}, {pretty_r}"
-            )
-        }
-        AssertMessage::Overflow(BinOp::Shr, _, r) => {
-            let pretty_r = pretty_operand(r);
-            write!(writer, "\"attempt to shift right by `{{}}`, which would overflow\", {pretty_r}")
-        }
-        AssertMessage::Overflow(BinOp::Shl, _, r) => {
-            let pretty_r = pretty_operand(r);
-            write!(writer, "\"attempt to shift left by `{{}}`, which would overflow\", {pretty_r}")
-        }
-        AssertMessage::Overflow(op, _, _) => unreachable!("`{:?}` cannot overflow", op),
-        AssertMessage::OverflowNeg(op) => {
-            let pretty_op = pretty_operand(op);
-            write!(writer, "\"attempt to negate `{{}}`, which would overflow\", {pretty_op}")
-        }
-        AssertMessage::DivisionByZero(op) => {
-            let pretty_op = pretty_operand(op);
-            write!(writer, "\"attempt to divide `{{}}` by zero\", {pretty_op}")
-        }
-        AssertMessage::RemainderByZero(op) => {
-            let pretty_op = pretty_operand(op);
-            write!(
-                writer,
-                "\"attempt to calculate the remainder of `{{}}` with a divisor of zero\", {pretty_op}"
-            )
-        }
-        AssertMessage::MisalignedPointerDereference { required, found } => {
-            let pretty_required = pretty_operand(required);
-            let pretty_found = pretty_operand(found);
-            write!(
-                writer,
-                "\"misaligned pointer dereference: address must be a multiple of {{}} but is {{}}\",{pretty_required}, {pretty_found}"
-            )
-        }
-        AssertMessage::NullPointerDereference => {
-            write!(writer, "\"null pointer dereference occurred\"")
-        }
-        AssertMessage::InvalidEnumConstruction(op) => {
-            let pretty_op = pretty_operand(op);
-            write!(writer, "\"trying to construct an enum from an invalid value {{}}\",{pretty_op}")
-        }
-        AssertMessage::ResumedAfterReturn(_)
-        | AssertMessage::ResumedAfterPanic(_)
-        | AssertMessage::ResumedAfterDrop(_) => {
-            write!(writer, "{}", msg.description().unwrap())
-        }
-    }
-}
-
-fn pretty_operand(operand: &Operand) -> String {
-    match operand {
-        Operand::Copy(copy) => {
-            format!("{copy:?}")
-        }
-        Operand::Move(mv) => {
-            format!("move {mv:?}")
-        }
-        Operand::Constant(cnst) => pretty_mir_const(&cnst.const_),
-    }
-}
-
-fn pretty_mir_const(literal: &MirConst) -> String {
-    with(|cx| cx.mir_const_pretty(literal))
-}
-
-fn pretty_ty_const(ct: &TyConst) -> String {
-    with(|cx| cx.ty_const_pretty(ct.id))
-}
-
-fn pretty_rvalue<W: Write>(writer: &mut W, rval: &Rvalue) -> io::Result<()> {
-    match rval {
-        Rvalue::AddressOf(mutability, place) => {
-            write!(writer, "&raw {} {:?}", pretty_raw_ptr_kind(*mutability), place)
-        }
-        Rvalue::Aggregate(aggregate_kind, operands) => {
-            // FIXME: Add pretty_aggregate function that returns a pretty string
-            pretty_aggregate(writer, aggregate_kind, operands)
-        }
-        Rvalue::BinaryOp(bin, op1, op2) => {
-            write!(writer, "{:?}({}, {})", bin, pretty_operand(op1), pretty_operand(op2))
-        }
-        Rvalue::Cast(_, op, ty) => {
-            write!(writer, "{} as {}", pretty_operand(op), ty)
-        }
-        Rvalue::CheckedBinaryOp(bin, op1, op2) => {
-            write!(writer, "Checked{:?}({}, {})", bin, pretty_operand(op1), pretty_operand(op2))
-        }
-        Rvalue::CopyForDeref(deref) => {
-            write!(writer, "CopyForDeref({deref:?})")
-        }
-        Rvalue::Discriminant(place) => {
-            write!(writer, "discriminant({place:?})")
-        }
-        Rvalue::Len(len) => {
-            write!(writer, "len({len:?})")
-        }
-        Rvalue::Ref(_, borrowkind, place) => {
-            let kind = match borrowkind {
-                BorrowKind::Shared => "&",
-                BorrowKind::Fake(FakeBorrowKind::Deep) => "&fake ",
-                BorrowKind::Fake(FakeBorrowKind::Shallow) => "&fake shallow ",
-                BorrowKind::Mut { .. } => "&mut ",
-            };
-            write!(writer, "{kind}{place:?}")
-        }
-        Rvalue::Repeat(op, cnst) => {
-            write!(writer, "[{}; {}]", pretty_operand(op), pretty_ty_const(cnst))
-        }
-        Rvalue::ShallowInitBox(_, _) => Ok(()),
-        Rvalue::ThreadLocalRef(item) => {
-            write!(writer, "thread_local_ref{item:?}")
-        }
-        Rvalue::NullaryOp(nul, ty) => {
-            write!(writer, "{nul:?}::<{ty}>() \" \"")
-        }
-        Rvalue::UnaryOp(un, op) => {
-            write!(writer, "{:?}({})", un, pretty_operand(op))
+/// Only keep the last segment of the item paths in `text`, as printed by the compiler.
+///
+/// The compiler only gives us strings, so paths are recognized textually: a run of
+/// identifiers joined by `::`. Inherent impls, e.g. `core::num::<impl u32>::MAX`, are replaced
+/// by their type. Literals and the source locations of closures are left alone.
+fn name_only(text: &str) -> String {
+    let is_ident_start = |c: char| c.is_alphabetic() || c == '_';
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let chars: Vec<char> = text.chars().collect();
+    let starts_with =
+        |i: usize, prefix: &str| chars[i..].iter().copied().take(prefix.len()).eq(prefix.chars());
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let is_char_literal =
+            c == '\'' && (chars.get(i + 1) == Some(&'\\') || chars.get(i + 2) == Some(&'\''));
+        if c == '"' || is_char_literal {
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            out.extend(&chars[start..i]);
+        } else if c == '@' {
+            // The location of a closure or coroutine, e.g. `{closure@src/lib.rs:1:9: 1:11}`.
+            while i < chars.len() && chars[i] != '}' {
+                i += 1;
+            }
+            out.extend(&chars[start..i]);
+        } else if starts_with(i, "<impl ") {
+            // The self type of an inherent impl, which ends at the matching `>`.
+            let mut depth = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    '<' => depth += 1,
+                    '>' if chars[i - 1] != '-' => depth -= 1,
+                    _ => {}
+                }
+                i += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            let self_ty: String = chars[start + "<impl ".len()..i - 1].iter().collect();
+            out.push_str(&name_only(&self_ty));
+        } else if c.is_ascii_digit() {
+            // A literal with a suffix, e.g. `10_usize`.
+            while i < chars.len() && is_ident(chars[i]) {
+                i += 1;
+            }
+            out.extend(&chars[start..i]);
+        } else if is_ident_start(c) {
+            let mut segments = vec![];
+            loop {
+                let segment_start = i;
+                while i < chars.len() && is_ident(chars[i]) {
+                    i += 1;
+                }
+                segments.push(chars[segment_start..i].iter().collect::<String>());
+                if chars.get(i) == Some(&':')
+                    && chars.get(i + 1) == Some(&':')
+                    && chars.get(i + 2).is_some_and(|c| is_ident_start(*c))
+                {
+                    i += 2;
+                } else {
+                    break;
+                }
+            }
+            if starts_with(i, "::<impl ") {
+                // The module of an inherent impl.
+                i += 2;
+                continue;
+            }
+            let last = segments.len() - 1;
+            let primitive = segments.iter().position(|segment| is_primitive(segment));
+            let first = primitive.map_or(last, |primitive| last.min(primitive));
+            out.push_str(&segments[first..].join("::"));
+        } else {
+            out.push(c);
+            i += 1;
         }
-        Rvalue::Use(op) => write!(writer, "{}", pretty_operand(op)),
     }
+    out
 }
 
-fn pretty_aggregate<W: Write>(
-    writer: &mut W,
-    aggregate_kind: &AggregateKind,
-    operands: &Vec<Operand>,
-) -> io::Result<()> {
-    let suffix = match aggregate_kind {
-        AggregateKind::Array(_) => {
-            write!(writer, "[")?;
-            "]"
-        }
-        AggregateKind::Tuple => {
-            write!(writer, "(")?;
-            ")"
-        }
-        AggregateKind::Adt(def, var, _, _, _) => {
-            if def.kind() == AdtKind::Enum {
-                write!(writer, "{}::{}", def.name(), def.variant(*var).unwrap().name())?;
-            } else {
-                write!(writer, "{}", def.variant(*var).unwrap().name())?;
-            }
-            if operands.is_empty() {
-                return Ok(());
-            }
-            // FIXME: Change this once we have CtorKind in StableMIR.
-            write!(writer, "(")?;
-            ")"
-        }
-        AggregateKind::Closure(def, _) => {
-            write!(writer, "{{closure@{}}}(", def.span().diagnostic())?;
-            ")"
-        }
-        AggregateKind::Coroutine(def, _) => {
-            write!(writer, "{{coroutine@{}}}(", def.span().diagnostic())?;
-            ")"
-        }
-        AggregateKind::CoroutineClosure(def, _) => {
-            write!(writer, "{{coroutine-closure@{}}}(", def.span().diagnostic())?;
-            ")"
-        }
-        AggregateKind::RawPtr(ty, mutability) => {
-            write!(
-                writer,
-                "*{} {ty} from (",
-                if *mutability == Mutability::Mut { "mut" } else { "const" }
-            )?;
-            ")"
-        }
-    };
-    let mut separator = "";
-    for op in operands {
-        write!(writer, "{}{}", separator, pretty_operand(op))?;
-        separator = ", ";
-    }
-    write!(writer, "{suffix}")
+fn is_primitive(name: &str) -> bool {
+    matches!(
+        name,
+        "bool"
+            | "char"
+            | "str"
+            | "i8"
+            | "i16"
+            | "i32"
+            | "i64"
+            | "i128"
+            | "isize"
+            | "u8"
+            | "u16"
+            | "u32"
+            | "u64"
+            | "u128"
+            | "usize"
+            | "f16"
+            | "f32"
+            | "f64"
+            | "f128"
+    )
 }
 
 fn pretty_mut(mutability: Mutability) -> &'static str {
@@ -2,7 +2,8 @@
 //!
 //! ## Overview
 //!
-//! We currently only support an immutable visitor.
+//! We support an immutable visitor, a mutable visitor, and an immutable visitor that can stop
+//! early, i.e., [MirVisitor], [MutMirVisitor] and [TryMirVisitor].
 //! The structure of this visitor is similar to the ones internal to `rustc`,
 //! and it follows the following conventions:
 //!
@@ -35,339 +36,391 @@
 //! The only place that `_` is acceptable is to match a field (or
 //! variant argument) that does not require visiting.
 
+use std::ops::ControlFlow;
+
 use crate::mir::*;
 use crate::ty::{GenericArgs, MirConst, Region, Ty, TyConst};
 use crate::{Error, Opaque, Span};
 
 macro_rules! make_mir_visitor {
-    ($visitor_trait_name:ident, $($mutability:ident)?) => {
+    // The visitor methods return `$ret`, and `$try` is the `?` operator for visitors that can
+    // stop early. Every call to another visitor method is followed by `$($try)?`, and methods
+    // that don't end with such a call end with `$($ok)?`.
+    (
+        @trait $(#[$attr:meta])* $visitor_trait_name:ident,
+        [$($mutability:ident)?],
+        [$($try:tt)?],
+        [$(-> $ret:ty, $ok:expr)?],
+        [$($item:item)*]
+    ) => {
+        $(#[$attr])*
         pub trait $visitor_trait_name {
-            fn visit_body(&mut self, body: &$($mutability)? Body) {
+            $($item)*
+
+            fn visit_body(&mut self, body: &$($mutability)? Body) $(-> $ret)? {
                 self.super_body(body)
             }
 
-            fn visit_basic_block(&mut self, bb: &$($mutability)? BasicBlock) {
-                self.super_basic_block(bb)
+            fn visit_basic_block(&mut self, block: BasicBlockIdx, bb: &$($mutability)? BasicBlock) $(-> $ret)? {
+                self.super_basic_block(block, bb)
             }
 
-            fn visit_ret_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) {
+            fn visit_ret_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) $(-> $ret)? {
                 self.super_ret_decl(local, decl)
             }
 
-            fn visit_arg_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) {
+            fn visit_arg_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) $(-> $ret)? {
                 self.super_arg_decl(local, decl)
             }
 
-            fn visit_local_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) {
+            fn visit_local_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) $(-> $ret)? {
                 self.super_local_decl(local, decl)
             }
 
-            fn visit_statement(&mut self, stmt: &$($mutability)? Statement, location: Location) {
+            fn visit_statement(&mut self, stmt: &$($mutability)? Statement, location: Location) $(-> $ret)? {
                 self.super_statement(stmt, location)
             }
 
-            fn visit_terminator(&mut self, term: &$($mutability)? Terminator, location: Location) {
+            fn visit_terminator(&mut self, term: &$($mutability)? Terminator, location: Location) $(-> $ret)? {
                 self.super_terminator(term, location)
             }
 
-            fn visit_span(&mut self, span: &$($mutability)? Span) {
+            fn visit_span(&mut self, span: &$($mutability)? Span) $(-> $ret)? {
                 self.super_span(span)
             }
 
-            fn visit_place(&mut self, place: &$($mutability)? Place, ptx: PlaceContext, location: Location) {
+            fn visit_place(&mut self, place: &$($mutability)? Place, ptx: PlaceContext, location: Option<Location>) $(-> $ret)? {
                 self.super_place(place, ptx, location)
             }
 
-            visit_place_fns!($($mutability)?);
+            visit_place_fns!([$($mutability)?], [$($try)?], [$(-> $ret, $ok)?]);
 
-            fn visit_local(&mut self, local: &$($mutability)? Local, ptx: PlaceContext, location: Location) {
+            fn visit_local(&mut self, local: &$($mutability)? Local, ptx: PlaceContext, location: Option<Location>) $(-> $ret)? {
                 let _ = (local, ptx, location);
+                $($ok)?
             }
 
-            fn visit_rvalue(&mut self, rvalue: &$($mutability)? Rvalue, location: Location) {
+            fn visit_rvalue(&mut self, rvalue: &$($mutability)? Rvalue, location: Location) $(-> $ret)? {
                 self.super_rvalue(rvalue, location)
             }
 
-            fn visit_operand(&mut self, operand: &$($mutability)? Operand, location: Location) {
+            fn visit_operand(&mut self, operand: &$($mutability)? Operand, location: Location) $(-> $ret)? {
                 self.super_operand(operand, location)
             }
 
-            fn visit_user_type_projection(&mut self, projection: &$($mutability)? UserTypeProjection) {
+            fn visit_user_type_projection(&mut self, projection: &$($mutability)? UserTypeProjection) $(-> $ret)? {
                 self.super_user_type_projection(projection)
             }
 
-            fn visit_ty(&mut self, ty: &$($mutability)? Ty, location: Location) {
+            fn visit_ty(&mut self, ty: &$($mutability)? Ty, location: Option<Location>) $(-> $ret)? {
                 let _ = location;
                 self.super_ty(ty)
             }
 
-            fn visit_const_operand(&mut self, constant: &$($mutability)? ConstOperand, location: Location) {
+            fn visit_const_operand(&mut self, constant: &$($mutability)? ConstOperand, location: Location) $(-> $ret)? {
                 self.super_const_operand(constant, location)
             }
 
-            fn visit_mir_const(&mut self, constant: &$($mutability)? MirConst, location: Location) {
+            fn visit_mir_const(&mut self, constant: &$($mutability)? MirConst, location: Option<Location>) $(-> $ret)? {
                 self.super_mir_const(constant, location)
             }
 
-            fn visit_ty_const(&mut self, constant: &$($mutability)? TyConst, location: Location) {
+            fn visit_ty_const(&mut self, constant: &$($mutability)? TyConst, location: Location) $(-> $ret)? {
                 let _ = location;
                 self.super_ty_const(constant)
             }
 
-            fn visit_region(&mut self, region: &$($mutability)? Region, location: Location) {
+            fn visit_region(&mut self, region: &$($mutability)? Region, location: Location) $(-> $ret)? {
                 let _ = location;
                 self.super_region(region)
             }
 
-            fn visit_args(&mut self, args: &$($mutability)? GenericArgs, location: Location) {
+            fn visit_args(&mut self, args: &$($mutability)? GenericArgs, location: Location) $(-> $ret)? {
                 let _ = location;
                 self.super_args(args)
             }
 
-            fn visit_assert_msg(&mut self, msg: &$($mutability)? AssertMessage, location: Location) {
+            fn visit_assert_msg(&mut self, msg: &$($mutability)? AssertMessage, location: Location) $(-> $ret)? {
                 self.super_assert_msg(msg, location)
             }
 
-            fn visit_var_debug_info(&mut self, var_debug_info: &$($mutability)? VarDebugInfo) {
-                self.super_var_debug_info(var_debug_info);
+            fn visit_var_debug_info(&mut self, var_debug_info: &$($mutability)? VarDebugInfo) $(-> $ret)? {
+                self.super_var_debug_info(var_debug_info)
             }
 
-            fn super_body(&mut self, body: &$($mutability)? Body) {
-                super_body!(self, body, $($mutability)?);
+            fn super_body(&mut self, body: &$($mutability)? Body) $(-> $ret)? {
+                super_body!(self, body, $($mutability)? $($try)?)
             }
 
-            fn super_basic_block(&mut self, bb: &$($mutability)? BasicBlock) {
+            fn super_basic_block(&mut self, block: BasicBlockIdx, bb: &$($mutability)? BasicBlock) $(-> $ret)? {
                 let BasicBlock { statements, terminator } = bb;
-                for stmt in statements {
-                    self.visit_statement(stmt, Location(stmt.span));
+                let terminator_index = statements.len();
+                for (statement_index, stmt) in statements.into_iter().enumerate() {
+                    let location = Location { block, statement_index, span: stmt.span };
+                    self.visit_statement(stmt, location)$($try)?;
                 }
-                self.visit_terminator(terminator, Location(terminator.span));
+                let location = Location { block, statement_index: terminator_index, span: terminator.span };
+                self.visit_terminator(terminator, location)
             }
 
-            fn super_local_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) {
+            fn super_local_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) $(-> $ret)? {
                 let _ = local;
-                let LocalDecl { ty, span, .. } = decl;
-                self.visit_ty(ty, Location(*span));
+                let LocalDecl { ty, .. } = decl;
+                self.visit_ty(ty, None)
             }
 
-            fn super_ret_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) {
+            fn super_ret_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) $(-> $ret)? {
                 self.super_local_decl(local, decl)
             }
 
-            fn super_arg_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) {
+            fn super_arg_decl(&mut self, local: Local, decl: &$($mutability)? LocalDecl) $(-> $ret)? {
                 self.super_local_decl(local, decl)
             }
 
-            fn super_statement(&mut self, stmt: &$($mutability)? Statement, location: Location) {
+            fn super_statement(&mut self, stmt: &$($mutability)? Statement, location: Location) $(-> $ret)? {
                 let Statement { kind, span } = stmt;
-                self.visit_span(span);
+                self.visit_span(span)$($try)?;
                 match kind {
                     StatementKind::Assign(place, rvalue) => {
-                        self.visit_place(place, PlaceContext::MUTATING, location);
-                        self.visit_rvalue(rvalue, location);
+                        self.visit_place(place, PlaceContext::MutatingUse(MutatingUseContext::Store), Some(location))$($try)?;
+                        self.visit_rvalue(rvalue, location)$($try)?;
+                    }
+                    StatementKind::FakeRead(_, place) => {
+                        self.visit_place(place, PlaceContext::NonMutatingUse(NonMutatingUseContext::Inspect), Some(location))$($try)?;
                     }
-                    StatementKind::FakeRead(_, place) | StatementKind::PlaceMention(place) => {
-                        self.visit_place(place, PlaceContext::NON_MUTATING, location);
+                    StatementKind::PlaceMention(place) => {
+                        self.visit_place(place, PlaceContext::NonMutatingUse(NonMutatingUseContext::PlaceMention), Some(location))$($try)?;
                     }
-                    StatementKind::SetDiscriminant { place, .. }
-                    | StatementKind::Deinit(place)
-                    | StatementKind::Retag(_, place) => {
-                        self.visit_place(place, PlaceContext::MUTATING, location);
+                    StatementKind::SetDiscriminant { place, .. } => {
+                        self.visit_place(place, PlaceContext::MutatingUse(MutatingUseContext::SetDiscriminant), Some(location))$($try)?;
                     }
-                    StatementKind::StorageLive(local) | StatementKind::StorageDead(local) => {
-                        self.visit_local(local, PlaceContext::NON_USE, location);
+                    StatementKind::Deinit(place) => {
+                        self.visit_place(place, PlaceContext::MutatingUse(MutatingUseContext::Deinit), Some(location))$($try)?;
                     }
-                    StatementKind::AscribeUserType { place, projections, variance: _ } => {
-                        self.visit_place(place, PlaceContext::NON_USE, location);
-                        self.visit_user_type_projection(projections);
+                    StatementKind::Retag(_, place) => {
+                        self.visit_place(place, PlaceContext::MutatingUse(MutatingUseContext::Retag), Some(location))$($try)?;
+                    }
+                    StatementKind::StorageLive(local) => {
+                        self.visit_local(local, PlaceContext::NonUse(NonUseContext::StorageLive), Some(location))$($try)?;
+                    }
+                    StatementKind::StorageDead(local) => {
+                        self.visit_local(local, PlaceContext::NonUse(NonUseContext::StorageDead), Some(location))$($try)?;
+                    }
+                    StatementKind::AscribeUserType { place, projections, variance } => {
+                        let ctx = PlaceContext::NonUse(NonUseContext::AscribeUserTy(*variance));
+                        self.visit_place(place, ctx, Some(location))$($try)?;
+                        self.visit_user_type_projection(projections)$($try)?;
                     }
                     StatementKind::Coverage(coverage) => visit_opaque(coverage),
                     StatementKind::Intrinsic(intrisic) => match intrisic {
                         NonDivergingIntrinsic::Assume(operand) => {
-                            self.visit_operand(operand, location);
+                            self.visit_operand(operand, location)$($try)?;
                         }
                         NonDivergingIntrinsic::CopyNonOverlapping(CopyNonOverlapping {
                             src,
                             dst,
                             count,
                         }) => {
-                            self.visit_operand(src, location);
-                            self.visit_operand(dst, location);
-                            self.visit_operand(count, location);
+                            self.visit_operand(src, location)$($try)?;
+                            self.visit_operand(dst, location)$($try)?;
+                            self.visit_operand(count, location)$($try)?;
                         }
                     },
                     StatementKind::ConstEvalCounter | StatementKind::Nop => {}
                 }
+                $($ok)?
             }
 
-            fn super_terminator(&mut self, term: &$($mutability)? Terminator, location: Location) {
+            fn super_terminator(&mut self, term: &$($mutability)? Terminator, location: Location) $(-> $ret)? {
                 let Terminator { kind, span } = term;
-                self.visit_span(span);
+                self.visit_span(span)$($try)?;
                 match kind {
                     TerminatorKind::Goto { .. }
                     | TerminatorKind::Resume
                     | TerminatorKind::Abort
                     | TerminatorKind::Unreachable => {}
                     TerminatorKind::Assert { cond, expected: _, msg, target: _, unwind: _ } => {
-                        self.visit_operand(cond, location);
-                        self.visit_assert_msg(msg, location);
+                        self.visit_operand(cond, location)$($try)?;
+                        self.visit_assert_msg(msg, location)$($try)?;
                     }
                     TerminatorKind::Drop { place, target: _, unwind: _ } => {
-                        self.visit_place(place, PlaceContext::MUTATING, location);
+                        self.visit_place(place, PlaceContext::MutatingUse(MutatingUseContext::Drop), Some(location))$($try)?;
                     }
                     TerminatorKind::Call { func, args, destination, target: _, unwind: _ } => {
-                        self.visit_operand(func, location);
+                        self.visit_operand(func, location)$($try)?;
                         for arg in args {
-                            self.visit_operand(arg, location);
+                            self.visit_operand(arg, location)$($try)?;
                         }
-                        self.visit_place(destination, PlaceContext::MUTATING, location);
+                        self.visit_place(destination, PlaceContext::MutatingUse(MutatingUseContext::Call), Some(location))$($try)?;
                     }
                     TerminatorKind::InlineAsm { operands, .. } => {
                         for op in operands {
                             let InlineAsmOperand { in_value, out_place, raw_rpr: _ } = op;
                             if let Some(input) = in_value {
-                                self.visit_operand(input, location);
+                                self.visit_operand(input, location)$($try)?;
                             }
                             if let Some(output) = out_place {
-                                self.visit_place(output, PlaceContext::MUTATING, location);
+                                self.visit_place(output, PlaceContext::MutatingUse(MutatingUseContext::AsmOutput), Some(location))$($try)?;
                             }
                         }
                     }
                     TerminatorKind::Return => {
                         let $($mutability)? local = RETURN_LOCAL;
-                        self.visit_local(&$($mutability)? local, PlaceContext::NON_MUTATING, location);
+                        let ctx = PlaceContext::NonMutatingUse(NonMutatingUseContext::Move);
+                        self.visit_local(&$($mutability)? local, ctx, Some(location))$($try)?;
                     }
                     TerminatorKind::SwitchInt { discr, targets: _ } => {
-                        self.visit_operand(discr, location);
+                        self.visit_operand(discr, location)$($try)?;
                     }
                 }
+                $($ok)?
             }
 
-            fn super_span(&mut self, span: &$($mutability)? Span) {
+            fn super_span(&mut self, span: &$($mutability)? Span) $(-> $ret)? {
                 let _ = span;
+                $($ok)?
             }
 
-            fn super_rvalue(&mut self, rvalue: &$($mutability)? Rvalue, location: Location) {
+            fn super_rvalue(&mut self, rvalue: &$($mutability)? Rvalue, location: Location) $(-> $ret)? {
                 match rvalue {
                     Rvalue::AddressOf(mutability, place) => {
-                        let pcx = PlaceContext { is_mut: *mutability == RawPtrKind::Mut };
-                        self.visit_place(place, pcx, location);
+                        let ctx = match mutability {
+                            RawPtrKind::Mut => PlaceContext::MutatingUse(MutatingUseContext::RawBorrow),
+                            RawPtrKind::Const => PlaceContext::NonMutatingUse(NonMutatingUseContext::RawBorrow),
+                            RawPtrKind::FakeForPtrMetadata => PlaceContext::NonMutatingUse(NonMutatingUseContext::Inspect),
+                        };
+                        self.visit_place(place, ctx, Some(location))$($try)?;
                     }
                     Rvalue::Aggregate(_, operands) => {
                         for op in operands {
-                            self.visit_operand(op, location);
+                            self.visit_operand(op, location)$($try)?;
                         }
                     }
                     Rvalue::BinaryOp(_, lhs, rhs) | Rvalue::CheckedBinaryOp(_, lhs, rhs) => {
-                        self.visit_operand(lhs, location);
-                        self.visit_operand(rhs, location);
+                        self.visit_operand(lhs, location)$($try)?;
+                        self.visit_operand(rhs, location)$($try)?;
                     }
                     Rvalue::Cast(_, op, ty) => {
-                        self.visit_operand(op, location);
-                        self.visit_ty(ty, location);
+                        self.visit_operand(op, location)$($try)?;
+                        self.visit_ty(ty, Some(location))$($try)?;
+                    }
+                    Rvalue::CopyForDeref(place) => {
+                        self.visit_place(place, PlaceContext::NonMutatingUse(NonMutatingUseContext::Copy), Some(location))$($try)?;
                     }
-                    Rvalue::CopyForDeref(place) | Rvalue::Discriminant(place) | Rvalue::Len(place) => {
-                        self.visit_place(place, PlaceContext::NON_MUTATING, location);
+                    Rvalue::Discriminant(place) | Rvalue::Len(place) => {
+                        self.visit_place(place, PlaceContext::NonMutatingUse(NonMutatingUseContext::Inspect), Some(location))$($try)?;
                     }
                     Rvalue::Ref(region, kind, place) => {
-                        self.visit_region(region, location);
-                        let pcx = PlaceContext { is_mut: matches!(kind, BorrowKind::Mut { .. }) };
-                        self.visit_place(place, pcx, location);
+                        self.visit_region(region, location)$($try)?;
+                        let ctx = match kind {
+                            BorrowKind::Shared => PlaceContext::NonMutatingUse(NonMutatingUseContext::SharedBorrow),
+                            BorrowKind::Fake(_) => PlaceContext::NonMutatingUse(NonMutatingUseContext::FakeBorrow),
+                            BorrowKind::Mut { .. } => PlaceContext::MutatingUse(MutatingUseContext::Borrow),
+                        };
+                        self.visit_place(place, ctx, Some(location))$($try)?;
                     }
                     Rvalue::Repeat(op, constant) => {
-                        self.visit_operand(op, location);
-                        self.visit_ty_const(constant, location);
+                        self.visit_operand(op, location)$($try)?;
+                        self.visit_ty_const(constant, location)$($try)?;
                     }
                     Rvalue::ShallowInitBox(op, ty) => {
-                        self.visit_ty(ty, location);
-                        self.visit_operand(op, location)
+                        self.visit_ty(ty, Some(location))$($try)?;
+                        self.visit_operand(op, location)$($try)?;
                     }
                     Rvalue::ThreadLocalRef(_) => {}
                     Rvalue::NullaryOp(_, ty) => {
-                        self.visit_ty(ty, location);
+                        self.visit_ty(ty, Some(location))$($try)?;
                     }
                     Rvalue::UnaryOp(_, op) | Rvalue::Use(op) => {
-                        self.visit_operand(op, location);
+                        self.visit_operand(op, location)$($try)?;
                     }
                 }
+                $($ok)?
             }
 
-            fn super_operand(&mut self, operand: &$($mutability)? Operand, location: Location) {
+            fn super_operand(&mut self, operand: &$($mutability)? Operand, location: Location) $(-> $ret)? {
                 match operand {
-                    Operand::Copy(place) | Operand::Move(place) => {
-                        self.visit_place(place, PlaceContext::NON_MUTATING, location)
+                    Operand::Copy(place) => {
+                        self.visit_place(place, PlaceContext::NonMutatingUse(NonMutatingUseContext::Copy), Some(location))
+                    }
+                    Operand::Move(place) => {
+                        self.visit_place(place, PlaceContext::NonMutatingUse(NonMutatingUseContext::Move), Some(location))
                     }
                     Operand::Constant(constant) => {
-                        self.visit_const_operand(constant, location);
+                        self.visit_const_operand(constant, location)
                     }
                 }
             }
 
-            fn super_user_type_projection(&mut self, projection: &$($mutability)? UserTypeProjection) {
+            fn super_user_type_projection(&mut self, projection: &$($mutability)? UserTypeProjection) $(-> $ret)? {
                 // This is a no-op on mir::Visitor.
                 let _ = projection;
+                $($ok)?
             }
 
-            fn super_ty(&mut self, ty: &$($mutability)? Ty) {
+            fn super_ty(&mut self, ty: &$($mutability)? Ty) $(-> $ret)? {
                 let _ = ty;
+                $($ok)?
             }
 
-            fn super_const_operand(&mut self, constant: &$($mutability)? ConstOperand, location: Location) {
+            fn super_const_operand(&mut self, constant: &$($mutability)? ConstOperand, location: Location) $(-> $ret)? {
                 let ConstOperand { span, user_ty: _, const_ } = constant;
-                self.visit_span(span);
-                self.visit_mir_const(const_, location);
+                self.visit_span(span)$($try)?;
+                self.visit_mir_const(const_, Some(location))
             }
 
-            fn super_mir_const(&mut self, constant: &$($mutability)? MirConst, location: Location) {
+            fn super_mir_const(&mut self, constant: &$($mutability)? MirConst, location: Option<Location>) $(-> $ret)? {
                 let MirConst { kind: _, ty, id: _ } = constant;
-                self.visit_ty(ty, location);
+                self.visit_ty(ty, location)
             }
 
-            fn super_ty_const(&mut self, constant: &$($mutability)? TyConst) {
+            fn super_ty_const(&mut self, constant: &$($mutability)? TyConst) $(-> $ret)? {
                 let _ = constant;
+                $($ok)?
             }
 
-            fn super_region(&mut self, region: &$($mutability)? Region) {
+            fn super_region(&mut self, region: &$($mutability)? Region) $(-> $ret)? {
                 let _ = region;
+                $($ok)?
             }
 
-            fn super_args(&mut self, args: &$($mutability)? GenericArgs) {
+            fn super_args(&mut self, args: &$($mutability)? GenericArgs) $(-> $ret)? {
                 let _ = args;
+                $($ok)?
             }
 
-            fn super_var_debug_info(&mut self, var_debug_info: &$($mutability)? VarDebugInfo) {
+            fn super_var_debug_info(&mut self, var_debug_info: &$($mutability)? VarDebugInfo) $(-> $ret)? {
                 let VarDebugInfo { source_info, composite, value, name: _, argument_index: _ } =
                     var_debug_info;
-                self.visit_span(&$($mutability)? source_info.span);
-                let location = Location(source_info.span);
+                self.visit_span(&$($mutability)? source_info.span)$($try)?;
                 if let Some(composite) = composite {
-                    self.visit_ty(&$($mutability)? composite.ty, location);
+                    self.visit_ty(&$($mutability)? composite.ty, None)$($try)?;
                 }
                 match value {
                     VarDebugInfoContents::Place(place) => {
-                        self.visit_place(place, PlaceContext::NON_USE, location);
+                        self.visit_place(place, PlaceContext::NonUse(NonUseContext::VarDebugInfo), None)
                     }
                     VarDebugInfoContents::Const(constant) => {
-                        self.visit_mir_const(&$($mutability)? constant.const_, location);
+                        self.visit_mir_const(&$($mutability)? constant.const_, None)
                     }
                 }
             }
 
-            fn super_assert_msg(&mut self, msg: &$($mutability)? AssertMessage, location: Location) {
+            fn super_assert_msg(&mut self, msg: &$($mutability)? AssertMessage, location: Location) $(-> $ret)? {
                 match msg {
                     AssertMessage::BoundsCheck { len, index } => {
-                        self.visit_operand(len, location);
-                        self.visit_operand(index, location);
+                        self.visit_operand(len, location)$($try)?;
+                        self.visit_operand(index, location)$($try)?;
                     }
                     AssertMessage::Overflow(_, left, right) => {
-                        self.visit_operand(left, location);
-                        self.visit_operand(right, location);
+                        self.visit_operand(left, location)$($try)?;
+                        self.visit_operand(right, location)$($try)?;
                     }
                     AssertMessage::OverflowNeg(op)
                     | AssertMessage::DivisionByZero(op)
                     | AssertMessage::RemainderByZero(op)
                     | AssertMessage::InvalidEnumConstruction(op) => {
-                        self.visit_operand(op, location);
+                        self.visit_operand(op, location)$($try)?;
                     }
                     AssertMessage::ResumedAfterReturn(_)
                     | AssertMessage::ResumedAfterPanic(_)
@@ -376,19 +429,34 @@ macro_rules! make_mir_visitor {
                         //nothing to visit
                     }
                     AssertMessage::MisalignedPointerDereference { required, found } => {
-                        self.visit_operand(required, location);
-                        self.visit_operand(found, location);
+                        self.visit_operand(required, location)$($try)?;
+                        self.visit_operand(found, location)$($try)?;
                     }
                 }
+                $($ok)?
             }
         }
     };
+
+    ($visitor_trait_name:ident, $($mutability:ident)?) => {
+        make_mir_visitor!(@trait $visitor_trait_name, [$($mutability)?], [], [], []);
+    };
+
+    ($(#[$attr:meta])* try $visitor_trait_name:ident) => {
+        make_mir_visitor!(
+            @trait $(#[$attr])* $visitor_trait_name,
+            [],
+            [?],
+            [-> ControlFlow<Self::Break>, ControlFlow::Continue(())],
+            [type Break;]
+        );
+    };
 }
 
 macro_rules! super_body {
-    ($self:ident, $body:ident, mut) => {
-        for bb in $body.blocks.iter_mut() {
-            $self.visit_basic_block(bb);
+    ($self:ident, $body:ident, mut) => {{
+        for (block, bb) in $body.blocks.iter_mut().enumerate() {
+            $self.visit_basic_block(block, bb);
         }
 
         $self.visit_ret_decl(RETURN_LOCAL, $body.ret_local_mut());
@@ -407,38 +475,46 @@ macro_rules! super_body {
         }
 
         $self.visit_span(&mut $body.span)
-    };
-
-    ($self:ident, $body:ident, ) => {
-        let Body { blocks, locals: _, arg_count, var_debug_info, spread_arg: _, span } = $body;
-
-        for bb in blocks {
-            $self.visit_basic_block(bb);
+    }};
+
+    ($self:ident, $body:ident, $($try:tt)?) => {{
+        let Body {
+            blocks,
+            locals: _,
+            arg_count,
+            var_debug_info,
+            spread_arg: _,
+            span,
+            source_scopes: _,
+        } = $body;
+
+        for (block, bb) in blocks.iter().enumerate() {
+            $self.visit_basic_block(block, bb)$($try)?;
         }
 
-        $self.visit_ret_decl(RETURN_LOCAL, $body.ret_local());
+        $self.visit_ret_decl(RETURN_LOCAL, $body.ret_local())$($try)?;
 
         for (idx, arg) in $body.arg_locals().iter().enumerate() {
-            $self.visit_arg_decl(idx + 1, arg)
+            $self.visit_arg_decl(idx + 1, arg)$($try)?;
         }
 
         let local_start = arg_count + 1;
         for (idx, arg) in $body.inner_locals().iter().enumerate() {
-            $self.visit_local_decl(idx + local_start, arg)
+            $self.visit_local_decl(idx + local_start, arg)$($try)?;
         }
 
         for info in var_debug_info.iter() {
-            $self.visit_var_debug_info(info);
+            $self.visit_var_debug_info(info)$($try)?;
         }
 
         $self.visit_span(span)
-    };
+    }};
 }
 
 macro_rules! visit_place_fns {
-    (mut) => {
-        fn super_place(&mut self, place: &mut Place, ptx: PlaceContext, location: Location) {
-            self.visit_local(&mut place.local, ptx, location);
+    ([mut], [], []) => {
+        fn super_place(&mut self, place: &mut Place, ptx: PlaceContext, location: Option<Location>) {
+            self.visit_local(&mut place.local, ptx.for_base(&place.projection), location);
 
             for elem in place.projection.iter_mut() {
                 self.visit_projection_elem(elem, ptx, location);
@@ -452,7 +528,7 @@ macro_rules! visit_place_fns {
             &mut self,
             elem: &mut ProjectionElem,
             ptx: PlaceContext,
-            location: Location,
+            location: Option<Location>,
         ) {
             self.super_projection_elem(elem, ptx, location)
         }
@@ -461,12 +537,17 @@ macro_rules! visit_place_fns {
             &mut self,
             elem: &mut ProjectionElem,
             ptx: PlaceContext,
-            location: Location,
+            location: Option<Location>,
         ) {
+            let _ = ptx;
             match elem {
                 ProjectionElem::Deref => {}
                 ProjectionElem::Field(_idx, ty) => self.visit_ty(ty, location),
-                ProjectionElem::Index(local) => self.visit_local(local, ptx, location),
+                ProjectionElem::Index(local) => self.visit_local(
+                    local,
+                    PlaceContext::NonMutatingUse(NonMutatingUseContext::Copy),
+                    location,
+                ),
                 ProjectionElem::ConstantIndex { offset: _, min_length: _, from_end: _ } => {}
                 ProjectionElem::Subslice { from: _, to: _, from_end: _ } => {}
                 ProjectionElem::Downcast(_idx) => {}
@@ -476,15 +557,21 @@ macro_rules! visit_place_fns {
         }
     };
 
-    () => {
-        fn super_place(&mut self, place: &Place, ptx: PlaceContext, location: Location) {
-            self.visit_local(&place.local, ptx, location);
+    ([], [$($try:tt)?], [$(-> $ret:ty, $ok:expr)?]) => {
+        fn super_place(
+            &mut self,
+            place: &Place,
+            ptx: PlaceContext,
+            location: Option<Location>,
+        ) $(-> $ret)? {
+            self.visit_local(&place.local, ptx.for_base(&place.projection), location)$($try)?;
 
             for (idx, elem) in place.projection.iter().enumerate() {
                 let place_ref =
                     PlaceRef { local: place.local, projection: &place.projection[..idx] };
-                self.visit_projection_elem(place_ref, elem, ptx, location);
+                self.visit_projection_elem(place_ref, elem, ptx, location)$($try)?;
             }
+            $($ok)?
         }
 
         fn visit_projection_elem<'a>(
@@ -492,34 +579,70 @@ macro_rules! visit_place_fns {
             place_ref: PlaceRef<'a>,
             elem: &ProjectionElem,
             ptx: PlaceContext,
-            location: Location,
-        ) {
+            location: Option<Location>,
+        ) $(-> $ret)? {
             let _ = place_ref;
-            self.super_projection_elem(elem, ptx, location);
+            self.super_projection_elem(elem, ptx, location)
         }
 
         fn super_projection_elem(
             &mut self,
             elem: &ProjectionElem,
             ptx: PlaceContext,
-            location: Location,
-        ) {
+            location: Option<Location>,
+        ) $(-> $ret)? {
+            let _ = ptx;
             match elem {
                 ProjectionElem::Deref => {}
-                ProjectionElem::Field(_idx, ty) => self.visit_ty(ty, location),
-                ProjectionElem::Index(local) => self.visit_local(local, ptx, location),
+                ProjectionElem::Field(_idx, ty) => {
+                    self.visit_ty(ty, location)$($try)?;
+                }
+                ProjectionElem::Index(local) => {
+                    let ctx = PlaceContext::NonMutatingUse(NonMutatingUseContext::Copy);
+                    self.visit_local(local, ctx, location)$($try)?;
+                }
                 ProjectionElem::ConstantIndex { offset: _, min_length: _, from_end: _ } => {}
                 ProjectionElem::Subslice { from: _, to: _, from_end: _ } => {}
                 ProjectionElem::Downcast(_idx) => {}
-                ProjectionElem::OpaqueCast(ty) => self.visit_ty(ty, location),
-                ProjectionElem::Subtype(ty) => self.visit_ty(ty, location),
+                ProjectionElem::OpaqueCast(ty) => {
+                    self.visit_ty(ty, location)$($try)?;
+                }
+                ProjectionElem::Subtype(ty) => {
+                    self.visit_ty(ty, location)$($try)?;
+                }
             }
+            $($ok)?
         }
     };
 }
 
 make_mir_visitor!(MirVisitor,);
 make_mir_visitor!(MutMirVisitor, mut);
+make_mir_visitor!(
+    /// A visitor that can stop the traversal early.
+    ///
+    /// This follows the same conventions as [MirVisitor], but every method returns a
+    /// [ControlFlow]. Returning [ControlFlow::Break] from any `visit_*` method stops the traversal,
+    /// and the break value is returned by [TryMirVisitor::visit_body].
+    ///
+    /// ```ignore (illustrative)
+    /// struct FindInlineAsm;
+    ///
+    /// impl TryMirVisitor for FindInlineAsm {
+    ///     type Break = Location;
+    ///
+    ///     fn visit_terminator(&mut self, term: &Terminator, location: Location) -> ControlFlow<Location> {
+    ///         match term.kind {
+    ///             TerminatorKind::InlineAsm { .. } => ControlFlow::Break(location),
+    ///             _ => self.super_terminator(term, location),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let first_asm = FindInlineAsm.visit_body(&body).break_value();
+    /// ```
+    try TryMirVisitor
+);
 
 /// This function is a no-op that gets used to ensure this visitor is kept up-to-date.
 ///
@@ -531,12 +654,35 @@ make_mir_visitor!(MutMirVisitor, mut);
 fn visit_opaque(_: &Opaque) {}
 
 /// The location of a statement / terminator in the code and the CFG.
+///
+/// The terminator of a block is at the index one past its last statement. Items that are not
+/// part of a basic block, such as local declarations and debug information, have no location,
+/// so the visitor methods that can reach them take an `Option<Location>`.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub struct Location(Span);
+pub struct Location {
+    /// The basic block that contains the statement or terminator.
+    pub block: BasicBlockIdx,
+    /// The index of the statement within the block.
+    pub statement_index: usize,
+    span: Span,
+}
 
 impl Location {
     pub fn span(&self) -> Span {
-        self.0
+        self.span
+    }
+
+    /// Location of the next statement or terminator in the same block. Returns `None` if this is
+    /// the location of the terminator, or if this location is not valid for the given body.
+    pub fn successor_within_block(&self, body: &Body) -> Option<Location> {
+        let bb = body.blocks.get(self.block)?;
+        let statement_index = self.statement_index + 1;
+        let span = match bb.statements.get(statement_index) {
+            Some(stmt) => stmt.span,
+            None if statement_index == bb.statements.len() => bb.terminator.span,
+            None => return None,
+        };
+        Some(Location { block: self.block, statement_index, span })
     }
 }
 
@@ -545,7 +691,7 @@ impl Location {
 pub fn statement_location(body: &Body, bb_idx: &BasicBlockIdx, stmt_idx: usize) -> Location {
     let bb = &body.blocks[*bb_idx];
     let stmt = &bb.statements[stmt_idx];
-    Location(stmt.span)
+    Location { block: *bb_idx, statement_index: stmt_idx, span: stmt.span }
 }
 
 /// Location of the terminator for a given basic block. Assumes that `bb_idx` is valid for a given
@@ -553,7 +699,7 @@ pub fn statement_location(body: &Body, bb_idx: &BasicBlockIdx, stmt_idx: usize)
 pub fn terminator_location(body: &Body, bb_idx: &BasicBlockIdx) -> Location {
     let bb = &body.blocks[*bb_idx];
     let terminator = &bb.terminator;
-    Location(terminator.span)
+    Location { block: *bb_idx, statement_index: bb.statements.len(), span: terminator.span }
 }
 
 /// Reference to a place used to represent a partial projection.
@@ -571,18 +717,133 @@ impl PlaceRef<'_> {
 
 /// Information about a place's usage.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct PlaceContext {
-    /// Whether the access is mutable or not. Keep this private so we can increment the type in a
-    /// backward compatible manner.
-    is_mut: bool,
+#[non_exhaustive]
+pub enum PlaceContext {
+    NonMutatingUse(NonMutatingUseContext),
+    MutatingUse(MutatingUseContext),
+    NonUse(NonUseContext),
 }
 
-impl PlaceContext {
-    const MUTATING: Self = PlaceContext { is_mut: true };
-    const NON_MUTATING: Self = PlaceContext { is_mut: false };
-    const NON_USE: Self = PlaceContext { is_mut: false };
+/// A use of a place that doesn't modify it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NonMutatingUseContext {
+    /// Being inspected in some way, like reading its discriminant or its length.
+    Inspect,
+    /// Consumed as part of an operand.
+    Copy,
+    /// Consumed as part of an operand.
+    Move,
+    /// Shared borrow.
+    SharedBorrow,
+    /// A fake borrow.
+    FakeBorrow,
+    /// `&raw const`.
+    RawBorrow,
+    /// `PlaceMention` statement.
+    PlaceMention,
+    /// Used as base for another place, e.g., `x` in `x.y`. Will not mutate the place.
+    ///
+    /// For example, `x` is used in this context in `z = x.y` and `f(&x.y)`.
+    Projection,
+}
+
+/// A use of a place that may modify it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MutatingUseContext {
+    /// Appears as LHS of an assignment.
+    Store,
+    /// Appears on `SetDiscriminant`.
+    SetDiscriminant,
+    /// Appears on `Deinit`.
+    Deinit,
+    /// Output operand of an inline assembly block.
+    AsmOutput,
+    /// Destination of a call.
+    Call,
+    /// Being dropped.
+    Drop,
+    /// Mutable borrow.
+    Borrow,
+    /// `&raw mut`.
+    RawBorrow,
+    /// Used as base for another place, e.g., `x` in `x.y`. Could potentially mutate the place.
+    ///
+    /// For example, `x` is used in this context in `x.y = z` and `f(&mut x.y)`.
+    Projection,
+    /// Retagging, a "Stacked Borrows" shadow state operation.
+    Retag,
+}
 
+/// A mention of a place that doesn't use its value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NonUseContext {
+    /// Starting a storage live range.
+    StorageLive,
+    /// Ending a storage live range.
+    StorageDead,
+    /// User type annotation assertions.
+    AscribeUserTy(Variance),
+    /// The data of a user variable, for debug info.
+    VarDebugInfo,
+}
+
+impl PlaceContext {
+    /// Returns `true` if this place context represents a use that potentially changes the value.
     pub fn is_mutating(&self) -> bool {
-        self.is_mut
+        matches!(self, PlaceContext::MutatingUse(_))
+    }
+
+    /// Returns `true` if this place context represents a use.
+    pub fn is_use(&self) -> bool {
+        !matches!(self, PlaceContext::NonUse(_))
+    }
+
+    /// Returns `true` if this place context represents a borrow, excluding fake borrows.
+    pub fn is_borrow(&self) -> bool {
+        matches!(
+            self,
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::SharedBorrow)
+                | PlaceContext::MutatingUse(MutatingUseContext::Borrow)
+        )
+    }
+
+    /// Returns `true` if this place context represents a raw address-of.
+    pub fn is_address_of(&self) -> bool {
+        matches!(
+            self,
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::RawBorrow)
+                | PlaceContext::MutatingUse(MutatingUseContext::RawBorrow)
+        )
+    }
+
+    /// Returns `true` if this place context represents a drop.
+    pub fn is_drop(&self) -> bool {
+        matches!(self, PlaceContext::MutatingUse(MutatingUseContext::Drop))
+    }
+
+    /// Returns `true` if this place context represents a storage live or storage dead marker.
+    pub fn is_storage_marker(&self) -> bool {
+        matches!(
+            self,
+            PlaceContext::NonUse(NonUseContext::StorageLive | NonUseContext::StorageDead)
+        )
+    }
+
+    /// The context in which the local of a place is used, given the projections that are
+    /// applied to it.
+    fn for_base(self, projection: &[ProjectionElem]) -> PlaceContext {
+        match self {
+            _ if projection.is_empty() => self,
+            PlaceContext::MutatingUse(_) => {
+                PlaceContext::MutatingUse(MutatingUseContext::Projection)
+            }
+            PlaceContext::NonMutatingUse(_) => {
+                PlaceContext::NonMutatingUse(NonMutatingUseContext::Projection)
+            }
+            PlaceContext::NonUse(_) => self,
+        }
     }
 }
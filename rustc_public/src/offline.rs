@@ -0,0 +1,754 @@
+//! Run analyses against a snapshot of a crate, without running the compiler.
+//!
+//! Snapshots are created with [write_crate_json](crate::export::write_crate_json) while the
+//! compiler is running. Once loaded, the rustc_public APIs that only inspect information recorded
+//! in the snapshot work as if the compilation was still running, e.g.: [Ty::kind],
+//! [DefId::name], [Span::get_lines], [Instance::body] and [Ty::layout].
+//!
+//! Requests that need information that wasn't recorded, or that need the compiler, such as
+//! creating new types or resolving instances, fail with [ErrorKind::Unsupported]. Requests that
+//! return a [Result] return this error, while other requests panic with a message describing the
+//! request, unless they are executed inside [try_with](crate::try_with).
+//!
+//! ```ignore(needs-extern-crate)
+//! let snapshot = rustc_public::offline::load("my_crate.json")?;
+//! snapshot.run(|| {
+//!     for item in rustc_public::all_local_items() {
+//!         println!("{}: {:?}", item.name(), item.ty().layout().map(|layout| layout.shape().size));
+//!     }
+//! })?;
+//! ```
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::abi::{FnAbi, Layout, LayoutShape, ReprOptions};
+use crate::compiler_interface::{self, CompilerInterface, SessionId, usage_error};
+use crate::crate_def::Attribute;
+use crate::diagnostics::Diagnostic;
+use crate::export::{
+    CrateExport, DefInfo, InstanceExport, InstanceInfo, ItemExport, SCHEMA_VERSION, SpanInfo,
+};
+use crate::lint::{Lint, LintLevel};
+use crate::mir::alloc::{AllocId, GlobalAlloc};
+use crate::mir::mono::{Instance, InstanceDef, StaticDef};
+use crate::mir::{BinOp, Body, Place, UnOp};
+use crate::target::MachineInfo;
+use crate::ty::{
+    AdtDef, AdtKind, AliasKind, AliasTy, Allocation, ClosureDef, ClosureKind, CoroutineDef, Discr,
+    FieldDef, FnDef, ForeignDef, ForeignItemKind, ForeignModule, ForeignModuleDef, GenericArgs,
+    GenericPredicates, Generics, ImplDef, ImplTrait, IntrinsicDef, LineInfo, MirConst, PolyFnSig,
+    RigidTy, Span, TraitDecl, TraitDef, Ty, TyConst, TyConstId, TyConstKind, TyKind, UintTy,
+    UnevaluatedConst, VariantDef, VariantIdx,
+};
+use crate::{
+    AssocItems, Crate, CrateItem, CrateItems, CrateNum, DefId, DefKind, DefPath, Error, ErrorKind,
+    Filename, ImplTraitDecls, IndexedVal, ItemKind, StableDefId, Symbol, TraitDecls, mir,
+};
+
+/// A crate snapshot loaded from a file created by
+/// [write_crate_json](crate::export::write_crate_json).
+pub struct Snapshot {
+    backend: OfflineBackend,
+    session: SessionId,
+}
+
+impl Snapshot {
+    /// Execute the given function with access to the information recorded in this snapshot.
+    ///
+    /// Objects created in one call can be used in later calls for the same snapshot.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> Result<R, Error> {
+        compiler_interface::run(&self.backend, self.session, f)
+    }
+
+    /// The name of the crate recorded in this snapshot.
+    pub fn crate_name(&self) -> &str {
+        &self.backend.recorded().export.crate_name
+    }
+}
+
+impl std::fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("crate_name", &self.crate_name())
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+/// Load the snapshot stored in the given file.
+pub fn load(path: impl AsRef<Path>) -> Result<Snapshot, Error> {
+    let file = File::open(path)?;
+    from_reader(BufReader::new(file))
+}
+
+/// Load a snapshot from the given reader.
+pub fn from_reader(reader: impl io::Read) -> Result<Snapshot, Error> {
+    let value: serde_json::Value = serde_json::from_reader(reader).map_err(json_error)?;
+    let version = value.get("schema_version").and_then(serde_json::Value::as_u64);
+    if version != Some(SCHEMA_VERSION.into()) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Expected a snapshot with schema version {SCHEMA_VERSION}, but found {}",
+                version.map_or("none".to_string(), |version| version.to_string())
+            ),
+        ));
+    }
+    let snapshot = Snapshot {
+        backend: OfflineBackend { recorded: OnceCell::new() },
+        session: SessionId::fresh(),
+    };
+    // Handles are created as they are deserialized, so they must belong to the snapshot session.
+    let recorded =
+        snapshot.run(|| CrateExport::deserialize(value).map(Recorded::new))?.map_err(json_error)?;
+    let _ = snapshot.backend.recorded.set(recorded);
+    Ok(snapshot)
+}
+
+fn json_error(error: serde_json::Error) -> Error {
+    Error::new(ErrorKind::Other, format!("Failed to read snapshot: {error}"))
+}
+
+/// Error for requests that cannot be fulfilled with a snapshot.
+fn unsupported(query: &str) -> Error {
+    Error::new(ErrorKind::Unsupported, format!("`{query}` is not supported by offline snapshots"))
+}
+
+/// Error for information that wasn't recorded in the snapshot.
+fn not_recorded(what: String) -> Error {
+    Error::new(ErrorKind::Unsupported, format!("The snapshot doesn't record {what}"))
+}
+
+/// The information recorded in a snapshot, indexed by the handles that identify it.
+struct Recorded {
+    export: CrateExport,
+    /// The position of each local item, indexed by its definition.
+    items: HashMap<usize, usize>,
+    /// The position of each instance, indexed by its definition.
+    instance_bodies: HashMap<usize, usize>,
+    /// The name of every crate, where the position is its number, and the local crate is first.
+    crates: Vec<Symbol>,
+}
+
+impl Recorded {
+    fn new(export: CrateExport) -> Recorded {
+        let items = export
+            .items
+            .iter()
+            .enumerate()
+            .map(|(position, item)| (item.def_id.to_index(), position))
+            .collect();
+        let instance_bodies = export
+            .instances
+            .iter()
+            .enumerate()
+            .map(|(position, instance)| (instance.instance.def.to_index(), position))
+            .collect();
+        let mut crates = vec![export.crate_name.clone()];
+        for def in export.tables.defs.values() {
+            if !crates.contains(&def.krate) {
+                crates.push(def.krate.clone());
+            }
+        }
+        Recorded { export, items, instance_bodies, crates }
+    }
+
+    fn item(&self, def_id: DefId) -> &ItemExport {
+        let position = self.items.get(&def_id.to_index()).unwrap_or_else(|| {
+            usage_error(not_recorded(format!("the item `{}`", self.def(def_id).path)))
+        });
+        &self.export.items[*position]
+    }
+
+    fn instances_of(&self, def_id: DefId) -> impl Iterator<Item = &InstanceExport> {
+        self.export
+            .instances
+            .iter()
+            .filter(move |instance| self.instance(instance.instance.def).def_id == def_id)
+    }
+
+    fn def(&self, def_id: DefId) -> &DefInfo {
+        self.export.tables.defs.get(&def_id.to_index()).unwrap_or_else(|| {
+            usage_error(not_recorded(format!("the definition `{}`", def_id.to_index())))
+        })
+    }
+
+    fn span(&self, span: Span) -> &SpanInfo {
+        self.export
+            .tables
+            .spans
+            .get(&span.to_index())
+            .unwrap_or_else(|| usage_error(not_recorded(format!("the span `{}`", span.to_index()))))
+    }
+
+    fn instance(&self, def: InstanceDef) -> &InstanceInfo {
+        self.export.tables.instances.get(&def.to_index()).unwrap_or_else(|| {
+            usage_error(not_recorded(format!("the instance `{}`", def.to_index())))
+        })
+    }
+
+    fn krate(&self, id: CrateNum) -> Crate {
+        Crate { id, name: self.crates[id].clone(), is_local: id == 0 }
+    }
+}
+
+/// A backend that answers requests using the information recorded in a snapshot.
+struct OfflineBackend {
+    recorded: OnceCell<Recorded>,
+}
+
+impl OfflineBackend {
+    fn recorded(&self) -> &Recorded {
+        self.recorded.get().expect("snapshots cannot be used while they are loaded")
+    }
+}
+
+impl CompilerInterface for OfflineBackend {
+    fn entry_fn(&self) -> Option<CrateItem> {
+        usage_error(unsupported("entry_fn"))
+    }
+
+    fn all_local_items(&self) -> CrateItems {
+        self.recorded().export.items.iter().map(|item| CrateItem(item.def_id)).collect()
+    }
+
+    fn mir_body(&self, item: DefId) -> mir::Body {
+        let item = self.recorded().item(item);
+        item.body
+            .clone()
+            .unwrap_or_else(|| usage_error(not_recorded(format!("the body of `{}`", item.name))))
+    }
+
+    fn has_body(&self, item: DefId) -> bool {
+        let recorded = self.recorded();
+        if let Some(&index) = recorded.items.get(&item.to_index()) {
+            recorded.export.items[index].body.is_some()
+        } else if let Some(instance) = recorded.instances_of(item).next() {
+            instance.body.is_some()
+        } else {
+            usage_error(not_recorded(format!("whether `{}` has a body", recorded.def(item).path)))
+        }
+    }
+
+    fn foreign_modules(&self, _crate_num: CrateNum) -> Vec<ForeignModuleDef> {
+        usage_error(unsupported("foreign_modules"))
+    }
+
+    fn crate_functions(&self, _crate_num: CrateNum) -> Vec<FnDef> {
+        usage_error(unsupported("crate_functions"))
+    }
+
+    fn crate_statics(&self, _crate_num: CrateNum) -> Vec<StaticDef> {
+        usage_error(unsupported("crate_statics"))
+    }
+
+    fn foreign_module(&self, _mod_def: ForeignModuleDef) -> ForeignModule {
+        usage_error(unsupported("foreign_module"))
+    }
+
+    fn foreign_items(&self, _mod_def: ForeignModuleDef) -> Vec<ForeignDef> {
+        usage_error(unsupported("foreign_items"))
+    }
+
+    fn all_trait_decls(&self) -> TraitDecls {
+        usage_error(unsupported("all_trait_decls"))
+    }
+
+    fn trait_decls(&self, _crate_num: CrateNum) -> TraitDecls {
+        usage_error(unsupported("trait_decls"))
+    }
+
+    fn trait_decl(&self, _trait_def: &TraitDef) -> TraitDecl {
+        usage_error(unsupported("trait_decl"))
+    }
+
+    fn all_trait_impls(&self) -> ImplTraitDecls {
+        usage_error(unsupported("all_trait_impls"))
+    }
+
+    fn trait_impls(&self, _crate_num: CrateNum) -> ImplTraitDecls {
+        usage_error(unsupported("trait_impls"))
+    }
+
+    fn trait_impl(&self, _trait_impl: &ImplDef) -> ImplTrait {
+        usage_error(unsupported("trait_impl"))
+    }
+
+    fn generics_of(&self, _def_id: DefId) -> Generics {
+        usage_error(unsupported("generics_of"))
+    }
+
+    fn identity_args(&self, _def_id: DefId) -> GenericArgs {
+        usage_error(unsupported("identity_args"))
+    }
+
+    fn predicates_of(&self, _def_id: DefId) -> GenericPredicates {
+        usage_error(unsupported("predicates_of"))
+    }
+
+    fn explicit_predicates_of(&self, _def_id: DefId) -> GenericPredicates {
+        usage_error(unsupported("explicit_predicates_of"))
+    }
+
+    fn generic_args_with_defaults(
+        &self,
+        _def_id: DefId,
+        _args: &GenericArgs,
+    ) -> Result<GenericArgs, Error> {
+        Err(unsupported("generic_args_with_defaults"))
+    }
+
+    fn inherent_impls(&self, _ty: Ty) -> Vec<ImplDef> {
+        usage_error(unsupported("inherent_impls"))
+    }
+
+    fn local_crate(&self) -> Crate {
+        self.recorded().krate(0)
+    }
+
+    fn external_crates(&self) -> Vec<Crate> {
+        let recorded = self.recorded();
+        (1..recorded.crates.len()).map(|id| recorded.krate(id)).collect()
+    }
+
+    fn find_crates(&self, name: &str) -> Vec<Crate> {
+        let recorded = self.recorded();
+        (0..recorded.crates.len())
+            .filter(|id| recorded.crates[*id] == name)
+            .map(|id| recorded.krate(id))
+            .collect()
+    }
+
+    fn def_name(&self, def_id: DefId, _trimmed: bool) -> Symbol {
+        // Trimmed names are not recorded, so we always use the fully qualified name.
+        self.recorded().def(def_id).path.clone()
+    }
+
+    fn stable_def_id(&self, def_id: DefId) -> StableDefId {
+        self.recorded().def(def_id).stable_id
+    }
+
+    fn find_stable_def_id(&self, id: StableDefId) -> Option<DefId> {
+        let defs = &self.recorded().export.tables.defs;
+        defs.iter().find(|(_, def)| def.stable_id == id).map(|(index, _)| DefId::to_val(*index))
+    }
+
+    fn def_path(&self, _def_id: DefId) -> DefPath {
+        usage_error(unsupported("def_path"))
+    }
+
+    fn resolve_path(&self, path: &str) -> Vec<DefId> {
+        // Only the fully qualified names are recorded, so re-exports are not taken into account.
+        let defs = &self.recorded().export.tables.defs;
+        defs.iter()
+            .filter(|(_, def)| def.path == path)
+            .map(|(index, _)| DefId::to_val(*index))
+            .collect()
+    }
+
+    fn def_kind(&self, _def_id: DefId) -> DefKind {
+        usage_error(unsupported("def_kind"))
+    }
+
+    fn tool_attrs(&self, _def_id: DefId, _attr: &[Symbol]) -> Vec<Attribute> {
+        usage_error(unsupported("tool_attrs"))
+    }
+
+    fn all_tool_attrs(&self, _def_id: DefId) -> Vec<Attribute> {
+        usage_error(unsupported("all_tool_attrs"))
+    }
+
+    fn span_to_string(&self, span: Span) -> String {
+        let span = self.recorded().span(span);
+        let lines = &span.lines;
+        format!(
+            "{}:{}:{}: {}:{}",
+            span.file, lines.start_line, lines.start_col, lines.end_line, lines.end_col
+        )
+    }
+
+    fn get_filename(&self, span: &Span) -> Filename {
+        self.recorded().span(*span).file.clone()
+    }
+
+    fn get_lines(&self, span: &Span) -> LineInfo {
+        self.recorded().span(*span).lines
+    }
+
+    fn span_source_callsite(&self, span: &Span) -> Span {
+        // Spans don't keep track of macro expansions here.
+        *span
+    }
+
+    fn span_source_lines(&self, _span: &Span) -> Option<Vec<String>> {
+        None
+    }
+
+    fn item_kind(&self, item: CrateItem) -> ItemKind {
+        self.recorded().item(item.0).kind
+    }
+
+    fn is_foreign_item(&self, _item: DefId) -> bool {
+        usage_error(unsupported("is_foreign_item"))
+    }
+
+    fn foreign_item_kind(&self, _def: ForeignDef) -> ForeignItemKind {
+        usage_error(unsupported("foreign_item_kind"))
+    }
+
+    fn adt_kind(&self, _def: AdtDef) -> AdtKind {
+        usage_error(unsupported("adt_kind"))
+    }
+
+    fn adt_is_box(&self, _def: AdtDef) -> bool {
+        usage_error(unsupported("adt_is_box"))
+    }
+
+    fn adt_is_simd(&self, _def: AdtDef) -> bool {
+        usage_error(unsupported("adt_is_simd"))
+    }
+
+    fn adt_is_cstr(&self, _def: AdtDef) -> bool {
+        usage_error(unsupported("adt_is_cstr"))
+    }
+
+    fn adt_repr(&self, _def: AdtDef) -> ReprOptions {
+        usage_error(unsupported("adt_repr"))
+    }
+
+    fn fn_sig(&self, _def: FnDef, _args: &GenericArgs) -> PolyFnSig {
+        usage_error(unsupported("fn_sig"))
+    }
+
+    fn intrinsic(&self, _item: DefId) -> Option<IntrinsicDef> {
+        usage_error(unsupported("intrinsic"))
+    }
+
+    fn intrinsic_name(&self, _def: IntrinsicDef) -> Symbol {
+        usage_error(unsupported("intrinsic_name"))
+    }
+
+    fn closure_sig(&self, _args: &GenericArgs) -> PolyFnSig {
+        usage_error(unsupported("closure_sig"))
+    }
+
+    fn adt_variants_len(&self, _def: AdtDef) -> usize {
+        usage_error(unsupported("adt_variants_len"))
+    }
+
+    fn adt_discr_for_variant(&self, _adt: AdtDef, _variant: VariantIdx) -> Discr {
+        usage_error(unsupported("adt_discr_for_variant"))
+    }
+
+    fn coroutine_discr_for_variant(
+        &self,
+        _coroutine: CoroutineDef,
+        _args: &GenericArgs,
+        _variant: VariantIdx,
+    ) -> Discr {
+        usage_error(unsupported("coroutine_discr_for_variant"))
+    }
+
+    fn variant_name(&self, _def: VariantDef) -> Symbol {
+        usage_error(unsupported("variant_name"))
+    }
+
+    fn variant_fields(&self, _def: VariantDef) -> Vec<FieldDef> {
+        usage_error(unsupported("variant_fields"))
+    }
+
+    fn eval_target_usize(&self, _cnst: &MirConst) -> Result<u64, Error> {
+        Err(unsupported("eval_target_usize"))
+    }
+
+    fn eval_target_usize_ty(&self, _cnst: &TyConst) -> Result<u64, Error> {
+        Err(unsupported("eval_target_usize_ty"))
+    }
+
+    fn try_new_const_zst(&self, _ty: Ty) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_zst"))
+    }
+
+    fn new_const_str(&self, _value: &str) -> MirConst {
+        usage_error(unsupported("new_const_str"))
+    }
+
+    fn new_const_bool(&self, _value: bool) -> MirConst {
+        usage_error(unsupported("new_const_bool"))
+    }
+
+    fn try_new_const_uint(&self, _value: u128, _uint_ty: UintTy) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_uint"))
+    }
+
+    fn try_new_ty_const_uint(&self, _value: u128, _uint_ty: UintTy) -> Result<TyConst, Error> {
+        Err(unsupported("try_new_ty_const_uint"))
+    }
+
+    fn try_new_const_from_bits(&self, _bits: u128, _ty: Ty) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_from_bits"))
+    }
+
+    fn new_const_from_ty_const(&self, _cnst: &TyConst, _ty: Ty) -> MirConst {
+        usage_error(unsupported("new_const_from_ty_const"))
+    }
+
+    fn try_new_const_unevaluated(&self, _cnst: &UnevaluatedConst) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_unevaluated"))
+    }
+
+    fn try_new_const_ptr(
+        &self,
+        _alloc: u64,
+        _offset: u64,
+        _immutable: bool,
+        _ty: Ty,
+    ) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_ptr"))
+    }
+
+    fn try_new_const_aggregate(
+        &self,
+        _ty: Ty,
+        _variant: Option<VariantIdx>,
+        _fields: &[MirConst],
+    ) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_aggregate"))
+    }
+
+    fn new_rigid_ty(&self, _kind: RigidTy) -> Ty {
+        usage_error(unsupported("new_rigid_ty"))
+    }
+
+    fn new_box_ty(&self, _ty: Ty) -> Ty {
+        usage_error(unsupported("new_box_ty"))
+    }
+
+    fn new_alias_ty(&self, _kind: AliasKind, _alias: &AliasTy) -> Ty {
+        usage_error(unsupported("new_alias_ty"))
+    }
+
+    fn try_new_ty_const(&self, _kind: &TyConstKind) -> Result<TyConst, Error> {
+        Err(unsupported("try_new_ty_const"))
+    }
+
+    fn mir_const_with_ty(&self, _cnst: &MirConst, _ty: Ty) -> MirConst {
+        usage_error(unsupported("mir_const_with_ty"))
+    }
+
+    fn ty_const_with_ty(&self, _cnst: &TyConst, _ty: Ty) -> TyConst {
+        usage_error(unsupported("ty_const_with_ty"))
+    }
+
+    fn def_ty(&self, item: DefId) -> Ty {
+        self.recorded().item(item).ty
+    }
+
+    fn def_ty_with_args(&self, _item: DefId, _args: &GenericArgs) -> Ty {
+        usage_error(unsupported("def_ty_with_args"))
+    }
+
+    fn mir_const_pretty(&self, _cnst: &MirConst, _trimmed: bool) -> String {
+        usage_error(unsupported("mir_const_pretty"))
+    }
+
+    fn span_of_an_item(&self, def_id: DefId) -> Span {
+        self.recorded().item(def_id).span
+    }
+
+    fn dummy_span(&self) -> Span {
+        usage_error(unsupported("dummy_span"))
+    }
+
+    fn def_descr(&self, def_id: DefId) -> String {
+        self.recorded().def(def_id).kind.clone()
+    }
+
+    fn ty_const_pretty(&self, _ct: TyConstId, _trimmed: bool) -> String {
+        usage_error(unsupported("ty_const_pretty"))
+    }
+
+    fn ty_pretty(&self, _ty: Ty, _trimmed: bool) -> String {
+        usage_error(unsupported("ty_pretty"))
+    }
+
+    fn ty_kind(&self, ty: Ty) -> TyKind {
+        let recorded = self.recorded();
+        recorded.export.tables.types.get(&ty.to_index()).cloned().unwrap_or_else(|| {
+            usage_error(not_recorded(format!("the kind of type `{}`", ty.to_index())))
+        })
+    }
+
+    fn rigid_ty_discriminant_ty(&self, _ty: &RigidTy) -> Ty {
+        usage_error(unsupported("rigid_ty_discriminant_ty"))
+    }
+
+    fn instance_body(&self, instance: InstanceDef) -> Option<Body> {
+        let recorded = self.recorded();
+        let index = recorded.instance_bodies.get(&instance.to_index()).unwrap_or_else(|| {
+            let name = &recorded.instance(instance).mangled_name;
+            usage_error(not_recorded(format!("the body of `{name}`")))
+        });
+        recorded.export.instances[*index].body.clone()
+    }
+
+    fn instance_ty(&self, _instance: InstanceDef) -> Ty {
+        usage_error(unsupported("instance_ty"))
+    }
+
+    fn instance_args(&self, def: InstanceDef) -> GenericArgs {
+        self.recorded().instance(def).args.clone()
+    }
+
+    fn instance_def_id(&self, instance: InstanceDef) -> DefId {
+        self.recorded().instance(instance).def_id
+    }
+
+    fn instance_mangled_name(&self, instance: InstanceDef) -> Symbol {
+        self.recorded().instance(instance).mangled_name.clone()
+    }
+
+    fn is_empty_drop_shim(&self, _def: InstanceDef) -> bool {
+        usage_error(unsupported("is_empty_drop_shim"))
+    }
+
+    fn mono_instance(&self, def_id: DefId) -> Instance {
+        let recorded = self.recorded();
+        recorded
+            .instances_of(def_id)
+            .find(|instance| recorded.instance(instance.instance.def).args.0.is_empty())
+            .map(|instance| instance.instance)
+            .unwrap_or_else(|| {
+                let path = &recorded.def(def_id).path;
+                usage_error(not_recorded(format!("the instance of `{path}`")))
+            })
+    }
+
+    fn requires_monomorphization(&self, def_id: DefId) -> bool {
+        self.recorded().item(def_id).requires_monomorphization
+    }
+
+    fn resolve_instance(&self, _def: FnDef, _args: &GenericArgs) -> Option<Instance> {
+        usage_error(unsupported("resolve_instance"))
+    }
+
+    fn resolve_drop_in_place(&self, _ty: Ty) -> Instance {
+        usage_error(unsupported("resolve_drop_in_place"))
+    }
+
+    fn resolve_for_fn_ptr(&self, _def: FnDef, _args: &GenericArgs) -> Option<Instance> {
+        usage_error(unsupported("resolve_for_fn_ptr"))
+    }
+
+    fn resolve_closure(
+        &self,
+        _def: ClosureDef,
+        _args: &GenericArgs,
+        _kind: ClosureKind,
+    ) -> Option<Instance> {
+        usage_error(unsupported("resolve_closure"))
+    }
+
+    fn eval_static_initializer(&self, _def: StaticDef) -> Result<Allocation, Error> {
+        Err(unsupported("eval_static_initializer"))
+    }
+
+    fn eval_instance(&self, _def: InstanceDef, _const_ty: Ty) -> Result<Allocation, Error> {
+        Err(unsupported("eval_instance"))
+    }
+
+    fn global_alloc(&self, id: AllocId) -> GlobalAlloc {
+        let recorded = self.recorded();
+        recorded.export.tables.allocs.get(&id.to_index()).cloned().unwrap_or_else(|| {
+            usage_error(not_recorded(format!("the allocation `{}`", id.to_index())))
+        })
+    }
+
+    fn vtable_allocation(&self, _global_alloc: &GlobalAlloc) -> Option<AllocId> {
+        usage_error(unsupported("vtable_allocation"))
+    }
+
+    fn krate(&self, def_id: DefId) -> Crate {
+        let recorded = self.recorded();
+        let name = &recorded.def(def_id).krate;
+        let id = recorded.crates.iter().position(|krate| krate == name).unwrap();
+        recorded.krate(id)
+    }
+
+    fn instance_name(&self, _def: InstanceDef, _trimmed: bool) -> Symbol {
+        usage_error(unsupported("instance_name"))
+    }
+
+    fn target_info(&self) -> MachineInfo {
+        self.recorded().export.machine.clone()
+    }
+
+    fn instance_abi(&self, _def: InstanceDef) -> Result<FnAbi, Error> {
+        Err(unsupported("instance_abi"))
+    }
+
+    fn fn_ptr_abi(&self, _fn_ptr: PolyFnSig) -> Result<FnAbi, Error> {
+        Err(unsupported("fn_ptr_abi"))
+    }
+
+    fn ty_layout(&self, ty: Ty) -> Result<Layout, Error> {
+        let recorded = self.recorded();
+        recorded
+            .export
+            .tables
+            .type_layouts
+            .get(&ty.to_index())
+            .copied()
+            .ok_or_else(|| not_recorded(format!("the layout of type `{}`", ty.to_index())))
+    }
+
+    fn layout_shape(&self, id: Layout) -> LayoutShape {
+        let recorded = self.recorded();
+        recorded
+            .export
+            .tables
+            .layouts
+            .get(&id.to_index())
+            .cloned()
+            .unwrap_or_else(|| usage_error(not_recorded(format!("the layout `{}`", id.to_index()))))
+    }
+
+    fn place_pretty(&self, _place: &Place) -> String {
+        usage_error(unsupported("place_pretty"))
+    }
+
+    fn binop_ty(&self, _bin_op: BinOp, _rhs: Ty, _lhs: Ty) -> Ty {
+        usage_error(unsupported("binop_ty"))
+    }
+
+    fn unop_ty(&self, _un_op: UnOp, _arg: Ty) -> Ty {
+        usage_error(unsupported("unop_ty"))
+    }
+
+    fn associated_items(&self, _def_id: DefId) -> AssocItems {
+        usage_error(unsupported("associated_items"))
+    }
+
+    fn emit_diagnostic(&self, _diagnostic: &Diagnostic) {
+        usage_error(unsupported("emit_diagnostic"))
+    }
+
+    fn lint_level(&self, _lint: &'static Lint, _item: DefId) -> Result<LintLevel, Error> {
+        Err(unsupported("lint_level"))
+    }
+
+    fn emit_lint(
+        &self,
+        _lint: &'static Lint,
+        _item: DefId,
+        _diagnostic: &Diagnostic,
+    ) -> Result<(), Error> {
+        Err(unsupported("emit_lint"))
+    }
+}
@@ -12,6 +12,7 @@ use rustc_span::def_id::CrateNum;
 use scoped_tls::scoped_thread_local;
 
 use crate::Error;
+use crate::compiler_interface::SessionId;
 use crate::unstable::{RustcInternal, Stable};
 
 pub mod pretty;
@@ -74,7 +75,10 @@ where
 pub(crate) fn with_container<R, B: Bridge>(
     f: impl for<'tcx> FnOnce(&mut Tables<'tcx, B>, &CompilerCtxt<'tcx, B>) -> R,
 ) -> R {
-    assert!(TLV.is_set());
+    assert!(
+        TLV.is_set(),
+        "rustc_public internal APIs can only be used while a compilation is running"
+    );
     TLV.with(|tlv| {
         let ptr = tlv.get();
         assert!(!ptr.is_null());
@@ -92,18 +96,15 @@ where
     let compiler_cx = RefCell::new(CompilerCtxt::new(tcx));
     let container = Container { tables: RefCell::new(Tables::default()), cx: compiler_cx };
 
-    crate::compiler_interface::run(&container, || init(&container, f))
+    crate::compiler_interface::run(&container, SessionId::fresh(), || init(&container, f))
 }
 
 /// Instantiate and run the compiler with the provided arguments and callback.
 ///
 /// The callback will be invoked after the compiler ran all its analyses, but before code generation.
-/// Note that this macro accepts two different formats for the callback:
+/// Note that this macro accepts three different formats for the callback:
 /// 1. An ident that resolves to a function that accepts no argument and returns `ControlFlow<B, C>`
 /// ```ignore(needs-extern-crate)
-/// # extern crate rustc_driver;
-/// # extern crate rustc_interface;
-/// # extern crate rustc_middle;
 /// # #[macro_use]
 /// # extern crate rustc_public;
 /// #
@@ -121,9 +122,6 @@ where
 /// ```
 /// 2. A closure expression:
 /// ```ignore(needs-extern-crate)
-/// # extern crate rustc_driver;
-/// # extern crate rustc_interface;
-/// # extern crate rustc_middle;
 /// # #[macro_use]
 /// # extern crate rustc_public;
 /// #
@@ -141,8 +139,40 @@ where
 /// #   assert_eq!(result, Err(CompilerError::Skipped))
 /// # }
 /// ```
+/// 3. A block with one callback per compilation phase, which can be any of
+///    `after_crate_root_parsing`, `after_expansion`, `after_analysis` and `after_codegen`:
+/// ```ignore(needs-extern-crate)
+/// # #[macro_use]
+/// # extern crate rustc_public;
+/// #
+/// # fn main() {
+/// #   use std::ops::ControlFlow;
+/// #   use rustc_public::CompilerError;
+///     fn check_attributes() -> ControlFlow<(), ()> {
+/// #       ControlFlow::Continue(())
+///     }
+///     fn analyze_code() -> ControlFlow<(), ()> {
+/// #       ControlFlow::Continue(())
+///     }
+/// #   let args = &["--verbose".to_string()];
+///     let result = run!(args, {
+///         after_expansion: check_attributes,
+///         after_analysis: || analyze_code(),
+///     });
+/// #   assert_eq!(result, Err(CompilerError::Skipped))
+/// # }
+/// ```
+///
+/// The callbacks are invoked in the order of the compilation phases.
+/// See [Driver](crate::driver::Driver) for more details on how the result is computed.
+///
+/// This macro is a thin wrapper around [Driver](crate::driver::Driver), which can be used
+/// directly for more control over the compilation.
 #[macro_export]
 macro_rules! run {
+    ($args:expr, { $($phase:ident : $callback:expr),+ $(,)? }) => {
+        $crate::run_driver!($args, [$($phase: $callback),+])
+    };
     ($args:expr, $callback_fn:ident) => {
         $crate::run_driver!($args, || $callback_fn())
     };
@@ -165,106 +195,45 @@ macro_rules! run_with_tcx {
     };
 }
 
-/// Optionally include an ident. This is needed due to macro hygiene.
+/// Convert the name of a callback into the phase it should be invoked.
 #[macro_export]
 #[doc(hidden)]
-macro_rules! optional {
-    (with_tcx $ident:ident) => {
-        $ident
+macro_rules! compiler_phase {
+    (after_crate_root_parsing) => {
+        $crate::driver::CompilerPhase::CrateRootParsing
+    };
+    (after_expansion) => {
+        $crate::driver::CompilerPhase::Expansion
+    };
+    (after_analysis) => {
+        $crate::driver::CompilerPhase::Analysis
+    };
+    (after_codegen) => {
+        $crate::driver::CompilerPhase::Codegen
     };
 }
 
 /// Prefer using [run!] and [run_with_tcx] instead.
 ///
-/// This macro implements the instantiation of a rustc_public driver, and it will invoke
-/// the given callback after the compiler analyses.
+/// This macro instantiates a rustc_public [Driver](crate::driver::Driver), which will invoke
+/// the given callbacks after their respective compilation phase.
+/// A single callback is invoked after the compiler analyses.
 ///
-/// The third argument determines whether the callback requires `tcx` as an argument.
+/// The last argument determines whether the callbacks require `tcx` as an argument.
 #[macro_export]
 #[doc(hidden)]
 macro_rules! run_driver {
-    ($args:expr, $callback:expr $(, $with_tcx:ident)?) => {{
-        use rustc_driver::{Callbacks, Compilation, run_compiler};
-        use rustc_middle::ty::TyCtxt;
-        use rustc_interface::interface;
-        use rustc_public::rustc_internal;
-        use rustc_public::CompilerError;
-        use std::ops::ControlFlow;
-
-        pub struct RustcPublic<B = (), C = (), F = fn($($crate::optional!($with_tcx TyCtxt))?) -> ControlFlow<B, C>>
-        where
-            B: Send,
-            C: Send,
-            F: FnOnce($($crate::optional!($with_tcx TyCtxt))?) -> ControlFlow<B, C> + Send,
-        {
-            callback: Option<F>,
-            result: Option<ControlFlow<B, C>>,
-        }
-
-        impl<B, C, F> RustcPublic<B, C, F>
-        where
-            B: Send,
-            C: Send,
-            F: FnOnce($($crate::optional!($with_tcx TyCtxt))?) -> ControlFlow<B, C> + Send,
-        {
-            /// Creates a new `RustcPublic` instance, with given test_function and arguments.
-            pub fn new(callback: F) -> Self {
-                RustcPublic { callback: Some(callback), result: None }
-            }
-
-            /// Runs the compiler against given target and tests it with `test_function`
-            pub fn run(&mut self, args: &[String]) -> Result<C, CompilerError<B>> {
-                let compiler_result = rustc_driver::catch_fatal_errors(|| -> interface::Result::<()> {
-                    run_compiler(&args, self);
-                    Ok(())
-                });
-                match (compiler_result, self.result.take()) {
-                    (Ok(Ok(())), Some(ControlFlow::Continue(value))) => Ok(value),
-                    (Ok(Ok(())), Some(ControlFlow::Break(value))) => {
-                        Err(CompilerError::Interrupted(value))
-                    }
-                    (Ok(Ok(_)), None) => Err(CompilerError::Skipped),
-                    // Two cases here:
-                    // - `run` finished normally and returned `Err`
-                    // - `run` panicked with `FatalErr`
-                    // You might think that normal compile errors cause the former, and
-                    // ICEs cause the latter. But some normal compiler errors also cause
-                    // the latter. So we can't meaningfully distinguish them, and group
-                    // them together.
-                    (Ok(Err(_)), _) | (Err(_), _) => Err(CompilerError::Failed),
-                }
-            }
-        }
-
-        impl<B, C, F> Callbacks for RustcPublic<B, C, F>
-        where
-            B: Send,
-            C: Send,
-            F: FnOnce($($crate::optional!($with_tcx TyCtxt))?) -> ControlFlow<B, C> + Send,
-        {
-            /// Called after analysis. Return value instructs the compiler whether to
-            /// continue the compilation afterwards (defaults to `Compilation::Continue`)
-            fn after_analysis<'tcx>(
-                &mut self,
-                _compiler: &interface::Compiler,
-                tcx: TyCtxt<'tcx>,
-            ) -> Compilation {
-                if let Some(callback) = self.callback.take() {
-                    rustc_internal::run(tcx, || {
-                        self.result = Some(callback($($crate::optional!($with_tcx tcx))?));
-                    })
-                    .unwrap();
-                    if self.result.as_ref().is_some_and(|val| val.is_continue()) {
-                        Compilation::Continue
-                    } else {
-                        Compilation::Stop
-                    }
-                } else {
-                    Compilation::Continue
-                }
-            }
-        }
-
-        RustcPublic::new($callback).run($args)
-    }};
+    ($args:expr, [$($phase:ident : $callback:expr),+]) => {
+        $crate::driver::Driver::new($args)
+            $(.callback($crate::compiler_phase!($phase), $callback))+
+            .run()
+    };
+    ($args:expr, [$($phase:ident : $callback:expr),+], with_tcx) => {
+        $crate::driver::Driver::new($args)
+            $(.callback_with_tcx($crate::compiler_phase!($phase), $callback))+
+            .run()
+    };
+    ($args:expr, $callback:expr $(, $with_tcx:ident)?) => {
+        $crate::run_driver!($args, [after_analysis: $callback] $(, $with_tcx)?)
+    };
 }
@@ -0,0 +1,890 @@
+//! Run tool code against a mock of the compiler, which is useful for unit tests.
+//!
+//! This module is only available with the `testing` feature.
+//!
+//! A [MockContext] is built by declaring the types, ADTs, functions, bodies and constants that
+//! the code under test inspects. Requests that inspect anything else, or that need the compiler, such as
+//! evaluating constants, fail with [ErrorKind::Unsupported]. Requests that return a [Result]
+//! return this error, while other requests panic, unless they are executed inside
+//! [try_with](crate::try_with).
+//!
+//! ```ignore(needs-extern-crate)
+//! use rustc_public::testing::{MockContext, with_mock};
+//!
+//! let mut ctx = MockContext::new("my_crate");
+//! let u8_ty = ctx.rigid_ty(RigidTy::Uint(UintTy::U8));
+//! let span = ctx.span("lib.rs", LineInfo::from((1, 1, 1, 20)));
+//! let sig = FnSig { inputs_and_output: vec![u8_ty], c_variadic: false, safety: Safety::Safe, abi: Abi::Rust };
+//! let id = ctx.function("id", sig);
+//! ctx.body(id, Body::new(blocks, locals, 1, vec![], None, span));
+//! let findings = with_mock(&ctx, || my_analysis(rustc_public::all_local_items())).unwrap();
+//! ```
+//!
+//! Tools can also implement [CompilerInterface] to provide their own backend, and run their code
+//! with [with_backend].
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::abi::{FnAbi, Layout, LayoutShape, ReprOptions};
+pub use crate::compiler_interface::CompilerInterface;
+use crate::compiler_interface::{self, SessionHandle, SessionId, usage_error};
+use crate::crate_def::Attribute;
+use crate::diagnostics::Diagnostic;
+use crate::lint::{Lint, LintLevel};
+use crate::mir::alloc::{AllocId, GlobalAlloc};
+use crate::mir::mono::{Instance, InstanceDef, InstanceKind, StaticDef};
+use crate::mir::{BinOp, Body, Mutability, Place, UnOp};
+use crate::target::{Endian, MachineInfo, MachineSize};
+use crate::ty::{
+    AdtDef, AdtKind, AliasKind, AliasTy, Allocation, Binder, ClosureDef, ClosureKind, ConstantKind,
+    CoroutineDef, Discr, FieldDef, FloatTy, FnDef, FnSig, ForeignDef, ForeignItemKind,
+    ForeignModule, ForeignModuleDef, GenericArgs, GenericPredicates, Generics, ImplDef, ImplTrait,
+    IntTy, IntrinsicDef, LineInfo, MirConst, MirConstId, PolyFnSig, ProvenanceMap, RigidTy, Span,
+    TraitDecl, TraitDef, Ty, TyConst, TyConstId, TyConstKind, TyKind, UintTy, UnevaluatedConst,
+    VariantDef, VariantIdx,
+};
+use crate::{
+    AssocItems, Crate, CrateDef, CrateItem, CrateItems, CrateNum, DefId, DefKind, DefPath, Error,
+    ErrorKind, Filename, ImplTraitDecls, IndexedVal, ItemKind, StableDefId, Symbol, TraitDecls,
+    mir,
+};
+
+/// Execute the given function using the given backend to answer the requests of rustc_public.
+///
+/// Objects created by one call cannot be used in later calls, since every call starts a new
+/// session.
+pub fn with_backend<R>(backend: &dyn CompilerInterface, f: impl FnOnce() -> R) -> Result<R, Error> {
+    compiler_interface::run(backend, SessionId::fresh(), f)
+}
+
+/// Execute the given function using the given mock context to answer the requests of
+/// rustc_public.
+///
+/// Objects created by the context, and objects created in one call, can be used in later calls
+/// for the same context.
+pub fn with_mock<R>(ctx: &MockContext, f: impl FnOnce() -> R) -> Result<R, Error> {
+    compiler_interface::run(ctx, ctx.session, f)
+}
+
+/// Error for requests that cannot be fulfilled by a mock context.
+fn unsupported(query: &str) -> Error {
+    Error::new(ErrorKind::Unsupported, format!("`{query}` is not supported by mock contexts"))
+}
+
+/// Error for information that wasn't declared in the mock context.
+fn not_declared(what: String) -> Error {
+    Error::new(ErrorKind::Unsupported, format!("The mock context doesn't declare {what}"))
+}
+
+/// A programmable backend that answers requests using the information declared in it.
+///
+/// Every function is non-generic and belongs to the local crate, whose target is a 64-bit
+/// little-endian machine.
+pub struct MockContext {
+    session: SessionId,
+    crate_name: Symbol,
+    /// The declared definitions, indexed by their [DefId].
+    defs: Vec<MockDef>,
+    /// The kind of every type, indexed by its [Ty]. New types can be created by the code under
+    /// test, e.g.: [Ty::new_ref].
+    types: RefCell<Vec<TyKind>>,
+    /// The file and lines of every span, indexed by its [Span].
+    spans: Vec<(Filename, LineInfo)>,
+    /// The shape of every layout, indexed by its [Layout].
+    layouts: Vec<LayoutShape>,
+    type_layouts: HashMap<Ty, Layout>,
+    /// The function of every instance, indexed by its [InstanceDef].
+    instances: RefCell<Vec<DefId>>,
+    /// The number of constants created so far, which is used to assign their [MirConstId].
+    consts: Cell<usize>,
+}
+
+struct MockDef {
+    name: Symbol,
+    kind: MockDefKind,
+    ty: Option<Ty>,
+    span: Option<Span>,
+}
+
+enum MockDefKind {
+    Adt { kind: AdtKind, variants: Vec<MockVariant> },
+    Field,
+    Fn { sig: FnSig, body: Option<Body> },
+}
+
+struct MockVariant {
+    name: Symbol,
+    fields: Vec<FieldDef>,
+}
+
+impl MockContext {
+    /// Create an empty context for a crate with the given name.
+    pub fn new(crate_name: impl Into<Symbol>) -> MockContext {
+        MockContext {
+            session: SessionId::fresh(),
+            crate_name: crate_name.into(),
+            defs: vec![],
+            types: RefCell::new(vec![]),
+            spans: vec![],
+            layouts: vec![],
+            type_layouts: HashMap::new(),
+            instances: RefCell::new(vec![]),
+            consts: Cell::new(0),
+        }
+    }
+
+    /// Declare a type of the given kind.
+    ///
+    /// Declaring the same kind twice returns the same type.
+    pub fn ty(&mut self, kind: TyKind) -> Ty {
+        self.intern(kind)
+    }
+
+    /// Declare a rigid type of the given kind.
+    pub fn rigid_ty(&mut self, kind: RigidTy) -> Ty {
+        self.intern(TyKind::RigidTy(kind))
+    }
+
+    /// Declare a span that covers the given lines of a file.
+    pub fn span(&mut self, file: impl Into<Filename>, lines: LineInfo) -> Span {
+        self.spans.push((file.into(), lines));
+        Span::new_in(self.spans.len() - 1, self.session)
+    }
+
+    /// Declare a non-generic ADT without any variant.
+    ///
+    /// Use [MockContext::variant] to declare its variants, or its fields for structs and unions.
+    pub fn adt(&mut self, name: impl Into<Symbol>, kind: AdtKind) -> AdtDef {
+        let adt = AdtDef(self.next_def_id());
+        let ty = self.rigid_ty(RigidTy::Adt(adt, GenericArgs(vec![])));
+        self.add_def(name, MockDefKind::Adt { kind, variants: vec![] }, Some(ty));
+        adt
+    }
+
+    /// Declare a variant of the given ADT with the given fields.
+    pub fn variant<S: Into<Symbol>>(
+        &mut self,
+        adt: AdtDef,
+        name: impl Into<Symbol>,
+        fields: impl IntoIterator<Item = (S, Ty)>,
+    ) -> VariantDef {
+        let fields = fields
+            .into_iter()
+            .map(|(name, ty)| {
+                let name = name.into();
+                FieldDef { def: self.add_def(name.clone(), MockDefKind::Field, Some(ty)), name }
+            })
+            .collect();
+        let MockDefKind::Adt { variants, .. } = &mut self.def_mut(adt.0).kind else {
+            panic!("`{adt:?}` is not an ADT")
+        };
+        variants.push(MockVariant { name: name.into(), fields });
+        VariantDef { idx: VariantIdx::to_val(variants.len() - 1), adt_def: adt }
+    }
+
+    /// Declare a non-generic function of the local crate with the given signature.
+    ///
+    /// Use [MockContext::body] to declare its body.
+    pub fn function(&mut self, name: impl Into<Symbol>, sig: FnSig) -> FnDef {
+        let def = FnDef(self.next_def_id());
+        let ty = self.rigid_ty(RigidTy::FnDef(def, GenericArgs(vec![])));
+        self.add_def(name, MockDefKind::Fn { sig, body: None }, Some(ty));
+        def
+    }
+
+    /// Declare the body of the given function.
+    pub fn body(&mut self, def: FnDef, body: Body) {
+        let MockDefKind::Fn { body: fn_body, .. } = &mut self.def_mut(def.0).kind else {
+            panic!("`{def:?}` is not a function")
+        };
+        *fn_body = Some(body);
+    }
+
+    /// Declare the span of the given definition.
+    pub fn item_span(&mut self, def: impl CrateDef, span: Span) {
+        self.def_mut(def.def_id()).span = Some(span);
+    }
+
+    /// Declare the layout of the given type.
+    pub fn layout(&mut self, ty: Ty, shape: LayoutShape) -> Layout {
+        self.layouts.push(shape);
+        let layout = Layout::new_in(self.layouts.len() - 1, self.session);
+        self.type_layouts.insert(ty, layout);
+        layout
+    }
+
+    /// Declare a constant of the given scalar type, i.e., a boolean, a character, an integer or
+    /// a float, whose value is represented by the given bits.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the type isn't a scalar, or if the bits don't fit in it.
+    pub fn scalar_const(&mut self, kind: RigidTy, bits: u128) -> MirConst {
+        let ty = self.rigid_ty(kind.clone());
+        self.new_scalar(ty, &kind, bits).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Declare a constant of the given zero-sized type, e.g.: the unit value.
+    pub fn zst_const(&mut self, ty: Ty) -> MirConst {
+        self.new_const(ConstantKind::ZeroSized, ty)
+    }
+
+    /// Declare a constant of the type of the given function, e.g.: the callee of a call.
+    pub fn fn_def_const(&mut self, def: FnDef) -> MirConst {
+        let ty = self.rigid_ty(RigidTy::FnDef(def, GenericArgs(vec![])));
+        self.zst_const(ty)
+    }
+
+    fn next_def_id(&self) -> DefId {
+        DefId::new_in(self.defs.len(), self.session)
+    }
+
+    fn add_def(&mut self, name: impl Into<Symbol>, kind: MockDefKind, ty: Option<Ty>) -> DefId {
+        let def_id = self.next_def_id();
+        self.defs.push(MockDef { name: name.into(), kind, ty, span: None });
+        def_id
+    }
+
+    fn def_mut(&mut self, def_id: DefId) -> &mut MockDef {
+        assert_eq!(def_id.1, self.session, "`{def_id:?}` wasn't declared by this mock context");
+        &mut self.defs[def_id.0 as usize]
+    }
+
+    fn def(&self, def_id: DefId) -> &MockDef {
+        &self.defs[def_id.to_index()]
+    }
+
+    fn adt_info(&self, def: AdtDef) -> (AdtKind, &[MockVariant]) {
+        let MockDefKind::Adt { kind, variants } = &self.def(def.0).kind else {
+            usage_error(Error::new(
+                ErrorKind::UnexpectedItem(def.0),
+                format!("Expected an ADT, but found `{}`", self.def(def.0).name),
+            ))
+        };
+        (*kind, variants)
+    }
+
+    fn functions(&self) -> impl Iterator<Item = CrateItem> {
+        self.defs
+            .iter()
+            .enumerate()
+            .filter(|(_, def)| matches!(def.kind, MockDefKind::Fn { .. }))
+            .map(|(index, _)| CrateItem(DefId::new_in(index, self.session)))
+    }
+
+    fn new_const(&self, kind: ConstantKind, ty: Ty) -> MirConst {
+        let id = MirConstId::new_in(self.consts.get(), self.session);
+        self.consts.set(self.consts.get() + 1);
+        MirConst::new(kind, ty, id)
+    }
+
+    /// Build a scalar constant, whose bytes are stored in little-endian order.
+    fn new_scalar(&self, ty: Ty, kind: &RigidTy, bits: u128) -> Result<MirConst, Error> {
+        let Some(size) = scalar_size(kind) else {
+            return Err(Error::new(ErrorKind::UnexpectedTy(ty), "Expected a scalar type"));
+        };
+        if size < 16 && bits >> (size * 8) != 0 {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("Value overflow: cannot store `{bits}` in {size} bytes"),
+            ));
+        }
+        let allocation = Allocation {
+            bytes: bits.to_le_bytes()[..size].iter().map(|byte| Some(*byte)).collect(),
+            provenance: ProvenanceMap { ptrs: vec![] },
+            align: size as u64,
+            mutability: Mutability::Not,
+        };
+        Ok(self.new_const(ConstantKind::Allocated(allocation), ty))
+    }
+
+    fn intern(&self, kind: TyKind) -> Ty {
+        let mut types = self.types.borrow_mut();
+        let index = types.iter().position(|ty| *ty == kind).unwrap_or_else(|| {
+            types.push(kind);
+            types.len() - 1
+        });
+        Ty::new_in(index, self.session)
+    }
+}
+
+/// The size in bytes of the given type if it is a scalar.
+fn scalar_size(kind: &RigidTy) -> Option<usize> {
+    match kind {
+        RigidTy::Bool | RigidTy::Int(IntTy::I8) | RigidTy::Uint(UintTy::U8) => Some(1),
+        RigidTy::Int(IntTy::I16) | RigidTy::Uint(UintTy::U16) | RigidTy::Float(FloatTy::F16) => {
+            Some(2)
+        }
+        RigidTy::Char
+        | RigidTy::Int(IntTy::I32)
+        | RigidTy::Uint(UintTy::U32)
+        | RigidTy::Float(FloatTy::F32) => Some(4),
+        RigidTy::Int(IntTy::I64 | IntTy::Isize)
+        | RigidTy::Uint(UintTy::U64 | UintTy::Usize)
+        | RigidTy::Float(FloatTy::F64) => Some(8),
+        RigidTy::Int(IntTy::I128) | RigidTy::Uint(UintTy::U128) | RigidTy::Float(FloatTy::F128) => {
+            Some(16)
+        }
+        _ => None,
+    }
+}
+
+impl CompilerInterface for MockContext {
+    fn entry_fn(&self) -> Option<CrateItem> {
+        self.functions().find(|item| self.def(item.0).name == "main")
+    }
+
+    fn all_local_items(&self) -> CrateItems {
+        self.functions().collect()
+    }
+
+    fn mir_body(&self, item: DefId) -> mir::Body {
+        let def = self.def(item);
+        match &def.kind {
+            MockDefKind::Fn { body: Some(body), .. } => body.clone(),
+            _ => usage_error(not_declared(format!("the body of `{}`", def.name))),
+        }
+    }
+
+    fn has_body(&self, item: DefId) -> bool {
+        matches!(self.def(item).kind, MockDefKind::Fn { body: Some(_), .. })
+    }
+
+    fn foreign_modules(&self, _crate_num: CrateNum) -> Vec<ForeignModuleDef> {
+        vec![]
+    }
+
+    fn crate_functions(&self, _crate_num: CrateNum) -> Vec<FnDef> {
+        self.functions().map(|item| FnDef(item.0)).collect()
+    }
+
+    fn crate_statics(&self, _crate_num: CrateNum) -> Vec<StaticDef> {
+        vec![]
+    }
+
+    fn foreign_module(&self, _mod_def: ForeignModuleDef) -> ForeignModule {
+        usage_error(unsupported("foreign_module"))
+    }
+
+    fn foreign_items(&self, _mod_def: ForeignModuleDef) -> Vec<ForeignDef> {
+        usage_error(unsupported("foreign_items"))
+    }
+
+    fn all_trait_decls(&self) -> TraitDecls {
+        vec![]
+    }
+
+    fn trait_decls(&self, _crate_num: CrateNum) -> TraitDecls {
+        vec![]
+    }
+
+    fn trait_decl(&self, _trait_def: &TraitDef) -> TraitDecl {
+        usage_error(unsupported("trait_decl"))
+    }
+
+    fn all_trait_impls(&self) -> ImplTraitDecls {
+        vec![]
+    }
+
+    fn trait_impls(&self, _crate_num: CrateNum) -> ImplTraitDecls {
+        vec![]
+    }
+
+    fn trait_impl(&self, _trait_impl: &ImplDef) -> ImplTrait {
+        usage_error(unsupported("trait_impl"))
+    }
+
+    fn generics_of(&self, _def_id: DefId) -> Generics {
+        usage_error(unsupported("generics_of"))
+    }
+
+    fn identity_args(&self, _def_id: DefId) -> GenericArgs {
+        usage_error(unsupported("identity_args"))
+    }
+
+    fn predicates_of(&self, _def_id: DefId) -> GenericPredicates {
+        usage_error(unsupported("predicates_of"))
+    }
+
+    fn explicit_predicates_of(&self, _def_id: DefId) -> GenericPredicates {
+        usage_error(unsupported("explicit_predicates_of"))
+    }
+
+    fn generic_args_with_defaults(
+        &self,
+        _def_id: DefId,
+        _args: &GenericArgs,
+    ) -> Result<GenericArgs, Error> {
+        Err(unsupported("generic_args_with_defaults"))
+    }
+
+    fn inherent_impls(&self, _ty: Ty) -> Vec<ImplDef> {
+        usage_error(unsupported("inherent_impls"))
+    }
+
+    fn local_crate(&self) -> Crate {
+        Crate { id: 0, name: self.crate_name.clone(), is_local: true }
+    }
+
+    fn external_crates(&self) -> Vec<Crate> {
+        vec![]
+    }
+
+    fn find_crates(&self, name: &str) -> Vec<Crate> {
+        if self.crate_name == name { vec![self.local_crate()] } else { vec![] }
+    }
+
+    fn def_name(&self, def_id: DefId, _trimmed: bool) -> Symbol {
+        self.def(def_id).name.clone()
+    }
+
+    fn stable_def_id(&self, _def_id: DefId) -> StableDefId {
+        usage_error(unsupported("stable_def_id"))
+    }
+
+    fn find_stable_def_id(&self, _id: StableDefId) -> Option<DefId> {
+        usage_error(unsupported("find_stable_def_id"))
+    }
+
+    fn def_path(&self, _def_id: DefId) -> DefPath {
+        usage_error(unsupported("def_path"))
+    }
+
+    fn resolve_path(&self, path: &str) -> Vec<DefId> {
+        (0..self.defs.len())
+            .filter(|index| self.defs[*index].name == path)
+            .map(|index| DefId::new_in(index, self.session))
+            .collect()
+    }
+
+    fn def_kind(&self, def_id: DefId) -> DefKind {
+        match &self.def(def_id).kind {
+            MockDefKind::Adt { kind: AdtKind::Struct, .. } => DefKind::Struct,
+            MockDefKind::Adt { kind: AdtKind::Enum, .. } => DefKind::Enum,
+            MockDefKind::Adt { kind: AdtKind::Union, .. } => DefKind::Union,
+            MockDefKind::Field => DefKind::Other,
+            MockDefKind::Fn { .. } => DefKind::Fn,
+        }
+    }
+
+    fn tool_attrs(&self, _def_id: DefId, _attr: &[Symbol]) -> Vec<Attribute> {
+        vec![]
+    }
+
+    fn all_tool_attrs(&self, _def_id: DefId) -> Vec<Attribute> {
+        vec![]
+    }
+
+    fn span_to_string(&self, span: Span) -> String {
+        let (file, lines) = &self.spans[span.to_index()];
+        format!(
+            "{file}:{}:{}: {}:{}",
+            lines.start_line, lines.start_col, lines.end_line, lines.end_col
+        )
+    }
+
+    fn get_filename(&self, span: &Span) -> Filename {
+        self.spans[span.to_index()].0.clone()
+    }
+
+    fn get_lines(&self, span: &Span) -> LineInfo {
+        self.spans[span.to_index()].1
+    }
+
+    fn span_source_callsite(&self, span: &Span) -> Span {
+        // Spans don't keep track of macro expansions here.
+        *span
+    }
+
+    fn span_source_lines(&self, _span: &Span) -> Option<Vec<String>> {
+        None
+    }
+
+    fn item_kind(&self, item: CrateItem) -> ItemKind {
+        match self.def(item.0).kind {
+            MockDefKind::Fn { .. } => ItemKind::Fn,
+            _ => usage_error(Error::new(
+                ErrorKind::UnexpectedItem(item.0),
+                format!("Expected a crate item, but found `{}`", self.def(item.0).name),
+            )),
+        }
+    }
+
+    fn is_foreign_item(&self, _item: DefId) -> bool {
+        false
+    }
+
+    fn foreign_item_kind(&self, _def: ForeignDef) -> ForeignItemKind {
+        usage_error(unsupported("foreign_item_kind"))
+    }
+
+    fn adt_kind(&self, def: AdtDef) -> AdtKind {
+        self.adt_info(def).0
+    }
+
+    fn adt_is_box(&self, _def: AdtDef) -> bool {
+        false
+    }
+
+    fn adt_is_simd(&self, _def: AdtDef) -> bool {
+        false
+    }
+
+    fn adt_is_cstr(&self, _def: AdtDef) -> bool {
+        false
+    }
+
+    fn adt_repr(&self, _def: AdtDef) -> ReprOptions {
+        usage_error(unsupported("adt_repr"))
+    }
+
+    fn fn_sig(&self, def: FnDef, _args: &GenericArgs) -> PolyFnSig {
+        let def = self.def(def.0);
+        match &def.kind {
+            MockDefKind::Fn { sig, .. } => Binder { value: sig.clone(), bound_vars: vec![] },
+            _ => usage_error(not_declared(format!("the signature of `{}`", def.name))),
+        }
+    }
+
+    fn intrinsic(&self, _item: DefId) -> Option<IntrinsicDef> {
+        None
+    }
+
+    fn intrinsic_name(&self, _def: IntrinsicDef) -> Symbol {
+        usage_error(unsupported("intrinsic_name"))
+    }
+
+    fn closure_sig(&self, _args: &GenericArgs) -> PolyFnSig {
+        usage_error(unsupported("closure_sig"))
+    }
+
+    fn adt_variants_len(&self, def: AdtDef) -> usize {
+        self.adt_info(def).1.len()
+    }
+
+    fn adt_discr_for_variant(&self, _adt: AdtDef, _variant: VariantIdx) -> Discr {
+        usage_error(unsupported("adt_discr_for_variant"))
+    }
+
+    fn coroutine_discr_for_variant(
+        &self,
+        _coroutine: CoroutineDef,
+        _args: &GenericArgs,
+        _variant: VariantIdx,
+    ) -> Discr {
+        usage_error(unsupported("coroutine_discr_for_variant"))
+    }
+
+    fn variant_name(&self, def: VariantDef) -> Symbol {
+        self.adt_info(def.adt_def).1[def.idx.to_index()].name.clone()
+    }
+
+    fn variant_fields(&self, def: VariantDef) -> Vec<FieldDef> {
+        self.adt_info(def.adt_def).1[def.idx.to_index()].fields.clone()
+    }
+
+    fn eval_target_usize(&self, _cnst: &MirConst) -> Result<u64, Error> {
+        Err(unsupported("eval_target_usize"))
+    }
+
+    fn eval_target_usize_ty(&self, _cnst: &TyConst) -> Result<u64, Error> {
+        Err(unsupported("eval_target_usize_ty"))
+    }
+
+    fn try_new_const_zst(&self, ty: Ty) -> Result<MirConst, Error> {
+        let zero_sized = match self.ty_kind(ty) {
+            TyKind::RigidTy(RigidTy::FnDef(..)) => true,
+            TyKind::RigidTy(RigidTy::Tuple(fields)) => fields.is_empty(),
+            _ => self
+                .type_layouts
+                .get(&ty)
+                .is_some_and(|layout| self.layouts[layout.to_index()].size.bytes() == 0),
+        };
+        if zero_sized {
+            Ok(self.new_const(ConstantKind::ZeroSized, ty))
+        } else {
+            Err(Error::new(
+                ErrorKind::UnexpectedTy(ty),
+                format!("Expected a zero-sized type, but found `{ty:?}`"),
+            ))
+        }
+    }
+
+    fn new_const_str(&self, _value: &str) -> MirConst {
+        usage_error(unsupported("new_const_str"))
+    }
+
+    fn new_const_bool(&self, value: bool) -> MirConst {
+        let ty = self.intern(TyKind::RigidTy(RigidTy::Bool));
+        self.new_scalar(ty, &RigidTy::Bool, value.into()).unwrap()
+    }
+
+    fn try_new_const_uint(&self, value: u128, uint_ty: UintTy) -> Result<MirConst, Error> {
+        let kind = RigidTy::Uint(uint_ty);
+        let ty = self.intern(TyKind::RigidTy(kind.clone()));
+        self.new_scalar(ty, &kind, value)
+    }
+
+    fn try_new_ty_const_uint(&self, _value: u128, _uint_ty: UintTy) -> Result<TyConst, Error> {
+        Err(unsupported("try_new_ty_const_uint"))
+    }
+
+    fn try_new_const_from_bits(&self, bits: u128, ty: Ty) -> Result<MirConst, Error> {
+        match self.ty_kind(ty) {
+            TyKind::RigidTy(kind) => self.new_scalar(ty, &kind, bits),
+            _ => Err(Error::new(ErrorKind::UnexpectedTy(ty), "Expected a scalar type")),
+        }
+    }
+
+    fn new_const_from_ty_const(&self, _cnst: &TyConst, _ty: Ty) -> MirConst {
+        usage_error(unsupported("new_const_from_ty_const"))
+    }
+
+    fn try_new_const_unevaluated(&self, _cnst: &UnevaluatedConst) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_unevaluated"))
+    }
+
+    fn try_new_const_ptr(
+        &self,
+        _alloc: u64,
+        _offset: u64,
+        _immutable: bool,
+        _ty: Ty,
+    ) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_ptr"))
+    }
+
+    fn try_new_const_aggregate(
+        &self,
+        _ty: Ty,
+        _variant: Option<VariantIdx>,
+        _fields: &[MirConst],
+    ) -> Result<MirConst, Error> {
+        Err(unsupported("try_new_const_aggregate"))
+    }
+
+    fn new_rigid_ty(&self, kind: RigidTy) -> Ty {
+        self.intern(TyKind::RigidTy(kind))
+    }
+
+    fn new_box_ty(&self, _ty: Ty) -> Ty {
+        usage_error(unsupported("new_box_ty"))
+    }
+
+    fn new_alias_ty(&self, kind: AliasKind, alias: &AliasTy) -> Ty {
+        self.intern(TyKind::Alias(kind, alias.clone()))
+    }
+
+    fn try_new_ty_const(&self, _kind: &TyConstKind) -> Result<TyConst, Error> {
+        Err(unsupported("try_new_ty_const"))
+    }
+
+    fn mir_const_with_ty(&self, cnst: &MirConst, ty: Ty) -> MirConst {
+        self.new_const(cnst.kind().clone(), ty)
+    }
+
+    fn ty_const_with_ty(&self, _cnst: &TyConst, _ty: Ty) -> TyConst {
+        usage_error(unsupported("ty_const_with_ty"))
+    }
+
+    fn def_ty(&self, item: DefId) -> Ty {
+        let def = self.def(item);
+        def.ty.unwrap_or_else(|| usage_error(not_declared(format!("the type of `{}`", def.name))))
+    }
+
+    fn def_ty_with_args(&self, item: DefId, args: &GenericArgs) -> Ty {
+        if !args.0.is_empty() {
+            usage_error(unsupported("def_ty_with_args"))
+        }
+        self.def_ty(item)
+    }
+
+    fn mir_const_pretty(&self, _cnst: &MirConst, _trimmed: bool) -> String {
+        usage_error(unsupported("mir_const_pretty"))
+    }
+
+    fn span_of_an_item(&self, def_id: DefId) -> Span {
+        let def = self.def(def_id);
+        def.span.unwrap_or_else(|| usage_error(not_declared(format!("the span of `{}`", def.name))))
+    }
+
+    fn dummy_span(&self) -> Span {
+        usage_error(unsupported("dummy_span"))
+    }
+
+    fn def_descr(&self, def_id: DefId) -> String {
+        match &self.def(def_id).kind {
+            MockDefKind::Adt { kind, .. } => kind.to_string(),
+            MockDefKind::Field => "field".to_string(),
+            MockDefKind::Fn { .. } => "function".to_string(),
+        }
+    }
+
+    fn ty_const_pretty(&self, _ct: TyConstId, _trimmed: bool) -> String {
+        usage_error(unsupported("ty_const_pretty"))
+    }
+
+    fn ty_pretty(&self, _ty: Ty, _trimmed: bool) -> String {
+        usage_error(unsupported("ty_pretty"))
+    }
+
+    fn ty_kind(&self, ty: Ty) -> TyKind {
+        self.types.borrow()[ty.to_index()].clone()
+    }
+
+    fn rigid_ty_discriminant_ty(&self, _ty: &RigidTy) -> Ty {
+        usage_error(unsupported("rigid_ty_discriminant_ty"))
+    }
+
+    fn instance_body(&self, instance: InstanceDef) -> Option<Body> {
+        let def_id = self.instance_def_id(instance);
+        self.has_body(def_id).then(|| self.mir_body(def_id))
+    }
+
+    fn instance_ty(&self, instance: InstanceDef) -> Ty {
+        self.def_ty(self.instance_def_id(instance))
+    }
+
+    fn instance_args(&self, _def: InstanceDef) -> GenericArgs {
+        GenericArgs(vec![])
+    }
+
+    fn instance_def_id(&self, instance: InstanceDef) -> DefId {
+        self.instances.borrow()[instance.to_index()]
+    }
+
+    fn instance_mangled_name(&self, instance: InstanceDef) -> Symbol {
+        self.def(self.instance_def_id(instance)).name.clone()
+    }
+
+    fn is_empty_drop_shim(&self, _def: InstanceDef) -> bool {
+        false
+    }
+
+    fn mono_instance(&self, def_id: DefId) -> Instance {
+        let mut instances = self.instances.borrow_mut();
+        let index = instances.iter().position(|def| *def == def_id).unwrap_or_else(|| {
+            instances.push(def_id);
+            instances.len() - 1
+        });
+        Instance { kind: InstanceKind::Item, def: InstanceDef::to_val(index) }
+    }
+
+    fn requires_monomorphization(&self, _def_id: DefId) -> bool {
+        false
+    }
+
+    fn resolve_instance(&self, def: FnDef, args: &GenericArgs) -> Option<Instance> {
+        if !args.0.is_empty() {
+            usage_error(unsupported("resolve_instance"))
+        }
+        Some(self.mono_instance(def.0))
+    }
+
+    fn resolve_drop_in_place(&self, _ty: Ty) -> Instance {
+        usage_error(unsupported("resolve_drop_in_place"))
+    }
+
+    fn resolve_for_fn_ptr(&self, def: FnDef, args: &GenericArgs) -> Option<Instance> {
+        self.resolve_instance(def, args)
+    }
+
+    fn resolve_closure(
+        &self,
+        _def: ClosureDef,
+        _args: &GenericArgs,
+        _kind: ClosureKind,
+    ) -> Option<Instance> {
+        usage_error(unsupported("resolve_closure"))
+    }
+
+    fn eval_static_initializer(&self, _def: StaticDef) -> Result<Allocation, Error> {
+        Err(unsupported("eval_static_initializer"))
+    }
+
+    fn eval_instance(&self, _def: InstanceDef, _const_ty: Ty) -> Result<Allocation, Error> {
+        Err(unsupported("eval_instance"))
+    }
+
+    fn global_alloc(&self, _id: AllocId) -> GlobalAlloc {
+        usage_error(unsupported("global_alloc"))
+    }
+
+    fn vtable_allocation(&self, _global_alloc: &GlobalAlloc) -> Option<AllocId> {
+        usage_error(unsupported("vtable_allocation"))
+    }
+
+    fn krate(&self, _def_id: DefId) -> Crate {
+        self.local_crate()
+    }
+
+    fn instance_name(&self, def: InstanceDef, _trimmed: bool) -> Symbol {
+        self.instance_mangled_name(def)
+    }
+
+    fn target_info(&self) -> MachineInfo {
+        MachineInfo { endian: Endian::Little, pointer_width: MachineSize::from_bits(64) }
+    }
+
+    fn instance_abi(&self, _def: InstanceDef) -> Result<FnAbi, Error> {
+        Err(unsupported("instance_abi"))
+    }
+
+    fn fn_ptr_abi(&self, _fn_ptr: PolyFnSig) -> Result<FnAbi, Error> {
+        Err(unsupported("fn_ptr_abi"))
+    }
+
+    fn ty_layout(&self, ty: Ty) -> Result<Layout, Error> {
+        self.type_layouts
+            .get(&ty)
+            .copied()
+            .ok_or_else(|| not_declared(format!("the layout of type `{}`", ty.to_index())))
+    }
+
+    fn layout_shape(&self, id: Layout) -> LayoutShape {
+        self.layouts[id.to_index()].clone()
+    }
+
+    fn place_pretty(&self, _place: &Place) -> String {
+        usage_error(unsupported("place_pretty"))
+    }
+
+    fn binop_ty(&self, bin_op: BinOp, lhs: Ty, _rhs: Ty) -> Ty {
+        match bin_op {
+            BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+                self.intern(TyKind::RigidTy(RigidTy::Bool))
+            }
+            BinOp::Cmp => usage_error(unsupported("binop_ty")),
+            _ => lhs,
+        }
+    }
+
+    fn unop_ty(&self, un_op: UnOp, arg: Ty) -> Ty {
+        match un_op {
+            UnOp::Not | UnOp::Neg => arg,
+            UnOp::PtrMetadata => usage_error(unsupported("unop_ty")),
+        }
+    }
+
+    fn associated_items(&self, _def_id: DefId) -> AssocItems {
+        usage_error(unsupported("associated_items"))
+    }
+
+    fn emit_diagnostic(&self, _diagnostic: &Diagnostic) {
+        usage_error(unsupported("emit_diagnostic"))
+    }
+
+    fn lint_level(&self, _lint: &'static Lint, _item: DefId) -> Result<LintLevel, Error> {
+        Err(unsupported("lint_level"))
+    }
+
+    fn emit_lint(
+        &self,
+        _lint: &'static Lint,
+        _item: DefId,
+        _diagnostic: &Diagnostic,
+    ) -> Result<(), Error> {
+        Err(unsupported("emit_lint"))
+    }
+}
@@ -188,11 +188,17 @@ impl RustcInternal for RigidTy {
                 def.0.internal(tables, tcx),
                 args.internal(tables, tcx),
             ),
-            RigidTy::Dynamic(predicate, region, dyn_kind) => rustc_ty::TyKind::Dynamic(
-                tcx.mk_poly_existential_predicates(&predicate.internal(tables, tcx)),
-                region.internal(tables, tcx),
-                dyn_kind.internal(tables, tcx),
-            ),
+            RigidTy::Dynamic(predicate, region, dyn_kind) => {
+                use rustc_ty::ExistentialPredicateStableCmpExt;
+                // The compiler expects the predicates in their canonical order.
+                let mut predicates = predicate.internal(tables, tcx);
+                predicates.sort_by(|a, b| a.skip_binder().stable_cmp(tcx.tcx(), &b.skip_binder()));
+                rustc_ty::TyKind::Dynamic(
+                    tcx.mk_poly_existential_predicates(&predicates),
+                    region.internal(tables, tcx),
+                    dyn_kind.internal(tables, tcx),
+                )
+            }
             RigidTy::Tuple(tys) => {
                 rustc_ty::TyKind::Tuple(tcx.mk_type_list(&tys.internal(tables, tcx)))
             }
@@ -2,15 +2,15 @@
 
 use rustc_middle::mir::mono::MonoItem;
 use rustc_middle::{bug, mir};
+use rustc_public_bridge::Tables;
 use rustc_public_bridge::context::CompilerCtxt;
-use rustc_public_bridge::{Tables, bridge};
 
 use crate::compiler_interface::BridgeTys;
 use crate::mir::alloc::GlobalAlloc;
 use crate::mir::{ConstOperand, Statement, UserTypeProjection, VarDebugInfoFragment};
 use crate::ty::{Allocation, ConstantKind, MirConst};
 use crate::unstable::Stable;
-use crate::{Error, alloc, opaque};
+use crate::{Error, ErrorKind, alloc, opaque};
 
 impl<'tcx> Stable<'tcx> for mir::Body<'tcx> {
     type T = crate::mir::Body;
@@ -45,6 +45,16 @@ impl<'tcx> Stable<'tcx> for mir::Body<'tcx> {
             self.spread_arg.stable(tables, cx),
             self.span.stable(tables, cx),
         )
+        .with_source_scopes(
+            self.basic_blocks
+                .iter()
+                .map(|block| {
+                    let statements = block.statements.iter().map(|stmt| stmt.source_info.scope);
+                    let terminator = block.terminator().source_info.scope;
+                    statements.chain([terminator]).map(Into::into).collect()
+                })
+                .collect(),
+        )
     }
 }
 
@@ -911,8 +921,21 @@ impl<'tcx> Stable<'tcx> for rustc_middle::mir::Const<'tcx> {
 impl<'tcx> Stable<'tcx> for mir::interpret::ErrorHandled {
     type T = Error;
 
-    fn stable(&self, _: &mut Tables<'_, BridgeTys>, _: &CompilerCtxt<'_, BridgeTys>) -> Self::T {
-        bridge::Error::new(format!("{self:?}"))
+    fn stable<'cx>(
+        &self,
+        tables: &mut Tables<'cx, BridgeTys>,
+        cx: &CompilerCtxt<'cx, BridgeTys>,
+    ) -> Self::T {
+        let (span, message) = match self {
+            mir::interpret::ErrorHandled::Reported(_, span) => {
+                (span, "Failed to evaluate constant")
+            }
+            mir::interpret::ErrorHandled::TooGeneric(span) => {
+                (span, "Failed to evaluate constant, since it is too generic")
+            }
+        };
+        let span = (!span.is_dummy()).then(|| span.stable(tables, cx));
+        Error::new(ErrorKind::ConstEval { span }, message)
     }
 }
 
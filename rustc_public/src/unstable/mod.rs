@@ -123,3 +123,27 @@ pub(crate) fn new_item_kind(kind: DefKind) -> ItemKind {
         DefKind::Ctor(_, rustc_hir::def::CtorKind::Fn) => ItemKind::Ctor(CtorKind::Fn),
     }
 }
+
+pub(crate) fn new_def_kind(kind: DefKind) -> crate::DefKind {
+    match kind {
+        DefKind::Mod => crate::DefKind::Mod,
+        DefKind::Struct => crate::DefKind::Struct,
+        DefKind::Union => crate::DefKind::Union,
+        DefKind::Enum => crate::DefKind::Enum,
+        DefKind::Variant => crate::DefKind::Variant,
+        DefKind::Trait => crate::DefKind::Trait,
+        DefKind::TyAlias => crate::DefKind::TyAlias,
+        DefKind::ForeignTy => crate::DefKind::ForeignTy,
+        DefKind::AssocTy => crate::DefKind::AssocTy,
+        DefKind::Fn => crate::DefKind::Fn,
+        DefKind::AssocFn => crate::DefKind::AssocFn,
+        DefKind::Const => crate::DefKind::Const,
+        DefKind::AssocConst => crate::DefKind::AssocConst,
+        DefKind::Static { .. } => crate::DefKind::Static,
+        DefKind::Ctor(_, rustc_hir::def::CtorKind::Const) => crate::DefKind::Ctor(CtorKind::Const),
+        DefKind::Ctor(_, rustc_hir::def::CtorKind::Fn) => crate::DefKind::Ctor(CtorKind::Fn),
+        DefKind::Closure | DefKind::SyntheticCoroutineBody => crate::DefKind::Closure,
+        DefKind::Impl { .. } => crate::DefKind::Impl,
+        _ => crate::DefKind::Other,
+    }
+}
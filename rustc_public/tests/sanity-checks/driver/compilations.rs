@@ -0,0 +1,16 @@
+//@check-pass
+//@compile-flags: --smir-driver
+
+//! Crate that is compiled again by the checks that start their own compilations.
+
+pub struct S(u32);
+
+impl S {
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+pub fn add(x: u32, y: u32) -> u32 {
+    x + y
+}
@@ -0,0 +1,40 @@
+//@check-pass
+
+//! Functions with loops, switches, and calls that may unwind
+
+pub fn classify(value: u8) -> &'static str {
+    match value {
+        0 => "zero",
+        1 | 2 => "small",
+        10 => "ten",
+        _ => "other",
+    }
+}
+
+pub fn sum_until(values: &[u32], limit: u32) -> u32 {
+    let mut total = 0;
+    for value in values {
+        if total > limit {
+            break;
+        }
+        total = total.saturating_add(*value);
+    }
+    total
+}
+
+pub fn parse_all(inputs: &[&str]) -> Vec<i64> {
+    let mut parsed = Vec::new();
+    for input in inputs {
+        let value = match input.parse::<i64>() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        parsed.push(value);
+    }
+    parsed
+}
+
+pub fn checked(a: u32, b: u32) -> Option<u32> {
+    let sum = a.checked_add(b)?;
+    if sum % 2 == 0 { Some(sum / 2) } else { None }
+}
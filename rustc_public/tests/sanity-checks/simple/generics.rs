@@ -0,0 +1,38 @@
+//@check-pass
+
+//! Generic items whose types refer to type and const parameters
+
+pub trait Container {
+    type Item: Clone;
+
+    fn first(&self) -> Option<&Self::Item>;
+}
+
+pub struct Buffer<T, const N: usize> {
+    pub items: [T; N],
+}
+
+impl<T: Clone, const N: usize> Container for Buffer<T, N> {
+    type Item = T;
+
+    fn first(&self) -> Option<&T> {
+        self.items.first()
+    }
+}
+
+pub fn first_or<C: Container>(container: &C, default: C::Item) -> C::Item {
+    container.first().cloned().unwrap_or(default)
+}
+
+pub fn repeat<T: Copy, const N: usize>(value: T) -> [T; N] {
+    [value; N]
+}
+
+pub fn apply<T, U>(value: T, f: impl Fn(T) -> U) -> U {
+    let wrapped = |value| f(value);
+    wrapped(value)
+}
+
+pub fn sizes<T>() -> (usize, usize) {
+    (size_of::<T>(), size_of::<Buffer<T, 4>>())
+}
@@ -0,0 +1,35 @@
+//@check-pass
+
+//! Statics whose initializers cover the different kinds of allocations
+
+use std::mem::MaybeUninit;
+
+pub struct Point {
+    pub x: i32,
+    pub y: u8,
+}
+
+pub struct Wrapper(pub u16, pub bool);
+
+pub enum Shape {
+    Empty,
+    Line(u32),
+    Named { initial: char },
+}
+
+pub static GREETING: &str = "hello world, this is a longer string";
+pub static ORIGIN: Point = Point { x: -3, y: 7 };
+pub static WRAPPED: Wrapper = Wrapper(5, true);
+pub static SOME_REF: Option<&i32> = Some(&42);
+pub static NONE_REF: Option<&i32> = None;
+pub static SHAPES: [Shape; 3] = [Shape::Empty, Shape::Line(9), Shape::Named { initial: 'z' }];
+pub static NUMBERS: &[u64] = &[1, 2, 3];
+pub static FN_PTR: fn() -> u8 = one;
+pub static TUPLE: (f64, (), (i8,)) = (1.5, (), (-1,));
+pub static NESTED: &&&str = &&"deep";
+pub static TO_STATIC: &Point = &ORIGIN;
+pub static UNINIT: MaybeUninit<u32> = MaybeUninit::uninit();
+
+pub fn one() -> u8 {
+    1
+}
@@ -0,0 +1,913 @@
+//! Module that contains checks that start their own compilations.
+//!
+//! Only one compilation can be in progress at a time, so these checks cannot be invoked from a
+//! compiler callback. They are invoked once the main compilation is over instead.
+use std::iter::zip;
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rustc_public::diagnostics::{Diagnostic, EmittedDiagnostic, Level};
+use rustc_public::driver::{CompilationResult, CompilerPhase, Driver};
+use rustc_public::lint::{Lint, LintLevel};
+use rustc_public::ty::RigidTy;
+use rustc_public::{CompilerError, CrateDef, DefPath, DefPathData, DefPathSegment, ErrorKind, run};
+
+use crate::TestResult;
+use crate::sanity_checks::{check, check_equal};
+
+/// Test that interrupting the compilation in one phase skips the callbacks of later phases.
+pub fn test_phase_break(args: &[String]) -> TestResult {
+    let analysis = AtomicBool::new(false);
+    let codegen = AtomicBool::new(false);
+    let result = run!(args, {
+        after_expansion: || ControlFlow::Break("expansion"),
+        after_analysis: || {
+            analysis.store(true, Ordering::Relaxed);
+            ControlFlow::Continue(())
+        },
+        after_codegen: || {
+            codegen.store(true, Ordering::Relaxed);
+            ControlFlow::Continue(())
+        },
+    });
+    check(
+        matches!(result, Err(CompilerError::Interrupted("expansion"))),
+        format!("Expected compilation to be interrupted after expansion: {result:?}"),
+    )?;
+    check(!analysis.load(Ordering::Relaxed), "Analysis callback should not run".to_string())?;
+    check(!codegen.load(Ordering::Relaxed), "Codegen callback should not run".to_string())
+}
+
+/// Test that a driver that doesn't continue the compilation still runs the callbacks of every
+/// registered phase.
+pub fn test_stop_after_codegen(args: &[String]) -> TestResult {
+    let analysis = AtomicBool::new(false);
+    let result = Driver::new(args)
+        .continue_compilation(false)
+        .callback(CompilerPhase::Analysis, || {
+            analysis.store(true, Ordering::Relaxed);
+            ControlFlow::<(), _>::Continue("analysis")
+        })
+        .callback(CompilerPhase::Codegen, || ControlFlow::Continue("codegen"))
+        .run();
+    check(analysis.load(Ordering::Relaxed), "Analysis callback should run".to_string())?;
+    check_equal(result, Ok("codegen"), "Expected the codegen callback to run last")
+}
+
+/// Test that diagnostics emitted by a tool are rendered by the compiler, and that errors cause
+/// the compilation to fail.
+pub fn test_diagnostics(args: &[String]) -> TestResult {
+    let compilation = Driver::new(args)
+        .callback(CompilerPhase::Analysis, || {
+            let item =
+                rustc_public::all_local_items().into_iter().find(|item| item.name() == "add");
+            let span = item.unwrap().span();
+            Diagnostic::warning("found an addition")
+                .span(span)
+                .label(span, "this function")
+                .note("additions may overflow")
+                .help("consider using `checked_add`")
+                .emit();
+            Diagnostic::error("additions are forbidden").emit();
+            Diagnostic::new(Level::Bug, "tools cannot report bugs").emit();
+            ControlFlow::<()>::Continue(())
+        })
+        .run_with_diagnostics();
+    check(
+        matches!(compilation.result, Err(CompilerError::Failed)),
+        format!("Expected compilation to fail: {:?}", compilation.result),
+    )?;
+    check(!compilation.is_ice(), format!("Unexpected ICE: {:?}", compilation.diagnostics))?;
+
+    let find = |message: &str| -> Result<&EmittedDiagnostic, String> {
+        compilation
+            .diagnostics
+            .iter()
+            .find(|diag| diag.message == message)
+            .ok_or_else(|| format!("Missing `{message}`: {:?}", compilation.diagnostics))
+    };
+    let warning = find("found an addition")?;
+    check(warning.level == Level::Warning, format!("Expected a warning: {warning:?}"))?;
+    check(
+        warning.location.as_ref().is_some_and(|loc| loc.filename.ends_with("compilations.rs")),
+        format!("Expected the item location: {warning:?}"),
+    )?;
+    let children: Vec<_> =
+        warning.children.iter().map(|child| (child.level, child.message.as_str())).collect();
+    check(
+        children.contains(&(Level::Note, "additions may overflow"))
+            && children.contains(&(Level::Help, "consider using `checked_add`")),
+        format!("Expected a note and a help message: {children:?}"),
+    )?;
+    let error = find("additions are forbidden")?;
+    check(error.is_error() && error.location.is_none(), format!("Unexpected error: {error:?}"))?;
+    let bug = find("tools cannot report bugs")?;
+    check_equal(bug.level, Level::Error, "Tool bugs should be reported as errors")?;
+    // The compiler reports that it is aborting the compilation afterwards.
+    let first_error = compilation.errors().next();
+    check(first_error == Some(error), format!("Expected the tool error first: {first_error:?}"))
+}
+
+/// Test that a process can run several compilations one after another, and that the
+/// rustc_public context cannot be used outside of them.
+pub fn test_sequential_compilations(args: &[String]) -> TestResult {
+    let outside = rustc_public::try_with(rustc_public::local_crate);
+    check(
+        outside.as_ref().is_err_and(|err| *err.kind() == ErrorKind::Session),
+        format!("Expected a session error outside of a compilation: {:?}", outside.map(|_| ())),
+    )?;
+    let items = || {
+        let names = rustc_public::all_local_items().iter().map(|item| item.name()).collect();
+        ControlFlow::<(), Vec<_>>::Continue(names)
+    };
+    let first = run!(args, items).map_err(|err| format!("First compilation failed: {err:?}"))?;
+    let second = run!(args, items).map_err(|err| format!("Second compilation failed: {err:?}"))?;
+    check(!first.is_empty(), "Expected local items".to_string())?;
+    check_equal(second, first, "Compilations of the same crate should have the same items")
+}
+
+/// Test that objects from a compilation cannot be used once it is over, nor during another
+/// compilation.
+pub fn test_cross_session_handles(args: &[String]) -> TestResult {
+    let first = run!(args, || {
+        let item = rustc_public::all_local_items().into_iter().find(|item| item.name() == "add");
+        let item = item.unwrap();
+        ControlFlow::<(), _>::Continue((item.def_id(), item.ty()))
+    });
+    let Ok((def_id, ty)) = first else {
+        return Err("First compilation failed".to_string());
+    };
+
+    let stale = rustc_public::try_with(|| ty.kind()).map(|_| ());
+    check(
+        stale.as_ref().is_err_and(|err| *err.kind() == ErrorKind::Session),
+        format!("Expected a stale type to be rejected: {stale:?}"),
+    )?;
+
+    let second = run!(args, || {
+        let ty = rustc_public::try_with(|| ty.kind()).map(|_| ());
+        let def_id = rustc_public::try_with(|| def_id.name());
+        // The session itself should remain usable.
+        let items = rustc_public::all_local_items().len();
+        ControlFlow::<(), _>::Continue((ty, def_id, items))
+    });
+    let Ok((ty, def_id, items)) = second else {
+        return Err("Second compilation failed".to_string());
+    };
+    for result in [ty, def_id.map(|_| ())] {
+        check(
+            result.as_ref().is_err_and(|err| {
+                *err.kind() == ErrorKind::Session && err.to_string().contains("cannot be used in")
+            }),
+            format!("Expected a handle from another session to be rejected: {result:?}"),
+        )?;
+    }
+    check(items > 0, "Expected local items in the second compilation".to_string())
+}
+
+/// Test that stable identifiers can be used to find the same definitions in another compilation,
+/// unlike the handles of the compilation that created them.
+pub fn test_stable_ids(args: &[String]) -> TestResult {
+    let paths = || {
+        let local = rustc_public::all_local_items().into_iter().map(|item| item.def_id());
+        let core = rustc_public::find_crates("core").into_iter().flat_map(|krate| krate.fn_defs());
+        let defs = local.chain(core.take(10).map(|def| def.def_id()));
+        let paths = defs.map(|def_id| (def_id.stable_id(), def_id.name())).collect::<Vec<_>>();
+        ControlFlow::<(), _>::Continue(paths)
+    };
+    let first = run!(args, paths).map_err(|err| format!("First compilation failed: {err:?}"))?;
+    check(first.len() > 10, "Expected local and external definitions".to_string())?;
+    let found = run!(args, || {
+        let names = first.iter().map(|(stable_id, _)| stable_id.def_id().map(|def| def.name()));
+        ControlFlow::<(), _>::Continue(names.collect::<Vec<_>>())
+    })
+    .map_err(|err| format!("Second compilation failed: {err:?}"))?;
+    for ((stable_id, name), found) in first.iter().zip(found) {
+        let found = found.map_err(|err| format!("Cannot find `{name}` ({stable_id}): {err}"))?;
+        check_equal(&found, name, "Unexpected definition")?;
+    }
+    Ok(())
+}
+
+/// Test that the stable identifiers of a crate are the same in its library and test builds,
+/// which have different crate identifiers.
+pub fn test_stable_ids_across_builds() -> TestResult {
+    let local_ids = || {
+        let items = rustc_public::all_local_items().into_iter();
+        let ids = items.map(|item| (item.name(), item.stable_id())).collect::<Vec<_>>();
+        ControlFlow::<(), _>::Continue(ids)
+    };
+    let build = |flag: &str| {
+        Driver::new(["rustc", "--crate-name=stable_ids", "--edition=2024", flag])
+            .source("stable_ids.rs", STABLE_IDS_SOURCE)
+            .continue_compilation(false)
+            .callback(CompilerPhase::Analysis, local_ids)
+            .run()
+            .map_err(|err| format!("The `{flag}` build failed: {err:?}"))
+    };
+    let lib = build("--crate-type=rlib")?;
+    let test = build("--test")?;
+    check(lib.len() > 3, format!("Expected the library items: {lib:?}"))?;
+    for (name, stable_id) in &lib {
+        let found = test.iter().find(|(test_name, _)| test_name == name).map(|(_, id)| id);
+        check_equal(found, Some(stable_id), &format!("Unexpected id of `{name}` with `--test`"))?;
+    }
+    check(test.len() > lib.len(), format!("Expected the test items: {test:?}"))
+}
+
+/// A crate whose test build adds items to the library ones.
+const STABLE_IDS_SOURCE: &str = r#"
+    pub struct S(u32);
+    impl S {
+        pub fn get(&self) -> u32 { self.0 }
+    }
+    impl Clone for S {
+        fn clone(&self) -> S { S(self.0) }
+    }
+    pub fn add(a: u32, b: u32) -> u32 { a + b }
+    #[cfg(test)]
+    mod tests {
+        #[test]
+        fn add() { assert_eq!(super::add(1, 2), 3); }
+    }
+"#;
+
+/// Test that paths of local and external definitions resolve to the definitions with that path.
+pub fn test_resolve_path(args: &[String]) -> TestResult {
+    let result = run!(args, || ControlFlow::<(), _>::Continue(resolve_paths()));
+    result.map_err(|err| format!("Compilation failed: {err:?}"))?
+}
+
+fn resolve_paths() -> TestResult {
+    let local = rustc_public::local_crate().name;
+    let resolve = |path: &str| -> Result<DefPath, String> {
+        match rustc_public::resolve_path(path).as_slice() {
+            [def_id] => Ok(def_id.def_path()),
+            defs => Err(format!("Expected one definition for `{path}`: {defs:?}")),
+        }
+    };
+    let named = |name: &str| DefPathData::Named(name.to_string());
+    // Return the self type of an inherent impl segment.
+    let impl_self_ty = |segment: &DefPathSegment| match &segment.data {
+        DefPathData::Impl { self_ty, trait_def: None } => match self_ty.kind().rigid() {
+            Some(RigidTy::Adt(def, _)) => Some(def.trimmed_name()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let push = resolve("alloc::vec::Vec::push")?;
+    check_equal(push.krate.name.as_str(), "alloc", "Unexpected crate of `push`")?;
+    let [.., vec, push] = push.segments.as_slice() else {
+        return Err(format!("Unexpected path of `push`: {push:?}"));
+    };
+    check_equal(impl_self_ty(vec).as_deref(), Some("Vec"), "Unexpected impl of `push`")?;
+    check_equal(&push.data, &named("push"), "Unexpected name of `push`")?;
+
+    // Children of types include the items of their trait impls.
+    let clone = resolve("alloc::vec::Vec::clone")?;
+    let [.., vec, clone] = clone.segments.as_slice() else {
+        return Err(format!("Unexpected path of `clone`: {clone:?}"));
+    };
+    let DefPathData::Impl { trait_def: Some(trait_def), .. } = &vec.data else {
+        return Err(format!("Expected a trait impl: {vec:?}"));
+    };
+    check_equal(trait_def.trimmed_name().as_str(), "Clone", "Unexpected trait of `clone`")?;
+    check_equal(&clone.data, &named("clone"), "Unexpected name of `clone`")?;
+
+    let drop = resolve("core::ptr::drop_in_place")?;
+    check_equal(drop.krate.name.as_str(), "core", "Unexpected crate of `drop_in_place`")?;
+    let data = drop.segments.iter().map(|segment| &segment.data).collect::<Vec<_>>();
+    check_equal(data, vec![&named("ptr"), &named("drop_in_place")], "Unexpected path")?;
+
+    let get = resolve(&format!("{local}::S::get"))?;
+    check_equal(&get.krate.name, &local, "Unexpected crate of `get`")?;
+    let [s, get] = get.segments.as_slice() else {
+        return Err(format!("Unexpected path of `get`: {get:?}"));
+    };
+    check_equal(impl_self_ty(s).as_deref(), Some("S"), "Unexpected impl of `get`")?;
+    check_equal(&get.data, &named("get"), "Unexpected name of `get`")?;
+
+    let missing = rustc_public::resolve_path(&format!("{local}::S::missing"));
+    check(missing.is_empty(), format!("Unexpected definitions: {missing:?}"))
+}
+
+/// Test that internal compiler errors triggered by a request are returned as errors, and that
+/// the compilation can still be inspected afterwards.
+pub fn test_internal_errors() -> TestResult {
+    let source = include_str!("../../rustc_public/tests/fixme/ice_too_generic.rs");
+    let result = Driver::new(["rustc", "--crate-name=ice_too_generic", "-Copt-level=1"])
+        .source("ice_too_generic.rs", source)
+        .continue_compilation(false)
+        .callback(CompilerPhase::Analysis, || {
+            let mut internal = 0;
+            let mut bodies = 0;
+            for item in rustc_public::all_local_items() {
+                match item.try_body() {
+                    Ok(_) => bodies += 1,
+                    Err(error) if *error.kind() == ErrorKind::Internal => {
+                        internal += 1;
+                        // The error should describe where the compiler panicked.
+                        if !error.message().contains(".rs:") {
+                            return ControlFlow::Break(format!("Expected a location: {error}"));
+                        }
+                        // The request should fail the same way, and the session remain usable.
+                        let again = item.try_body().map(|_| ());
+                        if !again.as_ref().is_err_and(|err| *err.kind() == ErrorKind::Internal) {
+                            return ControlFlow::Break(format!(
+                                "Expected the same error: {again:?}"
+                            ));
+                        }
+                        let _ = (item.name(), item.ty().kind(), item.span().get_lines());
+                    }
+                    Err(error) => return ControlFlow::Break(format!("Unexpected error: {error}")),
+                }
+            }
+            ControlFlow::Continue((internal, bodies, rustc_public::all_local_items().len()))
+        })
+        .run();
+    let Ok((internal, bodies, items)) = result else {
+        return Err(format!("Unexpected result: {result:?}"));
+    };
+    check(internal > 0, "Expected an internal error".to_string())?;
+    check(bodies > 0, "Expected other bodies to be retrieved".to_string())?;
+    check_equal(internal + bodies, items, "Every item should be visited")
+}
+
+/// Test that a crate snapshot answers queries the same way the compiler does, and that it
+/// rejects the queries whose answer wasn't recorded.
+pub fn test_offline_snapshot(args: &[String]) -> TestResult {
+    use rustc_public::export::{SCHEMA_VERSION, write_crate_json};
+    use rustc_public::offline;
+
+    let result = run!(args, || {
+        let mut json = vec![];
+        write_crate_json(&mut json).unwrap();
+        ControlFlow::<(), _>::Continue((json, item_answers()))
+    });
+    let Ok((json, online)) = result else {
+        return Err(format!("Export failed: {:?}", result.map(|_| ())));
+    };
+    let snapshot = offline::from_reader(json.as_slice()).map_err(|err| err.to_string())?;
+    let offline = snapshot.run(item_answers).map_err(|err| err.to_string())?;
+    check(!online.is_empty(), "Expected local items".to_string())?;
+    check_equal(offline, online, "Snapshot answers should match the compiler")?;
+
+    let unsupported = snapshot
+        .run(|| {
+            let item = rustc_public::all_local_items()[0];
+            rustc_public::try_with(|| item.def_id().def_path()).map(|_| ())
+        })
+        .map_err(|err| err.to_string())?;
+    check(
+        unsupported.as_ref().is_err_and(|err| *err.kind() == ErrorKind::Unsupported),
+        format!("Expected `def_path` to be unsupported: {unsupported:?}"),
+    )?;
+
+    let current = format!("\"schema_version\":{SCHEMA_VERSION}");
+    let json = String::from_utf8(json).unwrap();
+    check(json.contains(&current), "Expected the schema version".to_string())?;
+    let outdated = json.replace(&current, &format!("\"schema_version\":{}", SCHEMA_VERSION + 1));
+    let error = offline::from_reader(outdated.as_bytes()).map(|_| ());
+    check(
+        error.as_ref().is_err_and(|err| *err.kind() == ErrorKind::Unsupported),
+        format!("Expected a schema mismatch to be rejected: {error:?}"),
+    )
+}
+
+/// Information about a local item that is recorded in crate snapshots.
+#[derive(Debug, PartialEq)]
+struct ItemAnswers {
+    name: String,
+    kind: String,
+    ty: String,
+    size: Option<usize>,
+    /// The types of the body locals, and the number of statements of each basic block.
+    body: Option<(Vec<String>, Vec<usize>)>,
+}
+
+fn item_answers() -> Vec<ItemAnswers> {
+    let items = rustc_public::all_local_items();
+    items
+        .into_iter()
+        .map(|item| {
+            let ty = item.ty();
+            let body = item.try_body().ok().flatten().map(|body| {
+                let locals = body.locals().iter().map(|local| format!("{:?}", local.ty.kind()));
+                let blocks = body.blocks.iter().map(|block| block.statements.len());
+                (locals.collect(), blocks.collect())
+            });
+            ItemAnswers {
+                name: item.name(),
+                kind: format!("{:?}", item.kind()),
+                ty: format!("{:?}", ty.kind()),
+                size: ty.layout().ok().map(|layout| layout.shape().size.bytes()),
+                body,
+            }
+        })
+        .collect()
+}
+
+/// Test that the MIR printed by the `print` tests can be parsed back, and that dumping the parsed
+/// bodies prints the same text.
+///
+/// Those tests stop the compilation after printing the MIR, so they never reach the analysis.
+pub fn test_print_round_trip() -> TestResult {
+    macro_rules! fixture {
+        ($name:literal) => {
+            (
+                $name,
+                include_str!(concat!("../../rustc_public/tests/print/", $name, ".rs")),
+                include_str!(concat!("../../rustc_public/tests/print/", $name, ".stdout")),
+            )
+        };
+    }
+    let fixtures = [fixture!("async-closure"), fixture!("basic_function"), fixture!("operands")];
+    for (name, source, printed) in fixtures {
+        // The test normalizes the directory of the source file.
+        let printed = printed.replace("$DIR/", "");
+        // Build the crate the same way the test does, without printing its MIR.
+        let mut args =
+            vec!["rustc".to_string(), format!("--crate-name={}", name.replace('-', "_"))];
+        for line in source.lines() {
+            if let Some(flags) = line.strip_prefix("//@ compile-flags:") {
+                let flags = flags.replace("-Z unpretty=stable-mir", "");
+                args.extend(flags.split_whitespace().map(str::to_string));
+            } else if let Some(edition) = line.strip_prefix("//@ edition:") {
+                args.push(format!("--edition={}", edition.trim()));
+            }
+        }
+        let result = Driver::new(args)
+            .source(format!("{name}.rs"), source)
+            .continue_compilation(false)
+            .callback(CompilerPhase::Analysis, || {
+                let dumped = rustc_public::mir::parse::parse_bodies(&printed).map(|bodies| {
+                    let mut dumped = vec![];
+                    for (fn_name, body) in bodies {
+                        body.dump(&mut dumped, &fn_name).unwrap();
+                    }
+                    String::from_utf8(dumped).unwrap()
+                });
+                ControlFlow::<(), _>::Continue(dumped)
+            })
+            .run();
+        let dumped = match result {
+            Ok(dumped) => dumped.map_err(|err| format!("Cannot parse `{name}.stdout`: {err}"))?,
+            Err(err) => return Err(format!("Compilation of `{name}.rs` failed: {err:?}")),
+        };
+        let expected = printed.lines().filter(|line| !line.starts_with("//"));
+        check_equal(
+            dumped.lines().collect::<Vec<_>>(),
+            expected.collect(),
+            &format!("Unstable MIR round-trip of `{name}.stdout`"),
+        )?;
+    }
+    Ok(())
+}
+
+/// Include one of the `sanity-checks/simple` test crates, together with its name.
+macro_rules! simple_fixture {
+    ($name:literal) => {
+        (
+            $name,
+            include_str!(concat!("../../rustc_public/tests/sanity-checks/simple/", $name, ".rs")),
+        )
+    };
+}
+
+/// Compile a test crate and run `callback` on its local item named `item_name`.
+fn with_fixture_item<T: Send>(
+    (crate_name, source): (&str, &str),
+    item_name: &str,
+    callback: impl FnOnce(rustc_public::CrateItem) -> T + Send,
+) -> Result<T, String> {
+    let result = rustc_public::run_on_source(source, crate_name, ["--crate-type=lib"], || {
+        let items = rustc_public::all_local_items();
+        let item = items.into_iter().find(|item| item.name() == item_name);
+        ControlFlow::<(), _>::Continue(item.map(callback))
+    });
+    match result {
+        Ok(Some(output)) => Ok(output),
+        Ok(None) => Err(format!("Missing `{item_name}` in `{crate_name}.rs`")),
+        Err(err) => Err(format!("Compilation of `{crate_name}.rs` failed: {err:?}")),
+    }
+}
+
+/// Test that the pretty printer options change how locals and paths are printed.
+pub fn test_pretty_config_output() -> TestResult {
+    use rustc_public::mir::{PathStyle, PrettyConfig};
+    let outputs = with_fixture_item(simple_fixture!("branches"), "sum_until", |item| {
+        let body = item.expect_body();
+        [PathStyle::Full, PathStyle::Trimmed, PathStyle::NameOnly].map(|paths| {
+            let config = PrettyConfig {
+                local_types: true,
+                var_names: true,
+                elide_storage_markers: true,
+                paths,
+                ..Default::default()
+            };
+            let mut output = vec![];
+            body.dump_with(&config, &mut output, &item.name()).unwrap();
+            String::from_utf8(output).unwrap()
+        })
+    })?;
+    let [full, trimmed, name_only] = outputs;
+    for output in [&full, &trimmed, &name_only] {
+        for line in [
+            "    debug total => _3;",
+            "        (total: u32) = 0_u32;",
+            "        (_10: bool) = Gt(move (_11: u32), (limit: u32));",
+        ] {
+            check(output.contains(line), format!("Expected `{line}` in:\n{output}"))?;
+        }
+    }
+    let expected = [
+        (
+            &full,
+            "        (_6: std::option::Option<&u32>) = <std::slice::Iter<'_, u32> as \
+             std::iter::Iterator>::next((_7: &mut std::slice::Iter<'_, u32>)) -> [return: bb3, \
+             unwind continue];",
+        ),
+        (
+            &trimmed,
+            "        (_6: Option<&u32>) = <Iter<'_, u32> as Iterator>::next((_7: &mut Iter<'_, \
+             u32>)) -> [return: bb3, unwind continue];",
+        ),
+        (
+            &full,
+            "        (_12: u32) = core::num::<impl u32>::saturating_add(move (_13: u32), move \
+             (_14: u32)) -> [return: bb7, unwind continue];",
+        ),
+        (
+            &trimmed,
+            "        (_12: u32) = core::num::<impl u32>::saturating_add(move (_13: u32), move \
+             (_14: u32)) -> [return: bb7, unwind continue];",
+        ),
+        (
+            &name_only,
+            "        (_12: u32) = u32::saturating_add(move (_13: u32), move (_14: u32)) -> \
+             [return: bb7, unwind continue];",
+        ),
+    ];
+    for (output, line) in expected {
+        check(output.contains(line), format!("Expected `{line}` in:\n{output}"))?;
+    }
+    Ok(())
+}
+
+/// Test that graph edges are labelled by their kind of control flow, and that cleanup blocks
+/// are filled.
+pub fn test_graphviz_output() -> TestResult {
+    let write_dot = |item: rustc_public::CrateItem| {
+        let mut output = vec![];
+        item.expect_body().write_dot(&mut output, &Default::default()).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let output = with_fixture_item(simple_fixture!("branches"), "classify", write_dot)?;
+    let edges = output.lines().filter(|line| line.contains(" -> ")).collect::<Vec<_>>();
+    check_equal(
+        edges,
+        vec![
+            "    bb0 -> bb4 [label=\"0\"];",
+            "    bb0 -> bb3 [label=\"1\"];",
+            "    bb0 -> bb3 [label=\"2\"];",
+            "    bb0 -> bb2 [label=\"10\"];",
+            "    bb0 -> bb1 [label=\"otherwise\"];",
+            "    bb1 -> bb5 [label=\"goto\"];",
+            "    bb2 -> bb5 [label=\"goto\"];",
+            "    bb3 -> bb5 [label=\"goto\"];",
+            "    bb4 -> bb5 [label=\"goto\"];",
+        ],
+        "Unexpected edges of `classify`",
+    )?;
+
+    let output = with_fixture_item(simple_fixture!("branches"), "parse_all", write_dot)?;
+    for line in [
+        "    bb1 -> bb2 [label=\"return\"];",
+        "    bb1 -> bb10 [label=\"unwind\", style=\"dashed\"];",
+        "    bb10 -> bb11 [label=\"return\"];",
+    ] {
+        check(output.contains(line), format!("Expected `{line}` in:\n{output}"))?;
+    }
+    let filled = output
+        .lines()
+        .filter(|line| line.ends_with(", style=\"filled\", fillcolor=\"lightblue\"];"))
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect::<Vec<_>>();
+    check_equal(filled, vec!["bb10", "bb11"], "Unexpected cleanup blocks of `parse_all`")
+}
+
+/// Test that the source listing groups statements under the source lines they come from.
+pub fn test_listing_output() -> TestResult {
+    let output = with_fixture_item(simple_fixture!("branches"), "classify", |item| {
+        let mut output = vec![];
+        item.emit_mir_with_source(&mut output, &Default::default()).unwrap();
+        String::from_utf8(output).unwrap()
+    })?;
+    let expected = r#"fn classify(_1: u8) -> &str {
+    let mut _0: &str;
+    debug value => _1;
+    // branches.rs
+    //  6 |     match value {
+        bb0[0]: switchInt(_1) -> [0: bb4, 1: bb3, 2: bb3, 10: bb2, otherwise: bb1];
+    //  7 |         0 => "zero",
+        bb4[0]: _0 = "zero";
+        bb4[1]: goto -> bb5;
+    //  8 |         1 | 2 => "small",
+        bb3[0]: _0 = "small";
+        bb3[1]: goto -> bb5;
+    //  9 |         10 => "ten",
+        bb2[0]: _0 = "ten";
+        bb2[1]: goto -> bb5;
+    // 10 |         _ => "other",
+        bb1[0]: _0 = "other";
+        bb1[1]: goto -> bb5;
+    // 12 | }
+        bb5[0]: return;
+}
+"#;
+    check_equal(output.as_str(), expected, "Unexpected listing of `classify`")
+}
+
+/// Test that statics are dumped with the allocations they point to, and decoded as values of
+/// their type.
+pub fn test_alloc_dump_output() -> TestResult {
+    let dump = |name: &str| {
+        with_fixture_item(simple_fixture!("statics"), name, |item| {
+            let def = rustc_public::mir::mono::StaticDef::try_from(item).unwrap();
+            let alloc = def.eval_initializer().unwrap();
+            let mut output = vec![];
+            alloc.dump(&mut output).unwrap();
+            let mut typed = vec![];
+            alloc.dump_typed(def.ty(), &mut typed).unwrap();
+            (String::from_utf8(output).unwrap(), String::from_utf8(typed).unwrap())
+        })
+    };
+    let expected = [
+        (
+            "ORIGIN",
+            r#"alloc (size: 8, align: 4) {
+    0x00 │ fd ff ff ff 07 __ __ __                         │ .....░░░
+}
+"#,
+            "Point { x: -3_i32, y: 7_u8 }\n",
+        ),
+        (
+            "NESTED",
+            r#"alloc (size: 8, align: 8) {
+    0x00 │ ╾───────alloc0────────╼                         │ ╾──────╼
+}
+alloc0 (size: 8, align: 8) {
+    0x00 │ ╾───────alloc1────────╼                         │ ╾──────╼
+}
+alloc1 (size: 16, align: 8) {
+    0x00 │ ╾───────alloc2────────╼ 04 00 00 00 00 00 00 00 │ ╾──────╼........
+}
+alloc2 (size: 4, align: 1) {
+    0x00 │ 64 65 65 70                                     │ deep
+}
+"#,
+            "&&&\"deep\"\n",
+        ),
+        (
+            "FN_PTR",
+            r#"alloc (size: 8, align: 8) {
+    0x00 │ ╾───────alloc0────────╼                         │ ╾──────╼
+}
+alloc0 (fn: one)
+"#,
+            "fn one\n",
+        ),
+        (
+            "TO_STATIC",
+            r#"alloc (size: 8, align: 8) {
+    0x00 │ ╾───────alloc0────────╼                         │ ╾──────╼
+}
+alloc0 (static: ORIGIN)
+"#,
+            "&ORIGIN\n",
+        ),
+    ];
+    for (name, bytes, typed) in expected {
+        let (found_bytes, found_typed) = dump(name)?;
+        check_equal(found_bytes.as_str(), bytes, &format!("Unexpected dump of `{name}`"))?;
+        check_equal(found_typed.as_str(), typed, &format!("Unexpected value of `{name}`"))?;
+    }
+    Ok(())
+}
+
+/// Test that folding the type of a constant value that the compiler cannot rebuild from its
+/// bytes keeps the value.
+pub fn test_fold_const_value() -> TestResult {
+    use rustc_public::fold::{Foldable, TypeFolder};
+    use rustc_public::mir::alloc::GlobalAlloc;
+    use rustc_public::ty::{GenericArgKind, Ty, TyConstKind, TyKind, UintTy};
+
+    struct StrToBytes;
+    impl TypeFolder for StrToBytes {
+        fn fold_ty(&mut self, ty: &Ty) -> Ty {
+            match ty.kind() {
+                TyKind::RigidTy(RigidTy::Str) => {
+                    Ty::from_rigid_kind(RigidTy::Slice(Ty::unsigned_ty(UintTy::U8)))
+                }
+                _ => ty.super_fold(self),
+            }
+        }
+    }
+
+    let source = (
+        "const_value",
+        "#![feature(adt_const_params, unsized_const_params)]
+         #![allow(incomplete_features)]
+         pub struct Tag<const S: &'static str>;
+         pub fn tagged() -> Tag<\"hi\"> { Tag }",
+    );
+    let folded = with_fixture_item(source, "tagged", |item| {
+        let output = item.ty().kind().fn_sig().unwrap().skip_binder().output();
+        let TyKind::RigidTy(RigidTy::Adt(_, args)) = output.fold(&mut StrToBytes).kind() else {
+            return Err(format!("Unexpected return type `{output}`"));
+        };
+        let [GenericArgKind::Const(cnst)] = &args.0[..] else {
+            return Err(format!("Unexpected arguments `{args:?}`"));
+        };
+        let TyConstKind::Value(ty, alloc) = cnst.kind() else {
+            return Err(format!("Expected a constant value, found `{cnst:?}`"));
+        };
+        // The value is a reference to the bytes of the string.
+        let [(_, prov)] = &alloc.provenance.ptrs[..] else {
+            return Err(format!("Expected a reference, found `{alloc:?}`"));
+        };
+        let GlobalAlloc::Memory(bytes) = GlobalAlloc::from(prov.0) else {
+            return Err(format!("Expected memory behind `{alloc:?}`"));
+        };
+        Ok((ty.to_string(), bytes.raw_bytes().unwrap()))
+    })??;
+    check_equal(
+        folded,
+        ("&[u8]".to_string(), b"hi".to_vec()),
+        "Unexpected type or value of the folded constant",
+    )
+}
+
+/// Test that compiler errors are collected with their error code, and that they are not
+/// reported as internal compiler errors.
+pub fn test_compiler_errors() -> TestResult {
+    let compilation = Driver::new(["rustc", "--crate-name=mismatch", "--crate-type=lib"])
+        .source("mismatch.rs", "pub fn answer() -> u32 { \"42\" }")
+        .continue_compilation(false)
+        .callback(CompilerPhase::Analysis, || ControlFlow::<()>::Continue(()))
+        .run_with_diagnostics();
+    check(
+        matches!(compilation.result, Err(CompilerError::Failed)),
+        format!("Expected compilation to fail: {:?}", compilation.result),
+    )?;
+    check(!compilation.is_ice(), format!("Unexpected ICE: {:?}", compilation.diagnostics))?;
+    let error = compilation.errors().next().ok_or("Expected an error")?;
+    check_equal(error.code.as_deref(), Some("E0308"), "Unexpected error code")?;
+    check(!error.is_ice(), format!("Expected a regular error: {error:?}"))?;
+    check(
+        error.location.as_ref().is_some_and(|loc| loc.filename == "mismatch.rs"),
+        format!("Expected the error location: {error:?}"),
+    )
+}
+
+/// Test that in-memory sources can be compiled, including modules provided as virtual files.
+pub fn test_in_memory_source() -> TestResult {
+    let source = "pub mod math; pub fn zero() -> u32 { 0 }";
+    let result = rustc_public::run_on_source(source, "in_memory", ["--crate-type=lib"], || {
+        let items = rustc_public::all_local_items();
+        let names = items.iter().map(|item| (item.name(), item.span().get_filename()));
+        ControlFlow::<(), _>::Continue(names.collect::<Vec<_>>())
+    });
+    check_equal(
+        result,
+        Err(CompilerError::Failed),
+        "Expected missing module to fail the compilation",
+    )?;
+
+    let result = Driver::new(["rustc", "--crate-name=in_memory", "--crate-type=lib"])
+        .source("in_memory.rs", source)
+        .virtual_file("math.rs", "pub fn one() -> u32 { 1 }")
+        .continue_compilation(false)
+        .callback(CompilerPhase::Analysis, || {
+            let items = rustc_public::all_local_items();
+            let names = items.iter().map(|item| (item.name(), item.span().get_filename()));
+            ControlFlow::<(), _>::Continue(names.collect::<Vec<_>>())
+        })
+        .run();
+    check_equal(
+        result,
+        Ok(vec![
+            ("math::one".to_string(), "math.rs".to_string()),
+            ("zero".to_string(), "in_memory.rs".to_string()),
+        ]),
+        "Unexpected items",
+    )
+}
+
+static TOOL_LINT: Lint =
+    Lint::new("test_drive", "tool_lint", LintLevel::Warn, "lint used to test tool lints");
+
+/// The items of the crate used to test tool lints, and the lint attributes that apply to them.
+const TOOL_LINTS_SOURCE: &str = r#"
+    #[allow(test_drive::tool_lint)]
+    pub fn allowed() {}
+    #[deny(test_drive::tool_lint)]
+    pub fn denied() {}
+    pub fn warned() {}
+"#;
+
+/// The outcome of compiling [TOOL_LINTS_SOURCE] with [TOOL_LINT] reported for every item.
+struct LintRun {
+    compilation: CompilationResult<(), ()>,
+    /// The lint level of each item.
+    levels: Vec<(String, Option<LintLevel>)>,
+    /// Whether the lint level of an external item was rejected.
+    external_rejected: bool,
+}
+
+impl LintRun {
+    /// Compile the crate with the given extra compiler flags.
+    fn new(flags: &[&str]) -> LintRun {
+        let levels = Mutex::new(vec![]);
+        let external_rejected = AtomicBool::new(false);
+        let args = ["rustc", "--crate-name=tool_lints", "--crate-type=lib"];
+        let compilation = Driver::new(args.iter().chain(flags).copied())
+            .source("tool_lints.rs", TOOL_LINTS_SOURCE)
+            .continue_compilation(false)
+            .lint(&TOOL_LINT)
+            .callback(CompilerPhase::Analysis, || {
+                let mut levels = levels.lock().unwrap();
+                for item in rustc_public::all_local_items() {
+                    levels.push((item.name(), TOOL_LINT.level(item.def_id()).ok()));
+                    let diagnostic = Diagnostic::warning(format!("found `{}`", item.name()));
+                    TOOL_LINT.report(item.def_id(), diagnostic).unwrap();
+                }
+                // Lint levels are only tracked for items of the local crate.
+                let external = rustc_public::resolve_path("core::ptr::drop_in_place")[0];
+                let rejected = TOOL_LINT
+                    .level(external)
+                    .is_err_and(|err| matches!(err.kind(), ErrorKind::UnexpectedItem(_)));
+                external_rejected.store(rejected, Ordering::Relaxed);
+                ControlFlow::<()>::Continue(())
+            })
+            .run_with_diagnostics();
+        LintRun {
+            compilation,
+            levels: levels.into_inner().unwrap(),
+            external_rejected: external_rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Check the level of each item, and the level of the lints that were emitted.
+    fn check(&self, levels: [LintLevel; 3], emitted: &[(Level, &str)]) -> TestResult {
+        let names = ["allowed", "denied", "warned"].map(String::from);
+        let expected: Vec<_> = zip(names, levels.map(Some)).collect();
+        check_equal(&self.levels, &expected, "Unexpected lint levels")?;
+        let found: Vec<_> = self
+            .compilation
+            .diagnostics
+            .iter()
+            .filter(|diag| diag.message.starts_with("found"))
+            .map(|diag| (diag.level, diag.message.as_str()))
+            .collect();
+        check_equal(found.as_slice(), emitted, "Unexpected lints")
+    }
+}
+
+/// Test that the level of tool lints is resolved using lint attributes, and that only lints
+/// that aren't allowed are emitted.
+pub fn test_lints() -> TestResult {
+    let run = LintRun::new(&[]);
+    check(
+        matches!(run.compilation.result, Err(CompilerError::Failed)),
+        format!("Expected the denied lint to fail the compilation: {:?}", run.compilation.result),
+    )?;
+    check(run.external_rejected, "Expected external items to be rejected".to_string())?;
+    run.check(
+        [LintLevel::Allow, LintLevel::Deny, LintLevel::Warn],
+        &[(Level::Error, "found `denied`"), (Level::Warning, "found `warned`")],
+    )
+}
+
+/// Test that the `-D` and `-A` flags set the level of tool lints, unless a lint attribute
+/// overrides it.
+pub fn test_lint_flags() -> TestResult {
+    let denied = LintRun::new(&["-D", "test_drive::tool_lint"]);
+    denied.check(
+        [LintLevel::Allow, LintLevel::Deny, LintLevel::Deny],
+        &[(Level::Error, "found `denied`"), (Level::Error, "found `warned`")],
+    )?;
+    let allowed = LintRun::new(&["-Atest_drive::tool_lint"]);
+    allowed.check(
+        [LintLevel::Allow, LintLevel::Deny, LintLevel::Allow],
+        &[(Level::Error, "found `denied`")],
+    )?;
+    let allow_all = LintRun::new(&["-A", "test_drive::tool_lint", "--cap-lints=allow"]);
+    check(
+        allow_all.compilation.result.is_ok(),
+        format!("Expected no lint to fail the compilation: {:?}", allow_all.compilation.result),
+    )?;
+    allow_all.check([LintLevel::Allow; 3], &[])
+}
@@ -3,12 +3,10 @@
 #![feature(rustc_private)]
 #![feature(assert_matches)]
 
+mod driver_checks;
+mod mock_checks;
 mod sanity_checks;
 
-extern crate rustc_driver;
-extern crate rustc_interface;
-extern crate rustc_middle;
-
 use std::ops::ControlFlow;
 use std::panic::{AssertUnwindSafe, catch_unwind};
 use std::process::ExitCode;
@@ -22,6 +20,8 @@ const CHECK_ARG: &str = "--smir-check";
 const VERBOSE_ARG: &str = "--smir-verbose";
 /// Argument used to enable checks that may be failing due to an existing issue.
 const FIXME_ARG: &str = "--smir-fixme";
+/// Argument used to enable checks that start their own compilations or mock the compiler.
+const DRIVER_ARG: &str = "--smir-driver";
 
 // Use a static variable for simplicity.
 static VERBOSE: AtomicBool = AtomicBool::new(false);
@@ -40,7 +40,20 @@ fn main() -> ExitCode {
     let result = if smir_args.contains(&CHECK_ARG.to_string()) {
         VERBOSE.store(smir_args.contains(&VERBOSE_ARG.to_string()), Ordering::Relaxed);
         FIXME_CHECKS.store(smir_args.contains(&FIXME_ARG.to_string()), Ordering::Relaxed);
-        run!(&rustc_args, test_stable_mir)
+        let result = run!(&rustc_args, {
+            after_expansion: test_expansion,
+            after_analysis: test_stable_mir,
+            after_codegen: test_codegen,
+        });
+        if result.is_ok() && smir_args.contains(&DRIVER_ARG.to_string()) {
+            // These checks cannot run while the main compilation is in progress.
+            match test_driver(&rustc_args) {
+                ControlFlow::Continue(()) => result,
+                ControlFlow::Break(()) => Err(CompilerError::Failed),
+            }
+        } else {
+            result
+        }
     } else {
         run!(&rustc_args, || ControlFlow::<()>::Continue(()))
     };
@@ -57,6 +70,11 @@ macro_rules! run_tests {
             run_test(stringify!($test), || { $test() })
         },)+]
     };
+    ($args:expr => $( $test:path ),+ $(,)?) => {
+        [$({
+            run_test(stringify!($test), || { $test($args) })
+        },)+]
+    };
 }
 
 fn info(msg: String) {
@@ -66,18 +84,74 @@ fn info(msg: String) {
     }
 }
 
+/// Tests that are invoked right after macro expansion.
+fn test_expansion() -> ControlFlow<()> {
+    process_results(Vec::from(run_tests![sanity_checks::test_crates]))
+}
+
 /// This function invoke other tests and process their results.
 /// Tests should avoid panic,
 fn test_stable_mir() -> ControlFlow<()> {
     let mut results = Vec::from(run_tests![
         sanity_checks::test_entry_fn,
         sanity_checks::test_all_fns,
-        sanity_checks::test_crates,
-        sanity_checks::test_instances,
+        sanity_checks::test_mir_parse,
+        sanity_checks::test_mir_pretty_config,
+        sanity_checks::test_mir_graphviz,
+        sanity_checks::test_mir_listing,
+        sanity_checks::test_alloc_dump,
+        sanity_checks::test_place_contexts,
+        sanity_checks::test_try_visitor,
+        sanity_checks::test_type_folder,
+        sanity_checks::test_dataflow,
+        sanity_checks::test_error_kinds,
+        sanity_checks::test_export,
+        sanity_checks::test_stable_ids,
     ]);
     if FIXME_CHECKS.load(Ordering::Relaxed) {
         results.extend_from_slice(&run_tests!(sanity_checks::test_traits))
     }
+    process_results(results)
+}
+
+/// Tests that are invoked after code generation.
+fn test_codegen() -> ControlFlow<()> {
+    process_results(Vec::from(run_tests![sanity_checks::test_instances]))
+}
+
+/// Tests that run their own compilations, which may use the same arguments as the main compilation,
+/// or that run against a mock of the compiler.
+fn test_driver(args: &[String]) -> ControlFlow<()> {
+    let mut results = Vec::from(run_tests![args =>
+        driver_checks::test_phase_break,
+        driver_checks::test_stop_after_codegen,
+        driver_checks::test_diagnostics,
+        driver_checks::test_sequential_compilations,
+        driver_checks::test_cross_session_handles,
+        driver_checks::test_offline_snapshot,
+        driver_checks::test_stable_ids,
+        driver_checks::test_resolve_path,
+    ]);
+    results.extend_from_slice(&run_tests![
+        driver_checks::test_lints,
+        driver_checks::test_lint_flags,
+        driver_checks::test_stable_ids_across_builds,
+        driver_checks::test_internal_errors,
+        driver_checks::test_print_round_trip,
+        driver_checks::test_pretty_config_output,
+        driver_checks::test_graphviz_output,
+        driver_checks::test_listing_output,
+        driver_checks::test_alloc_dump_output,
+        driver_checks::test_fold_const_value,
+        driver_checks::test_compiler_errors,
+        driver_checks::test_in_memory_source,
+        mock_checks::test_mock_call,
+        mock_checks::test_mock_consts,
+    ]);
+    process_results(results)
+}
+
+fn process_results(results: Vec<TestResult>) -> ControlFlow<()> {
     let (success, failure): (Vec<_>, Vec<_>) = results.iter().partition(|r| r.is_ok());
     info(format!(
         "Ran {} tests. {} succeeded. {} failed",
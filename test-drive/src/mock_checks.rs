@@ -0,0 +1,101 @@
+//! Module that contains checks that run against a mock of the compiler.
+//!
+//! Mock contexts start their own session, so these checks cannot be invoked from a compiler
+//! callback either.
+use rustc_public::mir::mono::Instance;
+use rustc_public::mir::{
+    BasicBlock, Body, ConstOperand, LocalDecl, Mutability, Operand, Place, RETURN_LOCAL,
+    Terminator, TerminatorKind, UnwindAction,
+};
+use rustc_public::testing::{MockContext, with_mock};
+use rustc_public::ty::{
+    Abi, ConstantKind, FnDef, FnSig, GenericArgs, LineInfo, MirConst, RigidTy, UintTy,
+};
+use rustc_public::{CrateDef, ErrorKind, mir};
+
+use crate::TestResult;
+use crate::sanity_checks::{check, check_equal};
+
+/// Test that a mock body can call a function with a literal argument, and that the call can be
+/// inspected by the code under test.
+pub fn test_mock_call() -> TestResult {
+    let mut ctx = MockContext::new("mock");
+    let u32_ty = ctx.rigid_ty(RigidTy::Uint(UintTy::U32));
+    let span = ctx.span("lib.rs", LineInfo::from((1, 1, 3, 2)));
+    let sig = |inputs_and_output| FnSig {
+        inputs_and_output,
+        c_variadic: false,
+        safety: mir::Safety::Safe,
+        abi: Abi::Rust,
+    };
+    let double = ctx.function("double", sig(vec![u32_ty, u32_ty]));
+    let main = ctx.function("main", sig(vec![u32_ty]));
+    ctx.item_span(main, span);
+
+    let constant =
+        |const_: MirConst| Operand::Constant(ConstOperand { span, user_ty: None, const_ });
+    let call = TerminatorKind::Call {
+        func: constant(ctx.fn_def_const(double)),
+        args: vec![constant(ctx.scalar_const(RigidTy::Uint(UintTy::U32), 21))],
+        destination: Place::from(RETURN_LOCAL),
+        target: Some(1),
+        unwind: UnwindAction::Continue,
+    };
+    let blocks = [call, TerminatorKind::Return]
+        .map(|kind| BasicBlock { statements: vec![], terminator: Terminator { kind, span } });
+    let locals = vec![LocalDecl { ty: u32_ty, span, mutability: Mutability::Mut }];
+    ctx.body(main, Body::new(blocks.to_vec(), locals, 0, vec![], None, span));
+
+    with_mock(&ctx, || {
+        let entry = rustc_public::entry_fn().ok_or("Expected an entry function")?;
+        check_equal(entry.name(), "main".to_string(), "Unexpected entry function")?;
+        let body = entry.body().ok_or("Expected a body")?;
+        let TerminatorKind::Call { func, args, .. } = &body.blocks[0].terminator.kind else {
+            return Err(format!("Expected a call: {:?}", body.blocks[0].terminator));
+        };
+        let func_ty = func.ty(body.locals()).map_err(|err| err.to_string())?;
+        let callee = func_ty.kind().fn_def().map(|(def, _)| def);
+        check_equal(callee, Some(double), "Unexpected callee")?;
+        let Operand::Constant(arg) = &args[0] else {
+            return Err(format!("Expected a literal argument: {:?}", args[0]));
+        };
+        check_equal(arg.ty(), u32_ty, "Unexpected argument type")?;
+        check_equal(read_uint(&arg.const_), Some(21), "Unexpected argument")?;
+
+        let instance = Instance::resolve(double, &GenericArgs(vec![]));
+        let instance = instance.map_err(|err| err.to_string())?;
+        check_equal(FnDef(instance.def.def_id()), double, "Unexpected instance")?;
+        check(instance.body().is_none(), "The callee has no body".to_string())
+    })
+    .map_err(|err| err.to_string())?
+}
+
+/// Test that the code under test can create constants in a mock context.
+pub fn test_mock_consts() -> TestResult {
+    let mut ctx = MockContext::new("mock");
+    let unit = ctx.rigid_ty(RigidTy::Tuple(vec![]));
+    let u8_ty = ctx.rigid_ty(RigidTy::Uint(UintTy::U8));
+    with_mock(&ctx, || {
+        check_equal(read_uint(&MirConst::from_bool(true)), Some(1), "Unexpected boolean")?;
+        let max = MirConst::try_from_uint(255, UintTy::U8).map_err(|err| err.to_string())?;
+        check_equal(max.ty(), u8_ty, "Unexpected integer type")?;
+        check_equal(read_uint(&max), Some(255), "Unexpected integer")?;
+        let overflow = MirConst::try_from_uint(256, UintTy::U8);
+        check(overflow.is_err(), format!("Expected an overflow: {overflow:?}"))?;
+
+        let zst = MirConst::try_new_zero_sized(unit).map_err(|err| err.to_string())?;
+        check_equal(zst.kind(), &ConstantKind::ZeroSized, "Unexpected unit value")?;
+        let not_zst = MirConst::try_new_zero_sized(u8_ty).map_err(|err| err.kind().clone());
+        check(
+            matches!(not_zst, Err(ErrorKind::UnexpectedTy(ty)) if ty == u8_ty),
+            format!("Expected `u8` not to be zero-sized: {not_zst:?}"),
+        )?;
+        check(zst.id != max.id, "Constants should have distinct ids".to_string())
+    })
+    .map_err(|err| err.to_string())?
+}
+
+fn read_uint(cnst: &MirConst) -> Option<u128> {
+    let ConstantKind::Allocated(alloc) = cnst.kind() else { return None };
+    alloc.read_uint().ok()
+}
@@ -13,7 +13,7 @@ use rustc_public::{self, CrateDef, mir, ty};
 
 use crate::TestResult;
 
-fn check_equal<T>(val: T, expected: T, msg: &str) -> TestResult
+pub fn check_equal<T>(val: T, expected: T, msg: &str) -> TestResult
 where
     T: Debug + PartialEq,
 {
@@ -50,6 +50,777 @@ pub fn test_all_fns() -> TestResult {
     Ok(())
 }
 
+/// Test that the MIR of local functions can be parsed back from its textual representation.
+pub fn test_mir_parse() -> TestResult {
+    for (item, body) in local_bodies()? {
+        let name = item.name();
+        let mut expected = vec![];
+        body.dump(&mut expected, &name).unwrap();
+        let expected = String::from_utf8(expected).unwrap();
+        let parsed = mir::Body::parse(&expected)
+            .map_err(|error| format!("Failed to parse `{name}`: {error}\n{expected}"))?;
+        let mut found = vec![];
+        parsed.dump(&mut found, &name).unwrap();
+        check_equal(String::from_utf8(found).unwrap(), expected, "Unstable MIR round-trip")?;
+    }
+    Ok(())
+}
+
+/// Test that every `PrettyConfig` option can print the MIR of local functions.
+pub fn test_mir_pretty_config() -> TestResult {
+    use mir::{PathStyle, PrettyConfig};
+    for (item, body) in local_bodies()? {
+        let name = item.name();
+        let mut expected = vec![];
+        body.dump(&mut expected, &name).unwrap();
+        let mut found = vec![];
+        body.dump_with(&PrettyConfig::default(), &mut found, &name).unwrap();
+        check_equal(found, expected, "Default configuration differs from `dump`")?;
+
+        let mut lengths = vec![];
+        for paths in [PathStyle::Full, PathStyle::Trimmed, PathStyle::NameOnly] {
+            let config = PrettyConfig {
+                locations: true,
+                local_types: true,
+                var_names: true,
+                elide_storage_markers: true,
+                paths,
+            };
+            let mut output = vec![];
+            body.dump_with(&config, &mut output, &name).unwrap();
+            let output = String::from_utf8(output).unwrap();
+            check(
+                !output.contains("StorageLive(") && !output.contains("StorageDead("),
+                format!("Storage markers were not elided:\n{output}"),
+            )?;
+            let elided = body
+                .blocks
+                .iter()
+                .flat_map(|block| &block.statements)
+                .filter(|statement| {
+                    matches!(
+                        statement.kind,
+                        mir::StatementKind::StorageLive(_)
+                            | mir::StatementKind::StorageDead(_)
+                            | mir::StatementKind::Nop
+                    )
+                })
+                .count();
+            let locations: usize = body.blocks.iter().map(|block| block.statements.len() + 1).sum();
+            check_equal(
+                output.matches("  // bb").count(),
+                locations - elided,
+                "Unexpected number of location annotations",
+            )?;
+            lengths.push(output.len());
+        }
+        check(
+            lengths.is_sorted_by(|a, b| a >= b),
+            format!("Paths were not shortened: {lengths:?}"),
+        )?;
+    }
+    Ok(())
+}
+
+/// Test that the control-flow graph of local functions has one node per block and one edge per
+/// successor.
+pub fn test_mir_graphviz() -> TestResult {
+    /// Collect the source scopes of the terminators of a body.
+    struct TerminatorScopes<'a> {
+        body: &'a mir::Body,
+        scopes: HashSet<mir::SourceScope>,
+    }
+
+    impl MirVisitor for TerminatorScopes<'_> {
+        fn visit_terminator(&mut self, term: &mir::Terminator, location: mir::visit::Location) {
+            self.scopes.extend(self.body.source_scope(location));
+            self.super_terminator(term, location);
+        }
+    }
+
+    for (item, body) in local_bodies()? {
+        let options = mir::DotOptions {
+            title: Some(item.name()),
+            group_by_scope: true,
+            ..Default::default()
+        };
+        let mut output = vec![];
+        body.write_dot(&mut output, &options).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        for index in 0..body.blocks.len() {
+            check(
+                output.contains(&format!("    bb{index} [label=\"bb{index}:\\l")),
+                format!("Missing node for `bb{index}` in `{}`:\n{output}", item.name()),
+            )?;
+        }
+        let successors: usize =
+            body.blocks.iter().map(|block| block.terminator.successors().len()).sum();
+        check_equal(output.matches(" -> bb").count(), successors, "Unexpected number of edges")?;
+
+        let mut visitor = TerminatorScopes { body: &body, scopes: HashSet::new() };
+        visitor.visit_body(&body);
+        check_equal(
+            output.matches("subgraph cluster_scope").count(),
+            visitor.scopes.len(),
+            "Expected one cluster per scope",
+        )?;
+        for scope in visitor.scopes {
+            check(
+                output.contains(&format!("label=\"scope {scope}\";")),
+                format!("Missing cluster for scope {scope}:\n{output}"),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Test that source listings of local functions and their instances contain every statement and
+/// terminator exactly once.
+pub fn test_mir_listing() -> TestResult {
+    let locations = |body: &mir::Body| -> usize {
+        body.blocks.iter().map(|block| block.statements.len() + 1).sum()
+    };
+    let mut skipped = 0;
+    for (item, body) in local_bodies()? {
+        let mut output = vec![];
+        item.emit_mir_with_source(&mut output, &mir::ListingOptions::default()).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        check_equal(
+            output.lines().filter(|line| line.starts_with("        bb")).count(),
+            locations(&body),
+            "Unexpected number of statements in text listing",
+        )?;
+
+        if item.kind() != rustc_public::ItemKind::Fn {
+            continue;
+        }
+        let Ok(instance) = mir::mono::Instance::try_from(item) else { continue };
+        let body = match instance.try_body() {
+            Ok(Some(body)) => body,
+            Ok(None) => continue,
+            Err(error) if *error.kind() == rustc_public::ErrorKind::Internal => {
+                skipped += 1;
+                continue;
+            }
+            Err(error) => {
+                return Err(format!("Failed to get the body of `{}`: {error}", instance.name()));
+            }
+        };
+        let options =
+            mir::ListingOptions { format: mir::ListingFormat::Html, ..Default::default() };
+        let mut output = vec![];
+        instance.emit_mir_with_source(&mut output, &options).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        check_equal(
+            output.matches("<span id=\"bb").count(),
+            locations(&body),
+            "Unexpected number of statements in HTML listing",
+        )?;
+    }
+    if skipped > 0 {
+        crate::info(format!("Skipped {skipped} instances that trigger an internal compiler error"));
+    }
+    Ok(())
+}
+
+/// Test that the initializer of every local static can be dumped, both as bytes and as a value
+/// of the static's type.
+pub fn test_alloc_dump() -> TestResult {
+    for item in rustc_public::all_local_items() {
+        let Ok(def) = mir::mono::StaticDef::try_from(item) else { continue };
+        let Ok(alloc) = def.eval_initializer() else { continue };
+        let mut output = vec![];
+        alloc.dump(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let rows = output.split("\n}\n").next().unwrap().lines().skip(1).count();
+        check_equal(rows, alloc.bytes.len().div_ceil(16), "Unexpected number of rows")?;
+
+        let mut output = vec![];
+        alloc
+            .dump_typed(def.ty(), &mut output)
+            .map_err(|error| format!("Failed to decode `{}`: {error}", def.name()))?;
+    }
+    Ok(())
+}
+
+/// Test that the context of the locals visited at assignments, drops and storage markers
+/// matches the statement or terminator they appear in.
+pub fn test_place_contexts() -> TestResult {
+    use mir::visit::{MutatingUseContext, NonUseContext, PlaceContext};
+
+    #[derive(Default)]
+    struct ContextVisitor {
+        locals: Vec<(mir::Local, PlaceContext, Option<mir::visit::Location>)>,
+    }
+
+    impl MirVisitor for ContextVisitor {
+        fn visit_local(
+            &mut self,
+            local: &mir::Local,
+            ptx: PlaceContext,
+            location: Option<mir::visit::Location>,
+        ) {
+            self.locals.push((*local, ptx, location));
+        }
+    }
+
+    for (_, body) in local_bodies()? {
+        let mut visitor = ContextVisitor::default();
+        visitor.visit_body(&body);
+        let visited = |local: mir::Local, location: mir::visit::Location| {
+            visitor
+                .locals
+                .iter()
+                .filter(move |(other, _, at)| *other == local && *at == Some(location))
+                .map(|(_, ptx, _)| *ptx)
+        };
+        let expect = |place: &mir::Place, ctx: MutatingUseContext| {
+            if place.projection.is_empty() {
+                PlaceContext::MutatingUse(ctx)
+            } else {
+                PlaceContext::MutatingUse(MutatingUseContext::Projection)
+            }
+        };
+        for (block, data) in body.blocks.iter().enumerate() {
+            for index in 0..data.statements.len() {
+                let location = mir::visit::statement_location(&body, &block, index);
+                let (local, expected) = match &data.statements[index].kind {
+                    mir::StatementKind::Assign(place, _) => {
+                        (place.local, expect(place, MutatingUseContext::Store))
+                    }
+                    mir::StatementKind::StorageLive(local) => {
+                        (*local, PlaceContext::NonUse(NonUseContext::StorageLive))
+                    }
+                    mir::StatementKind::StorageDead(local) => {
+                        (*local, PlaceContext::NonUse(NonUseContext::StorageDead))
+                    }
+                    _ => continue,
+                };
+                check(
+                    visited(local, location).any(|ptx| ptx == expected),
+                    format!("Missing `{expected:?}` for `_{local}` at `{location:?}`"),
+                )?;
+            }
+            let location = mir::visit::terminator_location(&body, &block);
+            let (place, expected) = match &data.terminator.kind {
+                mir::TerminatorKind::Drop { place, .. } => {
+                    (place, expect(place, MutatingUseContext::Drop))
+                }
+                mir::TerminatorKind::Call { destination, .. } => {
+                    (destination, expect(destination, MutatingUseContext::Call))
+                }
+                _ => continue,
+            };
+            check(
+                visited(place.local, location).any(|ptx| ptx == expected),
+                format!("Missing `{expected:?}` for `_{}` at `{location:?}`", place.local),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Test that `TryMirVisitor` stops at the first terminator that returns, and doesn't visit
+/// anything after it.
+pub fn test_try_visitor() -> TestResult {
+    use std::ops::ControlFlow;
+
+    use mir::TryMirVisitor;
+
+    #[derive(Default)]
+    struct FindReturn {
+        visited: usize,
+    }
+
+    impl TryMirVisitor for FindReturn {
+        type Break = mir::visit::Location;
+
+        fn visit_statement(
+            &mut self,
+            stmt: &mir::Statement,
+            location: mir::visit::Location,
+        ) -> ControlFlow<Self::Break> {
+            self.visited += 1;
+            self.super_statement(stmt, location)
+        }
+
+        fn visit_terminator(
+            &mut self,
+            term: &mir::Terminator,
+            location: mir::visit::Location,
+        ) -> ControlFlow<Self::Break> {
+            self.visited += 1;
+            match term.kind {
+                mir::TerminatorKind::Return => ControlFlow::Break(location),
+                _ => self.super_terminator(term, location),
+            }
+        }
+    }
+
+    for (_, body) in local_bodies()? {
+        let expected = body
+            .blocks
+            .iter()
+            .position(|bb| bb.terminator.kind == mir::TerminatorKind::Return)
+            .map(|block| mir::visit::terminator_location(&body, &block));
+        let mut visitor = FindReturn::default();
+        let found = visitor.visit_body(&body).break_value();
+        check_equal(found, expected, "Unexpected break location")?;
+        let visited = match expected {
+            Some(location) => {
+                let before: usize =
+                    body.blocks[..location.block].iter().map(|bb| bb.statements.len() + 1).sum();
+                before + location.statement_index + 1
+            }
+            None => body.blocks.iter().map(|bb| bb.statements.len() + 1).sum(),
+        };
+        check_equal(visitor.visited, visited, "Unexpected number of visited statements")?;
+    }
+    Ok(())
+}
+
+/// Test that folding with the identity folder doesn't change anything, and that a folder can
+/// replace every type parameter in a body and in a function signature.
+pub fn test_type_folder() -> TestResult {
+    use std::ops::ControlFlow;
+
+    use rustc_public::fold::{Foldable, TypeFolder};
+    use rustc_public::visitor::{Visitable, Visitor};
+
+    struct Identity;
+    impl TypeFolder for Identity {}
+
+    struct ParamToUnit;
+    impl TypeFolder for ParamToUnit {
+        fn fold_ty(&mut self, ty: &ty::Ty) -> ty::Ty {
+            match ty.kind() {
+                ty::TyKind::Param(_) => ty::Ty::new_tuple(&[]),
+                _ => ty.super_fold(self),
+            }
+        }
+    }
+
+    struct FindParam;
+    impl Visitor for FindParam {
+        type Break = ty::Ty;
+        fn visit_ty(&mut self, ty: &ty::Ty) -> ControlFlow<Self::Break> {
+            match ty.kind() {
+                ty::TyKind::Param(_) => ControlFlow::Break(*ty),
+                _ => ty.super_visit(self),
+            }
+        }
+    }
+
+    for (item, body) in local_bodies()? {
+        let name = item.name();
+        let mut expected = vec![];
+        body.dump(&mut expected, &name).unwrap();
+        let mut found = vec![];
+        body.fold(&mut Identity).dump(&mut found, &name).unwrap();
+        check_equal(found, expected, "Identity fold changed the body")?;
+
+        let folded = body.fold(&mut ParamToUnit);
+        for (local, decl) in folded.local_decls() {
+            let param = decl.ty.visit(&mut FindParam).break_value();
+            check(param.is_none(), format!("`{name}`: `_{local}` still has a param: {param:?}"))?;
+        }
+        if let Some(sig) = item.ty().kind().fn_sig() {
+            let param = sig.fold(&mut ParamToUnit).visit(&mut FindParam).break_value();
+            check(param.is_none(), format!("`{name}`: signature still has a param: {param:?}"))?;
+        }
+    }
+
+    for def in rustc_public::local_crate().trait_decls() {
+        for (predicate, _) in TraitDef::declaration(&def).predicates_of().predicates {
+            check_equal(&predicate.fold(&mut Identity), &predicate, "Identity fold of predicate")?;
+        }
+    }
+    Ok(())
+}
+
+/// Test the dataflow framework with a forward and a backward analysis, and check that a results
+/// cursor returns the same state no matter the order in which locations are visited.
+pub fn test_dataflow() -> TestResult {
+    use std::collections::BTreeSet;
+
+    use mir::dataflow::{
+        Analysis, Backward, Direction, FlatSet, Forward, ResultsCursor, SwitchIntEdge,
+    };
+    use mir::visit::{Location, MutatingUseContext, PlaceContext};
+
+    /// Locals that may have been written to, even partially.
+    struct MaybeAssigned;
+
+    impl Analysis for MaybeAssigned {
+        type Domain = Vec<bool>;
+        type Direction = Forward;
+
+        fn bottom_value(&self, body: &mir::Body) -> Self::Domain {
+            vec![false; body.locals().len()]
+        }
+
+        fn initialize_start_state(&self, body: &mir::Body, state: &mut Self::Domain) {
+            state[1..=body.arg_locals().len()].fill(true);
+        }
+
+        fn apply_statement_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            statement: &mir::Statement,
+            _location: Location,
+        ) {
+            match &statement.kind {
+                mir::StatementKind::Assign(place, _)
+                | mir::StatementKind::SetDiscriminant { place, .. } => state[place.local] = true,
+                mir::StatementKind::StorageDead(local) => state[*local] = false,
+                _ => {}
+            }
+        }
+
+        fn apply_terminator_effect(
+            &mut self,
+            _state: &mut Self::Domain,
+            _terminator: &mir::Terminator,
+            _location: Location,
+        ) {
+        }
+
+        fn apply_call_return_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            destination: &mir::Place,
+            _location: Location,
+        ) {
+            state[destination.local] = true;
+        }
+    }
+
+    /// Locals whose current value may be read later. Partial writes don't kill a local.
+    struct MaybeLive;
+
+    #[derive(Default)]
+    struct DefUse {
+        defs: Vec<mir::Local>,
+        uses: Vec<mir::Local>,
+    }
+
+    impl MirVisitor for DefUse {
+        fn visit_local(
+            &mut self,
+            local: &mir::Local,
+            ptx: PlaceContext,
+            _location: Option<Location>,
+        ) {
+            match ptx {
+                PlaceContext::NonMutatingUse(_) => self.uses.push(*local),
+                PlaceContext::MutatingUse(
+                    MutatingUseContext::Store | MutatingUseContext::AsmOutput,
+                ) => self.defs.push(*local),
+                _ => {}
+            }
+        }
+    }
+
+    impl DefUse {
+        fn apply(self, state: &mut BTreeSet<mir::Local>) {
+            for local in self.defs {
+                state.remove(&local);
+            }
+            state.extend(self.uses);
+        }
+    }
+
+    impl Analysis for MaybeLive {
+        type Domain = BTreeSet<mir::Local>;
+        type Direction = Backward;
+
+        fn bottom_value(&self, _body: &mir::Body) -> Self::Domain {
+            BTreeSet::new()
+        }
+
+        fn initialize_start_state(&self, _body: &mir::Body, _state: &mut Self::Domain) {}
+
+        fn apply_statement_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            statement: &mir::Statement,
+            location: Location,
+        ) {
+            let mut visitor = DefUse::default();
+            visitor.visit_statement(statement, location);
+            visitor.apply(state);
+        }
+
+        fn apply_terminator_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            terminator: &mir::Terminator,
+            location: Location,
+        ) {
+            let mut visitor = DefUse::default();
+            visitor.visit_terminator(terminator, location);
+            visitor.apply(state);
+        }
+
+        fn apply_call_return_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            destination: &mir::Place,
+            _location: Location,
+        ) {
+            if destination.projection.is_empty() {
+                state.remove(&destination.local);
+            }
+        }
+    }
+
+    /// The value of the last switch discriminant, which is only known right after a switch.
+    struct KnownDiscriminant;
+
+    impl Analysis for KnownDiscriminant {
+        type Domain = FlatSet<u128>;
+        type Direction = Forward;
+
+        fn bottom_value(&self, _body: &mir::Body) -> Self::Domain {
+            FlatSet::Bottom
+        }
+
+        fn initialize_start_state(&self, _body: &mir::Body, state: &mut Self::Domain) {
+            *state = FlatSet::Top;
+        }
+
+        fn apply_statement_effect(
+            &mut self,
+            _state: &mut Self::Domain,
+            _statement: &mir::Statement,
+            _location: Location,
+        ) {
+        }
+
+        fn apply_terminator_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            _terminator: &mir::Terminator,
+            _location: Location,
+        ) {
+            *state = FlatSet::Top;
+        }
+
+        fn apply_switch_int_edge_effect(
+            &mut self,
+            state: &mut Self::Domain,
+            _discr: &mir::Operand,
+            edge: SwitchIntEdge,
+        ) {
+            if let SwitchIntEdge::Value(value) = edge {
+                *state = FlatSet::Elem(value);
+            }
+        }
+    }
+
+    /// Seek to every location of the body in program order, then in reverse order, and check
+    /// that both visits see the same states.
+    fn check_cursor<A: Analysis>(cursor: &mut ResultsCursor<A>) -> TestResult
+    where
+        A::Domain: Debug,
+    {
+        let body = cursor.body();
+        for (block, data) in body.blocks.iter().enumerate() {
+            let mut locations: Vec<_> = (0..data.statements.len())
+                .map(|index| mir::visit::statement_location(body, &block, index))
+                .collect();
+            locations.push(mir::visit::terminator_location(body, &block));
+
+            let mut states = vec![];
+            cursor.seek_to_block_start(block);
+            states.push(cursor.get().clone());
+            for location in &locations {
+                cursor.seek_before(*location);
+                check_equal(cursor.get(), states.last().unwrap(), "State before location")?;
+                cursor.seek_after(*location);
+                states.push(cursor.get().clone());
+            }
+            cursor.seek_to_block_end(block);
+            check_equal(cursor.get(), states.last().unwrap(), "State at block end")?;
+
+            let entry = if A::Direction::IS_FORWARD { &states[0] } else { states.last().unwrap() };
+            check_equal(cursor.results().entry_state(block), entry, "Entry state of block")?;
+
+            for (index, location) in locations.iter().enumerate().rev() {
+                cursor.seek_after(*location);
+                check_equal(cursor.get(), &states[index + 1], "State after location")?;
+                cursor.seek_before(*location);
+                check_equal(cursor.get(), &states[index], "State before location")?;
+            }
+        }
+        Ok(())
+    }
+
+    for (item, body) in local_bodies()? {
+        let name = item.name();
+
+        let mut assigned = MaybeAssigned.iterate_to_fixpoint(&body);
+        let mut cursor = assigned.cursor(&body);
+        check_cursor(&mut cursor)?;
+        for (block, data) in body.blocks.iter().enumerate() {
+            match &data.terminator.kind {
+                mir::TerminatorKind::Call { destination, target: Some(target), .. } => {
+                    cursor.seek_to_block_start(*target);
+                    check(
+                        cursor.get()[destination.local],
+                        format!("`{name}`: call destination not assigned in `bb{target}`"),
+                    )?;
+                }
+                mir::TerminatorKind::Return if !body.ret_local().ty.kind().is_unit() => {
+                    cursor.seek_before(mir::visit::terminator_location(&body, &block));
+                    check(cursor.get()[mir::RETURN_LOCAL], format!("`{name}`: `_0` not assigned"))?;
+                }
+                _ => {}
+            }
+        }
+
+        let mut live = MaybeLive.iterate_to_fixpoint(&body);
+        let mut cursor = live.cursor(&body);
+        check_cursor(&mut cursor)?;
+        if !body.blocks.is_empty() {
+            cursor.seek_to_block_start(0);
+            let unit_ret = body.ret_local().ty.kind().is_unit();
+            let inputs = |local: &mir::Local| {
+                (1..=body.arg_locals().len()).contains(local)
+                    || (unit_ret && *local == mir::RETURN_LOCAL)
+            };
+            let unexpected: Vec<_> = cursor.get().iter().filter(|local| !inputs(local)).collect();
+            check(unexpected.is_empty(), format!("`{name}`: live on entry: {unexpected:?}"))?;
+        }
+
+        let mut incoming = vec![0; body.blocks.len()];
+        for data in &body.blocks {
+            for target in data.terminator.successors() {
+                incoming[target] += 1;
+            }
+        }
+        let mut known = KnownDiscriminant.iterate_to_fixpoint(&body);
+        check_cursor(&mut known.cursor(&body))?;
+        for (block, data) in body.blocks.iter().enumerate() {
+            let mir::TerminatorKind::SwitchInt { targets, .. } = &data.terminator.kind else {
+                continue;
+            };
+            if *known.entry_state(block) == FlatSet::Bottom {
+                continue;
+            }
+            for (value, target) in targets.branches().filter(|(_, target)| incoming[*target] == 1) {
+                check_equal(
+                    known.entry_state(target),
+                    &FlatSet::Elem(value),
+                    "Value on switch edge",
+                )?;
+            }
+            check_equal(
+                known.entry_state(targets.otherwise()),
+                &FlatSet::Top,
+                "Value on otherwise edge",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Test that failed requests report the reason why they failed.
+pub fn test_error_kinds() -> TestResult {
+    use rustc_public::{ErrorKind, ItemKind};
+    for item in rustc_public::all_local_items() {
+        let def_id = item.def_id();
+        match mir::mono::Instance::try_from(item) {
+            Ok(_) => check(
+                !item.requires_monomorphization(),
+                format!("Expected `{}` to require monomorphization", item.name()),
+            )?,
+            Err(error) => check_equal(
+                error.kind(),
+                &ErrorKind::RequiresMonomorphization(def_id),
+                "Unexpected instance error",
+            )?,
+        }
+        if item.kind() != ItemKind::Static {
+            let error = mir::mono::StaticDef::try_from(item).unwrap_err();
+            check_equal(
+                error.kind(),
+                &ErrorKind::UnexpectedItem(def_id),
+                "Unexpected static error",
+            )?;
+        }
+    }
+    for (item, body) in local_bodies()? {
+        for local in body.locals() {
+            let Err(error) = local.ty.layout() else { continue };
+            // Only the layout of generic types may be unknown.
+            check(
+                item.requires_monomorphization()
+                    && matches!(error.kind(), ErrorKind::Layout { .. }),
+                format!("Unexpected layout error for `{}`: {error:?}", local.ty),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Test that the export of the local crate describes every local item.
+pub fn test_export() -> TestResult {
+    use rustc_public::export::{SCHEMA_VERSION, export_crate};
+    let export = export_crate().map_err(|err| format!("Export failed: {err}"))?;
+    check_equal(export.schema_version, SCHEMA_VERSION, "Unexpected schema version")?;
+    check_equal(export.crate_name, rustc_public::local_crate().name, "Unexpected crate name")?;
+    let items = rustc_public::all_local_items();
+    check_equal(export.items.len(), items.len(), "Every local item should be exported")?;
+    for (exported, item) in zip(&export.items, &items) {
+        check_equal(exported.def_id, item.def_id(), "Unexpected item order")?;
+        check_equal(exported.ty, item.ty(), "Unexpected item type")?;
+        let name = item.name();
+        check(
+            export.tables.defs.values().any(|def| def.path.ends_with(&name)),
+            format!("Missing definition of `{name}`"),
+        )?;
+        let file = exported.span.get_filename();
+        check(
+            export.tables.spans.values().any(|span| span.file == file),
+            format!("Missing span of `{name}`"),
+        )?;
+    }
+    check(
+        items.is_empty() || !export.tables.types.is_empty(),
+        "Expected the item types to be exported".to_string(),
+    )
+}
+
+/// Test that the stable identifiers of local and external definitions resolve back to them.
+pub fn test_stable_ids() -> TestResult {
+    use rustc_public::{ErrorKind, StableDefId};
+    let round_trip = |def_id: rustc_public::DefId| {
+        let stable_id = def_id.stable_id();
+        let found = stable_id.def_id().map_err(|err| format!("Cannot find `{def_id:?}`: {err}"))?;
+        check_equal(found, def_id, "Unexpected definition")
+    };
+    for item in rustc_public::all_local_items() {
+        round_trip(item.def_id())?;
+    }
+    let core = rustc_public::find_crates("core");
+    let core = core.first().ok_or("Cannot find `core`")?;
+    let external = core.fn_defs();
+    check(!external.is_empty(), "Expected `core` functions".to_string())?;
+    for def in external.iter().step_by(external.len().div_ceil(100)) {
+        round_trip(def.def_id())?;
+    }
+
+    // Missing definitions of an external crate are reported as well as local ones.
+    let local = rustc_public::all_local_items().first().map(CrateDef::def_id);
+    for def_id in local.into_iter().chain([external[0].def_id()]) {
+        let missing = StableDefId { path_hash: 0, ..def_id.stable_id() };
+        let error = missing.def_id().map(|_| ()).map_err(|err| err.kind().clone());
+        check_equal(error, Err(ErrorKind::NotFound), "Expected a missing definition")?;
+    }
+    Ok(())
+}
+
 /// Test that we can retrieve information about the trait declaration for every trait implementation.
 pub fn test_traits() -> TestResult {
     let all_traits = HashSet::<TraitDef>::from_iter(rustc_public::all_trait_decls().into_iter());
@@ -118,6 +889,29 @@ pub fn test_instances() -> TestResult {
     Ok(())
 }
 
+/// Return the local items that have a body, together with their body.
+///
+/// Items whose body triggers an internal compiler error are skipped, since some test crates are
+/// known to trigger one, and the number of skipped items is reported. Any other error fails.
+pub fn local_bodies() -> Result<Vec<(rustc_public::CrateItem, mir::Body)>, String> {
+    let mut bodies = vec![];
+    let mut skipped = 0;
+    for item in rustc_public::all_local_items() {
+        match item.try_body() {
+            Ok(Some(body)) => bodies.push((item, body)),
+            Ok(None) => {}
+            Err(error) if *error.kind() == rustc_public::ErrorKind::Internal => skipped += 1,
+            Err(error) => {
+                return Err(format!("Failed to get the body of `{}`: {error}", item.name()));
+            }
+        }
+    }
+    if skipped > 0 {
+        crate::info(format!("Skipped {skipped} bodies that trigger an internal compiler error"));
+    }
+    Ok(bodies)
+}
+
 /// Visit all local types, statements and terminator to ensure nothing crashes.
 fn check_body(name: &str, body: &mir::Body) -> Result<BodyVisitor, String> {
     let mut visitor = BodyVisitor::default();
@@ -144,13 +938,56 @@ fn check_body(name: &str, body: &mir::Body) -> Result<BodyVisitor, String> {
         )?;
     }
 
+    for (location, next) in zip(&visitor.locations, visitor.locations.iter().skip(1)) {
+        let visited = match body.stmt_at(*location) {
+            Some(stmt) => &visitor.statements[location.block][location.statement_index] == stmt,
+            None => body.terminator_at(*location) == Some(&visitor.terminators[location.block]),
+        };
+        check(visited, format!("Function `{name}`: Wrong location `{location:?}`"))?;
+        let successor = (next.block == location.block).then_some(*next);
+        check_equal(
+            location.successor_within_block(body),
+            successor,
+            &format!("Function `{name}`: Successor of `{location:?}`"),
+        )?;
+    }
+
     for local in body.locals() {
-        if !visitor.types.contains(&local.ty) {
+        if !visitor.decl_types.contains(&local.ty) {
             // Format fails due to unsupported CoroutineWitness.
             // See https://github.com/rust-lang/project-stable-mir/issues/50.
             check(false, format!("Function `{name}`: Missing type `{:?}`", local.ty))?;
         };
     }
+
+    // Only debug information is visited outside of the control-flow graph, including the
+    // locals that its places are indexed with.
+    let debug_indices: HashSet<_> = body
+        .var_debug_info
+        .iter()
+        .filter_map(|info| match &info.value {
+            mir::VarDebugInfoContents::Place(place) => Some(&place.projection),
+            mir::VarDebugInfoContents::Const(_) => None,
+        })
+        .flatten()
+        .filter_map(|elem| match elem {
+            mir::ProjectionElem::Index(local) => Some(*local),
+            _ => None,
+        })
+        .collect();
+    let debug_info = mir::visit::PlaceContext::NonUse(mir::visit::NonUseContext::VarDebugInfo);
+    for (local, ptx, location) in &visitor.locals {
+        let expected = match location {
+            Some(_) => *ptx != debug_info,
+            None => *ptx == debug_info || debug_indices.contains(local),
+        };
+        check(
+            expected,
+            format!(
+                "Function `{name}`: Unexpected location of `_{local}` in `{ptx:?}`: {location:?}"
+            ),
+        )?;
+    }
     Ok(visitor)
 }
 
@@ -158,27 +995,42 @@ fn check_body(name: &str, body: &mir::Body) -> Result<BodyVisitor, String> {
 struct BodyVisitor {
     statements: Vec<Vec<mir::Statement>>,
     terminators: Vec<mir::Terminator>,
-    types: HashSet<ty::Ty>,
+    locations: Vec<mir::visit::Location>,
+    locals: Vec<(mir::Local, mir::visit::PlaceContext, Option<mir::visit::Location>)>,
+    decl_types: HashSet<ty::Ty>,
 }
 
 impl mir::MirVisitor for BodyVisitor {
-    fn visit_basic_block(&mut self, bb: &mir::BasicBlock) {
+    fn visit_basic_block(&mut self, block: mir::BasicBlockIdx, bb: &mir::BasicBlock) {
         assert_eq!(self.statements.len(), self.terminators.len());
         self.statements.push(vec![]);
-        self.super_basic_block(bb)
+        self.super_basic_block(block, bb)
     }
     fn visit_statement(&mut self, stmt: &mir::Statement, loc: mir::visit::Location) {
         self.statements.last_mut().unwrap().push(stmt.clone());
+        self.locations.push(loc);
         self.super_statement(stmt, loc)
     }
 
     fn visit_terminator(&mut self, term: &mir::Terminator, location: mir::visit::Location) {
         self.terminators.push(term.clone());
+        self.locations.push(location);
         self.super_terminator(term, location);
     }
 
-    fn visit_ty(&mut self, ty: &ty::Ty, _location: mir::visit::Location) {
-        self.types.insert(*ty);
+    fn visit_local(
+        &mut self,
+        local: &mir::Local,
+        ptx: mir::visit::PlaceContext,
+        location: Option<mir::visit::Location>,
+    ) {
+        self.locals.push((*local, ptx, location));
+    }
+
+    fn visit_ty(&mut self, ty: &ty::Ty, location: Option<mir::visit::Location>) {
+        if location.is_none() {
+            self.decl_types.insert(*ty);
+        }
         self.super_ty(ty)
     }
 }